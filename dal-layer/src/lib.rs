@@ -1,3 +1,4 @@
 pub mod models;
+pub mod pipeline;
 pub mod repository;
 pub mod utils;
@@ -0,0 +1,323 @@
+use mongodb::bson::{DateTime, oid::ObjectId};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+use super::log_model::LogLevel;
+use crate::utils::date_helper::Converter;
+
+///Re-send a firing alert once an hour unless the rule says otherwise
+const DEFAULT_REPEAT_INTERVAL_SECS: u64 = 3600;
+
+///Longest window a condition may look back over, 30 days
+pub const MAX_WINDOW_SECS: u64 = 30 * 24 * 3600;
+
+///Longest a firing alert may go without being sent again, 30 days
+pub const MAX_REPEAT_INTERVAL_SECS: u64 = 30 * 24 * 3600;
+
+///When a rule fires
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AlertCondition {
+    ///More than `threshold` lines at `min_level` or above within the window
+    Threshold {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min_level: Option<LogLevel>,
+        threshold: u64,
+        window_secs: u64,
+    },
+    ///Any line whose line_content matches the regular expression `pattern` within the window
+    Match {
+        pattern: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min_level: Option<LogLevel>,
+        window_secs: u64,
+    },
+    ///No lines at all from the rule's service within the window
+    Absence { window_secs: u64 },
+}
+
+impl AlertCondition {
+    pub fn window_secs(&self) -> u64 {
+        match self {
+            AlertCondition::Threshold { window_secs, .. }
+            | AlertCondition::Match { window_secs, .. }
+            | AlertCondition::Absence { window_secs } => *window_secs,
+        }
+    }
+
+    ///Whether `count`, the lines the condition's query found in the window, makes it fire
+    pub fn is_firing(&self, count: u64) -> bool {
+        match self {
+            AlertCondition::Threshold { threshold, .. } => count > *threshold,
+            AlertCondition::Match { .. } => count > 0,
+            AlertCondition::Absence { .. } => count == 0,
+        }
+    }
+
+    ///Human readable account of an evaluation
+    pub fn describe(&self, count: u64) -> String {
+        let window = self.window_secs();
+        match self {
+            AlertCondition::Threshold {
+                min_level,
+                threshold,
+                ..
+            } => {
+                let level = min_level
+                    .map(|level| format!("{level} or worse "))
+                    .unwrap_or_default();
+                format!("{count} {level}lines in the last {window}s (threshold {threshold})")
+            }
+            AlertCondition::Match { pattern, .. } => {
+                format!("{count} lines matched `{pattern}` in the last {window}s")
+            }
+            AlertCondition::Absence { .. } if count == 0 => {
+                format!("No logs in the last {window}s")
+            }
+            AlertCondition::Absence { .. } => format!("{count} lines in the last {window}s"),
+        }
+    }
+}
+
+///Where a rule's notifications are sent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Notifier {
+    ///POST the notification as JSON to `url`
+    Webhook {
+        url: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+    ///Run the command configured on the server under `name`, the notification as JSON on
+    ///stdin and ALERT_* environment variables. Rules cannot name programs themselves.
+    Command { name: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub _id: ObjectId,
+    pub name: String,
+    ///Service the rule watches; every service when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_id: Option<ObjectId>,
+    pub condition: AlertCondition,
+    pub notifiers: Vec<Notifier>,
+    ///How often a firing alert is sent again
+    pub repeat_interval_secs: u64,
+    pub enabled: bool,
+    pub created_at: DateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AlertRuleRequest {
+    pub name: String,
+    pub service_id: Option<String>,
+    pub condition: AlertCondition,
+    #[serde(default)]
+    pub notifiers: Vec<Notifier>,
+    pub repeat_interval_secs: Option<u64>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl TryFrom<AlertRuleRequest> for AlertRule {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(item: AlertRuleRequest) -> Result<Self, Self::Error> {
+        let service_id = item
+            .service_id
+            .as_deref()
+            .map(ObjectId::parse_str)
+            .transpose()
+            .map_err(|err| format!("Failed to parse service_id: {err}"))?;
+
+        match &item.condition {
+            AlertCondition::Match { pattern, .. } => {
+                Regex::new(pattern).map_err(|err| format!("Invalid pattern: {err}"))?;
+            }
+            AlertCondition::Absence { .. } if service_id.is_none() => {
+                return Err("An absence rule needs a service_id".into());
+            }
+            _ => {}
+        }
+        if item.condition.window_secs() == 0 {
+            return Err("window_secs must be greater than 0".into());
+        }
+        if item.condition.window_secs() > MAX_WINDOW_SECS {
+            return Err(format!("window_secs must be at most {MAX_WINDOW_SECS}").into());
+        }
+        if item
+            .repeat_interval_secs
+            .is_some_and(|secs| secs > MAX_REPEAT_INTERVAL_SECS)
+        {
+            return Err(
+                format!("repeat_interval_secs must be at most {MAX_REPEAT_INTERVAL_SECS}").into(),
+            );
+        }
+
+        Ok(Self {
+            _id: ObjectId::new(),
+            name: item.name,
+            service_id,
+            condition: item.condition,
+            notifiers: item.notifiers,
+            repeat_interval_secs: item
+                .repeat_interval_secs
+                .unwrap_or(DEFAULT_REPEAT_INTERVAL_SECS),
+            enabled: item.enabled,
+            created_at: DateTime::now(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertState {
+    Ok,
+    Firing,
+}
+
+///Last evaluation of a rule, stored under the rule's id
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertStatus {
+    pub _id: ObjectId,
+    pub rule_name: String,
+    pub state: AlertState,
+    ///When the rule entered `state`
+    pub since: DateTime,
+    ///Lines found by the last evaluation
+    pub value: i64,
+    pub message: String,
+    pub evaluated_at: DateTime,
+    ///Last firing notification sent; cleared when the alert resolves
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notified_at: Option<DateTime>,
+}
+
+///Mutes the notifications of matching rules between `starts_at` and `ends_at`. Rules are still
+///evaluated, so a firing alert is sent once the silence ends.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Silence {
+    pub _id: ObjectId,
+    ///Silence only this rule
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule_id: Option<ObjectId>,
+    ///Silence the rules watching this service
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_id: Option<ObjectId>,
+    pub starts_at: DateTime,
+    pub ends_at: DateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+impl Silence {
+    ///Whether the silence mutes `rule` at `now`; a silence naming neither a rule nor a service
+    ///mutes every rule
+    pub fn mutes(&self, rule: &AlertRule, now: DateTime) -> bool {
+        self.starts_at <= now
+            && now < self.ends_at
+            && self.rule_id.is_none_or(|id| id == rule._id)
+            && self.service_id.is_none_or(|id| rule.service_id == Some(id))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SilenceRequest {
+    pub rule_id: Option<String>,
+    pub service_id: Option<String>,
+    ///RFC 3339 start; now when absent
+    pub starts_at: Option<String>,
+    ///RFC 3339 end
+    pub ends_at: String,
+    pub comment: Option<String>,
+}
+
+impl TryFrom<SilenceRequest> for Silence {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(item: SilenceRequest) -> Result<Self, Self::Error> {
+        let parse_id = |value: Option<String>, name: &str| {
+            value
+                .as_deref()
+                .map(ObjectId::parse_str)
+                .transpose()
+                .map_err(|err| format!("Failed to parse {name}: {err}"))
+        };
+        let parse_date =
+            |value: &str| Converter::parse_str_datetime(value).map_err(|err| err.to_string());
+
+        let starts_at = match &item.starts_at {
+            Some(starts_at) => parse_date(starts_at)?,
+            None => DateTime::now(),
+        };
+        let ends_at = parse_date(&item.ends_at)?;
+        if ends_at <= starts_at {
+            return Err("ends_at must be after starts_at".into());
+        }
+
+        Ok(Self {
+            _id: ObjectId::new(),
+            rule_id: parse_id(item.rule_id, "rule_id")?,
+            service_id: parse_id(item.service_id, "service_id")?,
+            starts_at,
+            ends_at,
+            comment: item.comment,
+        })
+    }
+}
+
+///Body of a webhook or command notification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertNotification {
+    pub rule_id: String,
+    pub rule_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_id: Option<String>,
+    pub state: AlertState,
+    pub value: i64,
+    pub message: String,
+    ///RFC 3339 time of the evaluation
+    pub at: String,
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(window_secs: u64, repeat_interval_secs: Option<u64>) -> AlertRuleRequest {
+        AlertRuleRequest {
+            name: String::from("errors"),
+            service_id: None,
+            condition: AlertCondition::Threshold {
+                min_level: Some(LogLevel::ERROR),
+                threshold: 50,
+                window_secs,
+            },
+            notifiers: Vec::new(),
+            repeat_interval_secs,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_rule_bounds() {
+        assert!(AlertRule::try_from(request(300, None)).is_ok());
+        assert!(
+            AlertRule::try_from(request(MAX_WINDOW_SECS, Some(MAX_REPEAT_INTERVAL_SECS))).is_ok()
+        );
+        assert!(AlertRule::try_from(request(0, None)).is_err());
+        assert!(AlertRule::try_from(request(MAX_WINDOW_SECS + 1, None)).is_err());
+        assert!(AlertRule::try_from(request(u64::MAX, None)).is_err());
+        assert!(AlertRule::try_from(request(300, Some(u64::MAX))).is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+///A collector is stale once it missed this many heartbeats in a row
+const STALE_AFTER_MISSED_HEARTBEATS: u64 = 3;
+
+///How far a collector has read one file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FileOffset {
+    pub path: String,
+    pub offset: u64,
+    ///Size of the file when the offset was taken
+    pub size: u64,
+}
+
+///State of one configured source of a collector
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SourceStatus {
+    ///application_name of the source
+    pub name: String,
+    ///`directory`, `syslog` or `container`
+    pub kind: String,
+    #[serde(default)]
+    pub offsets: Vec<FileOffset>,
+    ///Bytes written to the source's files that were not read yet
+    #[serde(default)]
+    pub lag_bytes: u64,
+    ///Lines read but not stored yet
+    #[serde(default)]
+    pub pending_lines: u64,
+    ///Lines and bytes read since the collector started
+    #[serde(default)]
+    pub lines_read: u64,
+    #[serde(default)]
+    pub bytes_read: u64,
+    ///Lines stored since the collector started
+    #[serde(default)]
+    pub lines_shipped: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    ///RFC 3339 time of `last_error`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error_at: Option<String>,
+}
+
+///Sent by a collector when it starts and then every `heartbeat_interval_secs`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CollectorHeartbeat {
+    pub collector_id: String,
+    pub hostname: String,
+    pub version: String,
+    pub heartbeat_interval_secs: u64,
+    #[serde(default)]
+    pub sources: Vec<SourceStatus>,
+    ///Lines held in memory across every source, waiting to be stored
+    #[serde(default)]
+    pub spool_size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+///A collector as last reported, stored under its collector_id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collector {
+    pub _id: String,
+    pub hostname: String,
+    pub version: String,
+    pub heartbeat_interval_secs: u64,
+    pub sources: Vec<SourceStatus>,
+    pub spool_size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub registered_at: DateTime,
+    pub last_heartbeat_at: DateTime,
+}
+
+impl Collector {
+    pub fn from_heartbeat(heartbeat: CollectorHeartbeat, now: DateTime) -> Collector {
+        Collector {
+            _id: heartbeat.collector_id,
+            hostname: heartbeat.hostname,
+            version: heartbeat.version,
+            heartbeat_interval_secs: heartbeat.heartbeat_interval_secs,
+            sources: heartbeat.sources,
+            spool_size: heartbeat.spool_size,
+            last_error: heartbeat.last_error,
+            registered_at: now,
+            last_heartbeat_at: now,
+        }
+    }
+}
+
+///A collector with how long ago it was last heard from
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectorView {
+    #[serde(flatten)]
+    pub collector: Collector,
+    pub seconds_since_heartbeat: i64,
+    ///No heartbeat for three of its intervals
+    pub stale: bool,
+}
+
+impl CollectorView {
+    pub fn new(collector: Collector, now: DateTime) -> CollectorView {
+        let seconds_since_heartbeat =
+            (now.timestamp_millis() - collector.last_heartbeat_at.timestamp_millis()).max(0) / 1000;
+        let stale_after = collector.heartbeat_interval_secs.max(1) * STALE_AFTER_MISSED_HEARTBEATS;
+
+        CollectorView {
+            stale: seconds_since_heartbeat > stale_after as i64,
+            seconds_since_heartbeat,
+            collector,
+        }
+    }
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_after_three_missed_heartbeats() {
+        let now = DateTime::from_millis(1_760_000_000_000);
+        let heartbeat = CollectorHeartbeat {
+            collector_id: String::from("web-01"),
+            hostname: String::from("web-01"),
+            version: String::from("0.1.0"),
+            heartbeat_interval_secs: 30,
+            sources: Vec::new(),
+            spool_size: 0,
+            last_error: None,
+        };
+        let heard_from = |secs_ago: i64| {
+            let at = DateTime::from_millis(now.timestamp_millis() - secs_ago * 1000);
+            CollectorView::new(Collector::from_heartbeat(heartbeat.clone(), at), now)
+        };
+
+        assert!(!heard_from(45).stale);
+        assert!(!heard_from(90).stale);
+        let view = heard_from(91);
+        assert!(view.stale);
+        assert_eq!(view.seconds_since_heartbeat, 91);
+    }
+}
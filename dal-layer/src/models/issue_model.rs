@@ -0,0 +1,88 @@
+use mongodb::bson::{DateTime, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::log_model::LogLevel;
+
+///Where an issue stands in triage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueStatus {
+    Unresolved,
+    Resolved,
+    ///Still counted, but not worth attention
+    Ignored,
+    ///Was resolved and occurred again afterwards
+    Regressed,
+}
+
+impl IssueStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IssueStatus::Unresolved => "unresolved",
+            IssueStatus::Resolved => "resolved",
+            IssueStatus::Ignored => "ignored",
+            IssueStatus::Regressed => "regressed",
+        }
+    }
+}
+
+///ERROR and FATAL lines sharing a fingerprint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Issue {
+    ///The fingerprint stored in `Log.fingerprint`
+    pub _id: String,
+    ///First line of the message with its variable parts masked
+    pub title: String,
+    pub level: LogLevel,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exception_class: Option<String>,
+    pub status: IssueStatus,
+    pub count: i64,
+    pub first_seen: DateTime,
+    pub last_seen: DateTime,
+    ///Services the error occurred in
+    pub services: Vec<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_at: Option<DateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regressed_at: Option<DateTime>,
+    ///Times the issue came back after being resolved
+    #[serde(default)]
+    pub regressions: i64,
+}
+
+///Occurrences of one fingerprint in a batch of stored logs
+#[derive(Debug, Clone, PartialEq)]
+pub struct IssueOccurrence {
+    pub fingerprint: String,
+    pub title: String,
+    pub level: LogLevel,
+    pub exception_class: Option<String>,
+    pub count: i64,
+    pub first_seen: DateTime,
+    pub last_seen: DateTime,
+    pub services: Vec<ObjectId>,
+}
+
+///Filters of the issue listing
+#[derive(Debug, Default, Deserialize, Serialize, Clone, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct IssueQuery {
+    pub status: Option<IssueStatus>,
+    ///Only issues that occurred in this service
+    pub service_id: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OccurrenceQuery {
+    pub limit: Option<i64>,
+}
+
+///New triage status of an issue
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct IssueStatusUpdate {
+    pub status: IssueStatus,
+}
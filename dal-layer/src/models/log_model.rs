@@ -1,11 +1,19 @@
 use chrono::Utc;
 use mongodb::bson::{DateTime, oid::ObjectId};
-use serde::{Deserialize, Serialize};
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt;
-use std::{alloc::System, time::SystemTime};
-use utoipa::ToSchema;
+use std::str::FromStr;
+use std::time::SystemTime;
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Debug, PartialEq, Deserialize, Serialize, Clone, ToSchema)]
+///Severity of a log line. Serialised as the upper case variant name, but parsed
+///case-insensitively with the usual aliases (`warning`, `err`, `critical`, ...) or from an
+///OpenTelemetry severity number.
+#[derive(Debug, PartialEq, Eq, Hash, Serialize, Clone, Copy, ToSchema)]
 pub enum LogLevel {
     INFO,
     ERROR,
@@ -13,6 +21,169 @@ pub enum LogLevel {
     WARN,
     TRACE,
     OTHER,
+    FATAL,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 7] = [
+        LogLevel::OTHER,
+        LogLevel::TRACE,
+        LogLevel::DEBUG,
+        LogLevel::INFO,
+        LogLevel::WARN,
+        LogLevel::ERROR,
+        LogLevel::FATAL,
+    ];
+
+    ///OpenTelemetry `SeverityNumber` of the first value of the level's range (0 = unspecified)
+    pub fn severity(&self) -> i32 {
+        match self {
+            LogLevel::OTHER => 0,
+            LogLevel::TRACE => 1,
+            LogLevel::DEBUG => 5,
+            LogLevel::INFO => 9,
+            LogLevel::WARN => 13,
+            LogLevel::ERROR => 17,
+            LogLevel::FATAL => 21,
+        }
+    }
+
+    ///Map an OpenTelemetry `SeverityNumber` (1-24) onto a level
+    pub fn from_severity_number(number: i32) -> LogLevel {
+        match number {
+            1..=4 => LogLevel::TRACE,
+            5..=8 => LogLevel::DEBUG,
+            9..=12 => LogLevel::INFO,
+            13..=16 => LogLevel::WARN,
+            17..=20 => LogLevel::ERROR,
+            21..=24 => LogLevel::FATAL,
+            _ => LogLevel::OTHER,
+        }
+    }
+
+    ///Syslog severity (RFC 5424 section 6.2.1, 0 = emergency ... 7 = debug)
+    pub fn syslog_severity(&self) -> u8 {
+        match self {
+            LogLevel::FATAL => 2,
+            LogLevel::ERROR => 3,
+            LogLevel::WARN => 4,
+            LogLevel::INFO | LogLevel::OTHER => 6,
+            LogLevel::DEBUG | LogLevel::TRACE => 7,
+        }
+    }
+
+    pub fn from_syslog_severity(severity: u8) -> LogLevel {
+        match severity {
+            0..=2 => LogLevel::FATAL,
+            3 => LogLevel::ERROR,
+            4 => LogLevel::WARN,
+            5 | 6 => LogLevel::INFO,
+            7 => LogLevel::DEBUG,
+            _ => LogLevel::OTHER,
+        }
+    }
+
+    ///Every level at least as severe as `self`, e.g. WARN gives WARN, ERROR and FATAL
+    pub fn and_above(&self) -> Vec<LogLevel> {
+        LogLevel::ALL
+            .into_iter()
+            .filter(|level| level >= self)
+            .collect()
+    }
+}
+
+impl Ord for LogLevel {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.severity().cmp(&other.severity())
+    }
+}
+
+impl PartialOrd for LogLevel {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            LogLevel::INFO => "INFO",
+            LogLevel::ERROR => "ERROR",
+            LogLevel::DEBUG => "DEBUG",
+            LogLevel::WARN => "WARN",
+            LogLevel::TRACE => "TRACE",
+            LogLevel::OTHER => "OTHER",
+            LogLevel::FATAL => "FATAL",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseLogLevelError(String);
+
+impl fmt::Display for ParseLogLevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown log level: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseLogLevelError {}
+
+impl FromStr for LogLevel {
+    type Err = ParseLogLevelError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let trimmed = value.trim();
+        if let Ok(number) = trimmed.parse::<i32>() {
+            return Ok(LogLevel::from_severity_number(number));
+        }
+
+        match trimmed.to_ascii_uppercase().as_str() {
+            "TRACE" | "TRC" | "FINEST" | "VERBOSE" => Ok(LogLevel::TRACE),
+            "DEBUG" | "DBG" | "FINE" | "FINER" => Ok(LogLevel::DEBUG),
+            "INFO" | "INF" | "INFORMATION" | "INFORMATIONAL" | "NOTICE" => Ok(LogLevel::INFO),
+            "WARN" | "WARNING" | "WRN" => Ok(LogLevel::WARN),
+            "ERROR" | "ERR" | "SEVERE" => Ok(LogLevel::ERROR),
+            "FATAL" | "CRITICAL" | "CRIT" | "EMERG" | "EMERGENCY" | "ALERT" | "PANIC" => {
+                Ok(LogLevel::FATAL)
+            }
+            "OTHER" | "UNSPECIFIED" => Ok(LogLevel::OTHER),
+            _ => Err(ParseLogLevelError(value.to_string())),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for LogLevel {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LogLevelVisitor;
+
+        impl Visitor<'_> for LogLevelVisitor {
+            type Value = LogLevel;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a log level name such as \"warn\" or a severity number")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<LogLevel, E> {
+                LogLevel::from_str(value).map_err(E::custom)
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<LogLevel, E> {
+                Ok(LogLevel::from_severity_number(
+                    i32::try_from(value).unwrap_or_default(),
+                ))
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<LogLevel, E> {
+                Ok(LogLevel::from_severity_number(
+                    i32::try_from(value).unwrap_or_default(),
+                ))
+            }
+        }
+
+        deserializer.deserialize_any(LogLevelVisitor)
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -22,6 +193,41 @@ pub struct Log {
     pub level: LogLevel,
     pub line_content: String,
     pub created_at: DateTime,
+    ///Free-form structured fields, e.g. the extra keys of a JSON log line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<HashMap<String, Value>>,
+    ///Name of the machine the line was produced on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    ///Path of the file the line was read from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_file: Option<String>,
+    ///Byte offset of the start of the line within `source_file`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_offset: Option<i64>,
+    ///When the line was stored, as opposed to when it was written. Stamped with the clock of
+    ///the database server right after the insert; the live tail follows it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ingested_at: Option<DateTime>,
+    ///W3C trace context ids (32 and 16 lowercase hex characters)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span_id: Option<String>,
+    ///Occurrences of this line folded into the record by deduplication, itself included
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat_count: Option<i64>,
+    ///When the first and the latest of those occurrences were written
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_seen: Option<DateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<DateTime>,
+    ///Id of the message template the line was clustered into
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template_id: Option<String>,
+    ///Issue an ERROR or FATAL line belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
 }
 
 impl TryFrom<LogRequest> for Log {
@@ -33,17 +239,56 @@ impl TryFrom<LogRequest> for Log {
             .with_timezone(&Utc)
             .into();
 
+        let my_service_id = ObjectId::parse_str(&item.my_service_id)
+            .map_err(|err| format!("Failed to parse my_service_id: {} ", err))?;
+
         Ok(Self {
             _id: ObjectId::new(),
-            my_service_id: ObjectId::parse_str(&item.my_service_id).expect("Failed to parse owner"),
+            my_service_id,
             level: item.level,
             line_content: item.line_content,
             created_at: DateTime::from(chono_datetime),
+            attributes: item.attributes,
+            host: item.host,
+            source_file: item.source_file,
+            line_offset: item.line_offset,
+            ingested_at: None,
+            trace_id: item.trace_id.map(|id| id.to_lowercase()),
+            span_id: item.span_id.map(|id| id.to_lowercase()),
+            repeat_count: None,
+            first_seen: None,
+            last_seen: None,
+            template_id: None,
+            fingerprint: None,
         })
     }
 }
 
 impl Log {
+    ///A line of `level` written now, with none of the optional fields set
+    #[cfg(test)]
+    pub fn sample(my_service_id: ObjectId, level: LogLevel, line_content: &str) -> Self {
+        Log {
+            _id: ObjectId::new(),
+            my_service_id,
+            level,
+            line_content: line_content.to_string(),
+            created_at: DateTime::now(),
+            attributes: None,
+            host: None,
+            source_file: None,
+            line_offset: None,
+            ingested_at: None,
+            trace_id: None,
+            span_id: None,
+            repeat_count: None,
+            first_seen: None,
+            last_seen: None,
+            template_id: None,
+            fingerprint: None,
+        }
+    }
+
     pub fn from_bulk(items: Vec<LogRequest>) -> Result<Vec<Self>, Box<dyn std::error::Error>> {
         items.into_iter().map(Self::try_from).collect()
     }
@@ -55,6 +300,41 @@ pub struct LogRequest {
     pub my_service_id: String,
     pub line_content: String,
     pub created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<HashMap<String, Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_offset: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span_id: Option<String>,
+}
+
+impl LogRequest {
+    ///A line with none of the optional fields set
+    pub fn new(
+        my_service_id: &str,
+        level: LogLevel,
+        line_content: &str,
+        created_at: String,
+    ) -> Self {
+        LogRequest {
+            level,
+            my_service_id: my_service_id.to_string(),
+            line_content: line_content.to_string(),
+            created_at,
+            attributes: None,
+            host: None,
+            source_file: None,
+            line_offset: None,
+            trace_id: None,
+            span_id: None,
+        }
+    }
 }
 
 impl fmt::Display for LogRequest {
@@ -62,3 +342,150 @@ impl fmt::Display for LogRequest {
         write!(f, "{}", serde_json::to_string(self).unwrap())
     }
 }
+
+///What became of the lines of a bulk request
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BulkLogResult {
+    pub stored: usize,
+    ///Dropped by a redaction rule, or counted on a recent line by deduplication
+    pub dropped: usize,
+    ///Over their service's ingestion limit
+    pub limited: usize,
+    ///Why each line that could not be converted was rejected, with its position in the request
+    pub invalid: Vec<String>,
+}
+
+///Filters accepted by the log search endpoint. Every field is optional and they are AND-ed together.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LogSearchQuery {
+    pub service_id: Option<String>,
+    ///Exact level; takes precedence over `min_level`
+    pub level: Option<LogLevel>,
+    ///Only return lines at least this severe, e.g. `WARN` for WARN, ERROR and FATAL
+    pub min_level: Option<LogLevel>,
+    pub host: Option<String>,
+    pub source_file: Option<String>,
+    pub trace_id: Option<String>,
+    pub span_id: Option<String>,
+    ///Attribute filter written as `key=value`, e.g. `user_id=42`
+    pub attribute: Option<String>,
+    ///Only return lines deduplication folded at least this many occurrences into
+    pub min_repeat_count: Option<i64>,
+    ///RFC 3339 lower bound (inclusive) on `created_at`
+    pub from: Option<String>,
+    ///RFC 3339 upper bound (exclusive) on `created_at`
+    pub to: Option<String>,
+    pub limit: Option<i64>,
+    ///`asc` for the oldest lines first; newest first when omitted
+    pub order: Option<SearchOrder>,
+}
+
+///Order of the lines returned by a search, on `created_at`
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+///Time range and service accepted by the log statistics endpoint
+#[derive(Debug, Default, Deserialize, Serialize, Clone, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LogStatsQuery {
+    pub service_id: Option<String>,
+    ///RFC 3339 lower bound (inclusive) on `created_at`
+    pub from: Option<String>,
+    ///RFC 3339 upper bound (exclusive) on `created_at`
+    pub to: Option<String>,
+}
+
+///How many lines of one level a service logged in the requested range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogStatsView {
+    pub service_id: String,
+    pub level: LogLevel,
+    ///Lines, repeats folded by deduplication included
+    pub count: i64,
+    pub first_seen: DateTime,
+    pub last_seen: DateTime,
+}
+
+///Window size accepted by the log context endpoint
+#[derive(Debug, Default, Deserialize, Serialize, Clone, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LogContextQuery {
+    ///Lines to return before the log, 50 when omitted
+    pub before: Option<i64>,
+    ///Lines to return after the log, 50 when omitted
+    pub after: Option<i64>,
+}
+
+///A log together with its neighbours from the same service and source file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogContextView {
+    pub before: Vec<Log>,
+    pub log: Log,
+    pub after: Vec<Log>,
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level_aliases() {
+        assert_eq!("warn".parse::<LogLevel>(), Ok(LogLevel::WARN));
+        assert_eq!("WARNING".parse::<LogLevel>(), Ok(LogLevel::WARN));
+        assert_eq!(" Err ".parse::<LogLevel>(), Ok(LogLevel::ERROR));
+        assert_eq!("critical".parse::<LogLevel>(), Ok(LogLevel::FATAL));
+        assert_eq!("EMERG".parse::<LogLevel>(), Ok(LogLevel::FATAL));
+        assert_eq!("panic".parse::<LogLevel>(), Ok(LogLevel::FATAL));
+        assert_eq!("13".parse::<LogLevel>(), Ok(LogLevel::WARN));
+        assert!("loud".parse::<LogLevel>().is_err());
+    }
+
+    #[test]
+    fn test_deserialize_level() {
+        let level: LogLevel = serde_json::from_str("\"warning\"").unwrap();
+        assert_eq!(level, LogLevel::WARN);
+
+        let level: LogLevel = serde_json::from_str("21").unwrap();
+        assert_eq!(level, LogLevel::FATAL);
+
+        assert_eq!(
+            serde_json::to_string(&LogLevel::FATAL).unwrap(),
+            "\"FATAL\""
+        );
+    }
+
+    #[test]
+    fn test_severity_ordering() {
+        assert!(LogLevel::FATAL > LogLevel::ERROR);
+        assert!(LogLevel::WARN > LogLevel::INFO);
+        assert!(LogLevel::OTHER < LogLevel::TRACE);
+        assert_eq!(
+            LogLevel::WARN.and_above(),
+            vec![LogLevel::WARN, LogLevel::ERROR, LogLevel::FATAL]
+        );
+    }
+
+    #[test]
+    fn test_syslog_severity_round_trip() {
+        for level in [
+            LogLevel::FATAL,
+            LogLevel::ERROR,
+            LogLevel::WARN,
+            LogLevel::INFO,
+            LogLevel::DEBUG,
+        ] {
+            assert_eq!(
+                LogLevel::from_syslog_severity(level.syslog_severity()),
+                level
+            );
+        }
+    }
+}
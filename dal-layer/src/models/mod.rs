@@ -1,4 +1,9 @@
+pub mod alert_model;
+pub mod collector_model;
 pub mod details;
+pub mod issue_model;
 pub mod log_model;
 pub mod my_service_model;
 pub mod response_model;
+pub mod template_model;
+pub mod trace_model;
@@ -1,15 +1,20 @@
 use chrono::Utc;
 use mongodb::bson::{DateTime, oid::ObjectId};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt;
-use std::{alloc::System, time::SystemTime};
-use utoipa::{IntoParams, ToSchema};
+use std::time::SystemTime;
+use utoipa::ToSchema;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct MyService {
+    ///Extended JSON, `{"$oid": "697dff2cbfc969fe967002b7"}`
+    #[schema(value_type = Object)]
     pub _id: ObjectId,
     pub name: String,
     pub description: Option<String>,
+    ///Extended JSON, `{"$date": {"$numberLong": "1717063200000"}}`
+    #[schema(value_type = Object)]
     pub onboarded_datetime: DateTime,
 }
 
@@ -46,6 +51,17 @@ impl fmt::Display for MyServiceView {
     }
 }
 
+impl From<MyService> for MyServiceView {
+    fn from(service: MyService) -> Self {
+        MyServiceView {
+            service_id: Some(service._id.to_hex()),
+            name: service.name,
+            description: service.description,
+            onboarded_datetime: service.onboarded_datetime.try_to_rfc3339_string().ok(),
+        }
+    }
+}
+
 impl MyServiceView {
     pub fn from_bulk(
         items: Vec<MyService>,
@@ -61,3 +77,153 @@ impl MyServiceView {
             .collect())
     }
 }
+
+///How a service is doing, judged from the lines it sent recently
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    ///Logging normally
+    Active,
+    ///No lines for longer than usual
+    Quiet,
+    ///No lines for so long the service may be down, or it never logged
+    Silent,
+    ///A large share of the recent lines are ERROR or FATAL
+    Erroring,
+}
+
+///Limits between the health statuses; every field has a default
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthThresholds {
+    ///Window the recent line and error counts cover
+    pub window_secs: u64,
+    ///Quiet once the last line is older than this
+    pub quiet_after_secs: u64,
+    ///Silent once the last line is older than this
+    pub silent_after_secs: u64,
+    ///Erroring when at least `min_errors` lines and this share of the window's lines are errors
+    pub error_ratio: f64,
+    pub min_errors: u64,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        HealthThresholds {
+            window_secs: 300,
+            quiet_after_secs: 300,
+            silent_after_secs: 1800,
+            error_ratio: 0.25,
+            min_errors: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HealthConfig {
+    ///Thresholds of every service not listed in `services`
+    #[serde(default)]
+    pub default: HealthThresholds,
+    ///Thresholds by service id
+    #[serde(default)]
+    pub services: HashMap<String, HealthThresholds>,
+}
+
+impl HealthConfig {
+    pub fn thresholds_for(&self, service_id: &ObjectId) -> &HealthThresholds {
+        self.services
+            .get(&service_id.to_hex())
+            .unwrap_or(&self.default)
+    }
+}
+
+///Lines a service sent, as counted by the database
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ServiceActivity {
+    pub last_log_at: Option<DateTime>,
+    pub recent_lines: u64,
+    pub recent_errors: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ServiceHealth {
+    pub status: HealthStatus,
+    ///RFC 3339 time of the newest line
+    pub last_log_at: Option<String>,
+    pub seconds_since_last_log: Option<i64>,
+    ///Lines and ERROR or FATAL lines within the last `window_secs`
+    pub recent_lines: u64,
+    pub recent_errors: u64,
+    pub window_secs: u64,
+}
+
+impl ServiceHealth {
+    pub fn from_activity(
+        activity: &ServiceActivity,
+        thresholds: &HealthThresholds,
+        now: DateTime,
+    ) -> ServiceHealth {
+        let seconds_since_last_log = activity
+            .last_log_at
+            .map(|at| (now.timestamp_millis() - at.timestamp_millis()).max(0) / 1000);
+        let erroring = activity.recent_errors >= thresholds.min_errors
+            && activity.recent_errors as f64
+                >= thresholds.error_ratio * activity.recent_lines as f64;
+
+        let status = match seconds_since_last_log {
+            None => HealthStatus::Silent,
+            Some(secs) if secs > thresholds.silent_after_secs as i64 => HealthStatus::Silent,
+            Some(_) if erroring => HealthStatus::Erroring,
+            Some(secs) if secs > thresholds.quiet_after_secs as i64 => HealthStatus::Quiet,
+            Some(_) => HealthStatus::Active,
+        };
+
+        ServiceHealth {
+            status,
+            last_log_at: activity
+                .last_log_at
+                .map(|at| at.try_to_rfc3339_string().unwrap_or_default()),
+            seconds_since_last_log,
+            recent_lines: activity.recent_lines,
+            recent_errors: activity.recent_errors,
+            window_secs: thresholds.window_secs,
+        }
+    }
+}
+
+///A registered service with its current health, as `GET /api/services` sends it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MyServiceStatus {
+    #[serde(flatten)]
+    pub service: MyService,
+    pub health: ServiceHealth,
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health(last_log_secs_ago: Option<i64>, lines: u64, errors: u64) -> HealthStatus {
+        let now = DateTime::from_millis(1_760_000_000_000);
+        let activity = ServiceActivity {
+            last_log_at: last_log_secs_ago
+                .map(|secs| DateTime::from_millis(now.timestamp_millis() - secs * 1000)),
+            recent_lines: lines,
+            recent_errors: errors,
+        };
+        ServiceHealth::from_activity(&activity, &HealthThresholds::default(), now).status
+    }
+
+    #[test]
+    fn test_health_status() {
+        assert_eq!(health(Some(5), 120, 2), HealthStatus::Active);
+        assert_eq!(health(Some(5), 20, 10), HealthStatus::Erroring);
+        // a few errors in a busy service are not enough
+        assert_eq!(health(Some(5), 1000, 10), HealthStatus::Active);
+        assert_eq!(health(Some(600), 0, 0), HealthStatus::Quiet);
+        assert_eq!(health(Some(7200), 0, 0), HealthStatus::Silent);
+        assert_eq!(health(None, 0, 0), HealthStatus::Silent);
+    }
+}
@@ -0,0 +1,42 @@
+use mongodb::bson::{DateTime, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+///Message template learned from the lines of one service, e.g. `Connection to <IP> timed out after <NUM> ms`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogTemplate {
+    ///Stable id stored in `Log.template_id`
+    pub _id: String,
+    pub my_service_id: ObjectId,
+    ///Tokens of the template; `<*>` marks a position that varies between lines
+    pub template: String,
+    pub updated_at: DateTime,
+}
+
+///Filters of the pattern listing
+#[derive(Debug, Default, Deserialize, Serialize, Clone, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PatternQuery {
+    pub service_id: Option<String>,
+    ///RFC 3339 lower bound (inclusive) on `created_at`
+    pub from: Option<String>,
+    ///RFC 3339 upper bound (exclusive) on `created_at`
+    pub to: Option<String>,
+    ///Most templates to return, the most frequent first
+    pub limit: Option<i64>,
+}
+
+///One template with how often and when its lines occurred
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternView {
+    pub template_id: String,
+    pub service_id: String,
+    ///`None` when the template has not been saved yet
+    pub template: Option<String>,
+    ///Lines matching the template, repeats folded by deduplication included
+    pub count: i64,
+    pub first_seen: DateTime,
+    pub last_seen: DateTime,
+    ///A few of the matching lines, oldest first
+    pub samples: Vec<String>,
+}
@@ -0,0 +1,59 @@
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+use super::log_model::Log;
+
+///Every log line recorded for one trace, grouped by the service that wrote it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceView {
+    pub trace_id: String,
+    pub total: usize,
+    ///Services in the order they first logged for this trace
+    pub services: Vec<TraceServiceLogs>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceServiceLogs {
+    pub service_id: String,
+    pub service_name: Option<String>,
+    pub first_seen: DateTime,
+    pub last_seen: DateTime,
+    ///Lines ordered by `created_at`
+    pub logs: Vec<Log>,
+}
+
+impl TraceView {
+    ///Group logs already sorted by time into per-service buckets, keeping time order
+    pub fn from_logs(
+        trace_id: &str,
+        logs: Vec<Log>,
+        service_name: impl Fn(&str) -> Option<String>,
+    ) -> TraceView {
+        let total = logs.len();
+        let mut services: Vec<TraceServiceLogs> = Vec::new();
+
+        for log in logs {
+            let service_id = log.my_service_id.to_hex();
+
+            match services.iter_mut().find(|s| s.service_id == service_id) {
+                Some(group) => {
+                    group.last_seen = log.created_at;
+                    group.logs.push(log);
+                }
+                None => services.push(TraceServiceLogs {
+                    service_name: service_name(&service_id),
+                    service_id,
+                    first_seen: log.created_at,
+                    last_seen: log.created_at,
+                    logs: vec![log],
+                }),
+            }
+        }
+
+        TraceView {
+            trace_id: trace_id.to_string(),
+            total,
+            services,
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use mongodb::bson::{DateTime, oid::ObjectId};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::models::log_model::{Log, LogLevel};
+
+///Most distinct lines remembered at once; new lines beyond it are stored without collapsing
+const MAX_ENTRIES: usize = 50_000;
+
+///Numbers, and hex runs such as ids and hashes, which differ between otherwise identical lines
+static VARIABLE_PARTS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b[0-9a-fA-F]{8,}\b|\d+").expect("the variable parts pattern is valid")
+});
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DedupRule {
+    ///How long repeats of a line are folded into its first occurrence
+    #[serde(default = "default_window_secs")]
+    pub window_secs: u64,
+    ///Ignore numbers and hex ids when comparing lines
+    #[serde(default = "default_normalize")]
+    pub normalize: bool,
+}
+
+fn default_window_secs() -> u64 {
+    60
+}
+
+fn default_normalize() -> bool {
+    true
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct DedupConfig {
+    ///Rule of every service not listed in `services`; no deduplication when absent
+    pub default: Option<DedupRule>,
+    ///Rules by service id
+    #[serde(default)]
+    pub services: HashMap<String, DedupRule>,
+}
+
+///Repeats of an already stored log to add to its count
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatUpdate {
+    pub log_id: ObjectId,
+    pub repeats: i64,
+    pub last_seen: DateTime,
+}
+
+type Key = (ObjectId, LogLevel, String);
+
+struct Entry {
+    log_id: ObjectId,
+    window_end: DateTime,
+    ///Repeats not yet added to the stored log
+    pending: i64,
+    last_seen: DateTime,
+}
+
+///Collapses identical lines of a service within a time window into the first one, which then
+///carries the repeat count and first/last seen times
+#[derive(Default)]
+pub struct Deduplicator {
+    config: DedupConfig,
+    entries: Mutex<HashMap<Key, Entry>>,
+}
+
+impl std::fmt::Debug for Deduplicator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Deduplicator")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl Deduplicator {
+    pub fn new(config: DedupConfig) -> Self {
+        Deduplicator {
+            config,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.config.default.is_none() && self.config.services.is_empty()
+    }
+
+    fn rule_for(&self, service_id: &ObjectId) -> Option<&DedupRule> {
+        self.config
+            .services
+            .get(&service_id.to_hex())
+            .or(self.config.default.as_ref())
+    }
+
+    ///Drop the repeats in `logs`. A repeat of a line in the same batch is counted on that line;
+    ///a repeat of a line stored earlier is kept for `take_updates`.
+    pub fn collapse(&self, logs: Vec<Log>) -> Vec<Log> {
+        if self.is_empty() {
+            return logs;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        let mut kept: Vec<Log> = Vec::with_capacity(logs.len());
+        let mut in_batch: HashMap<ObjectId, usize> = HashMap::new();
+
+        for mut log in logs {
+            let Some(rule) = self.rule_for(&log.my_service_id) else {
+                kept.push(log);
+                continue;
+            };
+
+            let text = if rule.normalize {
+                VARIABLE_PARTS
+                    .replace_all(&log.line_content, "#")
+                    .into_owned()
+            } else {
+                log.line_content.clone()
+            };
+            let key: Key = (log.my_service_id, log.level, text);
+
+            if let Some(entry) = entries.get_mut(&key)
+                && log.created_at < entry.window_end
+            {
+                let last_seen = entry.last_seen.max(log.created_at);
+                match in_batch.get(&entry.log_id) {
+                    Some(index) => {
+                        let first = &mut kept[*index];
+                        first.repeat_count = Some(first.repeat_count.unwrap_or(1) + 1);
+                        first.last_seen = Some(last_seen);
+                    }
+                    None => entry.pending += 1,
+                }
+                entry.last_seen = last_seen;
+                continue;
+            }
+
+            if entries.len() >= MAX_ENTRIES && !entries.contains_key(&key) {
+                kept.push(log);
+                continue;
+            }
+
+            let window = Duration::from_secs(rule.window_secs);
+            entries.insert(
+                key,
+                Entry {
+                    log_id: log._id,
+                    window_end: DateTime::from_millis(
+                        log.created_at.timestamp_millis() + window.as_millis() as i64,
+                    ),
+                    pending: 0,
+                    last_seen: log.created_at,
+                },
+            );
+            log.repeat_count = Some(1);
+            log.first_seen = Some(log.created_at);
+            log.last_seen = Some(log.created_at);
+            in_batch.insert(log._id, kept.len());
+            kept.push(log);
+        }
+
+        kept
+    }
+
+    ///Forget the lines first seen as the logs `ids`, which could not be stored, so their next
+    ///occurrence is stored instead of counted as a repeat of a line that does not exist
+    pub fn forget(&self, ids: impl IntoIterator<Item = ObjectId>) {
+        if self.is_empty() {
+            return;
+        }
+
+        let ids: HashSet<ObjectId> = ids.into_iter().collect();
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|_, entry| !ids.contains(&entry.log_id));
+    }
+
+    ///Counts to add to logs stored by earlier batches. Lines whose window has closed are
+    ///forgotten, so their next occurrence starts a new record.
+    pub fn take_updates(&self) -> Vec<RepeatUpdate> {
+        let now = DateTime::now();
+        let mut entries = self.entries.lock().unwrap();
+
+        let updates = entries
+            .values_mut()
+            .filter(|entry| entry.pending > 0)
+            .map(|entry| RepeatUpdate {
+                log_id: entry.log_id,
+                repeats: std::mem::take(&mut entry.pending),
+                last_seen: entry.last_seen,
+            })
+            .collect();
+
+        entries.retain(|_, entry| entry.window_end > now);
+        updates
+    }
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(service_id: ObjectId, line: &str, at_secs: i64) -> Log {
+        Log {
+            created_at: DateTime::from_millis(1_760_000_000_000 + at_secs * 1000),
+            ..Log::sample(service_id, LogLevel::ERROR, line)
+        }
+    }
+
+    fn deduplicator() -> Deduplicator {
+        Deduplicator::new(DedupConfig {
+            default: Some(DedupRule {
+                window_secs: 60,
+                normalize: true,
+            }),
+            services: HashMap::new(),
+        })
+    }
+
+    #[test]
+    fn test_collapse_within_batch() {
+        let deduplicator = deduplicator();
+        let service = ObjectId::new();
+
+        let logs = deduplicator.collapse(vec![
+            log(service, "Connection refused to 10.0.0.1:5432", 0),
+            log(service, "Connection refused to 10.0.0.2:5432", 5),
+            log(service, "Disk full", 6),
+            log(service, "Connection refused to 10.0.0.1:5432", 30),
+            // outside the window: a new record
+            log(service, "Connection refused to 10.0.0.1:5432", 61),
+        ]);
+
+        assert_eq!(logs.len(), 3);
+        assert_eq!(logs[0].repeat_count, Some(3));
+        assert_eq!(logs[0].first_seen, Some(logs[0].created_at));
+        assert_eq!(
+            logs[0].last_seen.unwrap().timestamp_millis() - logs[0].created_at.timestamp_millis(),
+            30_000
+        );
+        assert_eq!(logs[1].line_content, "Disk full");
+        assert_eq!(logs[2].repeat_count, Some(1));
+        assert!(deduplicator.take_updates().is_empty());
+    }
+
+    #[test]
+    fn test_repeats_across_batches_become_updates() {
+        let deduplicator = deduplicator();
+        let service = ObjectId::new();
+
+        let first = deduplicator.collapse(vec![log(service, "Connection refused", 0)]);
+        let second = deduplicator.collapse(vec![
+            log(service, "Connection refused", 10),
+            log(service, "Connection refused", 20),
+        ]);
+
+        assert!(second.is_empty());
+        let updates = deduplicator.take_updates();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].log_id, first[0]._id);
+        assert_eq!(updates[0].repeats, 2);
+        // the test window closed long ago, so the line is forgotten
+        assert!(deduplicator.take_updates().is_empty());
+        assert_eq!(
+            deduplicator
+                .collapse(vec![log(service, "Connection refused", 30)])
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_other_services_untouched() {
+        let deduplicator = Deduplicator::new(DedupConfig {
+            default: None,
+            services: HashMap::from([(
+                ObjectId::new().to_hex(),
+                DedupRule {
+                    window_secs: 60,
+                    normalize: false,
+                },
+            )]),
+        });
+        let service = ObjectId::new();
+
+        let logs = deduplicator.collapse(vec![log(service, "same", 0), log(service, "same", 1)]);
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].repeat_count, None);
+    }
+
+    #[test]
+    fn test_forget_lines_that_were_not_stored() {
+        let deduplicator = deduplicator();
+        let service = ObjectId::new();
+
+        let failed = deduplicator.collapse(vec![log(service, "Connection refused", 0)]);
+        deduplicator.forget(failed.iter().map(|log| log._id));
+
+        let retried = deduplicator.collapse(vec![log(service, "Connection refused", 10)]);
+        assert_eq!(retried.len(), 1);
+        assert_eq!(retried[0].repeat_count, Some(1));
+        assert!(deduplicator.take_updates().is_empty());
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::templates::mask;
+use crate::models::issue_model::IssueOccurrence;
+use crate::models::log_model::{Log, LogLevel};
+
+///Stack frames, innermost first, that take part in the fingerprint
+const TOP_FRAMES: usize = 3;
+
+const MAX_TITLE_CHARS: usize = 200;
+
+///Qualified class names such as `java.net.ConnectException` or `ValueError`
+static EXCEPTION_CLASS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b((?:[A-Za-z_$][\w$]*\.)*[A-Z][\w$]*(?:Exception|Error|Throwable))\b")
+        .expect("the exception class pattern is valid")
+});
+
+///`at com.acme.Repo.find(Repo.java:42)` (Java, JavaScript) and
+///`File "app.py", line 10, in handler` (Python, innermost last)
+static FRAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?m)^\s*at\s+(?P<at>[^\s(]+)|^\s*File "(?P<file>[^"]+)", line \d+, in (?P<function>\S+)"#)
+        .expect("the frame pattern is valid")
+});
+
+static LINE_NUMBERS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?::\d+)+$").expect("the line numbers pattern is valid"));
+
+///What identifies an error regardless of its variable parts
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorSignature {
+    pub fingerprint: String,
+    pub title: String,
+    pub exception_class: Option<String>,
+}
+
+fn attribute<'a>(log: &'a Log, key: &str) -> Option<&'a str> {
+    log.attributes.as_ref()?.get(key).and_then(Value::as_str)
+}
+
+///Fingerprint of an ERROR or FATAL line from its template, exception class and top stack
+///frames. A line without a template stands for itself by its masked first line. OTLP
+///`exception.type` and `exception.stacktrace` attributes are used when present.
+pub fn signature(log: &Log) -> Option<ErrorSignature> {
+    if log.level < LogLevel::ERROR {
+        return None;
+    }
+
+    let mut text = log.line_content.clone();
+    if let Some(stacktrace) = attribute(log, "exception_stacktrace") {
+        text.push('\n');
+        text.push_str(stacktrace);
+    }
+
+    let title: String = mask(text.lines().next().unwrap_or_default().trim())
+        .chars()
+        .take(MAX_TITLE_CHARS)
+        .collect();
+    let exception_class = attribute(log, "exception_type")
+        .map(str::to_string)
+        .or_else(|| {
+            EXCEPTION_CLASS
+                .captures(&text)
+                .map(|captures| captures[1].to_string())
+        });
+
+    let mut frames: Vec<String> = Vec::new();
+    let mut python_frames: Vec<String> = Vec::new();
+    for captures in FRAME.captures_iter(&text) {
+        if let Some(at) = captures.name("at") {
+            frames.push(LINE_NUMBERS.replace(at.as_str(), "").into_owned());
+        } else {
+            python_frames.push(format!("{}:{}", &captures["file"], &captures["function"]));
+        }
+    }
+    frames.extend(python_frames.into_iter().rev());
+    frames.truncate(TOP_FRAMES);
+
+    // lines differing in parts the template miner generalised share their template
+    let message = log.template_id.as_deref().unwrap_or(&title);
+    let digest = Sha256::new()
+        .chain_update(message.as_bytes())
+        .chain_update(b"\n")
+        .chain_update(exception_class.as_deref().unwrap_or_default().as_bytes())
+        .chain_update(b"\n")
+        .chain_update(frames.join("\n").as_bytes())
+        .finalize();
+
+    Some(ErrorSignature {
+        fingerprint: hex::encode(&digest[..8]),
+        title,
+        exception_class,
+    })
+}
+
+///Group the fingerprinted logs of a batch into one occurrence record per issue
+pub fn occurrences(logs: &[Log]) -> Vec<IssueOccurrence> {
+    let mut by_fingerprint: HashMap<String, IssueOccurrence> = HashMap::new();
+
+    for log in logs.iter().filter(|log| log.fingerprint.is_some()) {
+        let Some(signature) = signature(log) else {
+            continue;
+        };
+        let count = log.repeat_count.unwrap_or(1);
+        let last_seen = log.last_seen.unwrap_or(log.created_at);
+
+        by_fingerprint
+            .entry(signature.fingerprint.clone())
+            .and_modify(|occurrence| {
+                occurrence.count += count;
+                occurrence.level = occurrence.level.max(log.level);
+                occurrence.first_seen = occurrence.first_seen.min(log.created_at);
+                occurrence.last_seen = occurrence.last_seen.max(last_seen);
+                if !occurrence.services.contains(&log.my_service_id) {
+                    occurrence.services.push(log.my_service_id);
+                }
+            })
+            .or_insert_with(|| IssueOccurrence {
+                fingerprint: signature.fingerprint,
+                title: signature.title,
+                level: log.level,
+                exception_class: signature.exception_class,
+                count,
+                first_seen: log.created_at,
+                last_seen,
+                services: vec![log.my_service_id],
+            });
+    }
+
+    by_fingerprint.into_values().collect()
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::oid::ObjectId;
+
+    #[test]
+    fn test_signature_of_java_stack_trace() {
+        let service = ObjectId::new();
+        let first = Log::sample(
+            service,
+            LogLevel::ERROR,
+            "Lookup of order 42 failed\njava.lang.IllegalStateException: missing\n\tat com.acme.Repo.find(Repo.java:42)\n\tat com.acme.Api.get(Api.java:7)",
+        );
+        let second = Log::sample(
+            ObjectId::new(),
+            LogLevel::FATAL,
+            "Lookup of order 977 failed\njava.lang.IllegalStateException: gone\n\tat com.acme.Repo.find(Repo.java:44)\n\tat com.acme.Api.get(Api.java:9)",
+        );
+        let other_frames = Log::sample(
+            service,
+            LogLevel::ERROR,
+            "Lookup of order 42 failed\njava.lang.IllegalStateException: missing\n\tat com.acme.Cache.find(Cache.java:3)",
+        );
+
+        let signature = signature(&first).unwrap();
+        assert_eq!(signature.title, "Lookup of order <NUM> failed");
+        assert_eq!(
+            signature.exception_class.as_deref(),
+            Some("java.lang.IllegalStateException")
+        );
+        assert_eq!(
+            Some(signature.fingerprint.clone()),
+            super::signature(&second).map(|s| s.fingerprint)
+        );
+        assert_ne!(
+            Some(signature.fingerprint),
+            super::signature(&other_frames).map(|s| s.fingerprint)
+        );
+        assert!(super::signature(&Log::sample(service, LogLevel::WARN, "Lookup failed")).is_none());
+    }
+
+    #[test]
+    fn test_signature_uses_the_template() {
+        let service = ObjectId::new();
+        let mut alice = Log::sample(service, LogLevel::ERROR, "Login of alice refused");
+        let mut bob = Log::sample(service, LogLevel::ERROR, "Login of bob refused");
+        assert_ne!(
+            signature(&alice).unwrap().fingerprint,
+            signature(&bob).unwrap().fingerprint
+        );
+
+        alice.template_id = Some(String::from("3f9a0c1d5e7b2a46"));
+        bob.template_id = alice.template_id.clone();
+        assert_eq!(
+            signature(&alice).unwrap().fingerprint,
+            signature(&bob).unwrap().fingerprint
+        );
+        assert_eq!(signature(&bob).unwrap().title, "Login of bob refused");
+    }
+
+    #[test]
+    fn test_python_frames_innermost_first() {
+        let service = ObjectId::new();
+        let trace = "Traceback (most recent call last):\n  File \"app.py\", line 10, in handler\n  File \"db.py\", line 3, in query\nValueError: bad id";
+        let mut with_attribute = Log::sample(service, LogLevel::ERROR, "request failed");
+        with_attribute.attributes = Some(HashMap::from([(
+            String::from("exception_stacktrace"),
+            Value::from(trace),
+        )]));
+
+        let signature = signature(&with_attribute).unwrap();
+        assert_eq!(signature.exception_class.as_deref(), Some("ValueError"));
+        assert_eq!(signature.title, "request failed");
+    }
+
+    #[test]
+    fn test_occurrences_group_services() {
+        let (a, b) = (ObjectId::new(), ObjectId::new());
+        let mut logs = vec![
+            Log::sample(a, LogLevel::ERROR, "Timeout calling 10.0.0.1"),
+            Log::sample(b, LogLevel::ERROR, "Timeout calling 10.0.0.2"),
+            Log::sample(a, LogLevel::INFO, "Started"),
+        ];
+        logs[1].repeat_count = Some(4);
+        for log in logs.iter_mut() {
+            log.fingerprint = signature(log).map(|s| s.fingerprint);
+        }
+
+        let occurrences = occurrences(&logs);
+        assert_eq!(occurrences.len(), 1);
+        assert_eq!(occurrences[0].count, 5);
+        assert_eq!(occurrences[0].services, vec![a, b]);
+        assert_eq!(occurrences[0].title, "Timeout calling <IP>");
+    }
+}
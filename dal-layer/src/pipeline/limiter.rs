@@ -0,0 +1,336 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::models::log_model::{LogLevel, LogRequest};
+
+///What to do with lines over a service's limit
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitPolicy {
+    ///Drop every line over the limit
+    #[default]
+    Drop,
+    ///Keep WARN and above, and one in `sample_rate` of the lower levels
+    Sample,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ServiceLimit {
+    ///Sustained lines per second; bursts of up to one second's worth are allowed
+    pub lines_per_sec: Option<u32>,
+    ///Bytes of line_content per UTC day
+    pub bytes_per_day: Option<u64>,
+    #[serde(default)]
+    pub policy: LimitPolicy,
+    #[serde(default = "default_sample_rate")]
+    pub sample_rate: u64,
+}
+
+fn default_sample_rate() -> u64 {
+    10
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct LimitConfig {
+    ///Limit of every service not listed in `services`
+    pub default: Option<ServiceLimit>,
+    ///Limits by service id
+    #[serde(default)]
+    pub services: HashMap<String, ServiceLimit>,
+}
+
+///Why a line was over its limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitReason {
+    LinesPerSec,
+    BytesPerDay,
+}
+
+///Lines of one service dropped since the last report
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropReport {
+    pub service_id: String,
+    pub dropped: u64,
+    pub since: DateTime<Utc>,
+    pub reason: LimitReason,
+}
+
+impl DropReport {
+    ///Synthetic WARN line stored in place of the dropped ones so the gap is visible
+    pub fn to_request(&self) -> LogRequest {
+        let reason = match self.reason {
+            LimitReason::LinesPerSec => "lines_per_sec",
+            LimitReason::BytesPerDay => "bytes_per_day",
+        };
+
+        LogRequest {
+            my_service_id: self.service_id.clone(),
+            level: LogLevel::WARN,
+            line_content: format!(
+                "{} lines dropped by the {reason} ingestion limit since {}",
+                self.dropped,
+                self.since.to_rfc3339()
+            ),
+            created_at: Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            attributes: Some(HashMap::from([
+                (String::from("dropped_lines"), Value::from(self.dropped)),
+                (String::from("limit_reason"), Value::from(reason)),
+            ])),
+            host: None,
+            source_file: None,
+            line_offset: None,
+            trace_id: None,
+            span_id: None,
+        }
+    }
+}
+
+///Totals of one service since start up
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LimitStats {
+    pub accepted: u64,
+    pub dropped: u64,
+    ///Lines of the current UTC day counted against bytes_per_day
+    pub bytes_today: u64,
+}
+
+struct ServiceState {
+    tokens: f64,
+    refilled_at: Instant,
+    day: NaiveDate,
+    bytes_today: u64,
+    sampled: u64,
+    pending: Option<DropReport>,
+    stats: LimitStats,
+}
+
+///Per-service rate and volume limits so one noisy service cannot starve the others
+#[derive(Default)]
+pub struct Limiter {
+    config: LimitConfig,
+    state: Mutex<HashMap<String, ServiceState>>,
+}
+
+impl std::fmt::Debug for Limiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Limiter")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl Limiter {
+    pub fn new(config: LimitConfig) -> Self {
+        Limiter {
+            config,
+            state: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.config.default.is_none() && self.config.services.is_empty()
+    }
+
+    fn limit_for(&self, service_id: &str) -> Option<&ServiceLimit> {
+        self.config
+            .services
+            .get(service_id)
+            .or(self.config.default.as_ref())
+    }
+
+    ///Whether a line of `bytes` at `level` from `service_id` may be stored
+    pub fn admit(&self, service_id: &str, level: LogLevel, bytes: usize) -> bool {
+        self.admit_at(service_id, level, bytes, Instant::now(), Utc::now())
+    }
+
+    fn admit_at(
+        &self,
+        service_id: &str,
+        level: LogLevel,
+        bytes: usize,
+        now: Instant,
+        wall_clock: DateTime<Utc>,
+    ) -> bool {
+        let Some(limit) = self.limit_for(service_id) else {
+            return true;
+        };
+
+        let mut states = self.state.lock().unwrap();
+        let state = states
+            .entry(service_id.to_string())
+            .or_insert_with(|| ServiceState {
+                tokens: limit.lines_per_sec.unwrap_or(0) as f64,
+                refilled_at: now,
+                day: wall_clock.date_naive(),
+                bytes_today: 0,
+                sampled: 0,
+                pending: None,
+                stats: LimitStats::default(),
+            });
+
+        if let Some(rate) = limit.lines_per_sec {
+            let elapsed = now.duration_since(state.refilled_at).as_secs_f64();
+            state.tokens = (state.tokens + elapsed * rate as f64).min(rate as f64);
+            state.refilled_at = now;
+        }
+        if state.day != wall_clock.date_naive() {
+            state.day = wall_clock.date_naive();
+            state.bytes_today = 0;
+        }
+
+        let bytes = bytes as u64;
+        let over = if limit.lines_per_sec.is_some() && state.tokens < 1.0 {
+            Some(LimitReason::LinesPerSec)
+        } else if limit
+            .bytes_per_day
+            .is_some_and(|quota| state.bytes_today + bytes > quota)
+        {
+            Some(LimitReason::BytesPerDay)
+        } else {
+            None
+        };
+
+        let keep = match over {
+            None => true,
+            Some(_) if limit.policy == LimitPolicy::Drop => false,
+            Some(_) if level >= LogLevel::WARN => true,
+            Some(_) => {
+                state.sampled += 1;
+                limit.sample_rate <= 1 || state.sampled % limit.sample_rate == 1
+            }
+        };
+
+        if keep {
+            if limit.lines_per_sec.is_some() {
+                state.tokens = (state.tokens - 1.0).max(0.0);
+            }
+            state.bytes_today += bytes;
+            state.stats.accepted += 1;
+        } else {
+            state.stats.dropped += 1;
+            let report = state.pending.get_or_insert_with(|| DropReport {
+                service_id: service_id.to_string(),
+                dropped: 0,
+                since: wall_clock,
+                reason: LimitReason::LinesPerSec,
+            });
+            report.dropped += 1;
+            report.reason = over.unwrap_or(LimitReason::LinesPerSec);
+        }
+        state.stats.bytes_today = state.bytes_today;
+
+        keep
+    }
+
+    ///Take the drops recorded since the last call, one report per service
+    pub fn drop_reports(&self) -> Vec<DropReport> {
+        let mut states = self.state.lock().unwrap();
+        states
+            .values_mut()
+            .filter_map(|state| state.pending.take())
+            .collect()
+    }
+
+    pub fn stats(&self) -> BTreeMap<String, LimitStats> {
+        let states = self.state.lock().unwrap();
+        states
+            .iter()
+            .map(|(service_id, state)| (service_id.clone(), state.stats.clone()))
+            .collect()
+    }
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn limiter(limit: ServiceLimit) -> Limiter {
+        Limiter::new(LimitConfig {
+            default: Some(limit),
+            services: HashMap::new(),
+        })
+    }
+
+    #[test]
+    fn test_lines_per_sec_drop() {
+        let limiter = limiter(ServiceLimit {
+            lines_per_sec: Some(2),
+            ..Default::default()
+        });
+        let start = Instant::now();
+        let wall_clock = Utc::now();
+
+        let admitted: Vec<bool> = (0..4)
+            .map(|_| limiter.admit_at("svc", LogLevel::ERROR, 10, start, wall_clock))
+            .collect();
+        assert_eq!(admitted, vec![true, true, false, false]);
+
+        // one second later the bucket is full again
+        let later = start + Duration::from_secs(1);
+        assert!(limiter.admit_at("svc", LogLevel::INFO, 10, later, wall_clock));
+
+        let reports = limiter.drop_reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].dropped, 2);
+        assert_eq!(reports[0].reason, LimitReason::LinesPerSec);
+        assert!(limiter.drop_reports().is_empty());
+
+        let request = reports[0].to_request();
+        assert_eq!(request.level, LogLevel::WARN);
+        assert!(request.line_content.starts_with("2 lines dropped"));
+    }
+
+    #[test]
+    fn test_sample_keeps_warnings() {
+        let limiter = limiter(ServiceLimit {
+            bytes_per_day: Some(100),
+            policy: LimitPolicy::Sample,
+            sample_rate: 3,
+            ..Default::default()
+        });
+        let now = Instant::now();
+        let wall_clock = Utc::now();
+
+        assert!(limiter.admit_at("svc", LogLevel::INFO, 100, now, wall_clock));
+
+        let debug: Vec<bool> = (0..6)
+            .map(|_| limiter.admit_at("svc", LogLevel::DEBUG, 10, now, wall_clock))
+            .collect();
+        assert_eq!(debug, vec![true, false, false, true, false, false]);
+        assert!(limiter.admit_at("svc", LogLevel::WARN, 10, now, wall_clock));
+
+        // the quota starts over the next day
+        let tomorrow = wall_clock + chrono::Duration::days(1);
+        assert!(limiter.admit_at("svc", LogLevel::DEBUG, 10, now, tomorrow));
+
+        let stats = limiter.stats();
+        assert_eq!(stats["svc"].dropped, 4);
+        assert_eq!(stats["svc"].bytes_today, 10);
+    }
+
+    #[test]
+    fn test_unlimited_service() {
+        let limiter = Limiter::new(LimitConfig {
+            default: None,
+            services: HashMap::from([(
+                String::from("noisy"),
+                ServiceLimit {
+                    lines_per_sec: Some(1),
+                    ..Default::default()
+                },
+            )]),
+        });
+
+        assert!((0..10).all(|_| limiter.admit("quiet", LogLevel::DEBUG, 10)));
+        assert!(limiter.admit("noisy", LogLevel::DEBUG, 10));
+        assert!(!limiter.admit("noisy", LogLevel::DEBUG, 10));
+    }
+}
@@ -0,0 +1,110 @@
+//! Stages every log passes through before it is stored, shared by the collector and the API
+//! ingest routes.
+
+pub mod dedup;
+pub mod issues;
+pub mod limiter;
+pub mod redaction;
+pub mod templates;
+
+use crate::models::log_model::{Log, LogRequest};
+use dedup::{DedupConfig, Deduplicator};
+use limiter::{LimitConfig, Limiter};
+use mongodb::bson::oid::ObjectId;
+use redaction::{RedactionConfig, RedactionError, Redactor};
+use templates::TemplateMiner;
+
+///What the pipeline decided about one line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Keep,
+    ///Over the service's ingestion limit
+    Limited,
+    ///Matched a drop redaction rule
+    Redacted,
+}
+
+///Limits first, so lines that will be dropped anyway are not redacted, then redaction.
+///Deduplication and template mining run last, on the converted logs, so they see the
+///redacted lines.
+#[derive(Debug, Default)]
+pub struct Pipeline {
+    pub limiter: Limiter,
+    pub redactor: Redactor,
+    pub dedup: Deduplicator,
+    pub templates: TemplateMiner,
+}
+
+impl Pipeline {
+    pub fn new(
+        redaction: Option<&RedactionConfig>,
+        limits: Option<&LimitConfig>,
+        dedup: Option<&DedupConfig>,
+    ) -> Result<Self, RedactionError> {
+        Ok(Pipeline {
+            limiter: Limiter::new(limits.cloned().unwrap_or_default()),
+            dedup: Deduplicator::new(dedup.cloned().unwrap_or_default()),
+            templates: TemplateMiner::default(),
+            redactor: match redaction {
+                Some(redaction) => Redactor::new(redaction)?,
+                None => Redactor::default(),
+            },
+        })
+    }
+
+    pub fn process_log(&self, log: &mut Log) -> Verdict {
+        let service_id = log.my_service_id.to_hex();
+        if !self
+            .limiter
+            .admit(&service_id, log.level, log.line_content.len())
+        {
+            Verdict::Limited
+        } else if !self.redactor.redact_log(log) {
+            Verdict::Redacted
+        } else {
+            Verdict::Keep
+        }
+    }
+
+    pub fn process_request(&self, request: &mut LogRequest) -> Verdict {
+        if !self.limiter.admit(
+            &request.my_service_id,
+            request.level,
+            request.line_content.len(),
+        ) {
+            Verdict::Limited
+        } else if !self.redactor.redact_request(request) {
+            Verdict::Redacted
+        } else {
+            Verdict::Keep
+        }
+    }
+
+    ///Collapse the repeats among converted logs, cluster the rest into templates and
+    ///fingerprint the errors
+    pub fn finish_logs(&self, logs: Vec<Log>) -> Vec<Log> {
+        let mut logs = self.dedup.collapse(logs);
+        self.templates.assign_all(&mut logs);
+        for log in logs.iter_mut() {
+            log.fingerprint = issues::signature(log).map(|signature| signature.fingerprint);
+        }
+        logs
+    }
+
+    ///Undo what `finish_logs` remembered of the logs `ids` when they could not be stored
+    pub fn forget(&self, ids: impl IntoIterator<Item = ObjectId>) {
+        self.dedup.forget(ids);
+    }
+
+    ///Drop the requests the pipeline rejects and add a line for every service that lost lines
+    ///to its limit since the last call
+    pub fn process_requests(&self, requests: &mut Vec<LogRequest>) {
+        requests.retain_mut(|request| self.process_request(request) == Verdict::Keep);
+        requests.extend(
+            self.limiter
+                .drop_reports()
+                .iter()
+                .map(|report| report.to_request()),
+        );
+    }
+}
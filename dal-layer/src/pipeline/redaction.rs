@@ -0,0 +1,448 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use hmac::{Hmac, Mac};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::Sha256;
+
+use crate::models::log_model::{Log, LogRequest};
+
+///What happens to a match of a redaction rule
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactionAction {
+    ///Replace the match with `[REDACTED:<rule>]`
+    #[default]
+    Mask,
+    ///Replace the match with a keyed hash so the same value can still be joined across logs
+    Hash,
+    ///Do not store the line at all
+    Drop,
+}
+
+///Built-in detectors of common sensitive values
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Detector {
+    ///13 to 19 digit card numbers that pass the Luhn check
+    CreditCard,
+    Email,
+    Jwt,
+    ///The token after `Bearer` in an Authorization header
+    BearerToken,
+    ///IPv4 and IPv6 addresses
+    Ip,
+    ///International (`+44 20 7946 0958`) and North American (`(555) 123-4567`) numbers
+    Phone,
+}
+
+impl Detector {
+    pub const ALL: [Detector; 6] = [
+        Detector::CreditCard,
+        Detector::Email,
+        Detector::Jwt,
+        Detector::BearerToken,
+        Detector::Ip,
+        Detector::Phone,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Detector::CreditCard => "credit_card",
+            Detector::Email => "email",
+            Detector::Jwt => "jwt",
+            Detector::BearerToken => "bearer_token",
+            Detector::Ip => "ip",
+            Detector::Phone => "phone",
+        }
+    }
+
+    fn pattern(self) -> &'static str {
+        match self {
+            Detector::CreditCard => r"\b\d(?:[ -]?\d){12,18}\b",
+            Detector::Email => r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b",
+            Detector::Jwt => r"\beyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]*",
+            Detector::BearerToken => r"(?i)\bbearer\s+([A-Za-z0-9._~+/-]+=*)",
+            Detector::Ip => {
+                r"\b(?:(?:25[0-5]|2[0-4]\d|1?\d?\d)\.){3}(?:25[0-5]|2[0-4]\d|1?\d?\d)\b|\b(?:[0-9A-Fa-f]{1,4}:){7}[0-9A-Fa-f]{1,4}\b|\b(?:[0-9A-Fa-f]{1,4}:){1,6}:(?:[0-9A-Fa-f]{1,4}(?::[0-9A-Fa-f]{1,4})*)?\b"
+            }
+            Detector::Phone => {
+                r"\+\d{1,3}[ .-]?(?:\(?\d{1,4}\)?[ .-]?){2,4}\d{2,4}\b|\(?\b\d{3}\)?[ .-]\d{3}[ .-]\d{4}\b"
+            }
+        }
+    }
+}
+
+///A custom rule. When the pattern has a capture group only the first group is redacted, so
+///`password=(\S+)` keeps the `password=` prefix readable.
+#[derive(Deserialize, Debug, Clone)]
+pub struct RedactionRule {
+    pub name: String,
+    pub pattern: String,
+    ///Defaults to the config's `action`
+    pub action: Option<RedactionAction>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct RedactionConfig {
+    ///Built-in detectors to run; none unless listed
+    #[serde(default)]
+    pub detectors: Vec<Detector>,
+    #[serde(default)]
+    pub rules: Vec<RedactionRule>,
+    ///Action of the detectors and of rules that do not set their own
+    #[serde(default)]
+    pub action: RedactionAction,
+    ///Key mixed into hashed values so they cannot be reversed by hashing guesses
+    #[serde(default)]
+    pub hash_key: String,
+}
+
+///Why a redaction config was rejected
+#[derive(Debug)]
+pub enum RedactionError {
+    Pattern(regex::Error),
+    ///A detector or rule hashes matches but `hash_key` is empty
+    MissingHashKey,
+}
+
+impl std::fmt::Display for RedactionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RedactionError::Pattern(err) => write!(f, "invalid rule pattern: {err}"),
+            RedactionError::MissingHashKey => {
+                write!(f, "the hash action needs a non-empty hash_key")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RedactionError {}
+
+impl From<regex::Error> for RedactionError {
+    fn from(err: regex::Error) -> Self {
+        RedactionError::Pattern(err)
+    }
+}
+
+struct CompiledRule {
+    name: String,
+    regex: Regex,
+    action: RedactionAction,
+    ///Extra check a match must pass, e.g. Luhn for card numbers
+    validate: Option<fn(&str) -> bool>,
+    hits: AtomicU64,
+}
+
+///Applies the redaction rules to log lines and their string attributes before they are stored,
+///counting how often each rule fired
+#[derive(Default)]
+pub struct Redactor {
+    rules: Vec<CompiledRule>,
+    hash_key: String,
+}
+
+impl std::fmt::Debug for Redactor {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Redactor")
+            .field(
+                "rules",
+                &self.rules.iter().map(|rule| &rule.name).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl Redactor {
+    pub fn new(config: &RedactionConfig) -> Result<Self, RedactionError> {
+        let mut rules = Vec::new();
+
+        for detector in &config.detectors {
+            rules.push(CompiledRule {
+                name: detector.name().to_string(),
+                regex: Regex::new(detector.pattern())?,
+                action: config.action,
+                validate: (*detector == Detector::CreditCard)
+                    .then_some(passes_luhn as fn(&str) -> bool),
+                hits: AtomicU64::new(0),
+            });
+        }
+
+        for rule in &config.rules {
+            rules.push(CompiledRule {
+                name: rule.name.clone(),
+                regex: Regex::new(&rule.pattern)?,
+                action: rule.action.unwrap_or(config.action),
+                validate: None,
+                hits: AtomicU64::new(0),
+            });
+        }
+
+        let hashes = rules
+            .iter()
+            .any(|rule| rule.action == RedactionAction::Hash);
+        if hashes && config.hash_key.trim().is_empty() {
+            return Err(RedactionError::MissingHashKey);
+        }
+
+        Ok(Redactor {
+            rules,
+            hash_key: config.hash_key.clone(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    ///Number of matches per rule since start up
+    pub fn hits(&self) -> BTreeMap<String, u64> {
+        self.rules
+            .iter()
+            .map(|rule| (rule.name.clone(), rule.hits.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    ///Redact `text` in place. Returns false when a drop rule matched and the line must not be
+    ///stored.
+    pub fn redact_text(&self, text: &mut String) -> bool {
+        for rule in &self.rules {
+            let mut matched = 0;
+            let replaced = rule.regex.replace_all(text, |captures: &Captures| {
+                let whole = captures.get(0).map_or("", |m| m.as_str());
+                let target = captures.get(1).unwrap_or_else(|| captures.get(0).unwrap());
+
+                if rule
+                    .validate
+                    .is_some_and(|validate| !validate(target.as_str()))
+                {
+                    return whole.to_string();
+                }
+                matched += 1;
+
+                let start = target.start() - captures.get(0).unwrap().start();
+                let end = start + target.len();
+                format!(
+                    "{}{}{}",
+                    &whole[..start],
+                    self.replacement(rule, target.as_str()),
+                    &whole[end..]
+                )
+            });
+
+            if matched == 0 {
+                continue;
+            }
+            rule.hits.fetch_add(matched, Ordering::Relaxed);
+            if rule.action == RedactionAction::Drop {
+                return false;
+            }
+            *text = replaced.into_owned();
+        }
+
+        true
+    }
+
+    ///Text a match is replaced with. A hashed value is HMAC-SHA256 keyed with `hash_key`, of
+    ///which the first 64 bits are kept as 16 hex characters.
+    fn replacement(&self, rule: &CompiledRule, value: &str) -> String {
+        match rule.action {
+            RedactionAction::Hash => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(self.hash_key.as_bytes())
+                    .expect("HMAC accepts keys of any length");
+                mac.update(value.as_bytes());
+                let digest = mac.finalize().into_bytes();
+                format!("[{}:{}]", rule.name, hex::encode(&digest[..8]))
+            }
+            RedactionAction::Mask | RedactionAction::Drop => format!("[REDACTED:{}]", rule.name),
+        }
+    }
+
+    fn redact_value(&self, value: &mut Value) -> bool {
+        match value {
+            Value::String(text) => self.redact_text(text),
+            Value::Array(items) => items.iter_mut().all(|item| self.redact_value(item)),
+            Value::Object(fields) => fields.values_mut().all(|item| self.redact_value(item)),
+            _ => true,
+        }
+    }
+
+    fn redact_fields(
+        &self,
+        line_content: &mut String,
+        attributes: &mut Option<HashMap<String, Value>>,
+    ) -> bool {
+        if self.rules.is_empty() {
+            return true;
+        }
+
+        self.redact_text(line_content)
+            && attributes
+                .iter_mut()
+                .flat_map(|attributes| attributes.values_mut())
+                .all(|value| self.redact_value(value))
+    }
+
+    ///Redact a request's line and attributes; false means the line is to be dropped
+    pub fn redact_request(&self, request: &mut LogRequest) -> bool {
+        self.redact_fields(&mut request.line_content, &mut request.attributes)
+    }
+
+    ///Redact a log's line and attributes; false means the log is to be dropped
+    pub fn redact_log(&self, log: &mut Log) -> bool {
+        self.redact_fields(&mut log.line_content, &mut log.attributes)
+    }
+}
+
+///Luhn checksum of the digits in `value`, ignoring spaces and dashes
+fn passes_luhn(value: &str) -> bool {
+    let digits: Vec<u32> = value.chars().filter_map(|c| c.to_digit(10)).collect();
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, digit)| match index % 2 {
+            1 if *digit * 2 > 9 => digit * 2 - 9,
+            1 => digit * 2,
+            _ => *digit,
+        })
+        .sum();
+
+    sum.is_multiple_of(10)
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor(action: RedactionAction) -> Redactor {
+        Redactor::new(&RedactionConfig {
+            detectors: Detector::ALL.to_vec(),
+            rules: Vec::new(),
+            action,
+            hash_key: String::from("test"),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_mask_builtin_detectors() {
+        let redactor = redactor(RedactionAction::Mask);
+        let mut line = String::from(
+            "card=4111 1111 1111 1111 order=1234567890123 email=jane@example.com from 10.1.2.3 \
+             Authorization: Bearer abc.def-123 token=eyJhbGciOiJIUzI1NiJ9.eyJzdWIiOiIxIn0.c2ln \
+             call +44 20 7946 0958 at 2025-10-24 13:15:06",
+        );
+
+        assert!(redactor.redact_text(&mut line));
+
+        assert_eq!(
+            line,
+            "card=[REDACTED:credit_card] order=1234567890123 email=[REDACTED:email] from \
+             [REDACTED:ip] Authorization: Bearer [REDACTED:bearer_token] \
+             token=[REDACTED:jwt] call [REDACTED:phone] at 2025-10-24 13:15:06"
+        );
+        let hits = redactor.hits();
+        assert_eq!(hits["credit_card"], 1);
+        assert_eq!(hits["email"], 1);
+        assert_eq!(hits["phone"], 1);
+    }
+
+    #[test]
+    fn test_hash_is_stable_and_custom_rule_group() {
+        let redactor = Redactor::new(&RedactionConfig {
+            detectors: vec![Detector::Email],
+            rules: vec![RedactionRule {
+                name: String::from("password"),
+                pattern: String::from(r"password=(\S+)"),
+                action: Some(RedactionAction::Mask),
+            }],
+            action: RedactionAction::Hash,
+            hash_key: String::from("test"),
+        })
+        .unwrap();
+
+        let mut first = String::from("login jane@example.com password=hunter2");
+        let mut second = String::from("logout jane@example.com");
+        redactor.redact_text(&mut first);
+        redactor.redact_text(&mut second);
+
+        assert!(first.ends_with("password=[REDACTED:password]"));
+        let hashed = &first["login ".len().."login ".len() + 24];
+        // HMAC-SHA256 of the address keyed with "test", first 64 bits
+        assert_eq!(hashed, "[email:17e4a89fb7d60016]");
+        assert!(second.contains(hashed));
+        assert!(!first.contains("jane"));
+    }
+
+    #[test]
+    fn test_drop_rule_and_attributes() {
+        let redactor = Redactor::new(&RedactionConfig {
+            detectors: vec![Detector::Email],
+            rules: vec![RedactionRule {
+                name: String::from("healthcheck"),
+                pattern: String::from("GET /health"),
+                action: Some(RedactionAction::Drop),
+            }],
+            ..Default::default()
+        })
+        .unwrap();
+
+        let mut line = String::from("GET /health 200");
+        let mut attributes = None;
+        assert!(!redactor.redact_fields(&mut line, &mut attributes));
+
+        let mut line = String::from("signup");
+        let mut attributes = Some(HashMap::from([(
+            String::from("user"),
+            serde_json::json!({"email": "jane@example.com", "age": 31}),
+        )]));
+        assert!(redactor.redact_fields(&mut line, &mut attributes));
+        assert_eq!(
+            attributes.unwrap()["user"],
+            serde_json::json!({"email": "[REDACTED:email]", "age": 31})
+        );
+    }
+
+    #[test]
+    fn test_hash_needs_a_key() {
+        let mut config = RedactionConfig {
+            detectors: vec![Detector::Email],
+            action: RedactionAction::Hash,
+            hash_key: String::from("  "),
+            ..Default::default()
+        };
+        assert!(matches!(
+            Redactor::new(&config),
+            Err(RedactionError::MissingHashKey)
+        ));
+
+        config.action = RedactionAction::Mask;
+        config.rules.push(RedactionRule {
+            name: String::from("token"),
+            pattern: String::from("tok_[a-z0-9]+"),
+            action: Some(RedactionAction::Hash),
+        });
+        assert!(matches!(
+            Redactor::new(&config),
+            Err(RedactionError::MissingHashKey)
+        ));
+
+        config.hash_key = String::from("secret");
+        assert!(Redactor::new(&config).is_ok());
+    }
+
+    #[test]
+    fn test_luhn() {
+        assert!(passes_luhn("4111-1111-1111-1111"));
+        assert!(!passes_luhn("4111-1111-1111-1112"));
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use mongodb::bson::{DateTime, oid::ObjectId};
+use regex::{Captures, Regex};
+use sha2::{Digest, Sha256};
+
+use crate::models::log_model::Log;
+use crate::models::template_model::LogTemplate;
+
+///Share of a template's fixed tokens a line must match to join it
+const SIMILARITY_THRESHOLD: f64 = 0.5;
+
+///Leading tokens that must be identical for two lines to be compared at all
+const PREFIX_TOKENS: usize = 1;
+
+///Templates kept per (service, token count, prefix) group; further lines join the closest one
+const MAX_TEMPLATES_PER_GROUP: usize = 200;
+
+const WILDCARD: &str = "<*>";
+
+///Variable parts replaced before clustering. Numbers are listed before hex so that plain
+///digits become `<NUM>`.
+static VARIABLES: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"(?P<uuid>\b[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}\b)",
+        r"|(?P<ip>\b\d{1,3}(?:\.\d{1,3}){3}(?::\d+)?\b)",
+        r"|(?P<num>\b\d+(?:\.\d+)?\b)",
+        r"|(?P<hex>\b0x[0-9a-fA-F]+\b|\b[0-9a-fA-F]{8,}\b)",
+    ))
+    .expect("the variables pattern is valid")
+});
+
+///Replace UUIDs, IPs, numbers and hex ids with `<UUID>`, `<IP>`, `<NUM>` and `<HEX>`
+pub fn mask(line: &str) -> String {
+    VARIABLES
+        .replace_all(line, |captures: &Captures| {
+            if captures.name("uuid").is_some() {
+                "<UUID>"
+            } else if captures.name("ip").is_some() {
+                "<IP>"
+            } else if captures.name("num").is_some() {
+                "<NUM>"
+            } else {
+                "<HEX>"
+            }
+        })
+        .into_owned()
+}
+
+type GroupKey = (ObjectId, usize, Vec<String>);
+
+struct Cluster {
+    id: String,
+    tokens: Vec<String>,
+}
+
+impl Cluster {
+    fn similarity(&self, tokens: &[String]) -> f64 {
+        if tokens.is_empty() {
+            return 1.0;
+        }
+        let same = self
+            .tokens
+            .iter()
+            .zip(tokens)
+            .filter(|(template, token)| *template != WILDCARD && template == token)
+            .count();
+        same as f64 / tokens.len() as f64
+    }
+
+    ///Turn the positions where `tokens` differs into wildcards; true when the template changed
+    fn merge(&mut self, tokens: &[String]) -> bool {
+        let mut changed = false;
+        for (template, token) in self.tokens.iter_mut().zip(tokens) {
+            if template != token && template != WILDCARD {
+                *template = WILDCARD.to_string();
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+#[derive(Default)]
+struct MinerState {
+    groups: HashMap<GroupKey, Vec<Cluster>>,
+    ///Templates created or generalised since the last `take_changed`
+    changed: HashMap<String, LogTemplate>,
+}
+
+///Online clustering of lines into message templates, after the Drain algorithm: lines are
+///grouped by token count and leading tokens, then join the most similar template of their
+///group, which generalises the tokens that differ.
+#[derive(Default)]
+pub struct TemplateMiner {
+    state: Mutex<MinerState>,
+}
+
+impl std::fmt::Debug for TemplateMiner {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("TemplateMiner").finish_non_exhaustive()
+    }
+}
+
+fn group_key(service_id: ObjectId, tokens: &[String]) -> GroupKey {
+    let prefix = tokens
+        .iter()
+        .take(PREFIX_TOKENS)
+        .map(|token| {
+            if token.chars().any(|c| c.is_ascii_digit()) {
+                WILDCARD.to_string()
+            } else {
+                token.clone()
+            }
+        })
+        .collect();
+    (service_id, tokens.len(), prefix)
+}
+
+fn template_id(service_id: &ObjectId, template: &str) -> String {
+    let digest = Sha256::new()
+        .chain_update(service_id.bytes())
+        .chain_update(template.as_bytes())
+        .finalize();
+    hex::encode(&digest[..8])
+}
+
+impl TemplateMiner {
+    ///Start from templates learned before, so lines keep their template ids across restarts
+    pub fn seed(&self, templates: Vec<LogTemplate>) {
+        let mut state = self.state.lock().unwrap();
+        for template in templates {
+            let tokens: Vec<String> = template
+                .template
+                .split_whitespace()
+                .map(str::to_string)
+                .collect();
+            state
+                .groups
+                .entry(group_key(template.my_service_id, &tokens))
+                .or_default()
+                .push(Cluster {
+                    id: template._id,
+                    tokens,
+                });
+        }
+    }
+
+    ///Cluster the line of `log` and record its template id on it
+    pub fn assign(&self, log: &mut Log) {
+        let tokens: Vec<String> = mask(&log.line_content)
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        let service_id = log.my_service_id;
+
+        let mut state = self.state.lock().unwrap();
+        let MinerState { groups, changed } = &mut *state;
+        let clusters = groups.entry(group_key(service_id, &tokens)).or_default();
+
+        let best = clusters
+            .iter()
+            .enumerate()
+            .map(|(index, cluster)| (index, cluster.similarity(&tokens)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        let cluster = match best {
+            Some((index, similarity))
+                if similarity >= SIMILARITY_THRESHOLD
+                    || clusters.len() >= MAX_TEMPLATES_PER_GROUP =>
+            {
+                let cluster = &mut clusters[index];
+                if !cluster.merge(&tokens) {
+                    log.template_id = Some(cluster.id.clone());
+                    return;
+                }
+                cluster
+            }
+            _ => {
+                let template = tokens.join(" ");
+                clusters.push(Cluster {
+                    id: template_id(&service_id, &template),
+                    tokens,
+                });
+                clusters.last_mut().unwrap()
+            }
+        };
+
+        log.template_id = Some(cluster.id.clone());
+        changed.insert(
+            cluster.id.clone(),
+            LogTemplate {
+                _id: cluster.id.clone(),
+                my_service_id: service_id,
+                template: cluster.tokens.join(" "),
+                updated_at: DateTime::now(),
+            },
+        );
+    }
+
+    pub fn assign_all(&self, logs: &mut [Log]) {
+        for log in logs {
+            self.assign(log);
+        }
+    }
+
+    ///Templates created or generalised since the last call, to be saved
+    pub fn take_changed(&self) -> Vec<LogTemplate> {
+        let mut state = self.state.lock().unwrap();
+        state
+            .changed
+            .drain()
+            .map(|(_, template)| template)
+            .collect()
+    }
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::log_model::LogLevel;
+
+    fn log(service_id: ObjectId, line: &str) -> Log {
+        Log::sample(service_id, LogLevel::INFO, line)
+    }
+
+    #[test]
+    fn test_mask() {
+        assert_eq!(
+            mask(
+                "user 42 from 10.0.0.7:8080 req 550e8400-e29b-41d4-a716-446655440000 hash deadbeef99"
+            ),
+            "user <NUM> from <IP> req <UUID> hash <HEX>"
+        );
+        assert_eq!(mask("took 1.5 s at 0x7ff3"), "took <NUM> s at <HEX>");
+    }
+
+    #[test]
+    fn test_lines_cluster_into_templates() {
+        let miner = TemplateMiner::default();
+        let service = ObjectId::new();
+
+        let mut logs = vec![
+            log(service, "Connection to 10.0.0.1 timed out after 30 ms"),
+            log(service, "Connection to 10.0.0.2 timed out after 45 ms"),
+            log(service, "User alice logged in"),
+            log(service, "User bob logged in"),
+            log(service, "Disk /dev/sda1 is full"),
+        ];
+        miner.assign_all(&mut logs);
+
+        assert_eq!(logs[0].template_id, logs[1].template_id);
+        assert_eq!(logs[2].template_id, logs[3].template_id);
+        assert_ne!(logs[0].template_id, logs[2].template_id);
+        assert_ne!(logs[2].template_id, logs[4].template_id);
+
+        let mut templates: Vec<String> = miner
+            .take_changed()
+            .into_iter()
+            .map(|template| template.template)
+            .collect();
+        templates.sort();
+        assert_eq!(
+            templates,
+            vec![
+                "Connection to <IP> timed out after <NUM> ms",
+                "Disk /dev/sda1 is full",
+                "User <*> logged in",
+            ]
+        );
+        assert!(miner.take_changed().is_empty());
+    }
+
+    #[test]
+    fn test_seeded_templates_keep_their_ids() {
+        let service = ObjectId::new();
+        let first = TemplateMiner::default();
+        let mut logs = vec![
+            log(service, "User alice logged in"),
+            log(service, "User bob logged in"),
+        ];
+        first.assign_all(&mut logs);
+
+        let restarted = TemplateMiner::default();
+        restarted.seed(first.take_changed());
+        let mut later = log(service, "User carol logged in");
+        restarted.assign(&mut later);
+
+        assert_eq!(later.template_id, logs[0].template_id);
+        assert!(restarted.take_changed().is_empty());
+
+        // other services never share templates
+        let mut other = log(ObjectId::new(), "User alice logged in");
+        restarted.assign(&mut other);
+        assert_ne!(other.template_id, logs[0].template_id);
+    }
+}
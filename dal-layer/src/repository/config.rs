@@ -1,17 +1,100 @@
-#[derive(Debug, Clone)]
-pub struct Config {
-    pub database_url: String,
-    pub rust_log: String,
+use serde::Deserialize;
+use std::env;
+
+pub const DEFAULT_URI: &str = "mongodb://localhost:27017/?directConnection=true";
+pub const DEFAULT_DATABASE_NAME: &str = "rust_log_monitor";
+
+///Where the data lives and how many connections the pool keeps
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub uri: String,
+    pub name: String,
+    ///Most connections open at once
+    pub max_pool_size: u32,
+    ///Connections kept open while idle
+    pub min_pool_size: u32,
+    ///Seconds an idle connection is kept before it is closed
+    pub max_idle_secs: u64,
+    ///Pings of the server at start up before giving up; 1 fails fast
+    pub connect_attempts: u32,
+    ///Seconds between two start up pings
+    pub connect_retry_secs: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            uri: DEFAULT_URI.to_string(),
+            name: DEFAULT_DATABASE_NAME.to_string(),
+            max_pool_size: 20,
+            min_pool_size: 5,
+            max_idle_secs: 60,
+            connect_attempts: 10,
+            connect_retry_secs: 3,
+        }
+    }
 }
 
-impl Config {
-    pub fn init() -> Config {
-        let database_url: String = std::env::var("MONGO_URI").expect("Data url must be set");
-        let rust_log: String = std::env::var("RUST_LOG").expect("error in secret");
+impl DatabaseConfig {
+    ///Defaults overridden by MONGO_URI and MONGO_DB_NAME
+    pub fn from_env() -> DatabaseConfig {
+        let mut config = DatabaseConfig::default();
+        if let Ok(uri) = env::var("MONGO_URI") {
+            config.uri = uri;
+        }
+        if let Ok(name) = env::var("MONGO_DB_NAME") {
+            config.name = name;
+        }
+        config
+    }
 
-        Config {
-            database_url,
-            rust_log,
+    ///Problems that would stop the database from being used, one message each
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !self.uri.starts_with("mongodb://") && !self.uri.starts_with("mongodb+srv://") {
+            problems.push(format!(
+                "database.uri must start with mongodb:// or mongodb+srv://, got {:?}",
+                self.uri
+            ));
+        }
+        if self.name.trim().is_empty() {
+            problems.push(String::from("database.name must not be empty"));
         }
+        if self.max_pool_size == 0 {
+            problems.push(String::from("database.max_pool_size must be at least 1"));
+        }
+        if self.min_pool_size > self.max_pool_size {
+            problems.push(format!(
+                "database.min_pool_size ({}) is larger than database.max_pool_size ({})",
+                self.min_pool_size, self.max_pool_size
+            ));
+        }
+        if self.connect_attempts == 0 {
+            problems.push(String::from("database.connect_attempts must be at least 1"));
+        }
+        problems
+    }
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert!(DatabaseConfig::default().validate().is_empty());
+
+        let config = DatabaseConfig {
+            uri: String::from("localhost:27017"),
+            min_pool_size: 30,
+            ..Default::default()
+        };
+        let problems = config.validate();
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].starts_with("database.uri"));
+        assert!(problems[1].starts_with("database.min_pool_size (30)"));
     }
 }
@@ -1,56 +1,165 @@
-use crate::models::{log_model::Log, log_model::LogLevel, my_service_model::MyService};
+use crate::models::{
+    alert_model::{AlertRule, AlertStatus, Silence},
+    collector_model::Collector,
+    issue_model::{Issue, IssueOccurrence, IssueQuery, IssueStatus},
+    log_model::{
+        Log, LogContextView, LogLevel, LogSearchQuery, LogStatsQuery, LogStatsView, SearchOrder,
+    },
+    my_service_model::{MyService, ServiceActivity},
+    template_model::{LogTemplate, PatternQuery, PatternView},
+    trace_model::TraceView,
+};
+use crate::pipeline::dedup::RepeatUpdate;
+use crate::repository::config::DatabaseConfig;
 use crate::utils::date_helper::Converter;
+use crate::utils::metrics;
 
 use actix_web::Error;
-use chrono::Utc;
+use actix_web::error::{ErrorBadRequest, ErrorInternalServerError, ErrorServiceUnavailable};
 use futures::stream::TryStreamExt;
 
 use mongodb::Collection;
-use mongodb::bson::from_document;
 use mongodb::bson::oid::ObjectId;
-use mongodb::bson::{DateTime, doc};
-use mongodb::options::CountOptions;
+use mongodb::bson::{Bson, DateTime, Document, doc};
 use mongodb::results::InsertOneResult;
-use mongodb::results::UpdateResult;
 use mongodb::{Client, Cursor};
 use mongodb::{IndexModel, options::IndexOptions};
-use std::env;
+use std::collections::HashMap;
 use std::str::FromStr;
 
+use mongodb::error::{ErrorKind, InsertManyError};
+use mongodb::event::EventHandler;
 use mongodb::options::ClientOptions;
 
+///Upper bound on the number of documents a single search may return
+const MAX_SEARCH_LIMIT: i64 = 1000;
+
+///Upper bound on the number of lines returned for a single trace
+const MAX_TRACE_LINES: i64 = 5000;
+
+///Upper bound on the templates a single pattern listing may return
+const MAX_PATTERN_LIMIT: i64 = 500;
+
+///Upper bound on the issues or occurrences a single listing may return
+const MAX_ISSUE_LIMIT: i64 = 500;
+
+///Matching lines returned with each template of a pattern listing
+const PATTERN_SAMPLES: i32 = 3;
+
+///Upper bound on the lines returned on either side of a log by the context query
+pub const MAX_CONTEXT_LINES: i64 = 500;
+
+///Server code of a write rejected for a duplicate key
+const DUPLICATE_KEY: i32 = 11000;
+
+///Indexes of the log collection created by `ensure_indexes` that the queries rely on
+pub const REQUIRED_LOG_INDEXES: [&str; 6] = [
+    "created_at_1",
+    "trace_id_created_at",
+    "service_source_created_at_id",
+    "fingerprint_created_at",
+    "service_created_at",
+    "ingested_at_id",
+];
+
 pub struct Database {
     log: Collection<Log>,
     myservice: Collection<MyService>,
+    template: Collection<LogTemplate>,
+    issue: Collection<Issue>,
+    alert_rule: Collection<AlertRule>,
+    alert_status: Collection<AlertStatus>,
+    silence: Collection<Silence>,
+    collector: Collection<Collector>,
+    database: mongodb::Database,
 }
 
 impl Database {
-    ///This is going to initialize the database
+    ///This is going to initialize the database from MONGO_URI and MONGO_DB_NAME
     pub async fn init() -> Self {
-        let uri = env::var("MONGO_URI")
-            .unwrap_or_else(|_| "mongodb://localhost:27017/?directConnection=true".into());
+        Self::connect(&DatabaseConfig::from_env()).await
+    }
 
+    ///Open a client with the uri, database name and pool sizes of `config`. Nothing is sent to
+    ///the server until the first operation; use `ping` to check it is reachable.
+    pub async fn connect(config: &DatabaseConfig) -> Self {
         // Parse options so we can tune pool settings
-        let mut client_options = ClientOptions::parse(&uri)
+        let mut client_options = ClientOptions::parse(&config.uri)
             .await
             .expect("Failed to parse MongoDB URI");
 
         client_options.app_name = Some("rust-log-monitor".to_string());
 
-        // 👇 POOL SETTINGS (optional but recommended)
-        client_options.max_pool_size = Some(20); // max concurrent connections
-        client_options.min_pool_size = Some(5); // keep some warm
-        client_options.max_idle_time = Some(std::time::Duration::from_secs(60));
+        client_options.max_pool_size = Some(config.max_pool_size);
+        client_options.min_pool_size = Some(config.min_pool_size);
+        client_options.max_idle_time = Some(std::time::Duration::from_secs(config.max_idle_secs));
+        client_options.command_event_handler =
+            Some(EventHandler::callback(metrics::record_command));
 
         let client =
             Client::with_options(client_options).expect("Failed to initialize MongoDB client");
 
-        let db = client.database("rust_log_monitor");
+        let db = client.database(&config.name);
 
         let myservice: Collection<MyService> = db.collection("myservice");
         let log: Collection<Log> = db.collection("log");
+        let template: Collection<LogTemplate> = db.collection("log_template");
+        let issue: Collection<Issue> = db.collection("issue");
+        let alert_rule: Collection<AlertRule> = db.collection("alert_rule");
+        let alert_status: Collection<AlertStatus> = db.collection("alert_status");
+        let silence: Collection<Silence> = db.collection("silence");
+        let collector: Collection<Collector> = db.collection("collector");
 
-        Database { log, myservice }
+        Database {
+            log,
+            myservice,
+            template,
+            issue,
+            alert_rule,
+            alert_status,
+            silence,
+            collector,
+            database: db,
+        }
+    }
+
+    ///Round trip to the server; fails while MongoDB is unreachable
+    pub async fn ping(&self) -> Result<(), Error> {
+        self.database
+            .run_command(doc! { "ping": 1 })
+            .await
+            .map_err(ErrorServiceUnavailable)?;
+
+        Ok(())
+    }
+
+    ///Clock of the server, the one that stamps `ingested_at`
+    pub async fn server_time(&self) -> Result<DateTime, Error> {
+        let hello = self
+            .database
+            .run_command(doc! { "hello": 1 })
+            .await
+            .map_err(ErrorServiceUnavailable)?;
+
+        hello
+            .get_datetime("localTime")
+            .copied()
+            .map_err(ErrorInternalServerError)
+    }
+
+    ///Indexes of REQUIRED_LOG_INDEXES that do not exist on the log collection
+    pub async fn missing_indexes(&self) -> Result<Vec<String>, Error> {
+        let existing = self
+            .log
+            .list_index_names()
+            .await
+            .map_err(ErrorServiceUnavailable)?;
+
+        Ok(REQUIRED_LOG_INDEXES
+            .iter()
+            .filter(|name| !existing.iter().any(|index| index == *name))
+            .map(|name| name.to_string())
+            .collect())
     }
 
     pub async fn ensure_created_at_index(
@@ -64,8 +173,123 @@ impl Database {
         collection
             .create_index(index)
             .await
-            .ok()
-            .expect("Error creating the index");
+            .map_err(ErrorInternalServerError)?;
+
+        Ok(())
+    }
+
+    pub async fn ensure_trace_id_index(collection: &mongodb::Collection<Log>) -> Result<(), Error> {
+        let index = IndexModel::builder()
+            .keys(doc! { "trace_id": 1, "created_at": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name(String::from("trace_id_created_at"))
+                    .sparse(true)
+                    .build(),
+            )
+            .build();
+
+        collection
+            .create_index(index)
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        Ok(())
+    }
+
+    ///Backs the context query: neighbours of a line within one service and source file,
+    ///with `_id` breaking ties between lines that share a timestamp
+    pub async fn ensure_source_position_index(
+        collection: &mongodb::Collection<Log>,
+    ) -> Result<(), Error> {
+        let index = IndexModel::builder()
+            .keys(doc! { "my_service_id": 1, "source_file": 1, "created_at": 1, "_id": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name(String::from("service_source_created_at_id"))
+                    .build(),
+            )
+            .build();
+
+        collection
+            .create_index(index)
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        Ok(())
+    }
+
+    ///Backs the occurrence listing of an issue
+    pub async fn ensure_fingerprint_index(
+        collection: &mongodb::Collection<Log>,
+    ) -> Result<(), Error> {
+        let index = IndexModel::builder()
+            .keys(doc! { "fingerprint": 1, "created_at": -1 })
+            .options(
+                IndexOptions::builder()
+                    .name(String::from("fingerprint_created_at"))
+                    .sparse(true)
+                    .build(),
+            )
+            .build();
+
+        collection
+            .create_index(index)
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        Ok(())
+    }
+
+    ///Backs the newest-line lookup of the service health
+    pub async fn ensure_service_created_at_index(
+        collection: &mongodb::Collection<Log>,
+    ) -> Result<(), Error> {
+        let index = IndexModel::builder()
+            .keys(doc! { "my_service_id": 1, "created_at": -1 })
+            .options(
+                IndexOptions::builder()
+                    .name(String::from("service_created_at"))
+                    .build(),
+            )
+            .build();
+
+        collection
+            .create_index(index)
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        Ok(())
+    }
+
+    pub async fn ensure_ingested_at_index(
+        collection: &mongodb::Collection<Log>,
+    ) -> Result<(), Error> {
+        let index = IndexModel::builder()
+            .keys(doc! { "ingested_at": 1, "_id": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name(String::from("ingested_at_id"))
+                    .build(),
+            )
+            .build();
+
+        collection
+            .create_index(index)
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        Ok(())
+    }
+
+    ///Create the indexes the API queries rely on. Safe to call on every start up.
+    pub async fn ensure_indexes(&self) -> Result<(), Error> {
+        Self::ensure_created_at_index(&self.log).await?;
+        Self::ensure_trace_id_index(&self.log).await?;
+        Self::ensure_source_position_index(&self.log).await?;
+        Self::ensure_fingerprint_index(&self.log).await?;
+        Self::ensure_service_created_at_index(&self.log).await?;
+        Self::ensure_ingested_at_index(&self.log).await?;
 
         Ok(())
     }
@@ -76,8 +300,7 @@ impl Database {
             .myservice
             .insert_one(myservice)
             .await
-            .ok()
-            .expect("Error inserting application or microserice");
+            .map_err(ErrorInternalServerError)?;
 
         Ok(result)
     }
@@ -89,36 +312,37 @@ impl Database {
         }
 
         // Ordered=false means MongoDB will continue inserting even if one fails (e.g., duplicate key)
-        let options = mongodb::options::InsertManyOptions::builder()
-            .ordered(false)
-            .build();
-
         self.myservice
             .insert_many(services)
+            .ordered(false)
             .await
-            .ok()
-            .expect("problem inserting bulk service");
+            .map_err(ErrorInternalServerError)?;
 
         Ok(())
     }
 
+    pub async fn find_service_by_name(&self, name: &str) -> Result<Option<MyService>, Error> {
+        self.myservice
+            .find_one(doc! { "name": name })
+            .await
+            .map_err(ErrorInternalServerError)
+    }
+
     pub async fn get_services(&self) -> Result<Vec<MyService>, Error> {
         // No filter = return all documents (be careful with large collections!)
 
         let filter = doc! {};
 
-        let mut cursor: Cursor<MyService> = self
+        let cursor: Cursor<MyService> = self
             .myservice
             .find(filter)
             .await
-            .ok()
-            .expect("error reading through the services");
+            .map_err(ErrorInternalServerError)?;
 
         let services: Vec<MyService> = cursor
             .try_collect()
             .await
-            .ok()
-            .expect("error displaying the list of services"); // or iterate with try_next
+            .map_err(ErrorInternalServerError)?; // or iterate with try_next
 
         Ok(services)
     }
@@ -127,57 +351,878 @@ impl Database {
     pub async fn create_log(&self, log: Log) -> Result<InsertOneResult, Error> {
         let result = self
             .log
-            .insert_one(log)
+            .insert_one(&log)
             .await
-            .ok()
-            .expect("Error inserting application or microserice");
+            .map_err(ErrorInternalServerError)?;
+        self.stamp_ingested(&[log._id]).await?;
+        metrics::record_ingested(std::slice::from_ref(&log));
 
         Ok(result)
     }
 
-    /// Bulk insert using a single `insert_many` call
-    pub async fn insert_logs_bulk(&self, logs: Vec<Log>) -> Result<(), Error> {
+    /// Bulk insert using a single `insert_many` call. Unordered, so every line is attempted;
+    /// lines whose `_id` is stored already, by an earlier attempt of a retried batch, count
+    /// as stored.
+    pub async fn insert_logs_bulk(&self, logs: &[Log]) -> Result<(), Error> {
         if logs.is_empty() {
             return Ok(()); // nothing to insert
         }
 
-        // Ordered=false means MongoDB will continue inserting even if one fails (e.g., duplicate key)
-        let options = mongodb::options::InsertManyOptions::builder()
-            .ordered(true)
-            .build();
+        match self.log.insert_many(logs).ordered(false).await {
+            Ok(_) => {}
+            Err(err) if only_duplicate_keys(&err) => {}
+            Err(err) => return Err(ErrorInternalServerError(err)),
+        }
+        let ids: Vec<ObjectId> = logs.iter().map(|log| log._id).collect();
+        self.stamp_ingested(&ids).await?;
+        metrics::record_ingested(logs);
+
+        Ok(())
+    }
 
+    ///Set `ingested_at` of the stored lines that have none to the clock of the server. A line
+    ///stored again by a retried batch keeps its first stamp, so a stream does not send it twice.
+    async fn stamp_ingested(&self, ids: &[ObjectId]) -> Result<(), Error> {
         self.log
-            .insert_many(logs)
+            .update_many(
+                doc! { "_id": { "$in": ids }, "ingested_at": Bson::Null },
+                vec![doc! { "$set": { "ingested_at": "$$NOW" } }],
+            )
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        Ok(())
+    }
+
+    ///Add repeats absorbed by deduplication to the logs they repeat
+    pub async fn apply_repeat_updates(&self, updates: Vec<RepeatUpdate>) -> Result<(), Error> {
+        for update in updates {
+            self.log
+                .update_one(
+                    doc! { "_id": update.log_id },
+                    doc! {
+                        "$inc": { "repeat_count": update.repeats },
+                        "$max": { "last_seen": update.last_seen },
+                    },
+                )
+                .await
+                .map_err(ErrorInternalServerError)?;
+        }
+
+        Ok(())
+    }
+
+    ///Save templates learned by the template miner, replacing the text of generalised ones
+    pub async fn upsert_templates(&self, templates: Vec<LogTemplate>) -> Result<(), Error> {
+        for template in templates {
+            self.template
+                .update_one(
+                    doc! { "_id": &template._id },
+                    doc! {
+                        "$set": { "template": &template.template, "updated_at": template.updated_at },
+                        "$setOnInsert": { "my_service_id": template.my_service_id },
+                    },
+                )
+                .upsert(true)
+                .await
+                .map_err(ErrorInternalServerError)?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_templates(&self) -> Result<Vec<LogTemplate>, Error> {
+        let cursor: Cursor<LogTemplate> = self
+            .template
+            .find(doc! {})
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        cursor.try_collect().await.map_err(ErrorInternalServerError)
+    }
+
+    ///Templates of the lines in a time range with their counts and a few sample lines, the
+    ///most frequent first
+    pub async fn get_patterns(&self, query: &PatternQuery) -> Result<Vec<PatternView>, Error> {
+        let mut filter = doc! { "template_id": { "$exists": true } };
+        if let Some(service_id) = &query.service_id {
+            let serviceid = ObjectId::from_str(service_id).map_err(ErrorBadRequest)?;
+            filter.insert("my_service_id", serviceid);
+        }
+        let mut created_at = doc! {};
+        if let Some(from) = &query.from {
+            created_at.insert("$gte", Converter::parse_str_datetime(from)?);
+        }
+        if let Some(to) = &query.to {
+            created_at.insert("$lt", Converter::parse_str_datetime(to)?);
+        }
+        if !created_at.is_empty() {
+            filter.insert("created_at", created_at);
+        }
+        let limit = query.limit.unwrap_or(50).clamp(1, MAX_PATTERN_LIMIT);
+
+        let pipeline = vec![
+            doc! { "$match": filter },
+            doc! { "$sort": { "created_at": 1 } },
+            doc! { "$group": {
+                "_id": "$template_id",
+                "service_id": { "$first": "$my_service_id" },
+                "count": { "$sum": { "$ifNull": ["$repeat_count", 1] } },
+                "first_seen": { "$min": "$created_at" },
+                "last_seen": { "$max": { "$ifNull": ["$last_seen", "$created_at"] } },
+                "samples": { "$push": "$line_content" },
+            } },
+            doc! { "$sort": { "count": -1, "_id": 1 } },
+            doc! { "$limit": limit },
+            doc! { "$lookup": {
+                "from": "log_template",
+                "localField": "_id",
+                "foreignField": "_id",
+                "as": "template",
+            } },
+            doc! { "$project": {
+                "_id": 0,
+                "template_id": "$_id",
+                "service_id": { "$toString": "$service_id" },
+                "template": { "$arrayElemAt": ["$template.template", 0] },
+                "count": 1,
+                "first_seen": 1,
+                "last_seen": 1,
+                "samples": { "$slice": ["$samples", PATTERN_SAMPLES] },
+            } },
+        ];
+
+        let cursor = self
+            .log
+            .aggregate(pipeline)
+            .await
+            .map_err(ErrorInternalServerError)?;
+        let documents: Vec<Document> = cursor
+            .try_collect()
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        documents
+            .into_iter()
+            .map(|document| {
+                mongodb::bson::from_document(document).map_err(ErrorInternalServerError)
+            })
+            .collect()
+    }
+
+    ///Add the errors of a stored batch to their issues, creating new ones. A resolved issue
+    ///that occurs after it was resolved is marked as regressed.
+    pub async fn record_issue_occurrences(
+        &self,
+        occurrences: Vec<IssueOccurrence>,
+    ) -> Result<(), Error> {
+        for occurrence in occurrences {
+            self.issue
+                .update_one(
+                    doc! { "_id": &occurrence.fingerprint },
+                    doc! {
+                        "$setOnInsert": {
+                            "title": &occurrence.title,
+                            "level": occurrence.level.to_string(),
+                            "exception_class": occurrence.exception_class.clone(),
+                            "status": IssueStatus::Unresolved.as_str(),
+                            "regressions": 0_i64,
+                        },
+                        "$inc": { "count": occurrence.count },
+                        "$min": { "first_seen": occurrence.first_seen },
+                        "$max": { "last_seen": occurrence.last_seen },
+                        "$addToSet": { "services": { "$each": occurrence.services.clone() } },
+                    },
+                )
+                .upsert(true)
+                .await
+                .map_err(ErrorInternalServerError)?;
+
+            self.issue
+                .update_one(
+                    doc! {
+                        "_id": &occurrence.fingerprint,
+                        "status": IssueStatus::Resolved.as_str(),
+                        "resolved_at": { "$lt": occurrence.last_seen },
+                    },
+                    doc! {
+                        "$set": {
+                            "status": IssueStatus::Regressed.as_str(),
+                            "regressed_at": DateTime::now(),
+                        },
+                        "$inc": { "regressions": 1_i64 },
+                    },
+                )
+                .await
+                .map_err(ErrorInternalServerError)?;
+        }
+
+        Ok(())
+    }
+
+    ///Issues matching the filters, the most recently seen first
+    pub async fn get_issues(&self, query: &IssueQuery) -> Result<Vec<Issue>, Error> {
+        let mut filter = doc! {};
+        if let Some(status) = &query.status {
+            filter.insert("status", status.as_str());
+        }
+        if let Some(service_id) = &query.service_id {
+            let serviceid = ObjectId::from_str(service_id).map_err(ErrorBadRequest)?;
+            filter.insert("services", serviceid);
+        }
+        let limit = query.limit.unwrap_or(100).clamp(1, MAX_ISSUE_LIMIT);
+
+        let cursor: Cursor<Issue> = self
+            .issue
+            .find(filter)
+            .sort(doc! { "last_seen": -1 })
+            .limit(limit)
             .await
-            .ok()
-            .expect("error performing bulk log ");
+            .map_err(ErrorInternalServerError)?;
+
+        cursor.try_collect().await.map_err(ErrorInternalServerError)
+    }
+
+    pub async fn get_issue(&self, issue_id: &str) -> Result<Option<Issue>, Error> {
+        self.issue
+            .find_one(doc! { "_id": issue_id })
+            .await
+            .map_err(ErrorInternalServerError)
+    }
+
+    ///Lines of an issue, newest first
+    pub async fn get_issue_occurrences(
+        &self,
+        issue_id: &str,
+        limit: Option<i64>,
+    ) -> Result<Vec<Log>, Error> {
+        let cursor: Cursor<Log> = self
+            .log
+            .find(doc! { "fingerprint": issue_id })
+            .sort(doc! { "created_at": -1 })
+            .limit(limit.unwrap_or(50).clamp(1, MAX_ISSUE_LIMIT))
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        cursor.try_collect().await.map_err(ErrorInternalServerError)
+    }
+
+    ///Change the triage status of an issue; `None` when it does not exist
+    pub async fn set_issue_status(
+        &self,
+        issue_id: &str,
+        status: IssueStatus,
+    ) -> Result<Option<Issue>, Error> {
+        let update = match status {
+            IssueStatus::Resolved => doc! {
+                "$set": { "status": status.as_str(), "resolved_at": DateTime::now() },
+            },
+            _ => doc! {
+                "$set": { "status": status.as_str() },
+                "$unset": { "resolved_at": "" },
+            },
+        };
+
+        self.issue
+            .find_one_and_update(doc! { "_id": issue_id }, update)
+            .return_document(mongodb::options::ReturnDocument::After)
+            .await
+            .map_err(ErrorInternalServerError)
+    }
+
+    pub async fn create_alert_rule(&self, rule: AlertRule) -> Result<InsertOneResult, Error> {
+        self.alert_rule
+            .insert_one(rule)
+            .await
+            .map_err(ErrorInternalServerError)
+    }
+
+    pub async fn get_alert_rules(&self) -> Result<Vec<AlertRule>, Error> {
+        let cursor: Cursor<AlertRule> = self
+            .alert_rule
+            .find(doc! {})
+            .sort(doc! { "created_at": 1 })
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        cursor.try_collect().await.map_err(ErrorInternalServerError)
+    }
+
+    ///Delete a rule and its alert state. Returns false when the rule does not exist.
+    pub async fn delete_alert_rule(&self, rule_id: &str) -> Result<bool, Error> {
+        let ruleid = ObjectId::from_str(rule_id).map_err(ErrorBadRequest)?;
+
+        let result = self
+            .alert_rule
+            .delete_one(doc! { "_id": ruleid })
+            .await
+            .map_err(ErrorInternalServerError)?;
+        self.alert_status
+            .delete_one(doc! { "_id": ruleid })
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        Ok(result.deleted_count > 0)
+    }
+
+    pub async fn get_alert_statuses(&self) -> Result<Vec<AlertStatus>, Error> {
+        let cursor: Cursor<AlertStatus> = self
+            .alert_status
+            .find(doc! {})
+            .sort(doc! { "state": 1, "since": -1 })
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        cursor.try_collect().await.map_err(ErrorInternalServerError)
+    }
+
+    pub async fn save_alert_status(&self, status: &AlertStatus) -> Result<(), Error> {
+        self.alert_status
+            .replace_one(doc! { "_id": status._id }, status)
+            .upsert(true)
+            .await
+            .map_err(ErrorInternalServerError)?;
 
         Ok(())
     }
 
-    pub async fn get_logs_by_service(&self, service_id: &String) -> Result<Vec<Log>, Error> {
-        let serviceid = ObjectId::from_str(service_id).expect("Failed to parse service_id");
+    pub async fn create_silence(&self, silence: Silence) -> Result<InsertOneResult, Error> {
+        self.silence
+            .insert_one(silence)
+            .await
+            .map_err(ErrorInternalServerError)
+    }
+
+    ///Silences that have not ended yet, including the ones still to start
+    pub async fn get_silences(&self) -> Result<Vec<Silence>, Error> {
+        let cursor: Cursor<Silence> = self
+            .silence
+            .find(doc! { "ends_at": { "$gt": DateTime::now() } })
+            .sort(doc! { "starts_at": 1 })
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        cursor.try_collect().await.map_err(ErrorInternalServerError)
+    }
+
+    ///Returns false when the silence does not exist
+    pub async fn delete_silence(&self, silence_id: &str) -> Result<bool, Error> {
+        let silenceid = ObjectId::from_str(silence_id).map_err(ErrorBadRequest)?;
+
+        let result = self
+            .silence
+            .delete_one(doc! { "_id": silenceid })
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        Ok(result.deleted_count > 0)
+    }
+
+    ///Store a collector that just started, replacing what an earlier run of it reported
+    pub async fn register_collector(&self, collector: &Collector) -> Result<(), Error> {
+        self.collector
+            .replace_one(doc! { "_id": &collector._id }, collector)
+            .upsert(true)
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        Ok(())
+    }
+
+    ///Store a heartbeat. registered_at is kept, or set when the collector was never registered.
+    pub async fn record_heartbeat(&self, collector: &Collector) -> Result<(), Error> {
+        let mut fields = mongodb::bson::to_document(collector).map_err(ErrorInternalServerError)?;
+        fields.remove("_id");
+        let registered_at = fields.remove("registered_at");
+
+        self.collector
+            .update_one(
+                doc! { "_id": &collector._id },
+                doc! {
+                    "$set": fields,
+                    "$setOnInsert": { "registered_at": registered_at },
+                },
+            )
+            .upsert(true)
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        Ok(())
+    }
+
+    pub async fn get_collectors(&self) -> Result<Vec<Collector>, Error> {
+        let cursor: Cursor<Collector> = self
+            .collector
+            .find(doc! {})
+            .sort(doc! { "_id": 1 })
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        cursor.try_collect().await.map_err(ErrorInternalServerError)
+    }
+
+    ///Lines created since `since`, optionally of one service, at `min_level` or above and
+    ///matching the regular expression `pattern`
+    pub async fn count_logs_since(
+        &self,
+        service_id: Option<ObjectId>,
+        min_level: Option<LogLevel>,
+        pattern: Option<&str>,
+        since: DateTime,
+    ) -> Result<u64, Error> {
+        let mut filter = doc! { "created_at": { "$gte": since } };
+        if let Some(service_id) = service_id {
+            filter.insert("my_service_id", service_id);
+        }
+        if let Some(min_level) = min_level {
+            let levels: Vec<String> = min_level
+                .and_above()
+                .iter()
+                .map(LogLevel::to_string)
+                .collect();
+            filter.insert("level", doc! { "$in": levels });
+        }
+        if let Some(pattern) = pattern {
+            filter.insert("line_content", doc! { "$regex": pattern });
+        }
+
+        // a line stands for every repeat deduplication folded into it
+        let pipeline = vec![
+            doc! { "$match": filter },
+            doc! { "$group": {
+                "_id": null,
+                "count": { "$sum": { "$ifNull": ["$repeat_count", 1] } },
+            } },
+        ];
+        let counted = self
+            .log
+            .aggregate(pipeline)
+            .await
+            .map_err(ErrorInternalServerError)?
+            .try_next()
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        Ok(counted.map_or(0, |counted| count_field(&counted, "count")))
+    }
+
+    pub async fn get_service_by_id(&self, service_id: &str) -> Result<Option<MyService>, Error> {
+        let serviceid = ObjectId::from_str(service_id).map_err(ErrorBadRequest)?;
+
+        self.myservice
+            .find_one(doc! { "_id": serviceid })
+            .await
+            .map_err(ErrorInternalServerError)
+    }
+
+    ///Newest line of a service, and its lines and ERROR or FATAL lines created since `since`
+    pub async fn get_service_activity(
+        &self,
+        service_id: ObjectId,
+        since: DateTime,
+    ) -> Result<ServiceActivity, Error> {
+        let last_log = self
+            .log
+            .find_one(doc! { "my_service_id": service_id })
+            .sort(doc! { "created_at": -1 })
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        let errors: Vec<String> = LogLevel::ERROR
+            .and_above()
+            .iter()
+            .map(LogLevel::to_string)
+            .collect();
+        let pipeline = vec![
+            doc! { "$match": { "my_service_id": service_id, "created_at": { "$gte": since } } },
+            doc! { "$project": {
+                "level": 1,
+                "lines": { "$ifNull": ["$repeat_count", 1] },
+            } },
+            doc! { "$group": {
+                "_id": null,
+                "lines": { "$sum": "$lines" },
+                "errors": { "$sum": { "$cond": [{ "$in": ["$level", errors] }, "$lines", 0] } },
+            } },
+        ];
+        let recent = self
+            .log
+            .aggregate(pipeline)
+            .await
+            .map_err(ErrorInternalServerError)?
+            .try_next()
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        Ok(ServiceActivity {
+            last_log_at: last_log.map(|log| log.created_at),
+            recent_lines: recent
+                .as_ref()
+                .map_or(0, |recent| count_field(recent, "lines")),
+            recent_errors: recent.map_or(0, |recent| count_field(&recent, "errors")),
+        })
+    }
+
+    ///Activity of every service that logged, in two aggregations whatever the number of
+    ///services: the newest line of each, read from the service_created_at index, and the
+    ///lines and ERROR or FATAL lines of each created since its entry in `since`, or
+    ///`default_since` when it has none
+    pub async fn get_services_activity(
+        &self,
+        default_since: DateTime,
+        since: &HashMap<ObjectId, DateTime>,
+    ) -> Result<HashMap<ObjectId, ServiceActivity>, Error> {
+        let mut activity: HashMap<ObjectId, ServiceActivity> = HashMap::new();
+
+        let newest = vec![
+            doc! { "$sort": { "my_service_id": 1, "created_at": -1 } },
+            doc! { "$group": {
+                "_id": "$my_service_id",
+                "last_log_at": { "$first": "$created_at" },
+            } },
+        ];
+        let documents: Vec<Document> = self
+            .log
+            .aggregate(newest)
+            .await
+            .map_err(ErrorInternalServerError)?
+            .try_collect()
+            .await
+            .map_err(ErrorInternalServerError)?;
+        for document in documents {
+            if let (Ok(service_id), Ok(last_log_at)) = (
+                document.get_object_id("_id"),
+                document.get_datetime("last_log_at"),
+            ) {
+                activity.entry(service_id).or_default().last_log_at = Some(*last_log_at);
+            }
+        }
+
+        let earliest = since.values().copied().fold(default_since, DateTime::min);
+        let window_start = match since.is_empty() {
+            true => Bson::DateTime(default_since),
+            false => {
+                let branches: Vec<Document> = since
+                    .iter()
+                    .map(|(service_id, since)| {
+                        doc! { "case": { "$eq": ["$my_service_id", service_id] }, "then": since }
+                    })
+                    .collect();
+                Bson::Document(
+                    doc! { "$switch": { "branches": branches, "default": default_since } },
+                )
+            }
+        };
+        let errors: Vec<String> = LogLevel::ERROR
+            .and_above()
+            .iter()
+            .map(LogLevel::to_string)
+            .collect();
+        let recent = vec![
+            doc! { "$match": { "created_at": { "$gte": earliest } } },
+            doc! { "$match": { "$expr": { "$gte": ["$created_at", window_start] } } },
+            doc! { "$project": {
+                "my_service_id": 1,
+                "level": 1,
+                "lines": { "$ifNull": ["$repeat_count", 1] },
+            } },
+            doc! { "$group": {
+                "_id": "$my_service_id",
+                "lines": { "$sum": "$lines" },
+                "errors": { "$sum": { "$cond": [{ "$in": ["$level", errors] }, "$lines", 0] } },
+            } },
+        ];
+        let documents: Vec<Document> = self
+            .log
+            .aggregate(recent)
+            .await
+            .map_err(ErrorInternalServerError)?
+            .try_collect()
+            .await
+            .map_err(ErrorInternalServerError)?;
+        for document in documents {
+            if let Ok(service_id) = document.get_object_id("_id") {
+                let service = activity.entry(service_id).or_default();
+                service.recent_lines = count_field(&document, "lines");
+                service.recent_errors = count_field(&document, "errors");
+            }
+        }
+
+        Ok(activity)
+    }
+
+    pub async fn get_logs_by_service(&self, service_id: &str) -> Result<Vec<Log>, Error> {
+        let serviceid = ObjectId::from_str(service_id).map_err(ErrorBadRequest)?;
 
         let filter = doc! { "my_service_id": serviceid };
         let cursor: Cursor<Log> = self
             .log
             .find(filter)
             .await
-            .ok()
-            .expect("error creating cusor to view documents");
+            .map_err(ErrorInternalServerError)?;
+
+        let items: Vec<Log> = cursor
+            .try_collect()
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        Ok(items)
+    }
+
+    pub async fn get_log_by_id(&self, log_id: &str) -> Result<Option<Log>, Error> {
+        let logid = ObjectId::from_str(log_id).map_err(ErrorBadRequest)?;
+
+        self.log
+            .find_one(doc! { "_id": logid })
+            .await
+            .map_err(ErrorInternalServerError)
+    }
+
+    ///The `before` lines preceding and `after` lines following a log in the same service and
+    ///source file, ordered by (created_at, _id). `None` when the log does not exist.
+    pub async fn get_log_context(
+        &self,
+        log_id: &str,
+        before: i64,
+        after: i64,
+    ) -> Result<Option<LogContextView>, Error> {
+        let Some(log) = self.get_log_by_id(log_id).await? else {
+            return Ok(None);
+        };
+
+        let (before_window, after_window) = context_windows(&log, before, after);
+        let mut before_logs = self.find_window(before_window).await?;
+        before_logs.reverse();
+        let after_logs = self.find_window(after_window).await?;
+
+        Ok(Some(LogContextView {
+            before: before_logs,
+            log,
+            after: after_logs,
+        }))
+    }
+
+    async fn find_window(&self, window: ContextWindow) -> Result<Vec<Log>, Error> {
+        if window.limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let cursor: Cursor<Log> = self
+            .log
+            .find(window.filter)
+            .sort(window.sort)
+            .limit(window.limit)
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        cursor.try_collect().await.map_err(ErrorInternalServerError)
+    }
+
+    ///Search logs on any combination of the indexed and structured fields, newest first unless
+    ///`order` is `asc`
+    pub async fn search_logs(&self, query: &LogSearchQuery) -> Result<Vec<Log>, Error> {
+        let filter = Self::search_filter(query)?;
+        let limit = query.limit.unwrap_or(100).clamp(1, MAX_SEARCH_LIMIT);
+        let sort = match query.order.unwrap_or_default() {
+            SearchOrder::Asc => doc! { "created_at": 1, "_id": 1 },
+            SearchOrder::Desc => doc! { "created_at": -1, "_id": -1 },
+        };
+
+        let cursor: Cursor<Log> = self
+            .log
+            .find(filter)
+            .sort(sort)
+            .limit(limit)
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        let items: Vec<Log> = cursor
+            .try_collect()
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        Ok(items)
+    }
+
+    ///At most `limit` lines matching the filters of `query` stored after the line `after`,
+    ///as an (`ingested_at`, `_id`) pair, in the order they were stored. `ingested_at` is
+    ///stamped by the server, so polling with the last line seen follows the lines as they
+    ///are stored, however long ago their id was made.
+    pub async fn search_logs_ingested_after(
+        &self,
+        query: &LogSearchQuery,
+        after: (DateTime, ObjectId),
+        limit: i64,
+    ) -> Result<Vec<Log>, Error> {
+        let (ingested_at, id) = after;
+        let mut filter = Self::search_filter(query)?;
+        filter.insert(
+            "$or",
+            vec![
+                doc! { "ingested_at": { "$gt": ingested_at } },
+                doc! { "ingested_at": ingested_at, "_id": { "$gt": id } },
+            ],
+        );
+
+        let cursor: Cursor<Log> = self
+            .log
+            .find(filter)
+            .sort(doc! { "ingested_at": 1, "_id": 1 })
+            .limit(limit.clamp(1, MAX_SEARCH_LIMIT))
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        cursor.try_collect().await.map_err(ErrorInternalServerError)
+    }
+
+    ///Lines per service and level in a time range, with the first and last of each
+    pub async fn get_log_stats(&self, query: &LogStatsQuery) -> Result<Vec<LogStatsView>, Error> {
+        let filter = Self::search_filter(&LogSearchQuery {
+            service_id: query.service_id.clone(),
+            from: query.from.clone(),
+            to: query.to.clone(),
+            ..LogSearchQuery::default()
+        })?;
+
+        let pipeline = vec![
+            doc! { "$match": filter },
+            doc! { "$group": {
+                "_id": { "service_id": "$my_service_id", "level": "$level" },
+                "count": { "$sum": { "$ifNull": ["$repeat_count", 1] } },
+                "first_seen": { "$min": "$created_at" },
+                "last_seen": { "$max": { "$ifNull": ["$last_seen", "$created_at"] } },
+            } },
+            doc! { "$project": {
+                "_id": 0,
+                "service_id": { "$toString": "$_id.service_id" },
+                "level": "$_id.level",
+                "count": 1,
+                "first_seen": 1,
+                "last_seen": 1,
+            } },
+            doc! { "$sort": { "service_id": 1, "count": -1 } },
+        ];
+
+        let cursor = self
+            .log
+            .aggregate(pipeline)
+            .await
+            .map_err(ErrorInternalServerError)?;
+        let documents: Vec<Document> = cursor
+            .try_collect()
+            .await
+            .map_err(ErrorInternalServerError)?;
+
+        documents
+            .into_iter()
+            .map(|document| {
+                mongodb::bson::from_document(document).map_err(ErrorInternalServerError)
+            })
+            .collect()
+    }
+
+    fn search_filter(query: &LogSearchQuery) -> Result<Document, Error> {
+        let mut filter = doc! {};
+
+        if let Some(service_id) = &query.service_id {
+            let serviceid = ObjectId::from_str(service_id).map_err(ErrorBadRequest)?;
+            filter.insert("my_service_id", serviceid);
+        }
+        if let Some(level) = &query.level {
+            filter.insert("level", level.to_string());
+        } else if let Some(min_level) = &query.min_level {
+            let levels: Vec<String> = min_level
+                .and_above()
+                .iter()
+                .map(LogLevel::to_string)
+                .collect();
+            filter.insert("level", doc! { "$in": levels });
+        }
+        if let Some(host) = &query.host {
+            filter.insert("host", host);
+        }
+        if let Some(source_file) = &query.source_file {
+            filter.insert("source_file", source_file);
+        }
+        if let Some(trace_id) = &query.trace_id {
+            filter.insert("trace_id", trace_id.to_lowercase());
+        }
+        if let Some(span_id) = &query.span_id {
+            filter.insert("span_id", span_id.to_lowercase());
+        }
+        if let Some(attribute) = &query.attribute {
+            let (key, value) = attribute
+                .split_once('=')
+                .ok_or_else(|| ErrorBadRequest("attribute filter must look like key=value"))?;
+
+            // Attributes keep their JSON type, so match the raw string as well as any
+            // number or boolean it could have been written as.
+            let mut candidates: Vec<Bson> = vec![Bson::String(value.to_string())];
+            if let Ok(number) = value.parse::<i64>() {
+                candidates.push(Bson::Int64(number));
+            } else if let Ok(number) = value.parse::<f64>() {
+                candidates.push(Bson::Double(number));
+            }
+            if let Ok(flag) = value.parse::<bool>() {
+                candidates.push(Bson::Boolean(flag));
+            }
+
+            filter.insert(format!("attributes.{key}"), doc! { "$in": candidates });
+        }
+        if let Some(min_repeat_count) = query.min_repeat_count {
+            filter.insert("repeat_count", doc! { "$gte": min_repeat_count });
+        }
+
+        let mut created_at = doc! {};
+        if let Some(from) = &query.from {
+            created_at.insert("$gte", Converter::parse_str_datetime(from)?);
+        }
+        if let Some(to) = &query.to {
+            created_at.insert("$lt", Converter::parse_str_datetime(to)?);
+        }
+        if !created_at.is_empty() {
+            filter.insert("created_at", created_at);
+        }
+
+        Ok(filter)
+    }
+
+    ///Every line recorded for a trace across all services, ordered by time
+    pub async fn get_logs_by_trace(&self, trace_id: &str) -> Result<Vec<Log>, Error> {
+        let filter = doc! { "trace_id": trace_id.to_lowercase() };
+
+        let cursor: Cursor<Log> = self
+            .log
+            .find(filter)
+            .sort(doc! { "created_at": 1, "_id": 1 })
+            .limit(MAX_TRACE_LINES)
+            .await
+            .map_err(ErrorInternalServerError)?;
 
         let items: Vec<Log> = cursor
             .try_collect()
             .await
-            .ok()
-            .expect("error reading through te logs");
+            .map_err(ErrorInternalServerError)?;
 
         Ok(items)
     }
 
+    ///Lines of a trace grouped by the service that wrote them
+    pub async fn get_trace(&self, trace_id: &str) -> Result<TraceView, Error> {
+        let logs = self.get_logs_by_trace(trace_id).await?;
+        let services = self.get_services().await?;
+
+        Ok(TraceView::from_logs(trace_id, logs, |service_id| {
+            services
+                .iter()
+                .find(|s| s._id.to_hex() == service_id)
+                .map(|s| s.name.clone())
+        }))
+    }
+
     pub async fn get_logs_service_by_date_range(
         &self,
-        service_id: &String,
+        service_id: &str,
         start: DateTime,
         end: DateTime,
     ) -> Result<Vec<MyService>, Error> {
@@ -186,18 +1231,16 @@ impl Database {
             "created_at": { "$gte": start, "$lt": end }
         };
 
-        let mut cursor: Cursor<MyService> = self
+        let cursor: Cursor<MyService> = self
             .myservice
             .find(filter)
             .await
-            .ok()
-            .expect("error getting logs by id and date range");
+            .map_err(ErrorInternalServerError)?;
 
         let services: Vec<MyService> = cursor
             .try_collect()
             .await
-            .ok()
-            .expect("error reading through the service list");
+            .map_err(ErrorInternalServerError)?;
 
         Ok(services)
     }
@@ -215,8 +1258,31 @@ impl Database {
             .log
             .delete_many(filter)
             .await
-            .ok()
-            .expect("error deleting logs by date");
+            .map_err(ErrorInternalServerError)?;
+
+        Ok(result.deleted_count)
+    }
+
+    ///Delete the logs created before `cutoff` of the services in `services`, or of every service
+    ///but those in `except` when `services` is empty
+    pub async fn delete_logs_before(
+        &self,
+        cutoff: DateTime,
+        services: &[ObjectId],
+        except: &[ObjectId],
+    ) -> Result<u64, Error> {
+        let mut filter = doc! { "created_at": { "$lt": cutoff } };
+        if !services.is_empty() {
+            filter.insert("my_service_id", doc! { "$in": services });
+        } else if !except.is_empty() {
+            filter.insert("my_service_id", doc! { "$nin": except });
+        }
+
+        let result = self
+            .log
+            .delete_many(filter)
+            .await
+            .map_err(ErrorInternalServerError)?;
 
         Ok(result.deleted_count)
     }
@@ -230,3 +1296,139 @@ impl Database {
         self.log.count_documents(filter).await
     }
 }
+
+///A `$sum` of an aggregation, which the server returns as a 32 or 64 bit integer
+fn count_field(document: &Document, key: &str) -> u64 {
+    match document.get(key) {
+        Some(Bson::Int32(count)) => *count as u64,
+        Some(Bson::Int64(count)) => *count as u64,
+        Some(Bson::Double(count)) => *count as u64,
+        _ => 0,
+    }
+}
+
+///Whether every write of a failed `insert_many` was rejected for a duplicate key
+fn only_duplicate_keys(err: &mongodb::error::Error) -> bool {
+    match err.kind.as_ref() {
+        ErrorKind::InsertMany(InsertManyError {
+            write_errors: Some(errors),
+            write_concern_error: None,
+            ..
+        }) => errors.iter().all(|error| error.code == DUPLICATE_KEY),
+        _ => false,
+    }
+}
+
+///Filter, sort and limit of the lines on one side of a log in `get_log_context`
+#[derive(Debug)]
+struct ContextWindow {
+    filter: Document,
+    sort: Document,
+    limit: i64,
+}
+
+///Windows of the lines preceding (newest first) and following `log` in its service and source
+///file. Lines written in the same millisecond are ordered by `_id`, so none is skipped or repeated.
+fn context_windows(log: &Log, before: i64, after: i64) -> (ContextWindow, ContextWindow) {
+    // A missing source_file matches other lines without one, i.e. lines sent straight to the API
+    let scope = doc! {
+        "my_service_id": log.my_service_id,
+        "source_file": log.source_file.clone(),
+    };
+
+    let side = |operator: &str, direction: i32, limit: i64| {
+        let mut filter = scope.clone();
+        filter.insert(
+            "$or",
+            vec![
+                doc! { "created_at": { operator: log.created_at } },
+                doc! { "created_at": log.created_at, "_id": { operator: log._id } },
+            ],
+        );
+        ContextWindow {
+            filter,
+            sort: doc! { "created_at": direction, "_id": direction },
+            limit: limit.clamp(0, MAX_CONTEXT_LINES),
+        }
+    };
+
+    (side("$lt", -1, before), side("$gt", 1, after))
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert_many_error(codes: &[i32]) -> mongodb::error::Error {
+        let write_errors: Vec<_> = codes
+            .iter()
+            .enumerate()
+            .map(|(index, code)| serde_json::json!({"index": index, "code": code, "errmsg": "E"}))
+            .collect();
+        let error: InsertManyError =
+            serde_json::from_value(serde_json::json!({ "writeErrors": write_errors })).unwrap();
+        ErrorKind::InsertMany(error).into()
+    }
+
+    fn log_at(millis: i64) -> Log {
+        Log {
+            created_at: DateTime::from_millis(millis),
+            source_file: Some(String::from("/var/log/orders.log")),
+            ..Log::sample(ObjectId::new(), LogLevel::INFO, "GET /orders 200")
+        }
+    }
+
+    #[test]
+    fn test_context_windows_are_capped() {
+        let log = log_at(1_760_000_000_000);
+        let (before, after) = context_windows(&log, 100_000, -4);
+        assert_eq!(before.limit, MAX_CONTEXT_LINES);
+        assert_eq!(after.limit, 0);
+
+        let (before, _) = context_windows(&log, MAX_CONTEXT_LINES, 0);
+        assert_eq!(before.limit, 500);
+    }
+
+    #[test]
+    fn test_context_of_lines_sent_without_a_file() {
+        let mut log = log_at(1_760_000_000_000);
+        log.source_file = None;
+        let (before, _) = context_windows(&log, 1, 1);
+        assert_eq!(before.filter.get("source_file"), Some(&Bson::Null));
+    }
+
+    #[tokio::test]
+    async fn test_log_context_of_an_invalid_id() {
+        // Nothing is sent to the server before the id is parsed, so none needs to be running
+        let db = Database::connect(&DatabaseConfig {
+            min_pool_size: 0,
+            ..DatabaseConfig::default()
+        })
+        .await;
+
+        let err = db.get_log_context("not-an-id", 5, 5).await.unwrap_err();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[test]
+    fn test_count_field() {
+        let counted = doc! { "small": 3, "large": 5_000_000_000_i64, "missing": Bson::Null };
+        assert_eq!(count_field(&counted, "small"), 3);
+        assert_eq!(count_field(&counted, "large"), 5_000_000_000);
+        assert_eq!(count_field(&counted, "missing"), 0);
+        assert_eq!(count_field(&counted, "absent"), 0);
+    }
+
+    #[test]
+    fn test_duplicate_keys_of_a_retried_batch() {
+        assert!(only_duplicate_keys(&insert_many_error(&[11000, 11000])));
+        assert!(!only_duplicate_keys(&insert_many_error(&[11000, 121])));
+        let io = ErrorKind::Io(std::sync::Arc::new(std::io::Error::other("reset"))).into();
+        assert!(!only_duplicate_keys(&io));
+    }
+}
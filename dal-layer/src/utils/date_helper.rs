@@ -1,6 +1,8 @@
-use chrono::{TimeZone, Utc};
+use actix_web::Error;
+use actix_web::error::ErrorBadRequest;
+use chrono::Utc;
 use mongodb::bson::DateTime;
-use std::{alloc::System, time::SystemTime};
+use std::time::SystemTime;
 
 // let start_chrono = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
 // let end_chrono   = Utc.with_ymd_and_hms(2025, 12, 31, 23, 59, 59).unwrap();
@@ -11,7 +13,7 @@ use std::{alloc::System, time::SystemTime};
 pub struct Converter;
 
 impl Converter {
-    pub fn convert_str_datetime(created_at: &String) -> DateTime {
+    pub fn convert_str_datetime(created_at: &str) -> DateTime {
         let chono_datetime: SystemTime = chrono::DateTime::parse_from_rfc3339(created_at)
             .map_err(|err| format!("Format to parse start_time: {} ", err))
             .unwrap()
@@ -20,4 +22,14 @@ impl Converter {
 
         DateTime::from(chono_datetime)
     }
+
+    ///Same as `convert_str_datetime` but reports a malformed date as a bad request instead of panicking
+    pub fn parse_str_datetime(value: &str) -> Result<DateTime, Error> {
+        let chono_datetime: SystemTime = chrono::DateTime::parse_from_rfc3339(value)
+            .map_err(|err| ErrorBadRequest(format!("Failed to parse date {value}: {err}")))?
+            .with_timezone(&Utc)
+            .into();
+
+        Ok(DateTime::from(chono_datetime))
+    }
 }
@@ -0,0 +1,157 @@
+//! Prometheus registry shared by the API and the collector, with the metrics recorded by the
+//! data layer itself: MongoDB command latency and errors, and the lines stored per service.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use mongodb::event::command::CommandEvent;
+use prometheus::core::Collector;
+use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder};
+
+use crate::models::log_model::Log;
+
+///Prefix of every metric name
+pub const NAMESPACE: &str = "log_monitor";
+
+pub static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::new);
+
+static MONGO_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "mongo_command_duration_seconds",
+                "Time MongoDB took to answer a command",
+            )
+            .namespace(NAMESPACE)
+            .buckets(vec![
+                0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0,
+            ]),
+            &["command"],
+        )
+        .unwrap(),
+    )
+});
+
+static MONGO_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new("mongo_command_errors_total", "MongoDB commands that failed")
+                .namespace(NAMESPACE),
+            &["command"],
+        )
+        .unwrap(),
+    )
+});
+
+static INGESTED_LINES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new("ingested_lines_total", "Log lines stored").namespace(NAMESPACE),
+            &["service_id", "level"],
+        )
+        .unwrap(),
+    )
+});
+
+static INGESTED_BYTES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new("ingested_bytes_total", "Bytes of line_content stored").namespace(NAMESPACE),
+            &["service_id", "level"],
+        )
+        .unwrap(),
+    )
+});
+
+///Add `metric` to the shared registry. Panics on a name registered twice, which is a bug.
+pub fn register<T: Collector + Clone + 'static>(metric: T) -> T {
+    REGISTRY
+        .register(Box::new(metric.clone()))
+        .expect("metric registered twice");
+    metric
+}
+
+///Handler of the MongoDB client's command events
+pub fn record_command(event: CommandEvent) {
+    match event {
+        CommandEvent::Succeeded(event) => MONGO_DURATION
+            .with_label_values(&[event.command_name.as_str()])
+            .observe(event.duration.as_secs_f64()),
+        CommandEvent::Failed(event) => {
+            MONGO_DURATION
+                .with_label_values(&[event.command_name.as_str()])
+                .observe(event.duration.as_secs_f64());
+            MONGO_ERRORS
+                .with_label_values(&[event.command_name.as_str()])
+                .inc();
+        }
+        _ => {}
+    }
+}
+
+///Count the lines and bytes of stored logs by service and level
+pub fn record_ingested(logs: &[Log]) {
+    let mut totals: HashMap<(String, String), (u64, u64)> = HashMap::new();
+    for log in logs {
+        let total = totals
+            .entry((log.my_service_id.to_hex(), log.level.to_string()))
+            .or_default();
+        total.0 += 1;
+        total.1 += log.line_content.len() as u64;
+    }
+
+    for ((service_id, level), (lines, bytes)) in totals {
+        INGESTED_LINES
+            .with_label_values(&[service_id.as_str(), level.as_str()])
+            .inc_by(lines);
+        INGESTED_BYTES
+            .with_label_values(&[service_id.as_str(), level.as_str()])
+            .inc_by(bytes);
+    }
+}
+
+///Every registered metric in the Prometheus text format
+pub fn encode() -> String {
+    // make sure the data layer's metrics are listed before anything was recorded
+    LazyLock::force(&MONGO_DURATION);
+    LazyLock::force(&MONGO_ERRORS);
+    LazyLock::force(&INGESTED_LINES);
+    LazyLock::force(&INGESTED_BYTES);
+
+    TextEncoder::new()
+        .encode_to_string(&REGISTRY.gather())
+        .unwrap_or_default()
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::log_model::LogLevel;
+    use mongodb::bson::oid::ObjectId;
+
+    #[test]
+    fn test_record_ingested() {
+        let service = ObjectId::new();
+        let log = |level: LogLevel, line: &str| Log::sample(service, level, line);
+
+        record_ingested(&[
+            log(LogLevel::INFO, "started"),
+            log(LogLevel::INFO, "ready"),
+            log(LogLevel::ERROR, "failed"),
+        ]);
+
+        let service = service.to_hex();
+        let text = encode();
+        assert!(text.contains(&format!(
+            "log_monitor_ingested_lines_total{{level=\"INFO\",service_id=\"{service}\"}} 2"
+        )));
+        assert!(text.contains(&format!(
+            "log_monitor_ingested_bytes_total{{level=\"INFO\",service_id=\"{service}\"}} 12"
+        )));
+        assert!(text.contains(&format!(
+            "log_monitor_ingested_lines_total{{level=\"ERROR\",service_id=\"{service}\"}} 1"
+        )));
+    }
+}
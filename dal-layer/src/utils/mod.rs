@@ -1 +1,3 @@
 pub mod date_helper;
+pub mod metrics;
+pub mod trace_helper;
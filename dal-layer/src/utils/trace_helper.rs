@@ -0,0 +1,154 @@
+use regex::Regex;
+
+use crate::models::log_model::{Log, LogRequest};
+
+///W3C `traceparent` header value, e.g. `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`
+const TRACEPARENT_PATTERN: &str =
+    r"(?i)\b[0-9a-f]{2}-(?P<trace_id>[0-9a-f]{32})-(?P<span_id>[0-9a-f]{16})-[0-9a-f]{2}\b";
+
+///`trace_id=...` / `"traceId": "..."` style fields, optionally followed by a span id on the same line
+const TRACE_FIELD_PATTERN: &str = r"(?i)trace[_.-]?id\W{1,4}(?P<trace_id>[0-9a-f]{32})\b(?:.*?span[_.-]?id\W{1,4}(?P<span_id>[0-9a-f]{16})\b)?";
+
+///Trace and span id found in a line of text
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceContext {
+    pub trace_id: String,
+    pub span_id: Option<String>,
+}
+
+///Pulls W3C trace context out of `line_content` for logs that were not sent with it.
+///
+///A custom pattern should name its groups `trace_id` and optionally `span_id`; without a
+///`trace_id` group the first capture group is used.
+#[derive(Debug, Clone)]
+pub struct TraceExtractor {
+    patterns: Vec<Regex>,
+}
+
+impl Default for TraceExtractor {
+    fn default() -> Self {
+        TraceExtractor {
+            patterns: vec![
+                Regex::new(TRACEPARENT_PATTERN).expect("built-in traceparent pattern"),
+                Regex::new(TRACE_FIELD_PATTERN).expect("built-in trace field pattern"),
+            ],
+        }
+    }
+}
+
+impl TraceExtractor {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(TraceExtractor {
+            patterns: vec![Regex::new(pattern)?],
+        })
+    }
+
+    ///Use `pattern` when one is configured, otherwise the built-in W3C patterns
+    pub fn from_pattern(pattern: Option<&str>) -> Result<Self, regex::Error> {
+        match pattern {
+            Some(pattern) if !pattern.trim().is_empty() => Self::new(pattern),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn extract(&self, line: &str) -> Option<TraceContext> {
+        self.patterns.iter().find_map(|pattern| {
+            let captures = pattern.captures(line)?;
+            let trace_id = captures
+                .name("trace_id")
+                .or_else(|| captures.get(1))?
+                .as_str()
+                .to_lowercase();
+            let span_id = captures
+                .name("span_id")
+                .map(|span| span.as_str().to_lowercase());
+
+            Some(TraceContext { trace_id, span_id })
+        })
+    }
+
+    ///Fill `trace_id`/`span_id` from the line content unless the log already carries a trace id
+    pub fn apply(&self, log: &mut Log) {
+        if log.trace_id.is_some() {
+            return;
+        }
+        if let Some(context) = self.extract(&log.line_content) {
+            log.trace_id = Some(context.trace_id);
+            log.span_id = log.span_id.take().or(context.span_id);
+        }
+    }
+
+    pub fn apply_request(&self, request: &mut LogRequest) {
+        if request.trace_id.is_some() {
+            return;
+        }
+        if let Some(context) = self.extract(&request.line_content) {
+            request.trace_id = Some(context.trace_id);
+            request.span_id = request.span_id.take().or(context.span_id);
+        }
+    }
+}
+
+///A W3C trace id is 32 hex characters and not all zeros
+pub fn is_valid_trace_id(trace_id: &str) -> bool {
+    trace_id.len() == 32
+        && trace_id.chars().all(|c| c.is_ascii_hexdigit())
+        && trace_id.chars().any(|c| c != '0')
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_traceparent() {
+        let extractor = TraceExtractor::default();
+        let line =
+            "GET /pay traceparent=00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01 200";
+
+        let context = extractor.extract(line).unwrap();
+
+        assert_eq!(context.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(context.span_id.as_deref(), Some("00f067aa0ba902b7"));
+    }
+
+    #[test]
+    fn test_extract_trace_field() {
+        let extractor = TraceExtractor::default();
+
+        let context = extractor
+            .extract(
+                "charge failed trace_id=4bf92f3577b34da6a3ce929d0e0e4736 span_id=00f067aa0ba902b7",
+            )
+            .unwrap();
+        assert_eq!(context.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(context.span_id.as_deref(), Some("00f067aa0ba902b7"));
+
+        let context = extractor
+            .extract(r#"{"traceId": "4bf92f3577b34da6a3ce929d0e0e4736"}"#)
+            .unwrap();
+        assert_eq!(context.span_id, None);
+
+        assert_eq!(extractor.extract("no trace context here"), None);
+    }
+
+    #[test]
+    fn test_extract_custom_pattern() {
+        let extractor = TraceExtractor::new(r"\[req:([0-9a-f]{32})\]").unwrap();
+
+        let context = extractor
+            .extract("[req:4bf92f3577b34da6a3ce929d0e0e4736] charge accepted")
+            .unwrap();
+
+        assert_eq!(context.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+    }
+
+    #[test]
+    fn test_is_valid_trace_id() {
+        assert!(is_valid_trace_id("4bf92f3577b34da6a3ce929d0e0e4736"));
+        assert!(!is_valid_trace_id("00000000000000000000000000000000"));
+        assert!(!is_valid_trace_id("4bf92f35"));
+    }
+}
@@ -0,0 +1,149 @@
+//!Queries run against a real MongoDB at MONGO_URI, each in a database of its own that is dropped
+//!afterwards. Run with `cargo test -p dal-layer -- --ignored`.
+
+use dal_layer::models::log_model::{Log, LogLevel, LogRequest, LogSearchQuery};
+use dal_layer::repository::config::DatabaseConfig;
+use dal_layer::repository::db::Database;
+use mongodb::bson::{DateTime, oid::ObjectId};
+
+///A database of its own for one test, and the name to drop it by
+async fn database() -> (Database, String) {
+    let mut config = DatabaseConfig::from_env();
+    config.name = format!("log_monitor_test_{}", ObjectId::new().to_hex());
+    let db = Database::connect(&config).await;
+    db.ensure_indexes()
+        .await
+        .expect("MongoDB is reachable at MONGO_URI");
+    (db, config.name)
+}
+
+async fn drop_database(name: &str) {
+    let config = DatabaseConfig::from_env();
+    let client = mongodb::Client::with_uri_str(&config.uri).await.unwrap();
+    client.database(name).drop().await.unwrap();
+}
+
+fn log(service_id: ObjectId, line: &str, created_at: &str) -> Log {
+    Log::try_from(LogRequest::new(
+        &service_id.to_hex(),
+        LogLevel::INFO,
+        line,
+        created_at.to_string(),
+    ))
+    .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at MONGO_URI"]
+async fn test_follow_a_line_stored_long_after_its_id_was_made() {
+    let (db, name) = database().await;
+    let service = ObjectId::new();
+    let query = LogSearchQuery::default();
+
+    let fresh = log(service, "fresh", "2025-10-24T13:15:06.698Z");
+    db.insert_logs_bulk(std::slice::from_ref(&fresh))
+        .await
+        .unwrap();
+    let origin = (DateTime::from_millis(0), ObjectId::from_bytes([0; 12]));
+    let seen = db
+        .search_logs_ingested_after(&query, origin, 10)
+        .await
+        .unwrap();
+    assert_eq!(seen.len(), 1);
+    let cursor = (seen[0].ingested_at.unwrap(), seen[0]._id);
+
+    // Made an hour ago, stored now: behind the cursor by id, ahead of it by storage time. The
+    // pause keeps the two stamps apart, which a stream's lookback does not need.
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    let mut old = log(service, "retried", "2025-10-24T13:15:06.698Z");
+    let an_hour_ago = (fresh._id.timestamp().timestamp_millis() / 1000 - 3600) as u32;
+    old._id = ObjectId::from_parts(an_hour_ago, [1; 5], [0; 3]);
+    assert!(old._id < fresh._id);
+    db.insert_logs_bulk(std::slice::from_ref(&old))
+        .await
+        .unwrap();
+
+    let next = db
+        .search_logs_ingested_after(&query, cursor, 10)
+        .await
+        .unwrap();
+    assert_eq!(
+        next.iter().map(|log| log._id).collect::<Vec<_>>(),
+        vec![old._id]
+    );
+
+    // Stored again by a retried batch, it keeps its stamp and is not followed twice
+    db.insert_logs_bulk(std::slice::from_ref(&old))
+        .await
+        .unwrap();
+    let cursor = (next[0].ingested_at.unwrap(), next[0]._id);
+    assert!(
+        db.search_logs_ingested_after(&query, cursor, 10)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    drop_database(&name).await;
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at MONGO_URI"]
+async fn test_log_context_breaks_ties_on_id() {
+    let (db, name) = database().await;
+    let service = ObjectId::new();
+    let mut lines: Vec<Log> = [
+        ("a", "2025-10-24T13:15:05.000Z"),
+        ("b", "2025-10-24T13:15:06.000Z"),
+        ("target", "2025-10-24T13:15:06.000Z"),
+        ("c", "2025-10-24T13:15:06.000Z"),
+        ("d", "2025-10-24T13:15:07.000Z"),
+    ]
+    .into_iter()
+    .map(|(line, created_at)| log(service, line, created_at))
+    .collect();
+    for line in lines.iter_mut() {
+        line.source_file = Some(String::from("/var/log/orders.log"));
+    }
+    // Stored out of order, so neither the insert order nor the id's time decides
+    lines.swap(1, 3);
+    db.insert_logs_bulk(&lines).await.unwrap();
+
+    let context = db
+        .get_log_context(&lines[2]._id.to_hex(), 5, 5)
+        .await
+        .unwrap()
+        .expect("the log is stored");
+    let text = |logs: &[Log]| {
+        logs.iter()
+            .map(|log| log.line_content.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(text(&context.before), ["a", "b"]);
+    assert_eq!(context.log.line_content, "target");
+    assert_eq!(text(&context.after), ["c", "d"]);
+
+    let context = db
+        .get_log_context(&lines[2]._id.to_hex(), 1, 1)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(text(&context.before), ["b"]);
+    assert_eq!(text(&context.after), ["c"]);
+
+    drop_database(&name).await;
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at MONGO_URI"]
+async fn test_log_context_of_a_missing_log() {
+    let (db, name) = database().await;
+
+    let context = db
+        .get_log_context(&ObjectId::new().to_hex(), 5, 5)
+        .await
+        .unwrap();
+    assert!(context.is_none());
+
+    drop_database(&name).await;
+}
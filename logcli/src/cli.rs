@@ -0,0 +1,239 @@
+//! Command line of `logcli`: the server and profile options shared by every command, and the
+//! filters shared by `search`, `tail` and `export`.
+
+use std::path::PathBuf;
+
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use dal_layer::models::log_model::LogLevel;
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Search, follow and export the logs of the log monitor"
+)]
+pub struct Cli {
+    ///Profile of the config file holding the server and token
+    #[arg(long, short, global = true, env = "LOGCLI_PROFILE")]
+    pub profile: Option<String>,
+    ///URL of the API, overriding the profile
+    #[arg(long, global = true, env = "LOGCLI_SERVER")]
+    pub server: Option<String>,
+    ///API key, overriding the profile
+    #[arg(long, global = true, env = "LOGCLI_TOKEN", hide_env_values = true)]
+    pub token: Option<String>,
+    ///Config file of the profiles; by default $XDG_CONFIG_HOME/logcli/config.toml
+    #[arg(long, global = true, env = "LOGCLI_CONFIG")]
+    pub config: Option<PathBuf>,
+    ///Print one JSON object per line instead of text
+    #[arg(long, global = true)]
+    pub json: bool,
+    ///When to color the levels
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    ///When printing to a terminal and NO_COLOR is not set
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum Command {
+    ///List the registered services
+    Services,
+    ///Print the lines matching the filters, newest first
+    Search {
+        #[command(flatten)]
+        filters: Filters,
+        ///Most lines to print
+        #[arg(long, short = 'n', default_value_t = 100)]
+        limit: i64,
+        ///Print the oldest lines first
+        #[arg(long)]
+        oldest_first: bool,
+    },
+    ///Print the most recent matching lines, oldest first, and with -f the new ones as they
+    ///arrive
+    Tail {
+        #[command(flatten)]
+        filters: Filters,
+        ///Lines printed before following
+        #[arg(long, short = 'n', default_value_t = 10)]
+        lines: i64,
+        ///Keep printing the lines as they arrive
+        #[arg(long, short)]
+        follow: bool,
+    },
+    ///Write every matching line to a file, oldest first
+    Export {
+        #[command(flatten)]
+        filters: Filters,
+        ///File to write
+        #[arg(long, short)]
+        output: PathBuf,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Jsonl)]
+        format: ExportFormat,
+    },
+    ///Count the lines per service and level
+    Stats {
+        ///Service name or id
+        #[arg(long, short)]
+        service: Option<String>,
+        #[command(flatten)]
+        range: TimeRange,
+    },
+    ///Manage the profiles of the config file
+    #[command(subcommand)]
+    Profile(ProfileCommand),
+}
+
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum ProfileCommand {
+    ///Add or replace a profile
+    Set {
+        name: String,
+        ///URL of the API
+        #[arg(long)]
+        server: String,
+        ///API key sent as a bearer token
+        #[arg(long)]
+        token: Option<String>,
+        ///Use the profile when --profile is not given
+        #[arg(long)]
+        default: bool,
+    },
+    ///List the profiles, tokens hidden
+    List,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    ///One JSON object per line
+    Jsonl,
+    ///The lines as printed by search, without color
+    Text,
+}
+
+///Filters of the lines to print
+#[derive(Debug, Clone, Default, PartialEq, Args)]
+pub struct Filters {
+    ///Service name or id
+    #[arg(long, short)]
+    pub service: Option<String>,
+    ///Exact level
+    #[arg(long, short)]
+    pub level: Option<LogLevel>,
+    ///Only lines at least this severe, e.g. warn
+    #[arg(long, conflicts_with = "level")]
+    pub min_level: Option<LogLevel>,
+    #[arg(long)]
+    pub host: Option<String>,
+    ///Path of the file the lines were read from
+    #[arg(long)]
+    pub file: Option<String>,
+    ///W3C trace id
+    #[arg(long)]
+    pub trace: Option<String>,
+    #[arg(long)]
+    pub span: Option<String>,
+    ///Attribute written as key=value, e.g. user_id=42
+    #[arg(long)]
+    pub attr: Option<String>,
+    #[command(flatten)]
+    pub range: TimeRange,
+}
+
+///Time range of the lines, relative with --since or absolute with --from and --to
+#[derive(Debug, Clone, Default, PartialEq, Args)]
+pub struct TimeRange {
+    ///Only lines from this long ago, e.g. 30s, 15m, 2h or 7d
+    #[arg(long, value_parser = parse_age, conflicts_with = "from")]
+    pub since: Option<Duration>,
+    ///RFC 3339 time, included
+    #[arg(long)]
+    pub from: Option<DateTime<Utc>>,
+    ///RFC 3339 time, excluded
+    #[arg(long)]
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl TimeRange {
+    ///Lower and upper bound as the RFC 3339 strings the API expects
+    pub fn bounds(&self, now: DateTime<Utc>) -> (Option<String>, Option<String>) {
+        let from = self.since.map(|since| now - since).or(self.from);
+        let format = |time: DateTime<Utc>| time.to_rfc3339_opts(SecondsFormat::Millis, true);
+        (from.map(format), self.to.map(format))
+    }
+}
+
+///Parse an age such as `90s`, `15m`, `2h` or `7d`
+pub fn parse_age(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("{value:?} is not an age such as 30s, 15m, 2h or 7d");
+    let split = value.len().saturating_sub(1);
+    let (number, unit) = (value.get(..split).ok_or_else(invalid)?, &value[split..]);
+    let number: i64 = number.parse().map_err(|_| invalid())?;
+
+    match unit {
+        "s" => Ok(Duration::seconds(number)),
+        "m" => Ok(Duration::minutes(number)),
+        "h" => Ok(Duration::hours(number)),
+        "d" => Ok(Duration::days(number)),
+        _ => Err(invalid()),
+    }
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        let cli = Cli::parse_from([
+            "logcli",
+            "tail",
+            "-f",
+            "--service",
+            "checkout",
+            "--min-level",
+            "warning",
+            "--since",
+            "15m",
+            "--json",
+        ]);
+        assert!(cli.json);
+        let Command::Tail {
+            filters,
+            lines,
+            follow,
+        } = cli.command
+        else {
+            panic!("not a tail");
+        };
+        assert_eq!((lines, follow), (10, true));
+        assert_eq!(filters.service.as_deref(), Some("checkout"));
+        assert_eq!(filters.min_level, Some(LogLevel::WARN));
+
+        let now = DateTime::parse_from_rfc3339("2025-10-24T13:15:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            filters.range.bounds(now),
+            (Some(String::from("2025-10-24T13:00:00.000Z")), None)
+        );
+
+        assert!(parse_age("7x").is_err());
+        assert!(parse_age("").is_err());
+        assert!(
+            Cli::try_parse_from(["logcli", "search", "--level", "info", "--min-level", "warn"])
+                .is_err()
+        );
+    }
+}
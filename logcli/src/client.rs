@@ -0,0 +1,233 @@
+//! Calls to `rust-tooling-api`, unwrapping the `GenericResponse` of every endpoint.
+
+use std::collections::VecDeque;
+
+use dal_layer::models::{
+    log_model::{Log, LogSearchQuery, LogStatsQuery, LogStatsView},
+    my_service_model::{MyServiceStatus, MyServiceView},
+    response_model::GenericResponse,
+};
+use reqwest::{RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+
+use crate::profile::Profile;
+
+pub struct ApiClient {
+    http: reqwest::Client,
+    server: String,
+    token: Option<String>,
+}
+
+impl ApiClient {
+    pub fn new(profile: &Profile) -> Result<Self, String> {
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("logcli/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|err| err.to_string())?;
+
+        Ok(ApiClient {
+            http,
+            server: profile.server.trim_end_matches('/').to_string(),
+            token: profile.token.clone(),
+        })
+    }
+
+    pub async fn services(&self) -> Result<Vec<MyServiceView>, String> {
+        let services: Vec<MyServiceStatus> = self.fetch(self.get("/api/services")).await?;
+
+        Ok(services
+            .into_iter()
+            .map(|status| MyServiceView::from(status.service))
+            .collect())
+    }
+
+    pub async fn search(&self, query: &LogSearchQuery) -> Result<Vec<Log>, String> {
+        self.fetch(self.get("/api/logs/search").query(query)).await
+    }
+
+    pub async fn stats(&self, query: &LogStatsQuery) -> Result<Vec<LogStatsView>, String> {
+        self.fetch(self.get("/api/logs/stats").query(query)).await
+    }
+
+    ///Follow the lines matching `query`, starting with its `limit` most recent ones
+    pub async fn stream(&self, query: &LogSearchQuery) -> Result<LogStream, String> {
+        let response = self.send(self.get("/api/logs/stream").query(query)).await?;
+
+        Ok(LogStream {
+            response,
+            parser: EventParser::default(),
+            pending: VecDeque::new(),
+        })
+    }
+
+    fn get(&self, path: &str) -> RequestBuilder {
+        let request = self.http.get(format!("{}{path}", self.server));
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    async fn fetch<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, String> {
+        let response = self.send(request).await?;
+        let url = response.url().path().to_string();
+        let body: GenericResponse<T> = response
+            .json()
+            .await
+            .map_err(|err| format!("Unexpected response from {url}: {err}"))?;
+
+        Ok(body.data)
+    }
+
+    ///Send `request`, turning a failed status into the message of its response
+    async fn send(&self, request: RequestBuilder) -> Result<Response, String> {
+        let response = request
+            .send()
+            .await
+            .map_err(|err| format!("Cannot reach {}: {err}", self.server))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<GenericResponse<String>>(&body)
+            .map(|body| body.data)
+            .unwrap_or(body);
+        Err(format!("{status}: {message}"))
+    }
+}
+
+///Lines received from the stream endpoint
+pub struct LogStream {
+    response: Response,
+    parser: EventParser,
+    pending: VecDeque<String>,
+}
+
+impl LogStream {
+    ///Next line, or `None` once the server closed the stream
+    pub async fn next(&mut self) -> Result<Option<Log>, String> {
+        loop {
+            if let Some(data) = self.pending.pop_front() {
+                return serde_json::from_str(&data)
+                    .map(Some)
+                    .map_err(|err| format!("Unexpected event from the stream: {err}"));
+            }
+
+            match self.response.chunk().await {
+                Ok(Some(chunk)) => self.pending.extend(self.parser.push(&chunk)),
+                Ok(None) => return Ok(None),
+                Err(err) => return Err(format!("The stream was interrupted: {err}")),
+            }
+        }
+    }
+}
+
+///Splits Server-Sent Events received in chunks into the data of each event. Comments and the
+///other fields are skipped.
+#[derive(Debug, Default)]
+pub struct EventParser {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl EventParser {
+    ///Data of the events completed by `chunk`
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        while let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(self.data.join("\n"));
+                    self.data.clear();
+                }
+            } else if let Some(data) = line.strip_prefix("data:") {
+                self.data
+                    .push(data.strip_prefix(' ').unwrap_or(data).to_string());
+            }
+        }
+
+        events
+    }
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_layer::models::log_model::LogLevel;
+
+    #[test]
+    fn test_parse_events() {
+        let mut parser = EventParser::default();
+        let line = r#"{"_id":{"$oid":"697dff2cbfc969fe967002b8"},"my_service_id":{"$oid":"697dff2cbfc969fe967002b7"},"level":"ERROR","line_content":"payment declined","created_at":{"$date":{"$numberLong":"1761311706698"}}}"#;
+        let (first, second) = line.split_at(40);
+
+        assert!(
+            parser
+                .push(
+                    format!(": keep-alive\n\nid: 697dff2cbfc969fe967002b8\ndata: {first}")
+                        .as_bytes()
+                )
+                .is_empty()
+        );
+        let events = parser.push(format!("{second}\r\n\r\ndata: a\ndata: b\n\n").as_bytes());
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1], "a\nb");
+
+        let log: Log = serde_json::from_str(&events[0]).unwrap();
+        assert_eq!(log._id.to_hex(), "697dff2cbfc969fe967002b8");
+        assert_eq!(log.level, LogLevel::ERROR);
+        assert_eq!(
+            log.created_at.try_to_rfc3339_string().unwrap(),
+            "2025-10-24T13:15:06.698Z"
+        );
+    }
+
+    #[test]
+    fn test_decode_services() {
+        use dal_layer::models::my_service_model::{
+            HealthThresholds, MyService, ServiceActivity, ServiceHealth,
+        };
+        use mongodb::bson::{DateTime, oid::ObjectId};
+
+        let onboarded = DateTime::from_millis(1_717_063_200_000);
+        let status = MyServiceStatus {
+            service: MyService {
+                _id: ObjectId::parse_str("697dff2cbfc969fe967002b7").unwrap(),
+                name: String::from("payment"),
+                description: Some(String::from("This is the payment service")),
+                onboarded_datetime: onboarded,
+            },
+            health: ServiceHealth::from_activity(
+                &ServiceActivity::default(),
+                &HealthThresholds::default(),
+                onboarded,
+            ),
+        };
+        // the body exactly as the API serializes it
+        let body = serde_json::to_string(&GenericResponse {
+            code: String::from("200"),
+            data: vec![status],
+        })
+        .unwrap();
+        assert!(body.contains(r#""_id":{"$oid":"697dff2cbfc969fe967002b7"}"#));
+
+        let decoded: GenericResponse<Vec<MyServiceStatus>> = serde_json::from_str(&body).unwrap();
+        let view = MyServiceView::from(decoded.data[0].service.clone());
+        assert_eq!(view.service_id.as_deref(), Some("697dff2cbfc969fe967002b7"));
+        assert_eq!(view.name, "payment");
+        assert_eq!(
+            view.onboarded_datetime.as_deref(),
+            Some("2024-05-30T10:00:00Z")
+        );
+    }
+}
@@ -0,0 +1,297 @@
+mod cli;
+mod client;
+mod output;
+mod profile;
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, IsTerminal, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use chrono::Utc;
+use clap::Parser;
+use dal_layer::models::log_model::{LogSearchQuery, LogStatsQuery, SearchOrder};
+use dal_layer::models::my_service_model::MyServiceView;
+
+use crate::cli::{Cli, ColorChoice, Command, ExportFormat, Filters, ProfileCommand};
+use crate::client::ApiClient;
+use crate::output::Printer;
+use crate::profile::ProfileFile;
+
+///Lines asked for by each page of an export, the most the search endpoint returns
+const EXPORT_PAGE: i64 = 1000;
+
+///Wait before following again after the stream was interrupted
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("logcli: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), String> {
+    let config_path = cli.config.clone().unwrap_or_else(profile::default_path);
+    let profiles = ProfileFile::load(&config_path)?;
+
+    if let Command::Profile(command) = &cli.command {
+        return manage_profiles(profiles, command, &config_path);
+    }
+
+    let profile = profiles.resolve(
+        cli.profile.as_deref(),
+        cli.server.as_deref(),
+        cli.token.as_deref(),
+    )?;
+    let client = ApiClient::new(&profile)?;
+    let services = client.services().await?;
+    let color = match cli.color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+        }
+    };
+    let printer = Printer::new(cli.json, color, &services);
+    let mut out = std::io::stdout().lock();
+
+    match cli.command {
+        Command::Services => {
+            for service in &services {
+                writeln!(out, "{}", printer.service(service)).map_err(|err| err.to_string())?;
+            }
+        }
+        Command::Search {
+            filters,
+            limit,
+            oldest_first,
+        } => {
+            let mut query = search_query(&filters, &services)?;
+            query.limit = Some(limit);
+            query.order = oldest_first.then_some(SearchOrder::Asc);
+            for log in client.search(&query).await? {
+                writeln!(out, "{}", printer.line(log)).map_err(|err| err.to_string())?;
+            }
+        }
+        Command::Tail {
+            filters,
+            lines,
+            follow,
+        } => {
+            let mut query = search_query(&filters, &services)?;
+            query.limit = Some(lines);
+            if follow {
+                return follow_logs(&client, &printer, query, &mut out).await;
+            }
+
+            let mut recent = client.search(&query).await?;
+            recent.reverse();
+            for log in recent {
+                writeln!(out, "{}", printer.line(log)).map_err(|err| err.to_string())?;
+            }
+        }
+        Command::Export {
+            filters,
+            output,
+            format,
+        } => {
+            let query = search_query(&filters, &services)?;
+            let printer = Printer::new(format == ExportFormat::Jsonl, false, &services);
+            let exported = export(&client, &printer, query, &output).await?;
+            eprintln!("Exported {exported} lines to {}", output.display());
+        }
+        Command::Stats { service, range } => {
+            let (from, to) = range.bounds(Utc::now());
+            let query = LogStatsQuery {
+                service_id: service
+                    .map(|service| service_id(&service, &services))
+                    .transpose()?,
+                from,
+                to,
+            };
+            for line in printer.stats(&client.stats(&query).await?) {
+                writeln!(out, "{line}").map_err(|err| err.to_string())?;
+            }
+        }
+        Command::Profile(_) => unreachable!("handled before connecting"),
+    }
+
+    Ok(())
+}
+
+fn manage_profiles(
+    mut profiles: ProfileFile,
+    command: &ProfileCommand,
+    path: &Path,
+) -> Result<(), String> {
+    match command {
+        ProfileCommand::Set {
+            name,
+            server,
+            token,
+            default,
+        } => {
+            profiles.profiles.insert(
+                name.clone(),
+                profile::Profile {
+                    server: server.clone(),
+                    token: token.clone(),
+                },
+            );
+            if *default || profiles.default.is_none() {
+                profiles.default = Some(name.clone());
+            }
+            profiles.save(path)?;
+            eprintln!("Saved profile {name} to {}", path.display());
+        }
+        ProfileCommand::List => {
+            for (name, profile) in &profiles.profiles {
+                let default = match profiles.default.as_deref() == Some(name) {
+                    true => " (default)",
+                    false => "",
+                };
+                let token = match profile.token {
+                    Some(_) => "token set",
+                    None => "no token",
+                };
+                println!("{name}{default}: {} ({token})", profile.server);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+///The search query of `filters`, the service looked up by name
+fn search_query(filters: &Filters, services: &[MyServiceView]) -> Result<LogSearchQuery, String> {
+    let (from, to) = filters.range.bounds(Utc::now());
+
+    Ok(LogSearchQuery {
+        service_id: filters
+            .service
+            .as_deref()
+            .map(|service| service_id(service, services))
+            .transpose()?,
+        level: filters.level,
+        min_level: filters.min_level,
+        host: filters.host.clone(),
+        source_file: filters.file.clone(),
+        trace_id: filters.trace.clone(),
+        span_id: filters.span.clone(),
+        attribute: filters.attr.clone(),
+        from,
+        to,
+        ..LogSearchQuery::default()
+    })
+}
+
+///Id of the service named `service`, or `service` itself when it is an id
+fn service_id(service: &str, services: &[MyServiceView]) -> Result<String, String> {
+    services
+        .iter()
+        .find(|known| known.name == service || known.service_id.as_deref() == Some(service))
+        .and_then(|known| known.service_id.clone())
+        .ok_or_else(|| format!("No service is named {service:?}"))
+}
+
+///Print the lines as they arrive until interrupted, following again when the stream breaks
+async fn follow_logs(
+    client: &ApiClient,
+    printer: &Printer,
+    mut query: LogSearchQuery,
+    out: &mut impl Write,
+) -> Result<(), String> {
+    loop {
+        let mut stream = client.stream(&query).await?;
+        // lines sent before a reconnection were printed already
+        query.limit = Some(0);
+
+        loop {
+            match stream.next().await {
+                Ok(Some(log)) => {
+                    writeln!(out, "{}", printer.line(log)).map_err(|err| err.to_string())?;
+                    out.flush().map_err(|err| err.to_string())?;
+                }
+                Ok(None) => {
+                    eprintln!("logcli: the server closed the stream, following again");
+                    break;
+                }
+                Err(err) => {
+                    eprintln!("logcli: {err}, following again");
+                    break;
+                }
+            }
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+///Write every line of `query` to `path` page by page, oldest first. Returns how many lines
+///were written.
+async fn export(
+    client: &ApiClient,
+    printer: &Printer,
+    mut query: LogSearchQuery,
+    path: &Path,
+) -> Result<usize, String> {
+    let file =
+        File::create(path).map_err(|err| format!("Cannot create {}: {err}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    query.limit = Some(EXPORT_PAGE);
+    query.order = Some(SearchOrder::Asc);
+
+    let mut exported = 0;
+    // Each page starts at the time of the last line of the previous one, included, so the
+    // lines of that millisecond already written are skipped
+    let mut boundary: HashSet<String> = HashSet::new();
+    loop {
+        let page = client.search(&query).await?;
+        let full = page.len() as i64 == EXPORT_PAGE;
+        let Some(last) = page.last().map(|log| log.created_at) else {
+            break;
+        };
+
+        let mut written = 0;
+        let mut last_ids = HashSet::new();
+        for log in page {
+            if log.created_at == last {
+                last_ids.insert(log._id.to_hex());
+            }
+            if boundary.contains(&log._id.to_hex()) {
+                continue;
+            }
+            writeln!(out, "{}", printer.line(log))
+                .map_err(|err| format!("Cannot write {}: {err}", path.display()))?;
+            written += 1;
+        }
+        exported += written;
+
+        if !full {
+            break;
+        }
+        if written == 0 {
+            return Err(format!(
+                "More than {EXPORT_PAGE} lines share the time {}; narrow the filters",
+                last.try_to_rfc3339_string().unwrap_or_default()
+            ));
+        }
+        if query.from.as_deref() != last.try_to_rfc3339_string().ok().as_deref() {
+            boundary.clear();
+        }
+        boundary.extend(last_ids);
+        query.from = last.try_to_rfc3339_string().ok();
+    }
+
+    out.flush()
+        .map_err(|err| format!("Cannot write {}: {err}", path.display()))?;
+    Ok(exported)
+}
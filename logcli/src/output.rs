@@ -0,0 +1,234 @@
+//! How lines, services and statistics are printed: colored text for terminals, or one JSON
+//! object per line for piping.
+
+use std::collections::{BTreeMap, HashMap};
+
+use dal_layer::models::{
+    log_model::{Log, LogLevel, LogStatsView},
+    my_service_model::MyServiceView,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+const RESET: &str = "\x1b[0m";
+
+///A line as printed with --json: plain strings instead of the BSON ids and dates
+#[derive(Debug, Clone, Serialize)]
+pub struct LogRecord {
+    pub id: String,
+    ///Name of the service, its id when the service is not known
+    pub service: String,
+    pub service_id: String,
+    pub level: LogLevel,
+    pub created_at: String,
+    pub line_content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_count: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<HashMap<String, Value>>,
+}
+
+pub struct Printer {
+    pub json: bool,
+    pub color: bool,
+    ///Service names by id
+    pub services: HashMap<String, String>,
+}
+
+impl Printer {
+    pub fn new(json: bool, color: bool, services: &[MyServiceView]) -> Self {
+        Printer {
+            json,
+            color,
+            services: services
+                .iter()
+                .filter_map(|service| Some((service.service_id.clone()?, service.name.clone())))
+                .collect(),
+        }
+    }
+
+    pub fn record(&self, log: Log) -> LogRecord {
+        let service_id = log.my_service_id.to_hex();
+        LogRecord {
+            id: log._id.to_hex(),
+            service: self.service_name(&service_id).to_string(),
+            service_id,
+            level: log.level,
+            created_at: log
+                .created_at
+                .try_to_rfc3339_string()
+                .unwrap_or_else(|_| log.created_at.to_string()),
+            line_content: log.line_content,
+            host: log.host,
+            source_file: log.source_file,
+            trace_id: log.trace_id,
+            span_id: log.span_id,
+            repeat_count: log.repeat_count,
+            attributes: log.attributes,
+        }
+    }
+
+    ///`log` as printed: a JSON object with --json, otherwise its time, level, service and text
+    pub fn line(&self, log: Log) -> String {
+        let record = self.record(log);
+        if self.json {
+            return serde_json::to_string(&record).unwrap_or_default();
+        }
+
+        let level = format!("{:<5}", record.level.to_string());
+        let level = match self.color {
+            true => format!("{}{level}{RESET}", level_color(record.level)),
+            false => level,
+        };
+        let repeats = match record.repeat_count {
+            Some(count) if count > 1 => format!(" (x{count})"),
+            _ => String::new(),
+        };
+
+        format!(
+            "{} {level} {} {}{repeats}",
+            record.created_at, record.service, record.line_content
+        )
+    }
+
+    pub fn service(&self, service: &MyServiceView) -> String {
+        if self.json {
+            return serde_json::to_string(service).unwrap_or_default();
+        }
+
+        format!(
+            "{:<24}  {:<24}  {}",
+            service.service_id.as_deref().unwrap_or("-"),
+            service.name,
+            service.description.as_deref().unwrap_or("")
+        )
+    }
+
+    ///A table of the lines per service and level, or one JSON object per service
+    pub fn stats(&self, stats: &[LogStatsView]) -> Vec<String> {
+        let mut per_service: BTreeMap<&str, BTreeMap<LogLevel, i64>> = BTreeMap::new();
+        for row in stats {
+            *per_service
+                .entry(self.service_name(&row.service_id))
+                .or_default()
+                .entry(row.level)
+                .or_default() += row.count;
+        }
+
+        let mut lines = Vec::new();
+        if !self.json {
+            let mut header = format!("{:<24} {:>10}", "SERVICE", "TOTAL");
+            for level in LogLevel::ALL.iter().rev() {
+                let column = format!(" {:>8}", level.to_string());
+                header.push_str(&match self.color {
+                    true => format!("{}{column}{RESET}", level_color(*level)),
+                    false => column,
+                });
+            }
+            lines.push(header);
+        }
+
+        for (service, levels) in per_service {
+            let total: i64 = levels.values().sum();
+            if self.json {
+                let levels: BTreeMap<String, i64> = levels
+                    .into_iter()
+                    .map(|(level, count)| (level.to_string(), count))
+                    .collect();
+                let row =
+                    serde_json::json!({ "service": service, "total": total, "levels": levels });
+                lines.push(row.to_string());
+                continue;
+            }
+
+            let mut line = format!("{service:<24} {total:>10}");
+            for level in LogLevel::ALL.iter().rev() {
+                line.push_str(&format!(" {:>8}", levels.get(level).copied().unwrap_or(0)));
+            }
+            lines.push(line);
+        }
+
+        lines
+    }
+
+    fn service_name<'a>(&'a self, service_id: &'a str) -> &'a str {
+        self.services
+            .get(service_id)
+            .map_or(service_id, String::as_str)
+    }
+}
+
+///ANSI color of a level: the more severe, the more visible
+fn level_color(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::FATAL => "\x1b[1;31m",
+        LogLevel::ERROR => "\x1b[31m",
+        LogLevel::WARN => "\x1b[33m",
+        LogLevel::INFO => "\x1b[32m",
+        LogLevel::DEBUG => "\x1b[34m",
+        LogLevel::TRACE | LogLevel::OTHER => "\x1b[2m",
+    }
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_layer::models::log_model::LogRequest;
+
+    #[test]
+    fn test_print_lines() {
+        let services = [MyServiceView {
+            service_id: Some(String::from("697dff2cbfc969fe967002b7")),
+            name: String::from("checkout"),
+            description: None,
+            onboarded_datetime: None,
+        }];
+        let mut log = Log::try_from(LogRequest {
+            host: Some(String::from("payment-7d9f8")),
+            ..LogRequest::new(
+                "697dff2cbfc969fe967002b7",
+                LogLevel::WARN,
+                "retrying payment",
+                String::from("2025-10-24T13:15:06.698Z"),
+            )
+        })
+        .unwrap();
+        log.repeat_count = Some(3);
+
+        let text = Printer::new(false, false, &services);
+        assert_eq!(
+            text.line(log.clone()),
+            "2025-10-24T13:15:06.698Z WARN  checkout retrying payment (x3)"
+        );
+        let colored = Printer::new(false, true, &services);
+        assert!(colored.line(log.clone()).contains("\x1b[33mWARN \x1b[0m"));
+
+        let json = Printer::new(true, true, &[]);
+        let record: Value = serde_json::from_str(&json.line(log.clone())).unwrap();
+        assert_eq!(record["service"], "697dff2cbfc969fe967002b7");
+        assert_eq!(record["level"], "WARN");
+        assert_eq!(record["host"], "payment-7d9f8");
+        assert!(record.get("trace_id").is_none());
+
+        let stats = text.stats(&[LogStatsView {
+            service_id: String::from("697dff2cbfc969fe967002b7"),
+            level: LogLevel::ERROR,
+            count: 4,
+            first_seen: log.created_at,
+            last_seen: log.created_at,
+        }]);
+        assert_eq!(stats.len(), 2);
+        assert!(stats[1].starts_with("checkout"));
+        assert!(stats[1].contains("         4        0        4"));
+    }
+}
@@ -0,0 +1,170 @@
+//! Profiles of the config file, each holding the URL of an API and the key to call it with:
+//!
+//! ```toml
+//! default = "prod"
+//!
+//! [profiles.prod]
+//! server = "https://logs.example.com"
+//! token = "..."
+//! ```
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+///Server used when neither the flags nor a profile name one
+pub const DEFAULT_SERVER: &str = "http://localhost:5000";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileFile {
+    ///Profile used when --profile is not given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub server: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl ProfileFile {
+    ///Read the file at `path`; a missing file has no profile
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content)
+                .map_err(|err| format!("Invalid config file {}: {err}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(ProfileFile::default()),
+            Err(err) => Err(format!("Cannot read {}: {err}", path.display())),
+        }
+    }
+
+    ///Write the file to `path`, readable by its owner only since it holds the tokens
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let content = toml::to_string(self).map_err(|err| err.to_string())?;
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)
+                .map_err(|err| format!("Cannot create {}: {err}", parent.display()))?;
+        }
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options
+            .open(path)
+            .and_then(|mut file| file.write_all(content.as_bytes()))
+            .map_err(|err| format!("Cannot write {}: {err}", path.display()))
+    }
+
+    ///Server and token to use: the flags first, then the profile named `name`, or the default
+    ///one, then [`DEFAULT_SERVER`] without a token
+    pub fn resolve(
+        &self,
+        name: Option<&str>,
+        server: Option<&str>,
+        token: Option<&str>,
+    ) -> Result<Profile, String> {
+        let profile = match name.or(self.default.as_deref()) {
+            Some(name) => Some(
+                self.profiles
+                    .get(name)
+                    .ok_or_else(|| format!("No profile is named {name:?}"))?,
+            ),
+            None => None,
+        };
+
+        Ok(Profile {
+            server: server
+                .map(str::to_string)
+                .or_else(|| profile.map(|profile| profile.server.clone()))
+                .unwrap_or_else(|| String::from(DEFAULT_SERVER)),
+            token: token
+                .map(str::to_string)
+                .or_else(|| profile.and_then(|profile| profile.token.clone())),
+        })
+    }
+}
+
+///$XDG_CONFIG_HOME/logcli/config.toml, or ~/.config/logcli/config.toml
+pub fn default_path() -> PathBuf {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_default();
+
+    config_home.join("logcli").join("config.toml")
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_and_save() {
+        let file: ProfileFile = toml::from_str(
+            r#"
+            default = "prod"
+
+            [profiles.prod]
+            server = "https://logs.example.com"
+            token = "prod-0123456789abcdef"
+
+            [profiles.local]
+            server = "http://localhost:5000"
+            "#,
+        )
+        .unwrap();
+
+        let prod = file.resolve(None, None, None).unwrap();
+        assert_eq!(prod.server, "https://logs.example.com");
+        assert_eq!(prod.token.as_deref(), Some("prod-0123456789abcdef"));
+
+        let local = file
+            .resolve(Some("local"), None, Some("local-0123456789abcdef"))
+            .unwrap();
+        assert_eq!(local.server, "http://localhost:5000");
+        assert_eq!(local.token.as_deref(), Some("local-0123456789abcdef"));
+
+        let flags = file
+            .resolve(None, Some("http://10.0.0.7:5000"), None)
+            .unwrap();
+        assert_eq!(flags.server, "http://10.0.0.7:5000");
+        assert!(file.resolve(Some("staging"), None, None).is_err());
+        assert_eq!(
+            ProfileFile::default()
+                .resolve(None, None, None)
+                .unwrap()
+                .server,
+            DEFAULT_SERVER
+        );
+
+        let path = env::temp_dir()
+            .join(format!("logcli-{}", std::process::id()))
+            .join("config.toml");
+        assert_eq!(ProfileFile::load(&path).unwrap(), ProfileFile::default());
+        file.save(&path).unwrap();
+        assert_eq!(ProfileFile::load(&path).unwrap(), file);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+//! Typed calls to `rust-tooling-api`, unwrapping the `GenericResponse` of every endpoint.
+
+use std::time::Duration;
+
+use chrono::{SecondsFormat, Utc};
+use dal_layer::models::{
+    log_model::{BulkLogResult, LogRequest},
+    my_service_model::{MyServiceStatus, MyServiceView},
+    response_model::GenericResponse,
+};
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+
+///Time allowed for one call before it fails
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("cannot reach the API: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("{status}: {message}")]
+    Status { status: StatusCode, message: String },
+}
+
+impl ClientError {
+    ///Whether the same call may succeed later: the API could not be reached, was unavailable
+    ///or asked to slow down
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::Http(err) => !err.is_builder() && !err.is_decode(),
+            ClientError::Status { status, .. } => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogClient {
+    http: reqwest::Client,
+    server: String,
+    token: Option<String>,
+}
+
+impl LogClient {
+    ///Client of the API at `server`, e.g. `https://logs.example.com`, sending `token` as a
+    ///bearer token when the API requires keys
+    pub fn new(server: &str, token: Option<String>) -> Result<Self, ClientError> {
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("rust-log-client/", env!("CARGO_PKG_VERSION")))
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+
+        Ok(LogClient {
+            http,
+            server: server.trim_end_matches('/').to_string(),
+            token,
+        })
+    }
+
+    pub async fn services(&self) -> Result<Vec<MyServiceView>, ClientError> {
+        let services: Vec<MyServiceStatus> = self
+            .call(self.request(Method::GET, "/api/services"))
+            .await?;
+
+        Ok(services
+            .into_iter()
+            .map(|status| MyServiceView::from(status.service))
+            .collect())
+    }
+
+    ///Register `service`, returning its id. `onboarded_datetime` defaults to now.
+    pub async fn register_service(&self, service: &MyServiceView) -> Result<String, ClientError> {
+        let service = MyServiceView {
+            service_id: None,
+            onboarded_datetime: Some(
+                service
+                    .onboarded_datetime
+                    .clone()
+                    .unwrap_or_else(|| Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+            ),
+            ..service.clone()
+        };
+
+        self.call(self.request(Method::POST, "/api/service").json(&service))
+            .await
+    }
+
+    ///Id of the service called `name`, registering it with `description` when it is unknown
+    pub async fn service_id(
+        &self,
+        name: &str,
+        description: Option<&str>,
+    ) -> Result<String, ClientError> {
+        let known = self
+            .services()
+            .await?
+            .into_iter()
+            .find(|service| service.name == name)
+            .and_then(|service| service.service_id);
+        if let Some(id) = known {
+            return Ok(id);
+        }
+
+        self.register_service(&MyServiceView {
+            service_id: None,
+            name: name.to_string(),
+            description: description.map(str::to_string),
+            onboarded_datetime: None,
+        })
+        .await
+    }
+
+    ///Store one line, returning its id
+    pub async fn send_log(&self, log: &LogRequest) -> Result<String, ClientError> {
+        self.call(self.request(Method::POST, "/api/log").json(log))
+            .await
+    }
+
+    ///Store a batch of lines in one request
+    pub async fn send_logs(&self, logs: &[LogRequest]) -> Result<BulkLogResult, ClientError> {
+        self.call(self.request(Method::POST, "/api/logs/bulk").json(logs))
+            .await
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self.http.request(method, format!("{}{path}", self.server));
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    async fn call<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, ClientError> {
+        let response = check(request.send().await?).await?;
+        let body: GenericResponse<T> = response.json().await?;
+
+        Ok(body.data)
+    }
+}
+
+///`response` when its status is a success, otherwise the message it carries
+async fn check(response: Response) -> Result<Response, ClientError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<GenericResponse<String>>(&body)
+        .map(|body| body.data)
+        .unwrap_or(body);
+    Err(ClientError::Status { status, message })
+}
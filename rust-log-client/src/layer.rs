@@ -0,0 +1,167 @@
+//! `tracing` adapter: a layer queuing every event on a [`Shipper`], with the fields of the
+//! spans it happened in. `message` becomes the line, `trace_id` and `span_id` fields the trace
+//! context, and every other field an attribute.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use dal_layer::models::log_model::{LogLevel, LogRequest};
+use serde_json::Value;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::shipper::{self, Shipper, is_shipping_thread};
+
+pub struct ShipperLayer {
+    shipper: Shipper,
+}
+
+impl ShipperLayer {
+    pub fn new(shipper: Shipper) -> Self {
+        ShipperLayer { shipper }
+    }
+}
+
+///Fields of a span, kept in its extensions for the events within it
+struct SpanFields(HashMap<String, Value>);
+
+#[derive(Default)]
+struct FieldVisitor(HashMap<String, Value>);
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), Value::from(format!("{value:?}")));
+    }
+}
+
+impl<S> Layer<S> for ShipperLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut fields = FieldVisitor::default();
+            attrs.record(&mut fields);
+            span.extensions_mut().insert(SpanFields(fields.0));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(SpanFields(fields)) = span.extensions_mut().get_mut::<SpanFields>()
+        {
+            let mut recorded = FieldVisitor::default();
+            values.record(&mut recorded);
+            fields.extend(recorded.0);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if is_shipping_thread() {
+            return;
+        }
+
+        // the fields of inner spans override those of outer ones, and the event's override all
+        let mut fields = HashMap::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(SpanFields(span_fields)) = span.extensions().get::<SpanFields>() {
+                    fields.extend(span_fields.clone());
+                }
+            }
+        }
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        fields.extend(visitor.0);
+
+        let metadata = event.metadata();
+        let level = match *metadata.level() {
+            Level::ERROR => LogLevel::ERROR,
+            Level::WARN => LogLevel::WARN,
+            Level::INFO => LogLevel::INFO,
+            Level::DEBUG => LogLevel::DEBUG,
+            Level::TRACE => LogLevel::TRACE,
+        };
+        let mut take_str = |name: &str| match fields.remove(name) {
+            Some(Value::String(value)) => Some(value),
+            Some(value) => Some(value.to_string()),
+            None => None,
+        };
+        let message = take_str("message").unwrap_or_default();
+        let trace_id = take_str("trace_id");
+        let span_id = take_str("span_id");
+        fields.insert(String::from("target"), Value::from(metadata.target()));
+
+        self.shipper.ship(LogRequest {
+            attributes: Some(fields),
+            trace_id,
+            span_id,
+            ..shipper::request(level, message)
+        });
+    }
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shipper::tests::queue;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_ship_events() {
+        let (shipper, mut lines) = queue(10);
+        let subscriber = tracing_subscriber::registry().with(ShipperLayer::new(shipper));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!(
+                "checkout",
+                trace_id = "4bf92f3577b34da6a3ce929d0e0e4736",
+                order = 42,
+                user = tracing::field::Empty
+            );
+            let _entered = span.enter();
+            span.record("user", "alice");
+            tracing::warn!(order = 43, retry = true, "payment declined");
+        });
+
+        let line = lines.try_recv().unwrap();
+        assert_eq!(line.level, LogLevel::WARN);
+        assert_eq!(line.line_content, "payment declined");
+        assert_eq!(
+            line.trace_id.as_deref(),
+            Some("4bf92f3577b34da6a3ce929d0e0e4736")
+        );
+        let attributes = line.attributes.unwrap();
+        assert_eq!(attributes["order"], 43);
+        assert_eq!(attributes["user"], "alice");
+        assert_eq!(attributes["retry"], true);
+        assert!(!attributes.contains_key("message"));
+        assert!(lines.try_recv().is_err());
+    }
+}
@@ -0,0 +1,29 @@
+//! Ships the logs of a Rust service straight to `rust-tooling-api`, instead of writing files
+//! for the collector to read.
+//!
+//! [`LogClient`] calls the API directly. [`Shipper`] queues lines and sends them in batches
+//! from a background thread; [`ShipperLogger`] feeds it from the `log` crate and
+//! [`ShipperLayer`] from `tracing`:
+//!
+//! ```no_run
+//! use rust_log_client::{OnLoss, Shipper, ShipperConfig, ShipperLogger};
+//!
+//! let mut config = ShipperConfig::new("https://logs.example.com", "payment");
+//! config.token = std::env::var("LOG_API_TOKEN").ok();
+//! config.on_loss = Some(OnLoss::new(|loss| eprintln!("{loss}")));
+//! let shipper = Shipper::start(config).unwrap();
+//! let _guard = ShipperLogger::new(shipper, log::LevelFilter::Info).init().unwrap();
+//!
+//! log::info!("payment service started");
+//! // the queued lines are sent when the guard is dropped
+//! ```
+
+pub mod client;
+pub mod layer;
+pub mod logger;
+pub mod shipper;
+
+pub use client::{ClientError, LogClient};
+pub use layer::ShipperLayer;
+pub use logger::ShipperLogger;
+pub use shipper::{Loss, OnLoss, Shipper, ShipperConfig, ShipperStats, ShutdownGuard};
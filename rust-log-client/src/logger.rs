@@ -0,0 +1,111 @@
+//! `log` adapter: a logger queuing every record at or above its level on a [`Shipper`].
+
+use std::collections::HashMap;
+
+use dal_layer::models::log_model::{LogLevel, LogRequest};
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+use serde_json::Value;
+
+use crate::shipper::{self, Shipper, ShutdownGuard, is_shipping_thread};
+
+pub struct ShipperLogger {
+    shipper: Shipper,
+    level: LevelFilter,
+}
+
+impl ShipperLogger {
+    pub fn new(shipper: Shipper, level: LevelFilter) -> Self {
+        ShipperLogger { shipper, level }
+    }
+
+    ///Install as the logger of the `log` crate. The returned guard flushes and stops the
+    ///shipper when dropped, so keep it until `main` returns.
+    pub fn init(self) -> Result<ShutdownGuard, SetLoggerError> {
+        let guard = ShutdownGuard::new(self.shipper.clone());
+        let level = self.level;
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(level);
+
+        Ok(guard)
+    }
+}
+
+impl Log for ShipperLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level && !is_shipping_thread()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.shipper.ship(to_request(record));
+        }
+    }
+
+    fn flush(&self) {
+        self.shipper.flush();
+    }
+}
+
+fn to_request(record: &Record) -> LogRequest {
+    let level = match record.level() {
+        Level::Error => LogLevel::ERROR,
+        Level::Warn => LogLevel::WARN,
+        Level::Info => LogLevel::INFO,
+        Level::Debug => LogLevel::DEBUG,
+        Level::Trace => LogLevel::TRACE,
+    };
+
+    let mut attributes = HashMap::from([(String::from("target"), Value::from(record.target()))]);
+    if let Some(module_path) = record.module_path() {
+        attributes.insert(String::from("module_path"), Value::from(module_path));
+    }
+    if let Some(file) = record.file() {
+        attributes.insert(String::from("file"), Value::from(file));
+    }
+    if let Some(line) = record.line() {
+        attributes.insert(String::from("line"), Value::from(line));
+    }
+
+    LogRequest {
+        attributes: Some(attributes),
+        ..shipper::request(level, record.args().to_string())
+    }
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shipper::tests::queue;
+
+    #[test]
+    fn test_log_records() {
+        let (shipper, mut lines) = queue(10);
+        let logger = ShipperLogger::new(shipper, LevelFilter::Info);
+
+        logger.log(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("payment::checkout")
+                .line(Some(42))
+                .args(format_args!("retrying order {}", 7))
+                .build(),
+        );
+        logger.log(
+            &Record::builder()
+                .level(Level::Debug)
+                .args(format_args!("too verbose"))
+                .build(),
+        );
+
+        let line = lines.try_recv().unwrap();
+        assert_eq!(line.level, LogLevel::WARN);
+        assert_eq!(line.line_content, "retrying order 7");
+        assert_eq!(line.host.as_deref(), Some("payment-7d9f8"));
+        let attributes = line.attributes.unwrap();
+        assert_eq!(attributes["target"], "payment::checkout");
+        assert_eq!(attributes["line"], 42);
+        assert!(lines.try_recv().is_err());
+    }
+}
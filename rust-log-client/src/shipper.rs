@@ -0,0 +1,633 @@
+//! Ships lines to the API from a background thread. Lines are queued without blocking the
+//! caller and sent in batches; when the queue is full new lines are dropped and counted, and
+//! the queued lines are sent on flush and on shutdown.
+
+use std::cell::Cell;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, mpsc as std_mpsc};
+use std::thread;
+use std::time::Duration;
+
+use chrono::{SecondsFormat, Utc};
+use dal_layer::models::log_model::LogRequest;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::time::MissedTickBehavior;
+
+use crate::client::{ClientError, LogClient};
+
+thread_local! {
+    ///Set on the threads of the shipper, whose own logging (e.g. of the HTTP client) must not
+    ///be shipped or it would feed itself
+    static SHIPPING: Cell<bool> = const { Cell::new(false) };
+}
+
+///Whether the current thread is one of the shipper's
+pub fn is_shipping_thread() -> bool {
+    SHIPPING.with(Cell::get)
+}
+
+#[derive(Debug, Clone)]
+pub struct ShipperConfig {
+    ///URL of the API, e.g. `https://logs.example.com`
+    pub server: String,
+    ///API key sent as a bearer token
+    pub token: Option<String>,
+    ///Service the lines are stored under, registered when the API does not know it
+    pub service_name: String,
+    ///Id of the service, skipping the look up by name
+    pub service_id: Option<String>,
+    ///Set on lines that name no host; defaults to the hostname
+    pub host: Option<String>,
+    ///Lines queued before new ones are dropped
+    pub capacity: usize,
+    ///Most lines sent in one request
+    pub batch_size: usize,
+    ///Longest a line waits to be sent
+    pub flush_interval: Duration,
+    ///Attempts at a batch the API could not take before it is dropped
+    pub max_attempts: u32,
+    ///Wait before the second attempt, doubled before each later one
+    pub retry_backoff: Duration,
+    ///Longest `flush` and `shutdown` wait for the lines to be sent
+    pub flush_timeout: Duration,
+    ///Told of every line lost; lost lines are only counted in `ShipperStats` without it
+    pub on_loss: Option<OnLoss>,
+}
+
+impl ShipperConfig {
+    pub fn new(server: &str, service_name: &str) -> Self {
+        ShipperConfig {
+            server: server.to_string(),
+            token: None,
+            service_name: service_name.to_string(),
+            service_id: None,
+            host: hostname::get()
+                .ok()
+                .and_then(|name| name.into_string().ok()),
+            capacity: 10_000,
+            batch_size: 200,
+            flush_interval: Duration::from_secs(1),
+            max_attempts: 3,
+            retry_backoff: Duration::from_millis(500),
+            flush_timeout: Duration::from_secs(5),
+            on_loss: None,
+        }
+    }
+}
+
+///Lines the shipper lost
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Loss {
+    ///Dropped because the queue of `capacity` lines was full
+    Overflow { lines: u64, capacity: usize },
+    ///Refused by the API as invalid or over its ingestion limit
+    Refused { lines: u64, reason: String },
+    ///Not taken by the API after every attempt
+    Failed { lines: u64, error: String },
+}
+
+impl fmt::Display for Loss {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Loss::Overflow { lines, capacity } => {
+                write!(
+                    f,
+                    "Dropped {lines} log lines, the queue of {capacity} was full"
+                )
+            }
+            Loss::Refused { lines, reason } => {
+                write!(f, "The log API refused {lines} lines, e.g. {reason}")
+            }
+            Loss::Failed { lines, error } => {
+                write!(
+                    f,
+                    "Dropped {lines} log lines the API could not take: {error}"
+                )
+            }
+        }
+    }
+}
+
+///Callback told of the lines the shipper lost. It runs on the shipper's thread, whose logging
+///is never shipped, so it may log the loss without feeding the shipper.
+#[derive(Clone)]
+pub struct OnLoss(Arc<dyn Fn(&Loss) + Send + Sync>);
+
+impl OnLoss {
+    pub fn new(callback: impl Fn(&Loss) + Send + Sync + 'static) -> Self {
+        OnLoss(Arc::new(callback))
+    }
+}
+
+impl fmt::Debug for OnLoss {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("OnLoss(..)")
+    }
+}
+
+///Lines shipped and lost since the shipper started
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShipperStats {
+    ///Stored by the API
+    pub sent: u64,
+    ///Dropped because the queue was full or the shipper stopped
+    pub overflowed: u64,
+    ///Lost because the API could not be reached, or refused them as invalid or over a limit
+    pub failed: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    sent: AtomicU64,
+    overflowed: AtomicU64,
+    failed: AtomicU64,
+}
+
+enum Control {
+    Flush(std_mpsc::Sender<()>),
+    Shutdown(std_mpsc::Sender<()>),
+}
+
+///Handle to the shipper; clones share the same queue and thread
+#[derive(Clone)]
+pub struct Shipper {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    lines: mpsc::Sender<LogRequest>,
+    control: mpsc::UnboundedSender<Control>,
+    counters: Arc<Counters>,
+    host: Option<String>,
+    flush_timeout: Duration,
+    thread: Mutex<Option<thread::JoinHandle<()>>>,
+}
+
+impl Shipper {
+    ///Start the thread shipping to the API of `config`. It runs its own runtime, so the
+    ///shipper can be used with or without one.
+    pub fn start(config: ShipperConfig) -> Result<Self, String> {
+        let client =
+            LogClient::new(&config.server, config.token.clone()).map_err(|err| err.to_string())?;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .on_thread_start(|| SHIPPING.with(|shipping| shipping.set(true)))
+            .build()
+            .map_err(|err| format!("Cannot start the log shipper: {err}"))?;
+
+        let (shipper, lines, control) = Shipper::channel(&config);
+        let worker = Worker {
+            client,
+            config: config.clone(),
+            service_id: config.service_id.clone(),
+            lines,
+            control,
+            counters: shipper.inner.counters.clone(),
+            batch: Vec::new(),
+            reported_overflow: 0,
+        };
+        let thread = thread::Builder::new()
+            .name(String::from("log-shipper"))
+            .spawn(move || {
+                SHIPPING.with(|shipping| shipping.set(true));
+                runtime.block_on(worker.run());
+            })
+            .map_err(|err| format!("Cannot start the log shipper: {err}"))?;
+        *shipper
+            .inner
+            .thread
+            .lock()
+            .unwrap_or_else(|err| err.into_inner()) = Some(thread);
+
+        Ok(shipper)
+    }
+
+    fn channel(
+        config: &ShipperConfig,
+    ) -> (
+        Self,
+        mpsc::Receiver<LogRequest>,
+        mpsc::UnboundedReceiver<Control>,
+    ) {
+        let (lines, lines_receiver) = mpsc::channel(config.capacity.max(1));
+        let (control, control_receiver) = mpsc::unbounded_channel();
+        let shipper = Shipper {
+            inner: Arc::new(Inner {
+                lines,
+                control,
+                counters: Arc::default(),
+                host: config.host.clone(),
+                flush_timeout: config.flush_timeout,
+                thread: Mutex::new(None),
+            }),
+        };
+
+        (shipper, lines_receiver, control_receiver)
+    }
+
+    ///Queue `log` without waiting. Its `my_service_id` is set by the shipper. Returns false when
+    ///the line was dropped because the queue is full or the shipper stopped.
+    pub fn ship(&self, mut log: LogRequest) -> bool {
+        if log.host.is_none() {
+            log.host = self.inner.host.clone();
+        }
+
+        match self.inner.lines.try_send(log) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_) | TrySendError::Closed(_)) => {
+                self.inner
+                    .counters
+                    .overflowed
+                    .fetch_add(1, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+
+    ///Send the queued lines, waiting at most `flush_timeout`
+    pub fn flush(&self) {
+        let (done, wait) = std_mpsc::channel();
+        if self.inner.control.send(Control::Flush(done)).is_ok() {
+            let _ = wait.recv_timeout(self.inner.flush_timeout);
+        }
+    }
+
+    ///Send the queued lines and stop the thread, waiting at most `flush_timeout`. Lines shipped
+    ///afterwards are dropped.
+    pub fn shutdown(&self) {
+        let (done, wait) = std_mpsc::channel();
+        if self.inner.control.send(Control::Shutdown(done)).is_err()
+            || wait.recv_timeout(self.inner.flush_timeout).is_err()
+        {
+            return;
+        }
+
+        let thread = self
+            .inner
+            .thread
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .take();
+        if let Some(thread) = thread {
+            let _ = thread.join();
+        }
+    }
+
+    pub fn stats(&self) -> ShipperStats {
+        let counters = &self.inner.counters;
+        ShipperStats {
+            sent: counters.sent.load(Ordering::Relaxed),
+            overflowed: counters.overflowed.load(Ordering::Relaxed),
+            failed: counters.failed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+///Shuts the shipper down when dropped, so the lines queued when `main` returns are sent
+#[must_use = "the shipper is shut down when the guard is dropped"]
+pub struct ShutdownGuard {
+    shipper: Shipper,
+}
+
+impl ShutdownGuard {
+    pub fn new(shipper: Shipper) -> Self {
+        ShutdownGuard { shipper }
+    }
+}
+
+impl Drop for ShutdownGuard {
+    fn drop(&mut self) {
+        self.shipper.shutdown();
+    }
+}
+
+///A line at `level` written now, to be completed by the shipper
+pub(crate) fn request(
+    level: dal_layer::models::log_model::LogLevel,
+    line_content: String,
+) -> LogRequest {
+    LogRequest {
+        level,
+        my_service_id: String::new(),
+        line_content,
+        created_at: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        attributes: None,
+        host: None,
+        source_file: None,
+        line_offset: None,
+        trace_id: None,
+        span_id: None,
+    }
+}
+
+struct Worker {
+    client: LogClient,
+    config: ShipperConfig,
+    service_id: Option<String>,
+    lines: mpsc::Receiver<LogRequest>,
+    control: mpsc::UnboundedReceiver<Control>,
+    counters: Arc<Counters>,
+    batch: Vec<LogRequest>,
+    ///Overflowed lines already reported
+    reported_overflow: u64,
+}
+
+impl Worker {
+    async fn run(mut self) {
+        let mut tick = tokio::time::interval(self.config.flush_interval);
+        tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                line = self.lines.recv() => match line {
+                    Some(line) => {
+                        self.batch.push(line);
+                        if self.batch.len() >= self.config.batch_size {
+                            self.send_batch().await;
+                        }
+                    }
+                    // every handle was dropped
+                    None => {
+                        self.send_batch().await;
+                        return;
+                    }
+                },
+                control = self.control.recv() => match control {
+                    Some(Control::Flush(done)) => {
+                        self.send_queued().await;
+                        let _ = done.send(());
+                    }
+                    Some(Control::Shutdown(done)) => {
+                        self.lines.close();
+                        self.send_queued().await;
+                        let _ = done.send(());
+                        return;
+                    }
+                    None => {
+                        self.send_queued().await;
+                        return;
+                    }
+                },
+                _ = tick.tick() => self.send_batch().await,
+            }
+        }
+    }
+
+    ///Send the lines waiting in the queue as well as the batch
+    async fn send_queued(&mut self) {
+        while let Ok(line) = self.lines.try_recv() {
+            self.batch.push(line);
+            if self.batch.len() >= self.config.batch_size {
+                self.send_batch().await;
+            }
+        }
+        self.send_batch().await;
+    }
+
+    async fn send_batch(&mut self) {
+        self.report_overflow();
+        if self.batch.is_empty() {
+            return;
+        }
+        let mut batch = std::mem::take(&mut self.batch);
+        let lines = batch.len() as u64;
+
+        let mut attempt = 1;
+        let result = loop {
+            match self.try_send(&mut batch).await {
+                Err(err) if err.is_retryable() && attempt < self.config.max_attempts => {
+                    tokio::time::sleep(self.config.retry_backoff * 2u32.pow(attempt - 1)).await;
+                    attempt += 1;
+                }
+                result => break result,
+            }
+        };
+
+        match result {
+            Ok(result) => {
+                self.counters
+                    .sent
+                    .fetch_add(result.stored as u64, Ordering::Relaxed);
+                let refused = (result.limited + result.invalid.len()) as u64;
+                self.counters.failed.fetch_add(refused, Ordering::Relaxed);
+                if refused > 0 {
+                    let reason = result
+                        .invalid
+                        .first()
+                        .cloned()
+                        .unwrap_or_else(|| String::from("over the service's ingestion limit"));
+                    self.report(Loss::Refused {
+                        lines: refused,
+                        reason,
+                    });
+                }
+            }
+            Err(err) => {
+                self.counters.failed.fetch_add(lines, Ordering::Relaxed);
+                self.report(Loss::Failed {
+                    lines,
+                    error: err.to_string(),
+                });
+            }
+        }
+    }
+
+    async fn try_send(
+        &mut self,
+        batch: &mut [LogRequest],
+    ) -> Result<dal_layer::models::log_model::BulkLogResult, ClientError> {
+        let service_id = match &self.service_id {
+            Some(id) => id.clone(),
+            None => {
+                let id = self
+                    .client
+                    .service_id(&self.config.service_name, None)
+                    .await?;
+                self.service_id = Some(id.clone());
+                id
+            }
+        };
+        for line in batch.iter_mut() {
+            line.my_service_id.clone_from(&service_id);
+        }
+
+        self.client.send_logs(batch).await
+    }
+
+    fn report_overflow(&mut self) {
+        let overflowed = self.counters.overflowed.load(Ordering::Relaxed);
+        if overflowed > self.reported_overflow {
+            self.report(Loss::Overflow {
+                lines: overflowed - self.reported_overflow,
+                capacity: self.config.capacity,
+            });
+            self.reported_overflow = overflowed;
+        }
+    }
+
+    fn report(&self, loss: Loss) {
+        if let Some(OnLoss(callback)) = &self.config.on_loss {
+            callback(&loss);
+        }
+    }
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use dal_layer::models::log_model::LogLevel;
+    use serde_json::Value;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    ///A shipper without a thread, and the queue its lines go to
+    pub(crate) fn queue(capacity: usize) -> (Shipper, mpsc::Receiver<LogRequest>) {
+        let mut config = ShipperConfig::new("http://localhost:5000", "checkout");
+        config.capacity = capacity;
+        config.host = Some(String::from("payment-7d9f8"));
+        let (shipper, lines, _) = Shipper::channel(&config);
+        (shipper, lines)
+    }
+
+    ///Serve the endpoints the shipper calls, sending the body of each bulk request to the
+    ///returned receiver
+    fn fake_api() -> (String, std_mpsc::Receiver<Vec<Value>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        let (batches, received) = std_mpsc::channel();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { return };
+                let batches = batches.clone();
+                thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut stream = stream;
+                    loop {
+                        let mut request_line = String::new();
+                        if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+                            return;
+                        }
+                        let mut length = 0;
+                        loop {
+                            let mut header = String::new();
+                            reader.read_line(&mut header).unwrap();
+                            if header.trim().is_empty() {
+                                break;
+                            }
+                            if let Some((name, value)) = header.split_once(':')
+                                && name.eq_ignore_ascii_case("content-length")
+                            {
+                                length = value.trim().parse().unwrap();
+                            }
+                        }
+                        let mut body = vec![0; length];
+                        reader.read_exact(&mut body).unwrap();
+
+                        // checkout is registered already, as the API serializes its services
+                        let data = if request_line.starts_with("GET /api/services") {
+                            serde_json::json!([{
+                                "_id": {"$oid": "697dff2cbfc969fe967002b7"},
+                                "name": "checkout",
+                                "description": null,
+                                "onboarded_datetime": {"$date": {"$numberLong": "1717063200000"}},
+                                "health": {
+                                    "status": "active",
+                                    "last_log_at": "2025-10-24T13:15:06.698Z",
+                                    "seconds_since_last_log": 4,
+                                    "recent_lines": 120,
+                                    "recent_errors": 2,
+                                    "window_secs": 300
+                                }
+                            }])
+                        } else if request_line.starts_with("POST /api/service ") {
+                            serde_json::json!("697dff2cbfc969fe967002c1")
+                        } else {
+                            let lines: Vec<Value> = serde_json::from_slice(&body).unwrap();
+                            let stored = lines.len();
+                            batches.send(lines).unwrap();
+                            serde_json::json!({"stored": stored, "dropped": 0, "limited": 0, "invalid": []})
+                        };
+                        let body = serde_json::json!({"code": "200", "data": data}).to_string();
+                        write!(
+                            stream,
+                            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+                            body.len()
+                        )
+                        .unwrap();
+                    }
+                });
+            }
+        });
+
+        (address, received)
+    }
+
+    #[test]
+    fn test_ship_in_batches() {
+        let (server, batches) = fake_api();
+        let mut config = ShipperConfig::new(&server, "checkout");
+        config.batch_size = 2;
+        config.flush_interval = Duration::from_secs(60);
+        let shipper = Shipper::start(config).unwrap();
+
+        for message in ["first", "second", "third"] {
+            assert!(shipper.ship(request(LogLevel::INFO, message.to_string())));
+        }
+        let first = batches.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(first.len(), 2);
+        // the id of the listed service, not of a second registration
+        assert_eq!(first[0]["my_service_id"], "697dff2cbfc969fe967002b7");
+        assert_eq!(first[1]["line_content"], "second");
+
+        // the third line waits for the batch to fill, or for shutdown
+        shipper.shutdown();
+        let last = batches.try_recv().unwrap();
+        assert_eq!(last[0]["line_content"], "third");
+        assert!(!shipper.ship(request(LogLevel::INFO, String::from("late"))));
+        assert_eq!(
+            shipper.stats(),
+            ShipperStats {
+                sent: 3,
+                overflowed: 1,
+                failed: 0
+            }
+        );
+
+        let (shipper, _lines) = queue(1);
+        assert!(shipper.ship(request(LogLevel::INFO, String::from("queued"))));
+        assert!(!shipper.ship(request(LogLevel::INFO, String::from("dropped"))));
+        assert_eq!(shipper.stats().overflowed, 1);
+    }
+
+    #[test]
+    fn test_report_losses() {
+        let losses = Arc::new(Mutex::new(Vec::new()));
+        let mut config = ShipperConfig::new("http://127.0.0.1:1", "checkout");
+        config.service_id = Some(String::from("697dff2cbfc969fe967002b7"));
+        config.max_attempts = 1;
+        config.flush_interval = Duration::from_secs(60);
+        let reported = losses.clone();
+        config.on_loss = Some(OnLoss::new(move |loss| {
+            reported
+                .lock()
+                .unwrap()
+                .push((loss.clone(), is_shipping_thread()));
+        }));
+        let shipper = Shipper::start(config).unwrap();
+
+        assert!(shipper.ship(request(LogLevel::ERROR, String::from("lost"))));
+        shipper.shutdown();
+
+        let losses = losses.lock().unwrap();
+        assert_eq!(losses.len(), 1);
+        assert!(matches!(losses[0].0, Loss::Failed { lines: 1, .. }));
+        // reported from the shipper's thread, whose logging is not shipped
+        assert!(losses[0].1);
+        assert_eq!(shipper.stats().failed, 1);
+    }
+}
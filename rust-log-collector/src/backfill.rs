@@ -0,0 +1,178 @@
+//! `rust-log-collector backfill`: lines of old files timed within a window, read through the
+//! pipeline of their source. The files are left in place and the checkpoints untouched, so a
+//! backfill can be run again over the same files.
+
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::{ALogFile, Config};
+
+///Times from `from`, included, to `to`, excluded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Window {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+impl Window {
+    pub fn new(from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Self, String> {
+        if from >= to {
+            return Err(format!(
+                "--from {} is not before --to {}",
+                from.to_rfc3339(),
+                to.to_rfc3339()
+            ));
+        }
+        Ok(Window { from, to })
+    }
+
+    ///Whether the RFC 3339 `created_at` of a line is within the window
+    pub fn contains(&self, created_at: &str) -> bool {
+        DateTime::parse_from_rfc3339(created_at).is_ok_and(|time| {
+            let time = time.with_timezone(&Utc);
+            self.from <= time && time < self.to
+        })
+    }
+}
+
+///Source of the file at `path`: the one named `name`, otherwise the only directory source
+///whose log_location holds the file
+pub fn source_for<'a>(
+    configs: &'a [Config],
+    name: Option<&str>,
+    path: &Path,
+) -> Result<&'a Config, String> {
+    if let Some(name) = name {
+        return configs
+            .iter()
+            .find(|config| config.application_name == name)
+            .ok_or_else(|| format!("no source is named {name:?}"));
+    }
+
+    let path = fs::canonicalize(path).map_err(|err| format!("{}: {err}", path.display()))?;
+    let holding: Vec<&Config> = configs
+        .iter()
+        .filter(|config| config.is_directory() && !config.log_location.is_empty())
+        .filter(|config| {
+            fs::canonicalize(&config.log_location).is_ok_and(|location| path.starts_with(location))
+        })
+        .collect();
+
+    match holding.as_slice() {
+        [config] => Ok(config),
+        [] => Err(format!(
+            "{} is in the log_location of no directory source; name one with --source",
+            path.display()
+        )),
+        _ => Err(format!(
+            "{} is in the log_location of several sources; name one with --source",
+            path.display()
+        )),
+    }
+}
+
+///Read the file at `path` into `store`, keeping the lines timed within `window` by a JSON
+///time field or the time they start with. A line without a time of its own is taken as
+///written when the file was last modified. Returns how many lines were kept and how many
+///were left out.
+pub fn read_file(
+    store: &mut ALogFile,
+    service_id: &str,
+    path: &Path,
+    window: &Window,
+) -> std::io::Result<(usize, usize)> {
+    let modified: DateTime<Utc> = fs::metadata(path)?.modified()?.into();
+    let first = store.logs_in_file.len();
+    store.read_file_at(
+        service_id,
+        path,
+        &modified.to_rfc3339_opts(SecondsFormat::Millis, true),
+    )?;
+
+    let read = store.logs_in_file.len() - first;
+    let mut index = 0;
+    store.logs_in_file.retain(|request| {
+        index += 1;
+        index <= first || window.contains(&request.created_at)
+    });
+    let kept = store.logs_in_file.len() - first;
+
+    Ok((kept, read - kept))
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::parse_time;
+    use dal_layer::utils::trace_helper::TraceExtractor;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_read_file_in_window() {
+        let dir = std::env::temp_dir().join(format!("backfill-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.log.1");
+        fs::write(
+            &path,
+            concat!(
+                r#"{"level":"info","msg":"before","ts":"2025-10-23T23:59:59Z"}"#,
+                "\n",
+                r#"{"level":"info","msg":"first","ts":"2025-10-24T00:00:00Z"}"#,
+                "\n",
+                r#"{"level":"error","msg":"last","ts":"2025-10-24T23:59:59.999Z"}"#,
+                "\n",
+                "2025-10-24 12:00:00.250 WARN timed in the text\n",
+                "2025-10-25 00:00:00 WARN timed after the window\n",
+                "INFO modified now, after the window\n",
+            ),
+        )
+        .unwrap();
+
+        let configs: Vec<Config> = serde_json::from_str(&format!(
+            r#"[{{"application_name": "checkout", "log_location": "{}"}},
+                {{"application_name": "webclient", "log_location": "/nonexistent"}}]"#,
+            dir.display()
+        ))
+        .unwrap();
+        assert_eq!(
+            source_for(&configs, None, &path).unwrap().application_name,
+            "checkout"
+        );
+        assert!(source_for(&configs, Some("billing"), &path).is_err());
+
+        let window = Window::new(
+            parse_time("2025-10-24").unwrap(),
+            parse_time("2025-10-25").unwrap(),
+        )
+        .unwrap();
+        let mut store = ALogFile::new(
+            String::from("checkout"),
+            TraceExtractor::default(),
+            Arc::default(),
+        );
+
+        assert_eq!(
+            read_file(&mut store, "453452345235", &path, &window).unwrap(),
+            (3, 3)
+        );
+        let kept: Vec<&str> = store
+            .logs_in_file
+            .iter()
+            .map(|request| request.line_content.as_str())
+            .collect();
+        assert_eq!(
+            kept,
+            [
+                "first",
+                "last",
+                "2025-10-24 12:00:00.250 WARN timed in the text"
+            ]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
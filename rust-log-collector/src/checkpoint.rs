@@ -0,0 +1,173 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+///Read positions of files the collector tails instead of deleting, and the compressed files
+///it has already ingested, kept in a JSON file so a restart carries on where the last run stopped.
+#[derive(Debug, Default)]
+pub struct Checkpoint {
+    path: PathBuf,
+    offsets: HashMap<String, u64>,
+    ///Identities of the ingested archives, see [`identity`]
+    completed: BTreeSet<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct CheckpointFile {
+    #[serde(default)]
+    offsets: HashMap<String, u64>,
+    #[serde(default)]
+    completed: BTreeSet<String>,
+}
+
+///Checkpoint file of a source that does not name one
+pub fn default_path(application_name: &str) -> PathBuf {
+    PathBuf::from(format!("{application_name}.checkpoint.json"))
+}
+
+impl Checkpoint {
+    ///Load the checkpoint at `path`; a missing or unreadable file starts from scratch
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let file = fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str::<CheckpointFile>(&data).ok())
+            .unwrap_or_default();
+
+        Checkpoint {
+            path,
+            offsets: file.offsets,
+            completed: file.completed,
+        }
+    }
+
+    ///Offset to resume `file` from. A file shorter than its offset was truncated or replaced,
+    ///so it is read again from the start.
+    pub fn offset(&self, file: &Path, len: u64) -> u64 {
+        match self.offsets.get(&key(file)) {
+            Some(offset) if *offset <= len => *offset,
+            _ => 0,
+        }
+    }
+
+    pub fn set(&mut self, file: &Path, offset: u64) {
+        self.offsets.insert(key(file), offset);
+    }
+
+    ///Whether an immutable (compressed) file has been ingested already, under this name or
+    ///another one it was rotated from
+    pub fn is_completed(&self, file: &Path) -> bool {
+        identity(file).is_some_and(|identity| self.completed.contains(&identity))
+    }
+
+    pub fn mark_completed(&mut self, file: &Path) {
+        if let Some(identity) = identity(file) {
+            self.completed.insert(identity);
+        }
+    }
+
+    ///Forget files that no longer exist so the checkpoint does not grow forever. Returns
+    ///whether anything was forgotten.
+    pub fn retain(&mut self, files: &[PathBuf]) -> bool {
+        let before = self.offsets.len() + self.completed.len();
+        let live: Vec<String> = files.iter().map(|file| key(file)).collect();
+        self.offsets.retain(|file, _| live.contains(file));
+        let identities: BTreeSet<String> = files.iter().filter_map(|file| identity(file)).collect();
+        self.completed
+            .retain(|identity| identities.contains(identity));
+
+        self.offsets.len() + self.completed.len() != before
+    }
+
+    ///Write the checkpoint through a temporary file so a crash never leaves it half written
+    pub fn save(&self) -> io::Result<()> {
+        let data = serde_json::to_string_pretty(&CheckpointFile {
+            offsets: self.offsets.clone(),
+            completed: self.completed.clone(),
+        })?;
+
+        let temporary = self.path.with_extension("tmp");
+        fs::write(&temporary, data)?;
+        fs::rename(&temporary, &self.path)
+    }
+}
+
+fn key(file: &Path) -> String {
+    file.to_string_lossy().to_string()
+}
+
+///What an archive is recognised by: its inode, size and modification time, not its path,
+///since numbered rotation renames `app.log.1.gz` to `app.log.2.gz` and reuses the old name
+///for the next archive. `None` when the file cannot be read.
+fn identity(file: &Path) -> Option<String> {
+    let metadata = fs::metadata(file).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_nanos();
+    #[cfg(unix)]
+    let inode = std::os::unix::fs::MetadataExt::ino(&metadata);
+    #[cfg(not(unix))]
+    let inode = 0;
+
+    Some(format!("{inode}:{}:{modified}", metadata.len()))
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let path = std::env::temp_dir().join(format!("checkpoint-{}.json", std::process::id()));
+        let file = PathBuf::from("/var/log/pods/app/0.log");
+
+        let mut checkpoint = Checkpoint::load(&path);
+        assert_eq!(checkpoint.offset(&file, 100), 0);
+
+        checkpoint.set(&file, 42);
+        checkpoint.save().unwrap();
+
+        let reloaded = Checkpoint::load(&path);
+        assert_eq!(reloaded.offset(&file, 100), 42);
+        // truncated since the offset was stored
+        assert_eq!(reloaded.offset(&file, 10), 0);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_completed_archives_follow_rotation() {
+        let dir = std::env::temp_dir().join(format!("checkpoint-rotation-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let first = dir.join("app.log.1.gz");
+        let second = dir.join("app.log.2.gz");
+        fs::write(&first, b"first archive").unwrap();
+
+        let mut checkpoint = Checkpoint::load(dir.join("checkpoint.json"));
+        checkpoint.mark_completed(&first);
+        checkpoint.save().unwrap();
+
+        // rotation renames the archive and writes a new one under the old name
+        fs::rename(&first, &second).unwrap();
+        fs::write(&first, b"the next archive").unwrap();
+        let mut reloaded = Checkpoint::load(dir.join("checkpoint.json"));
+        assert!(reloaded.is_completed(&second));
+        assert!(!reloaded.is_completed(&first));
+
+        // the archive was deleted
+        assert!(!reloaded.retain(std::slice::from_ref(&second)));
+        assert!(reloaded.retain(std::slice::from_ref(&first)));
+        assert!(!reloaded.is_completed(&second));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,111 @@
+//! Command line of the collector: `run` (the default), `once`, `validate`, `dry-run` and
+//! `backfill`, all reading the sources from `--config`.
+
+use std::path::PathBuf;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::{Parser, Subcommand};
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Ships log files, syslog and container logs to the log monitor"
+)]
+pub struct Cli {
+    ///JSON file listing the sources
+    #[arg(
+        long,
+        short,
+        global = true,
+        env = "COLLECTOR_CONFIG",
+        default_value = "config.json"
+    )]
+    pub config: PathBuf,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, PartialEq, Subcommand)]
+pub enum Command {
+    ///Ship every source until stopped (the default)
+    Run,
+    ///Make one pass over the directory sources and exit, e.g. from cron
+    Once,
+    ///Check the sources' paths, permissions, patterns and certificates without touching the
+    ///database
+    Validate,
+    ///Print the records the directory sources would ship as JSON lines, without storing or
+    ///deleting anything
+    DryRun {
+        ///Only this source
+        #[arg(long)]
+        source: Option<String>,
+    },
+    ///Store the lines of files timed from `--from` up to `--to`, leaving the files and the
+    ///checkpoints alone
+    Backfill {
+        ///RFC 3339 time or YYYY-MM-DD, included
+        #[arg(long, value_parser = parse_time)]
+        from: DateTime<Utc>,
+        ///RFC 3339 time or YYYY-MM-DD, excluded
+        #[arg(long, value_parser = parse_time)]
+        to: DateTime<Utc>,
+        ///Source whose service and pipeline the lines go through; by default the directory
+        ///source whose log_location holds the file
+        #[arg(long)]
+        source: Option<String>,
+        ///Files to read, compressed ones included
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+}
+
+///Parse an RFC 3339 time, or a date taken as midnight UTC
+pub fn parse_time(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc())
+        .map_err(|_| format!("{value:?} is neither an RFC 3339 time nor a YYYY-MM-DD date"))
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        let cli = Cli::parse_from(["rust-log-collector"]);
+        assert_eq!(cli.command, None);
+
+        let cli = Cli::parse_from([
+            "rust-log-collector",
+            "backfill",
+            "--from",
+            "2025-10-24",
+            "--to",
+            "2025-10-25T06:00:00+02:00",
+            "/var/log/app/app.log.1.gz",
+            "--config",
+            "/etc/collector.json",
+        ]);
+        assert_eq!(cli.config, PathBuf::from("/etc/collector.json"));
+        let Some(Command::Backfill {
+            from, to, paths, ..
+        }) = cli.command
+        else {
+            panic!("not a backfill");
+        };
+        assert_eq!(from.to_rfc3339(), "2025-10-24T00:00:00+00:00");
+        assert_eq!(to.to_rfc3339(), "2025-10-25T04:00:00+00:00");
+        assert_eq!(paths, vec![PathBuf::from("/var/log/app/app.log.1.gz")]);
+
+        assert!(
+            Cli::try_parse_from(["rust-log-collector", "backfill", "--from", "yesterday"]).is_err()
+        );
+    }
+}
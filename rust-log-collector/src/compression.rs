@@ -0,0 +1,84 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+
+///Compression of a rotated log file, recognised by its extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "gz" => Some(Compression::Gzip),
+            "zst" | "zstd" => Some(Compression::Zstd),
+            "bz2" => Some(Compression::Bzip2),
+            _ => None,
+        }
+    }
+}
+
+///Open a log file, decompressing it on the fly when it is compressed
+pub fn open(path: &Path) -> io::Result<Box<dyn Read>> {
+    let file = BufReader::new(File::open(path)?);
+
+    // the multi-member decoders also read files made by appending compressed chunks
+    Ok(match Compression::from_path(path) {
+        Some(Compression::Gzip) => Box::new(MultiGzDecoder::new(file)),
+        Some(Compression::Zstd) => Box::new(zstd::Decoder::with_buffer(file)?),
+        Some(Compression::Bzip2) => Box::new(MultiBzDecoder::new(file)),
+        None => Box::new(file),
+    })
+}
+
+///Read a whole log file as text, compressed or not
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+    let mut content = String::new();
+    open(path)?.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+
+    #[test]
+    fn test_read_compressed_files() {
+        let dir = std::env::temp_dir().join(format!("compressed-logs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let text = "INFO started\nERROR failed\n";
+
+        let gzip = dir.join("app.log.1.gz");
+        let mut encoder =
+            flate2::write::GzEncoder::new(File::create(&gzip).unwrap(), Default::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+
+        let zstd = dir.join("app.log.2.zst");
+        fs::write(&zstd, zstd::encode_all(text.as_bytes(), 0).unwrap()).unwrap();
+
+        let bzip2 = dir.join("app.log.3.bz2");
+        let mut encoder =
+            bzip2::write::BzEncoder::new(File::create(&bzip2).unwrap(), Default::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+
+        for path in [&gzip, &zstd, &bzip2] {
+            assert!(Compression::from_path(path).is_some());
+            assert_eq!(read_to_string(path).unwrap(), text);
+        }
+        assert_eq!(Compression::from_path(Path::new("app.log")), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,183 @@
+use serde::Deserialize;
+
+///Longest message re-assembled from partial records before it is shipped as is
+const MAX_MESSAGE_BYTES: usize = 1024 * 1024;
+
+///On-disk format of the container runtime's log files
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerFormat {
+    ///Docker's json-file driver: `{"log":"..\n","stream":"stdout","time":"..."}` per line
+    Docker,
+    ///containerd/CRI-O: `<time> <stream> <P|F> <text>` per line
+    Cri,
+}
+
+///One line of a container log file. Long lines are split by the runtime into partial records
+///that have to be joined back together.
+#[derive(Debug, PartialEq)]
+struct Record {
+    time: Option<String>,
+    stream: Option<String>,
+    partial: bool,
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct DockerLine {
+    log: String,
+    stream: Option<String>,
+    time: Option<String>,
+}
+
+fn parse_docker(line: &str) -> Option<Record> {
+    let line: DockerLine = serde_json::from_str(line).ok()?;
+    let partial = !line.log.ends_with('\n');
+
+    Some(Record {
+        time: line.time,
+        stream: line.stream,
+        partial,
+        text: line.log.trim_end_matches(['\r', '\n']).to_string(),
+    })
+}
+
+fn parse_cri(line: &str) -> Option<Record> {
+    let mut fields = line.splitn(4, ' ');
+    let time = fields.next()?;
+    let stream = fields.next()?;
+    let tag = fields.next()?;
+    let text = fields.next().unwrap_or("");
+
+    chrono::DateTime::parse_from_rfc3339(time).ok()?;
+
+    Some(Record {
+        time: Some(time.to_string()),
+        stream: Some(stream.to_string()),
+        // the tag may carry more flags after the partial marker, e.g. `P:x`
+        partial: tag.starts_with('P'),
+        text: text.to_string(),
+    })
+}
+
+///A complete log message unwrapped from its container envelope
+#[derive(Debug, PartialEq)]
+pub struct ContainerMessage {
+    ///Byte offset of the message's first record in the file
+    pub offset: u64,
+    pub time: Option<String>,
+    pub stream: Option<String>,
+    pub text: String,
+}
+
+///Unwrap the complete messages in `data`, which was read from `base_offset` of a file.
+///Returns the messages and the number of bytes fully consumed: a trailing line without its
+///newline, or a message whose final record has not been written yet, is left to be read again
+///on the next pass.
+pub fn read_messages(
+    format: ContainerFormat,
+    data: &[u8],
+    base_offset: u64,
+) -> (Vec<ContainerMessage>, usize) {
+    let mut messages = Vec::new();
+    let mut pending: Option<ContainerMessage> = None;
+    let mut consumed = 0;
+    let mut position = 0;
+
+    for raw_line in data.split_inclusive(|byte| *byte == b'\n') {
+        if !raw_line.ends_with(b"\n") {
+            break;
+        }
+
+        let line_offset = base_offset + position as u64;
+        position += raw_line.len();
+
+        let line = String::from_utf8_lossy(raw_line);
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.trim().is_empty() {
+            if pending.is_none() {
+                consumed = position;
+            }
+            continue;
+        }
+
+        // Keep lines the runtime did not write rather than dropping them
+        let record = match format {
+            ContainerFormat::Docker => parse_docker(line),
+            ContainerFormat::Cri => parse_cri(line),
+        }
+        .unwrap_or_else(|| Record {
+            time: None,
+            stream: None,
+            partial: false,
+            text: line.to_string(),
+        });
+
+        let message = pending.get_or_insert_with(|| ContainerMessage {
+            offset: line_offset,
+            time: record.time.clone(),
+            stream: record.stream.clone(),
+            text: String::new(),
+        });
+        message.text.push_str(&record.text);
+
+        if !record.partial || message.text.len() >= MAX_MESSAGE_BYTES {
+            messages.extend(pending.take());
+            consumed = position;
+        }
+    }
+
+    (messages, consumed)
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_docker_messages() {
+        let data = concat!(
+            r#"{"log":"first line\n","stream":"stdout","time":"2025-10-24T13:15:06.698123456Z"}"#,
+            "\n",
+            r#"{"log":"split ","stream":"stderr","time":"2025-10-24T13:15:07Z"}"#,
+            "\n",
+            r#"{"log":"message\n","stream":"stderr","time":"2025-10-24T13:15:07Z"}"#,
+            "\n",
+            r#"{"log":"unfinished","stream":"stdout","time":"2025-10-24T13:15:08Z"}"#,
+            "\n",
+        );
+        let first_len = data.find('\n').unwrap() + 1;
+
+        let (messages, consumed) = read_messages(ContainerFormat::Docker, data.as_bytes(), 100);
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].text, "first line");
+        assert_eq!(
+            messages[0].time.as_deref(),
+            Some("2025-10-24T13:15:06.698123456Z")
+        );
+        assert_eq!(messages[1].text, "split message");
+        assert_eq!(messages[1].stream.as_deref(), Some("stderr"));
+        assert_eq!(messages[1].offset, 100 + first_len as u64);
+        // the partial record is read again once its end has been written
+        assert_eq!(consumed, data.rfind(r#"{"log":"unfinished"#).unwrap());
+    }
+
+    #[test]
+    fn test_read_cri_messages() {
+        let data = "2025-10-24T13:15:06.698Z stdout F hello world\n\
+                    2025-10-24T13:15:07Z stderr P part one, \n\
+                    2025-10-24T13:15:07Z stderr F part two\n\
+                    2025-10-24T13:15:08Z stdout F no newline yet";
+
+        let (messages, consumed) = read_messages(ContainerFormat::Cri, data.as_bytes(), 0);
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].text, "hello world");
+        assert_eq!(messages[0].stream.as_deref(), Some("stdout"));
+        assert_eq!(messages[1].text, "part one, part two");
+        assert_eq!(consumed, data.rfind("2025-10-24T13:15:08Z").unwrap());
+    }
+}
@@ -0,0 +1,539 @@
+//! Container input: tails Docker json-file logs (`/var/lib/docker/containers/<id>/<id>-json.log`)
+//! and Kubernetes CRI logs (`/var/log/pods/<namespace>_<pod>_<uid>/<container>/<n>.log`).
+//! Files are left in place for the runtime to rotate; read positions are kept in a
+//! `Checkpoint` instead.
+
+pub mod format;
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use dal_layer::models::collector_model::FileOffset;
+use dal_layer::models::log_model::LogRequest;
+use dal_layer::pipeline::Pipeline;
+use dal_layer::repository::db::Database;
+use dal_layer::utils::trace_helper::TraceExtractor;
+use log::{error, info, warn};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::time::{MissedTickBehavior, interval};
+
+use crate::checkpoint::Checkpoint;
+use crate::service_resolver::ServiceResolver;
+use crate::{
+    ALogFile, append_continuation, fleet, is_continuation, local_hostname, parse_line,
+    parse_line_at,
+};
+pub use format::{ContainerFormat, ContainerMessage, read_messages};
+
+///Most bytes read from one file per poll, so a large backlog is shipped over several polls
+const MAX_READ_BYTES: u64 = 8 * 1024 * 1024;
+
+const DOCKER_ROOT: &str = "/var/lib/docker/containers";
+const CRI_ROOT: &str = "/var/log/pods";
+
+///Strips the ReplicaSet hash and pod suffix from a pod name: `api-7d9f8b6c5d-x2k4q` -> `api`
+static WORKLOAD_SUFFIX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(-[0-9a-f]{6,10})?-[0-9a-z]{5}$|-\d+$")
+        .expect("the workload suffix pattern is valid")
+});
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ContainerConfig {
+    pub format: ContainerFormat,
+    ///Directory holding the runtime's logs; defaults to the runtime's standard location
+    pub path: Option<String>,
+    ///Service name for a container, using `{namespace}`, `{pod}`, `{workload}`, `{container}`,
+    ///`{container_id}` and `{image}` placeholders
+    #[serde(default = "default_service_template")]
+    pub service_template: String,
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+}
+
+fn default_service_template() -> String {
+    String::from("{container}")
+}
+
+fn default_poll_interval_secs() -> u64 {
+    10
+}
+
+impl ContainerConfig {
+    pub(crate) fn root(&self) -> PathBuf {
+        let default = match self.format {
+            ContainerFormat::Docker => DOCKER_ROOT,
+            ContainerFormat::Cri => CRI_ROOT,
+        };
+        PathBuf::from(self.path.as_deref().unwrap_or(default))
+    }
+}
+
+///Who wrote a container log file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContainerMeta {
+    pub namespace: Option<String>,
+    pub pod: Option<String>,
+    pub container: String,
+    pub container_id: Option<String>,
+    pub image: Option<String>,
+}
+
+impl ContainerMeta {
+    ///Fill the placeholders of `template` for this container
+    pub fn service_name(&self, template: &str) -> String {
+        let pod = self.pod.as_deref().unwrap_or("");
+
+        template
+            .replace("{namespace}", self.namespace.as_deref().unwrap_or(""))
+            .replace("{pod}", pod)
+            .replace("{workload}", &WORKLOAD_SUFFIX.replace(pod, ""))
+            .replace("{container}", &self.container)
+            .replace("{container_id}", self.container_id.as_deref().unwrap_or(""))
+            .replace("{image}", self.image.as_deref().unwrap_or(""))
+    }
+
+    fn attributes(&self) -> HashMap<String, Value> {
+        let fields = [
+            ("k8s_namespace", self.namespace.as_deref()),
+            ("k8s_pod", self.pod.as_deref()),
+            ("container_name", Some(self.container.as_str())),
+            ("container_id", self.container_id.as_deref()),
+            ("container_image", self.image.as_deref()),
+        ];
+
+        fields
+            .into_iter()
+            .filter_map(|(key, value)| value.map(|value| (key.to_string(), Value::from(value))))
+            .collect()
+    }
+}
+
+///A log file found under the runtime's directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerFile {
+    pub path: PathBuf,
+    pub meta: ContainerMeta,
+}
+
+///List the container log files under `root`
+pub fn discover(format: ContainerFormat, root: &Path) -> io::Result<Vec<ContainerFile>> {
+    match format {
+        ContainerFormat::Docker => discover_docker(root),
+        ContainerFormat::Cri => discover_cri(root),
+    }
+}
+
+fn discover_docker(root: &Path) -> io::Result<Vec<ContainerFile>> {
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(root)? {
+        let dir = entry?.path();
+        let Some(id) = dir.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+
+        let path = dir.join(format!("{id}-json.log"));
+        if path.is_file() {
+            files.push(ContainerFile {
+                meta: docker_meta(&dir, id),
+                path,
+            });
+        }
+    }
+
+    Ok(files)
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+struct DockerConfig {
+    name: Option<String>,
+    config: Option<DockerContainerConfig>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+struct DockerContainerConfig {
+    image: Option<String>,
+    #[serde(default)]
+    labels: HashMap<String, String>,
+}
+
+///Name, image and Kubernetes labels from the container's `config.v2.json`
+fn docker_meta(dir: &Path, id: &str) -> ContainerMeta {
+    let config: DockerConfig = fs::read_to_string(dir.join("config.v2.json"))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default();
+    let container_config = config.config.unwrap_or_default();
+    let label = |key: &str| container_config.labels.get(key).cloned();
+
+    let container = label("io.kubernetes.container.name")
+        .or_else(|| {
+            config
+                .name
+                .map(|name| name.trim_start_matches('/').to_string())
+        })
+        .unwrap_or_else(|| id.chars().take(12).collect());
+
+    ContainerMeta {
+        namespace: label("io.kubernetes.pod.namespace"),
+        pod: label("io.kubernetes.pod.name"),
+        container,
+        container_id: Some(id.to_string()),
+        image: container_config.image,
+    }
+}
+
+fn discover_cri(root: &Path) -> io::Result<Vec<ContainerFile>> {
+    let mut files = Vec::new();
+
+    for pod_entry in fs::read_dir(root)? {
+        let pod_dir = pod_entry?.path();
+        if !pod_dir.is_dir() {
+            continue;
+        }
+
+        // <namespace>_<pod>_<uid>; neither namespaces nor pod names may contain '_'
+        let dir_name = pod_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut parts = dir_name.splitn(3, '_');
+        let (namespace, pod) = (parts.next(), parts.next());
+
+        for container_entry in fs::read_dir(&pod_dir)? {
+            let container_dir = container_entry?.path();
+            if !container_dir.is_dir() {
+                continue;
+            }
+            let container = container_dir
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            // only the live `<restart>.log` files; rotated ones are `<n>.log.<date>[.gz]`
+            for file_entry in fs::read_dir(&container_dir)? {
+                let path = file_entry?.path();
+                if path.is_file() && path.extension().is_some_and(|ext| ext == "log") {
+                    files.push(ContainerFile {
+                        path,
+                        meta: ContainerMeta {
+                            namespace: namespace.map(str::to_string),
+                            pod: pod.map(str::to_string),
+                            container: container.clone(),
+                            container_id: None,
+                            image: None,
+                        },
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+///Messages read from one file and the offset to resume it from afterwards
+struct FileRead {
+    file: ContainerFile,
+    messages: Vec<ContainerMessage>,
+    next_offset: u64,
+    ///Bytes consumed from the file by this read
+    bytes_read: u64,
+    ///Length of the file when it was read
+    size: u64,
+}
+
+fn read_new(
+    format: ContainerFormat,
+    file: ContainerFile,
+    checkpoint: &Checkpoint,
+) -> io::Result<FileRead> {
+    let mut handle = File::open(&file.path)?;
+    let len = handle.metadata()?.len();
+    let offset = checkpoint.offset(&file.path, len);
+
+    let mut data = Vec::new();
+    handle.seek(SeekFrom::Start(offset))?;
+    handle.take(MAX_READ_BYTES).read_to_end(&mut data)?;
+
+    let (messages, consumed) = read_messages(format, &data, offset);
+
+    Ok(FileRead {
+        file,
+        messages,
+        next_offset: offset + consumed as u64,
+        bytes_read: consumed as u64,
+        size: len,
+    })
+}
+
+///Turn a container message into a request, keeping the runtime's timestamp unless the
+///application logged its own
+pub fn to_log_request(
+    message: &ContainerMessage,
+    meta: &ContainerMeta,
+    service_id: &str,
+    source_file: &Path,
+) -> LogRequest {
+    let mut request = match &message.time {
+        Some(time) => parse_line_at(service_id, &message.text, time.clone()),
+        None => parse_line(service_id, &message.text),
+    };
+
+    let attributes = request.attributes.get_or_insert_with(HashMap::new);
+    for (key, value) in meta.attributes() {
+        attributes.entry(key).or_insert(value);
+    }
+    if let Some(stream) = &message.stream {
+        attributes
+            .entry(String::from("stream"))
+            .or_insert_with(|| Value::from(stream.as_str()));
+    }
+
+    request.source_file = Some(source_file.to_string_lossy().to_string());
+    request.line_offset = i64::try_from(message.offset).ok();
+    request
+}
+
+///Read what was appended to every container log since the last poll and store it
+async fn poll(
+    application_name: &str,
+    config: &ContainerConfig,
+    checkpoint: Checkpoint,
+    trace_extractor: &TraceExtractor,
+    db: &Database,
+    resolver: &ServiceResolver,
+    pipeline: &Arc<Pipeline>,
+) -> Checkpoint {
+    let format = config.format;
+    let root = config.root();
+
+    let (mut checkpoint, listing) = tokio::task::spawn_blocking(move || {
+        let files = match discover(format, &root) {
+            Ok(files) => files,
+            Err(err) => return (checkpoint, Err(err)),
+        };
+        let paths: Vec<PathBuf> = files.iter().map(|file| file.path.clone()).collect();
+
+        let reads: Vec<FileRead> = files
+            .into_iter()
+            .filter_map(|file| {
+                let path = file.path.clone();
+                read_new(format, file, &checkpoint)
+                    .inspect_err(|err| error!("Error reading {}: {err}", path.display()))
+                    .ok()
+            })
+            .collect();
+
+        (checkpoint, Ok((paths, reads)))
+    })
+    .await
+    .expect("container log reader panicked");
+
+    let (paths, reads) = match listing {
+        Ok(listing) => listing,
+        Err(err) => {
+            error!("Error listing container logs for {application_name}: {err}");
+            fleet::record_error(application_name, err);
+            return checkpoint;
+        }
+    };
+
+    let host = local_hostname();
+    let mut batch = ALogFile::new(
+        application_name.to_string(),
+        TraceExtractor::default(),
+        pipeline.clone(),
+    );
+
+    // the reads whose lines are in the batch, or that had none
+    let mut batched = Vec::with_capacity(reads.len());
+    for read in &reads {
+        fleet::record_read(
+            application_name,
+            read.messages.len(),
+            read.bytes_read as usize,
+        );
+        if !read.messages.is_empty() {
+            let service = read.file.meta.service_name(&config.service_template);
+            let service_id = match resolver.resolve(&service).await {
+                Ok(id) => id,
+                Err(err) => {
+                    // leave the offset alone so the lines are picked up on the next poll
+                    warn!("Could not resolve container service {service}: {err}");
+                    continue;
+                }
+            };
+
+            let first = batch.logs_in_file.len();
+            for message in &read.messages {
+                if batch.logs_in_file.len() > first
+                    && is_continuation(&message.text)
+                    && let Some(previous) = batch.logs_in_file.last_mut()
+                {
+                    append_continuation(previous, &message.text);
+                    continue;
+                }
+                let mut request =
+                    to_log_request(message, &read.file.meta, &service_id, &read.file.path);
+                trace_extractor.apply_request(&mut request);
+                request.host = host.clone();
+                batch.logs_in_file.push(request);
+            }
+        }
+
+        batched.push(read);
+    }
+
+    // the offsets only move past lines that were stored; a failed batch is read again
+    if !batch.store(db).await {
+        return checkpoint;
+    }
+
+    for read in &batched {
+        checkpoint.set(&read.file.path, read.next_offset);
+    }
+    fleet::set_offsets(
+        application_name,
+        batched
+            .iter()
+            .map(|read| FileOffset {
+                path: read.file.path.to_string_lossy().to_string(),
+                offset: read.next_offset,
+                size: read.size,
+            })
+            .collect(),
+    );
+
+    checkpoint.retain(&paths);
+    if let Err(err) = checkpoint.save() {
+        error!("Could not save checkpoint for {application_name}: {err}");
+    }
+
+    checkpoint
+}
+
+///Start polling the container logs of one source
+pub fn start(
+    application_name: &str,
+    config: ContainerConfig,
+    checkpoint_path: PathBuf,
+    trace_extractor: TraceExtractor,
+    db: Arc<Database>,
+    resolver: Arc<ServiceResolver>,
+    pipeline: Arc<Pipeline>,
+) {
+    let application_name = application_name.to_string();
+    info!(
+        "Tailing {:?} container logs in {}",
+        config.format,
+        config.root().display()
+    );
+
+    tokio::spawn(async move {
+        let mut checkpoint = Checkpoint::load(checkpoint_path);
+        let mut ticker = interval(Duration::from_secs(config.poll_interval_secs.max(1)));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            checkpoint = poll(
+                &application_name,
+                &config,
+                checkpoint,
+                &trace_extractor,
+                &db,
+                &resolver,
+                &pipeline,
+            )
+            .await;
+        }
+    });
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_name_template() {
+        let meta = ContainerMeta {
+            namespace: Some(String::from("payments")),
+            pod: Some(String::from("api-7d9f8b6c5d-x2k4q")),
+            container: String::from("server"),
+            container_id: None,
+            image: Some(String::from("registry/api:1.2")),
+        };
+
+        assert_eq!(meta.service_name("{container}"), "server");
+        assert_eq!(meta.service_name("{namespace}/{workload}"), "payments/api");
+        assert_eq!(meta.service_name("{pod}"), "api-7d9f8b6c5d-x2k4q");
+    }
+
+    #[test]
+    fn test_discover_cri_and_docker() {
+        let root = std::env::temp_dir().join(format!("container-logs-{}", std::process::id()));
+
+        let cri = root.join("pods/payments_api-7d9f8b6c5d-x2k4q_0b1c/server");
+        fs::create_dir_all(&cri).unwrap();
+        fs::write(cri.join("0.log"), "").unwrap();
+        fs::write(cri.join("0.log.20251024-131506.gz"), "").unwrap();
+
+        let id = "4f0c2b7e9a1d";
+        let docker = root.join("docker").join(id);
+        fs::create_dir_all(&docker).unwrap();
+        fs::write(docker.join(format!("{id}-json.log")), "").unwrap();
+        fs::write(
+            docker.join("config.v2.json"),
+            r#"{"Name":"/web-1","Config":{"Image":"nginx:1.27","Labels":{}}}"#,
+        )
+        .unwrap();
+
+        let files = discover(ContainerFormat::Cri, &root.join("pods")).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].meta.namespace.as_deref(), Some("payments"));
+        assert_eq!(files[0].meta.pod.as_deref(), Some("api-7d9f8b6c5d-x2k4q"));
+        assert_eq!(files[0].meta.container, "server");
+
+        let files = discover(ContainerFormat::Docker, &root.join("docker")).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].meta.container, "web-1");
+        assert_eq!(files[0].meta.image.as_deref(), Some("nginx:1.27"));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_to_log_request() {
+        let message = ContainerMessage {
+            offset: 120,
+            time: Some(String::from("2025-10-24T13:15:06.698123456Z")),
+            stream: Some(String::from("stderr")),
+            text: String::from("ERROR connection refused"),
+        };
+        let meta = ContainerMeta {
+            container: String::from("server"),
+            namespace: Some(String::from("payments")),
+            ..Default::default()
+        };
+
+        let request = to_log_request(&message, &meta, "453452345235", Path::new("0.log"));
+
+        assert_eq!(request.created_at, "2025-10-24T13:15:06.698123456Z");
+        assert_eq!(request.line_offset, Some(120));
+        let attributes = request.attributes.unwrap();
+        assert_eq!(attributes["stream"], Value::from("stderr"));
+        assert_eq!(attributes["k8s_namespace"], Value::from("payments"));
+        assert_eq!(attributes["container_name"], Value::from("server"));
+    }
+}
@@ -0,0 +1,117 @@
+//! `rust-log-collector dry-run`: the records the directory sources would ship, printed instead
+//! of stored. Files are neither deleted nor recorded in the checkpoints.
+
+use std::io::Write;
+use std::sync::Arc;
+
+use dal_layer::utils::trace_helper::TraceExtractor;
+use log::{error, info};
+
+use crate::file_matcher::FileMatcher;
+use crate::{ALogFile, Config};
+
+///Write the records of the directory sources, or of `source` only, to `out` as JSON lines,
+///after redaction, limits and deduplication. Without a database, `my_service_id` is the
+///configured service_id or the application_name. Returns how many records were written.
+pub fn print_records(
+    configs: &[Config],
+    source: Option<&str>,
+    out: &mut impl Write,
+) -> Result<usize, String> {
+    if let Some(source) = source {
+        match configs
+            .iter()
+            .find(|config| config.application_name == source)
+        {
+            None => return Err(format!("no source is named {source:?}")),
+            Some(config) if !config.is_directory() => {
+                return Err(format!(
+                    "{source} is a {} source; only directory sources can be dry run",
+                    config.kind()
+                ));
+            }
+            Some(_) => {}
+        }
+    }
+
+    let mut printed = 0;
+    for config in configs {
+        if source.is_some_and(|source| source != config.application_name) {
+            continue;
+        }
+        if !config.is_directory() {
+            info!(
+                "Skipping {}, a {} source",
+                config.application_name,
+                config.kind()
+            );
+            continue;
+        }
+
+        let name = &config.application_name;
+        let pipeline = config
+            .pipeline()
+            .map_err(|err| format!("{name}: invalid redaction config: {err}"))?;
+        let trace_extractor = TraceExtractor::from_pattern(config.trace_id_pattern.as_deref())
+            .map_err(|err| format!("{name}: invalid trace_id_pattern: {err}"))?;
+        let files = FileMatcher::new(config)
+            .map_err(|err| err.to_string())
+            .and_then(|matcher| matcher.find_files().map_err(|err| err.to_string()))
+            .map_err(|err| format!("{name}: cannot list the files: {err}"))?;
+
+        let mut store = ALogFile::new(name.clone(), trace_extractor, Arc::new(pipeline));
+        let service_id = config.service_id.as_deref().unwrap_or(name);
+        for path in &files {
+            if let Err(err) = store.read_file(service_id, path) {
+                error!("Error reading file {}: {err}", path.display());
+            }
+        }
+
+        let mut requests = std::mem::take(&mut store.logs_in_file);
+        store.pipeline.process_requests(&mut requests);
+        for request in &requests {
+            let line = serde_json::to_string(request).map_err(|err| err.to_string())?;
+            writeln!(out, "{line}").map_err(|err| err.to_string())?;
+        }
+        printed += requests.len();
+    }
+
+    Ok(printed)
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_print_records() {
+        let configs: Vec<Config> = serde_json::from_str(
+            r#"[{"application_name": "webclient", "log_location": ".", "include": ["log.txt"]},
+                {"application_name": "edge", "syslog": {"udp": "0.0.0.0:5514"}}]"#,
+        )
+        .unwrap();
+
+        let mut out = Vec::new();
+        let printed = print_records(&configs, None, &mut out).unwrap();
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!((printed, lines.len()), (3, 3));
+        assert_eq!(lines[0]["my_service_id"], "webclient");
+        assert_eq!(lines[2]["trace_id"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        // nothing is deleted
+        assert!(Path::new("log.txt").exists());
+
+        let err = print_records(&configs, Some("edge"), &mut Vec::new()).unwrap_err();
+        assert_eq!(
+            err,
+            "edge is a syslog source; only directory sources can be dry run"
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use walkdir::WalkDir;
+
+use crate::Config;
+
+///Used when a source lists no include patterns, matching what the collector always read
+const DEFAULT_INCLUDE: [&str; 2] = ["*.txt", "*.log"];
+
+///Globs split by what they are matched against: a pattern without '/' matches the file name,
+///one starting with '/' the full path and any other the path relative to `log_location`
+struct PatternSet {
+    names: GlobSet,
+    relative: GlobSet,
+    absolute: GlobSet,
+}
+
+impl PatternSet {
+    fn new(patterns: &[String]) -> Result<Self, globset::Error> {
+        let mut names = GlobSetBuilder::new();
+        let mut relative = GlobSetBuilder::new();
+        let mut absolute = GlobSetBuilder::new();
+
+        for pattern in patterns {
+            if !pattern.contains('/') {
+                names.add(Glob::new(pattern)?);
+            } else {
+                // keep `*` inside one directory so only `**` crosses into sub directories
+                let glob = GlobBuilder::new(pattern).literal_separator(true).build()?;
+                if pattern.starts_with('/') {
+                    absolute.add(glob);
+                } else {
+                    relative.add(glob);
+                }
+            }
+        }
+
+        Ok(PatternSet {
+            names: names.build()?,
+            relative: relative.build()?,
+            absolute: absolute.build()?,
+        })
+    }
+
+    fn is_match(&self, root: &Path, path: &Path) -> bool {
+        path.file_name()
+            .is_some_and(|name| self.names.is_match(name))
+            || self.absolute.is_match(path)
+            || path
+                .strip_prefix(root)
+                .is_ok_and(|relative| self.relative.is_match(relative))
+    }
+}
+
+///Directory to walk for an absolute pattern: everything before the first wildcard
+fn literal_prefix(pattern: &str) -> PathBuf {
+    let wildcard = pattern.find(['*', '?', '[', '{']).unwrap_or(pattern.len());
+    let literal = &pattern[..wildcard];
+
+    match literal.rfind('/') {
+        Some(0) => PathBuf::from("/"),
+        Some(end) => PathBuf::from(&literal[..end]),
+        None => PathBuf::from("/"),
+    }
+}
+
+///Finds the files a directory source should read: include/exclude globs, optional recursion
+///and a maximum age so stale files are left alone
+pub struct FileMatcher {
+    log_location: Option<PathBuf>,
+    ///Extra roots to walk for absolute include patterns, and whether to walk them recursively
+    pattern_roots: Vec<(PathBuf, bool)>,
+    recursive: bool,
+    include: PatternSet,
+    exclude: PatternSet,
+    max_age: Option<Duration>,
+}
+
+impl FileMatcher {
+    pub fn new(config: &Config) -> Result<Self, globset::Error> {
+        let include: Vec<String> = if config.include.is_empty() {
+            DEFAULT_INCLUDE
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .collect()
+        } else {
+            config.include.clone()
+        };
+
+        let pattern_roots = include
+            .iter()
+            .filter(|pattern| pattern.starts_with('/'))
+            .map(|pattern| (literal_prefix(pattern), pattern.contains("**")))
+            .collect();
+
+        Ok(FileMatcher {
+            log_location: (!config.log_location.is_empty())
+                .then(|| PathBuf::from(&config.log_location)),
+            pattern_roots,
+            recursive: config.recursive,
+            include: PatternSet::new(&include)?,
+            exclude: PatternSet::new(&config.exclude)?,
+            max_age: config.max_file_age_secs.map(Duration::from_secs),
+        })
+    }
+
+    ///Default `*.txt`/`*.log` matching of the files directly inside `dir`
+    pub fn for_dir(dir: &str) -> Self {
+        FileMatcher {
+            log_location: Some(PathBuf::from(dir)),
+            pattern_roots: Vec::new(),
+            recursive: false,
+            include: PatternSet::new(&DEFAULT_INCLUDE.map(String::from))
+                .expect("default include patterns are valid"),
+            exclude: PatternSet::new(&[]).expect("empty pattern set is valid"),
+            max_age: None,
+        }
+    }
+
+    ///Whether a file at `path`, last modified at `modified`, should be read
+    pub fn is_match(&self, path: &Path, modified: Option<SystemTime>) -> bool {
+        let root = self.log_location.as_deref().unwrap_or(Path::new("/"));
+
+        if !self.include.is_match(root, path) || self.exclude.is_match(root, path) {
+            return false;
+        }
+
+        match (self.max_age, modified) {
+            (Some(max_age), Some(modified)) => modified.elapsed().is_ok_and(|age| age <= max_age),
+            _ => true,
+        }
+    }
+
+    ///Walk the source's directories for matching files. Run on every pass, so files created
+    ///since the collector started are picked up.
+    pub fn find_files(&self) -> io::Result<Vec<PathBuf>> {
+        let mut roots: Vec<(&Path, bool)> = Vec::new();
+        if let Some(location) = &self.log_location {
+            roots.push((location, self.recursive));
+        }
+        for (root, recursive) in &self.pattern_roots {
+            roots.push((root, *recursive || self.recursive));
+        }
+
+        if let Some((root, _)) = roots.first()
+            && !root.is_dir()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not a directory", root.display()),
+            ));
+        }
+
+        let mut files = BTreeSet::new();
+        for (root, recursive) in roots {
+            let walker =
+                WalkDir::new(root)
+                    .min_depth(1)
+                    .max_depth(if recursive { usize::MAX } else { 1 });
+
+            for entry in walker.into_iter().filter_map(Result::ok) {
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                let modified = entry.metadata().ok().and_then(|meta| meta.modified().ok());
+                if self.is_match(entry.path(), modified) {
+                    files.insert(entry.into_path());
+                }
+            }
+        }
+
+        Ok(files.into_iter().collect())
+    }
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn config(log_location: &Path) -> Config {
+        serde_json::from_value(serde_json::json!({
+            "application_name": "webclient",
+            "log_location": log_location.to_string_lossy(),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_include_exclude_and_recursion() {
+        let root = std::env::temp_dir().join(format!("file-matcher-{}", std::process::id()));
+        fs::create_dir_all(root.join("nested/deeper")).unwrap();
+        for file in [
+            "app.log",
+            "app-debug.log",
+            "worker.out",
+            "app.log.1.gz",
+            "notes.md",
+            "nested/api.log",
+            "nested/deeper/db.log",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+
+        let mut source = config(&root);
+        source.include = vec![String::from("*.log"), String::from("*.out")];
+        source.exclude = vec![String::from("*-debug.log"), String::from("*.gz")];
+
+        let names = |files: Vec<PathBuf>| -> Vec<String> {
+            files
+                .iter()
+                .map(|file| {
+                    file.strip_prefix(&root)
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .collect()
+        };
+
+        let flat = FileMatcher::new(&source).unwrap().find_files().unwrap();
+        assert_eq!(names(flat), vec!["app.log", "worker.out"]);
+
+        source.recursive = true;
+        let recursive = FileMatcher::new(&source).unwrap().find_files().unwrap();
+        assert_eq!(
+            names(recursive),
+            vec![
+                "app.log",
+                "nested/api.log",
+                "nested/deeper/db.log",
+                "worker.out"
+            ]
+        );
+
+        source.include = vec![String::from("nested/*.log")];
+        let relative = FileMatcher::new(&source).unwrap().find_files().unwrap();
+        assert_eq!(names(relative), vec!["nested/api.log"]);
+
+        let mut absolute = config(Path::new(""));
+        absolute.include = vec![format!("{}/**/*.log", root.display())];
+        absolute.exclude = vec![String::from("*-debug.log")];
+        let found = FileMatcher::new(&absolute).unwrap().find_files().unwrap();
+        assert_eq!(
+            names(found),
+            vec!["app.log", "nested/api.log", "nested/deeper/db.log"]
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_max_file_age() {
+        let mut source = config(Path::new("/var/log/app"));
+        source.max_file_age_secs = Some(3600);
+        let matcher = FileMatcher::new(&source).unwrap();
+        let path = Path::new("/var/log/app/app.log");
+
+        assert!(matcher.is_match(path, Some(SystemTime::now())));
+        assert!(!matcher.is_match(path, Some(SystemTime::now() - Duration::from_secs(7200))));
+    }
+
+    #[test]
+    fn test_literal_prefix() {
+        assert_eq!(
+            literal_prefix("/var/log/app/**/*.log"),
+            PathBuf::from("/var/log/app")
+        );
+        assert_eq!(literal_prefix("/*.log"), PathBuf::from("/"));
+    }
+}
@@ -0,0 +1,370 @@
+//! Registration with rust-tooling-api and periodic heartbeats carrying the state of every
+//! configured source, so the API can list the collector fleet and spot stale collectors.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+use dal_layer::models::collector_model::{CollectorHeartbeat, FileOffset, SourceStatus};
+use log::{info, warn};
+use tokio::time::{MissedTickBehavior, interval};
+
+use crate::{local_hostname, metrics};
+
+const DEFAULT_HEARTBEAT_INTERVAL_SECS: u64 = 30;
+
+///Longest a registration or heartbeat request may take
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+///State of every source of this process, by application_name
+static SOURCES: LazyLock<Mutex<BTreeMap<String, SourceStatus>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+///Where and how often to report, read from COLLECTOR_API_URL, COLLECTOR_ID,
+///COLLECTOR_HEARTBEAT_INTERVAL_SECS, COLLECTOR_API_TOKEN and the COLLECTOR_TLS_* variables
+#[derive(Debug, Clone, PartialEq)]
+pub struct FleetConfig {
+    ///Base url of rust-tooling-api, e.g. `http://localhost:5000`
+    pub api_url: String,
+    ///Defaults to the hostname
+    pub collector_id: String,
+    pub heartbeat_interval_secs: u64,
+    ///API key sent as a bearer token when the API requires one
+    pub api_token: Option<String>,
+    ///PEM certificate authority trusted for an API serving HTTPS with a private certificate,
+    ///next to the system's
+    pub tls_ca_path: Option<PathBuf>,
+    ///PEM certificate and key presented to an API requiring client certificates
+    pub tls_cert_path: Option<PathBuf>,
+    pub tls_key_path: Option<PathBuf>,
+}
+
+impl FleetConfig {
+    ///`None` when COLLECTOR_API_URL is not set: the collector then runs without reporting
+    pub fn from_env() -> Option<FleetConfig> {
+        let api_url = env::var("COLLECTOR_API_URL").ok()?;
+        let collector_id = collector_id();
+        let heartbeat_interval_secs = env::var("COLLECTOR_HEARTBEAT_INTERVAL_SECS")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .unwrap_or(DEFAULT_HEARTBEAT_INTERVAL_SECS)
+            .max(1);
+
+        Some(FleetConfig {
+            api_url: api_url.trim_end_matches('/').to_string(),
+            collector_id,
+            heartbeat_interval_secs,
+            api_token: env::var("COLLECTOR_API_TOKEN").ok(),
+            tls_ca_path: env::var_os("COLLECTOR_TLS_CA").map(PathBuf::from),
+            tls_cert_path: env::var_os("COLLECTOR_TLS_CERT").map(PathBuf::from),
+            tls_key_path: env::var_os("COLLECTOR_TLS_KEY").map(PathBuf::from),
+        })
+    }
+
+    ///HTTP client trusting `tls_ca_path` and presenting the client certificate, when set
+    pub fn client(&self) -> Result<reqwest::Client, String> {
+        let mut builder = reqwest::Client::builder().use_rustls_tls();
+
+        if let Some(ca_path) = &self.tls_ca_path {
+            let pem = fs::read(ca_path)
+                .map_err(|err| format!("COLLECTOR_TLS_CA: reading {}: {err}", ca_path.display()))?;
+            for certificate in reqwest::Certificate::from_pem_bundle(&pem)
+                .map_err(|err| format!("COLLECTOR_TLS_CA: {err}"))?
+            {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        match (&self.tls_cert_path, &self.tls_key_path) {
+            (Some(cert_path), Some(key_path)) => {
+                let mut pem = fs::read(cert_path).map_err(|err| {
+                    format!("COLLECTOR_TLS_CERT: reading {}: {err}", cert_path.display())
+                })?;
+                pem.extend(fs::read(key_path).map_err(|err| {
+                    format!("COLLECTOR_TLS_KEY: reading {}: {err}", key_path.display())
+                })?);
+                let identity = reqwest::Identity::from_pem(&pem)
+                    .map_err(|err| format!("COLLECTOR_TLS_CERT: {err}"))?;
+                builder = builder.identity(identity);
+            }
+            (None, None) => {}
+            _ => {
+                return Err(String::from(
+                    "COLLECTOR_TLS_CERT and COLLECTOR_TLS_KEY must be set together",
+                ));
+            }
+        }
+
+        builder.build().map_err(|err| err.to_string())
+    }
+}
+
+fn update(name: &str, change: impl FnOnce(&mut SourceStatus)) {
+    let mut sources = SOURCES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let status = sources
+        .entry(name.to_string())
+        .or_insert_with(|| SourceStatus {
+            name: name.to_string(),
+            ..Default::default()
+        });
+    change(status);
+}
+
+///Add a configured source; `kind` is `directory`, `syslog` or `container`
+pub fn register_source(name: &str, kind: &str) {
+    update(name, |status| status.kind = kind.to_string());
+}
+
+///Read positions of the source's files and the bytes left behind them
+pub fn set_offsets(name: &str, offsets: Vec<FileOffset>) {
+    update(name, |status| {
+        status.lag_bytes = offsets
+            .iter()
+            .map(|file| file.size.saturating_sub(file.offset))
+            .sum();
+        status.offsets = offsets;
+        metrics::FILE_LAG_BYTES
+            .with_label_values(&[name])
+            .set(status.lag_bytes as i64);
+    });
+}
+
+pub fn record_read(name: &str, lines: usize, bytes: usize) {
+    update(name, |status| {
+        status.lines_read += lines as u64;
+        status.bytes_read += bytes as u64;
+    });
+    metrics::LINES_READ
+        .with_label_values(&[name])
+        .inc_by(lines as u64);
+    metrics::BYTES_READ
+        .with_label_values(&[name])
+        .inc_by(bytes as u64);
+}
+
+///Lines read and waiting to be stored
+pub fn set_pending(name: &str, lines: usize) {
+    update(name, |status| status.pending_lines = lines as u64);
+    metrics::SPOOL_LINES
+        .with_label_values(&[name])
+        .set(lines as i64);
+}
+
+pub fn record_shipped(name: &str, lines: usize) {
+    update(name, |status| {
+        status.lines_shipped += lines as u64;
+        status.pending_lines = 0;
+    });
+    metrics::SPOOL_LINES.with_label_values(&[name]).set(0);
+    if lines > 0 {
+        metrics::BATCHES_SENT.with_label_values(&[name]).inc();
+    }
+}
+
+pub fn record_retry(name: &str) {
+    metrics::RETRIES.with_label_values(&[name]).inc();
+}
+
+///A batch given up on; its lines are dropped
+pub fn record_failed_batch(name: &str, error: impl Display) {
+    update(name, |status| status.pending_lines = 0);
+    metrics::SPOOL_LINES.with_label_values(&[name]).set(0);
+    metrics::BATCH_FAILURES.with_label_values(&[name]).inc();
+    record_error(name, error);
+}
+
+pub fn record_error(name: &str, error: impl Display) {
+    update(name, |status| {
+        status.last_error = Some(error.to_string());
+        status.last_error_at = Some(Utc::now().to_rfc3339());
+    });
+}
+
+///Heartbeat with the current state of every source
+pub fn heartbeat(config: &FleetConfig) -> CollectorHeartbeat {
+    snapshot(&config.collector_id, config.heartbeat_interval_secs)
+}
+
+///Id the collector reports under: COLLECTOR_ID, or else the hostname
+pub fn collector_id() -> String {
+    env::var("COLLECTOR_ID")
+        .ok()
+        .or_else(local_hostname)
+        .unwrap_or_else(|| String::from("collector"))
+}
+
+///State of the collector and every source, as sent in the heartbeats and shown on /status
+pub fn snapshot(collector_id: &str, heartbeat_interval_secs: u64) -> CollectorHeartbeat {
+    let sources: Vec<SourceStatus> = SOURCES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .values()
+        .cloned()
+        .collect();
+    let last_error = sources
+        .iter()
+        .filter(|source| source.last_error.is_some())
+        .max_by(|a, b| a.last_error_at.cmp(&b.last_error_at))
+        .map(|source| {
+            format!(
+                "{}: {}",
+                source.name,
+                source.last_error.as_deref().unwrap_or_default()
+            )
+        });
+
+    CollectorHeartbeat {
+        collector_id: collector_id.to_string(),
+        hostname: local_hostname().unwrap_or_default(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        heartbeat_interval_secs,
+        spool_size: sources.iter().map(|source| source.pending_lines).sum(),
+        sources,
+        last_error,
+    }
+}
+
+async fn send(
+    request: reqwest::RequestBuilder,
+    heartbeat: &CollectorHeartbeat,
+    api_token: Option<&str>,
+) -> Result<(), String> {
+    let request = match api_token {
+        Some(token) => request.bearer_auth(token),
+        None => request,
+    };
+    let response = request
+        .timeout(REQUEST_TIMEOUT)
+        .json(heartbeat)
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        return Err(response.status().to_string());
+    }
+
+    Ok(())
+}
+
+///Register with the API, retrying every interval until it answers, then send a heartbeat
+///every interval
+pub fn start(config: FleetConfig) {
+    info!(
+        "Reporting to {} as collector {} every {}s",
+        config.api_url, config.collector_id, config.heartbeat_interval_secs
+    );
+
+    let client = match config.client() {
+        Ok(client) => client,
+        Err(err) => {
+            warn!("Not reporting to {}: {err}", config.api_url);
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        let register_url = format!("{}/api/collectors", config.api_url);
+        let heartbeat_url = format!(
+            "{}/api/collectors/{}/heartbeat",
+            config.api_url, config.collector_id
+        );
+
+        let mut ticker = interval(Duration::from_secs(config.heartbeat_interval_secs));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut registered = false;
+
+        loop {
+            ticker.tick().await;
+            let heartbeat = heartbeat(&config);
+
+            if registered {
+                if let Err(err) = send(
+                    client.put(&heartbeat_url),
+                    &heartbeat,
+                    config.api_token.as_deref(),
+                )
+                .await
+                {
+                    warn!("Heartbeat to {heartbeat_url} failed: {err}");
+                }
+            } else {
+                match send(
+                    client.post(&register_url),
+                    &heartbeat,
+                    config.api_token.as_deref(),
+                )
+                .await
+                {
+                    Ok(()) => registered = true,
+                    Err(err) => warn!("Could not register with {register_url}: {err}"),
+                }
+            }
+        }
+    });
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heartbeat_reports_sources() {
+        let config = FleetConfig {
+            api_url: String::from("http://localhost:5000"),
+            collector_id: String::from("web-01"),
+            heartbeat_interval_secs: 30,
+            api_token: None,
+            tls_ca_path: None,
+            tls_cert_path: None,
+            tls_key_path: None,
+        };
+
+        register_source("fleet-test-syslog", "syslog");
+        set_pending("fleet-test-syslog", 40);
+        register_source("fleet-test-containers", "container");
+        set_offsets(
+            "fleet-test-containers",
+            vec![FileOffset {
+                path: String::from("/var/log/containers/api.log"),
+                offset: 600,
+                size: 1000,
+            }],
+        );
+        record_shipped("fleet-test-containers", 12);
+        record_error("fleet-test-containers", "connection refused");
+
+        let heartbeat = heartbeat(&config);
+        let source = |name: &str| {
+            heartbeat
+                .sources
+                .iter()
+                .find(|source| source.name == name)
+                .cloned()
+                .unwrap()
+        };
+
+        assert_eq!(heartbeat.collector_id, "web-01");
+        assert_eq!(heartbeat.version, env!("CARGO_PKG_VERSION"));
+        assert!(heartbeat.spool_size >= 40);
+        assert_eq!(source("fleet-test-syslog").pending_lines, 40);
+        let containers = source("fleet-test-containers");
+        assert_eq!(
+            (
+                containers.kind.as_str(),
+                containers.lag_bytes,
+                containers.lines_shipped
+            ),
+            ("container", 400, 12)
+        );
+        assert_eq!(containers.last_error.as_deref(), Some("connection refused"));
+    }
+}
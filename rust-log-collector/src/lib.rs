@@ -1,77 +1,339 @@
-use chrono::Utc;
+use chrono::{NaiveDateTime, SecondsFormat, Utc};
+use regex::Regex;
 use serde::Deserialize;
 use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
 use std::fs;
-use std::io::{self, BufRead};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
 
+use compression::Compression;
 use dal_layer::models::log_model::{Log, LogLevel, LogRequest};
+use dal_layer::pipeline::Pipeline;
+use dal_layer::pipeline::dedup::DedupConfig;
+use dal_layer::pipeline::issues;
+use dal_layer::pipeline::limiter::LimitConfig;
+use dal_layer::pipeline::redaction::{RedactionConfig, RedactionError};
 use dal_layer::repository::db::Database;
+use dal_layer::utils::trace_helper::TraceExtractor;
+use file_matcher::FileMatcher;
+use log::{debug, error, warn};
+
+pub mod backfill;
+pub mod checkpoint;
+pub mod cli;
+pub mod compression;
+pub mod container;
+pub mod dry_run;
+pub mod file_matcher;
+pub mod fleet;
+pub mod metrics;
+pub mod service_resolver;
+pub mod status_server;
+pub mod syslog;
+pub mod validate;
+
+///Keys a JSON log line may use for the message, level, timestamp and trace context.
+///Whatever is left over becomes the log's attributes.
+const JSON_MESSAGE_KEYS: [&str; 2] = ["message", "msg"];
+const JSON_LEVEL_KEYS: [&str; 3] = ["level", "severity", "lvl"];
+const JSON_TIME_KEYS: [&str; 4] = ["timestamp", "@timestamp", "time", "ts"];
+const JSON_TRACE_ID_KEYS: [&str; 3] = ["trace_id", "traceId", "trace.id"];
+const JSON_SPAN_ID_KEYS: [&str; 3] = ["span_id", "spanId", "span.id"];
+
+///An exception line following the message it was logged with, `java.io.IOException: reset`
+///or Python's closing `ValueError: bad id`
+static EXCEPTION_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:[A-Za-z_$][\w$]*\.)*[A-Z][\w$]*(?:Exception|Error|Throwable)(?::|$)")
+        .expect("the exception line pattern is valid")
+});
+
+///A time at the start of a text line, optionally in brackets: `2025-10-24 13:15:06.698`,
+///`2025-10-24T13:15:06,698` or RFC 3339 with its offset
+static LEADING_TIME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\[?(\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(?:[.,]\d+)?(?:Z|[+-]\d{2}:\d{2})?)")
+        .expect("the leading time pattern is valid")
+});
+
+///Attempts at storing a batch before its lines are given up, waiting STORE_RETRY_DELAY after
+///the first failure and twice as long after each following one
+const STORE_ATTEMPTS: u32 = 3;
+const STORE_RETRY_DELAY: Duration = Duration::from_millis(500);
 
 #[derive(Debug)]
 pub struct ALogFile {
     pub application_name: String,
     pub logs_in_file: Vec<LogRequest>,
+    ///Fills trace_id/span_id from the line text when the line did not carry them as fields
+    pub trace_extractor: TraceExtractor,
+    ///Ingestion limits and redaction applied before the lines are shipped
+    pub pipeline: Arc<Pipeline>,
 }
 
 impl ALogFile {
-    pub async fn store_in_db(&mut self) {
+    ///An empty batch of lines of `application_name`
+    pub fn new(
+        application_name: String,
+        trace_extractor: TraceExtractor,
+        pipeline: Arc<Pipeline>,
+    ) -> Self {
+        ALogFile {
+            application_name,
+            logs_in_file: Vec::new(),
+            trace_extractor,
+            pipeline,
+        }
+    }
+
+    pub async fn store_in_db(&mut self) -> bool {
         //store the logs in the database at this point
         // self.logs
 
         let db = Database::init().await;
 
-        let logsResult = Log::from_bulk(self.logs_in_file.clone());
+        self.store(&db).await
+    }
 
-        match logsResult {
-            Ok(logs) => {
-                db.insert_logs_bulk(logs).await;
-            }
+    ///Insert the pending logs with an existing connection and clear them. Returns false when
+    ///the logs could not be stored.
+    pub async fn store(&mut self, db: &Database) -> bool {
+        let mut requests = std::mem::take(&mut self.logs_in_file);
+        self.pipeline.process_requests(&mut requests);
+
+        let logs = match Log::from_bulk(requests) {
+            Ok(logs) => self.pipeline.finish_logs(logs),
             Err(error) => {
-                println!(
+                error!(
                     "There was an error inserting the logs from file {:?}",
                     error
                 );
+                fleet::record_failed_batch(&self.application_name, error);
+                return false;
             }
+        };
+        let occurrences = issues::occurrences(&logs);
+        let shipped = logs.len();
+
+        // every log already has its _id, so a retry after a partial write skips the lines
+        // stored by the failed attempt instead of storing them twice
+        let mut attempt: u32 = 1;
+        loop {
+            // actix errors are not Send, so only the message is kept across the retry delay
+            let error = match db.insert_logs_bulk(&logs).await {
+                Ok(()) => break,
+                Err(error) => error.to_string(),
+            };
+            if attempt == STORE_ATTEMPTS {
+                error!("There was an error inserting the logs from file {error}");
+                self.pipeline.forget(logs.iter().map(|log| log._id));
+                fleet::record_failed_batch(&self.application_name, error);
+                return false;
+            }
+            warn!(
+                "Storing {} logs of {} failed, retrying: {error}",
+                shipped, self.application_name
+            );
+            fleet::record_retry(&self.application_name);
+            tokio::time::sleep(STORE_RETRY_DELAY * 2u32.pow(attempt - 1)).await;
+            attempt += 1;
+        }
+        fleet::record_shipped(&self.application_name, shipped);
+
+        // repeats of lines stored by an earlier batch are counted on those lines
+        if let Err(error) = db
+            .apply_repeat_updates(self.pipeline.dedup.take_updates())
+            .await
+        {
+            error!("There was an error updating the repeat counts {:?}", error);
+        }
+        if let Err(error) = db
+            .upsert_templates(self.pipeline.templates.take_changed())
+            .await
+        {
+            error!("There was an error saving the log templates {:?}", error);
         }
+        if let Err(error) = db.record_issue_occurrences(occurrences).await {
+            error!("There was an error updating the issues {:?}", error);
+        }
+
+        true
     }
 
-    pub fn read_file_logs(&mut self, service_id: &str, filepath: &PathBuf) {
-        let result = fs::read_to_string(filepath);
+    pub fn read_file_logs(&mut self, service_id: &str, filepath: &Path) {
+        if let Err(error) = self.read_file(service_id, filepath) {
+            error!("Error reading file {error}");
+        }
+    }
 
-        match result {
-            Ok(content) => {
-                let lines = {
-                    let mut v: Vec<LogRequest> = content
-                        .lines()
-                        .map(|l| LogRequest {
-                            my_service_id: service_id.to_string().clone(),
-                            level: LogLevel::INFO,
-                            line_content: l.to_string(),
-                            created_at: Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
-                        })
-                        .collect();
-
-                    println!("got here friend {:?}", v);
-                    self.logs_in_file = v;
-                };
+    ///Read every line of a file, decompressing gzip, zstd and bzip2 files on the way. The
+    ///line_offset of a compressed file's lines is their position in the decompressed text.
+    pub fn read_file(&mut self, service_id: &str, filepath: &Path) -> std::io::Result<()> {
+        self.read_file_at(service_id, filepath, &now_rfc3339())
+    }
+
+    ///Same as `read_file`, giving the lines that carry no time of their own `created_at`
+    pub fn read_file_at(
+        &mut self,
+        service_id: &str,
+        filepath: &Path,
+        created_at: &str,
+    ) -> std::io::Result<()> {
+        let content = compression::read_to_string(filepath)?;
+        let host = local_hostname();
+        let source_file = filepath.to_string_lossy().to_string();
+
+        let first = self.logs_in_file.len();
+        let mut offset: usize = 0;
+        let mut lines: usize = 0;
+        for raw_line in content.split_inclusive('\n') {
+            let line_offset = offset;
+            offset += raw_line.len();
+
+            let line = raw_line.trim_end_matches(['\r', '\n']);
+            if line.trim().is_empty() {
+                continue;
             }
-            Err(error) => {
-                println!("Error reading file {error}");
+            if self.logs_in_file.len() > first
+                && is_continuation(line)
+                && let Some(previous) = self.logs_in_file.last_mut()
+            {
+                append_continuation(previous, line);
+                continue;
             }
-        };
 
-        //return vec![];
+            let mut request = parse_line_at(service_id, line, created_at.to_string());
+            self.trace_extractor.apply_request(&mut request);
+            request.host = host.clone();
+            request.source_file = Some(source_file.clone());
+            request.line_offset = i64::try_from(line_offset).ok();
+
+            self.logs_in_file.push(request);
+            lines += 1;
+        }
+        fleet::record_read(&self.application_name, lines, content.len());
+
+        Ok(())
     }
 }
 
+///Name of the machine the collector runs on, attached to every line it ships
+pub fn local_hostname() -> Option<String> {
+    hostname::get()
+        .ok()
+        .map(|name| name.to_string_lossy().to_string())
+}
+
+///Turn one line of a log file into a request. JSON object lines are unpacked into message,
+///level, timestamp, trace context and attributes; anything else is kept verbatim.
+pub fn parse_line(service_id: &str, line: &str) -> LogRequest {
+    parse_line_at(service_id, line, now_rfc3339())
+}
+
+fn now_rfc3339() -> String {
+    Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+///Same as `parse_line`, with the RFC 3339 time to use when the line carries none of its own
+pub fn parse_line_at(service_id: &str, line: &str, created_at: String) -> LogRequest {
+    let mut request = LogRequest::new(service_id, return_log_level(line), line, created_at);
+
+    let trimmed = line.trim();
+    if !trimmed.starts_with('{') {
+        if let Some(time) = leading_time(trimmed) {
+            request.created_at = time;
+        }
+        return request;
+    }
+
+    let Ok(mut fields) = serde_json::from_str::<Map<String, Value>>(trimmed) else {
+        return request;
+    };
+
+    if let Some(message) = take_string(&mut fields, &JSON_MESSAGE_KEYS) {
+        request.line_content = message;
+    }
+    request.level = match take_string(&mut fields, &JSON_LEVEL_KEYS) {
+        Some(level) => level
+            .parse::<LogLevel>()
+            .unwrap_or_else(|_| return_log_level(&level)),
+        None => LogLevel::OTHER,
+    };
+    if let Some(timestamp) = take_string(&mut fields, &JSON_TIME_KEYS)
+        && chrono::DateTime::parse_from_rfc3339(&timestamp).is_ok()
+    {
+        request.created_at = timestamp;
+    }
+    request.trace_id = take_string(&mut fields, &JSON_TRACE_ID_KEYS);
+    request.span_id = take_string(&mut fields, &JSON_SPAN_ID_KEYS);
+
+    if !fields.is_empty() {
+        request.attributes = Some(fields.into_iter().collect::<HashMap<String, Value>>());
+    }
+
+    request
+}
+
+///The time a text line starts with, as RFC 3339. A time without an offset is taken as UTC.
+fn leading_time(line: &str) -> Option<String> {
+    let time = LEADING_TIME
+        .captures(line)?
+        .get(1)?
+        .as_str()
+        .replace(',', ".");
+    let time = match chrono::DateTime::parse_from_rfc3339(&time) {
+        Ok(time) => time.with_timezone(&Utc),
+        Err(_) => {
+            NaiveDateTime::parse_from_str(&time.replacen('T', " ", 1), "%Y-%m-%d %H:%M:%S%.f")
+                .ok()?
+                .and_utc()
+        }
+    };
+
+    Some(time.to_rfc3339_opts(SecondsFormat::Millis, true))
+}
+
+///Whether `line` belongs to the line before it, as the frames and causes of a stack trace
+///do: it is indented, starts with `at ` or `Caused by:`, or names an exception
+pub fn is_continuation(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    if trimmed.is_empty() || trimmed.starts_with('{') {
+        return false;
+    }
+
+    trimmed.len() < line.len()
+        || trimmed.starts_with("at ")
+        || trimmed.starts_with("Caused by:")
+        || EXCEPTION_LINE.is_match(trimmed)
+}
+
+///Add a continuation line to the message it belongs to
+pub fn append_continuation(request: &mut LogRequest, line: &str) {
+    request.line_content.push('\n');
+    request.line_content.push_str(line);
+}
+
+///Remove the first of `keys` present in `fields` and return it as a string
+fn take_string(fields: &mut Map<String, Value>, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .find_map(|key| fields.remove(*key))
+        .map(|value| match value {
+            Value::String(text) => text,
+            other => other.to_string(),
+        })
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Directory {
     pub application_name: String,
     pub service_id: Option<String>,
     pub files: Vec<PathBuf>,
+    pub trace_id_pattern: Option<String>,
+    ///Records the compressed files already ingested
+    pub checkpoint_path: PathBuf,
 }
 
 impl fmt::Display for Directory {
@@ -81,32 +343,30 @@ impl fmt::Display for Directory {
 }
 
 impl Directory {
+    ///Delete the files that were read. Compressed files are rotated archives owned by the
+    ///application, so they are left in place and tracked in the checkpoint instead.
     pub async fn delete_files_in_dir(&self) {
-        if !self.files.is_empty() {
-            for path in self.files.iter() {
-                if path.exists() && path.is_file() {
-                    if let Err(e) = fs::remove_file(path) {
-                        println!("Failed to delete file {:?}: {}", path, e);
-                    }
-                }
+        for path in self.files.iter() {
+            if Compression::from_path(path).is_none()
+                && path.exists()
+                && path.is_file()
+                && let Err(e) = fs::remove_file(path)
+            {
+                warn!("Failed to delete file {:?}: {}", path, e);
             }
         }
     }
 
-    ///This module is going to read the directory for all the files that exits there
-    pub fn read_dir(&mut self, app_name: &str, dir: &str) -> Result<(), Box<dyn Error>> {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
+    ///This module is going to read the directory for all the `.txt` and `.log` files that exits there
+    pub fn read_dir(&mut self, dir: &str) -> Result<(), Box<dyn Error>> {
+        self.find_files(&FileMatcher::for_dir(dir))
+    }
 
-            if path.is_file() {
-                if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-                    if ext == "txt" || ext == "log" {
-                        println!("Log file: {:?}", path);
-                        self.files.push(path);
-                    }
-                }
-            }
+    ///Collect the files the source's include/exclude patterns select
+    pub fn find_files(&mut self, matcher: &FileMatcher) -> Result<(), Box<dyn Error>> {
+        for path in matcher.find_files()? {
+            debug!("Log file: {:?}", path);
+            self.files.push(path);
         }
 
         Ok(())
@@ -116,8 +376,84 @@ impl Directory {
 #[derive(Deserialize, Debug, Clone)]
 pub struct Config {
     pub application_name: String,
+    ///Directory to read log files from; unused by syslog sources
+    #[serde(default)]
     pub log_location: String,
     pub service_id: Option<String>, //id of the microservice in the database.
+    ///Regex with a `trace_id` (and optional `span_id`) group; the W3C formats are used when absent
+    #[serde(default)]
+    pub trace_id_pattern: Option<String>,
+    ///Globs of the files to read, `*.txt` and `*.log` when empty. Patterns without a '/' match
+    ///the file name, absolute ones the full path and the rest the path under log_location.
+    #[serde(default)]
+    pub include: Vec<String>,
+    ///Globs of files to skip even though they match `include`
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    ///Also look in the sub directories of log_location
+    #[serde(default)]
+    pub recursive: bool,
+    ///Ignore files not modified within this many seconds
+    #[serde(default)]
+    pub max_file_age_secs: Option<u64>,
+    ///Where read positions and ingested compressed files are recorded; defaults to
+    ///`<application_name>.checkpoint.json`
+    #[serde(default)]
+    pub checkpoint_path: Option<String>,
+    ///Sensitive-data rules applied to this source's lines before they are shipped
+    #[serde(default)]
+    pub redaction: Option<RedactionConfig>,
+    ///Per-service lines/sec and bytes/day limits of this source, keyed by service id
+    #[serde(default)]
+    pub limits: Option<LimitConfig>,
+    ///Collapse repeats of a line within a time window into one record, keyed by service id
+    #[serde(default)]
+    pub dedup: Option<DedupConfig>,
+    ///Makes this source a syslog listener instead of a log directory
+    #[serde(default)]
+    pub syslog: Option<syslog::SyslogConfig>,
+    ///Makes this source tail Docker or Kubernetes container logs instead of a log directory
+    #[serde(default)]
+    pub container: Option<container::ContainerConfig>,
+}
+
+impl Config {
+    ///Sources read from `log_location` on the upload tick; the others run their own tasks
+    pub fn is_directory(&self) -> bool {
+        self.syslog.is_none() && self.container.is_none()
+    }
+
+    ///Kind of source reported in the collector heartbeats
+    pub fn kind(&self) -> &'static str {
+        if self.syslog.is_some() {
+            "syslog"
+        } else if self.container.is_some() {
+            "container"
+        } else {
+            "directory"
+        }
+    }
+
+    pub fn pipeline(&self) -> Result<Pipeline, RedactionError> {
+        Pipeline::new(
+            self.redaction.as_ref(),
+            self.limits.as_ref(),
+            self.dedup.as_ref(),
+        )
+    }
+
+    ///The pipeline of a backfill. The ingestion limits are rates over wall-clock time that
+    ///old lines read all at once would exceed, so they are left out.
+    pub fn backfill_pipeline(&self) -> Result<Pipeline, RedactionError> {
+        Pipeline::new(self.redaction.as_ref(), None, self.dedup.as_ref())
+    }
+
+    pub fn checkpoint_path(&self) -> PathBuf {
+        self.checkpoint_path
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| checkpoint::default_path(&self.application_name))
+    }
 }
 
 ///This would read the config file for application name and log location
@@ -125,26 +461,35 @@ pub fn read_config(config_filename: String) -> Result<Vec<Config>, Box<dyn Error
     let data: String = fs::read_to_string(config_filename)?;
 
     // Parse JSON string into struct
-    let config: Vec<Config> = serde_json::from_str(&data).unwrap();
-    println!("list here {:?}", config);
+    let config: Vec<Config> = serde_json::from_str(&data)?;
+    debug!("Loaded config {:?}", config);
 
-    return Ok(config);
+    Ok(config)
 }
 
+///Level of the first level word in the line, i.e. its level field. Whole words only, so
+///`terror` or `information` do not count, and words in the message after it do not change it.
 fn return_log_level(str: &str) -> LogLevel {
-    if str.contains("INFO") || str.contains("info") {
-        LogLevel::INFO
-    } else if str.contains("ERROR") || str.contains("error") {
-        LogLevel::ERROR
-    } else if str.contains("WARN") || str.contains("warn") {
-        LogLevel::WARN
-    } else if str.contains("TRACE") || str.contains("trace") {
-        LogLevel::TRACE
-    } else if str.contains("DEBUG") || str.contains("debug") {
-        LogLevel::DEBUG
-    } else {
-        LogLevel::OTHER
-    }
+    const LEVELS: [(&str, LogLevel); 9] = [
+        ("FATAL", LogLevel::FATAL),
+        ("CRITICAL", LogLevel::FATAL),
+        ("PANIC", LogLevel::FATAL),
+        ("ERROR", LogLevel::ERROR),
+        ("WARN", LogLevel::WARN),
+        ("WARNING", LogLevel::WARN),
+        ("INFO", LogLevel::INFO),
+        ("DEBUG", LogLevel::DEBUG),
+        ("TRACE", LogLevel::TRACE),
+    ];
+
+    str.split(|c: char| !c.is_ascii_alphanumeric())
+        .find_map(|word| {
+            LEVELS
+                .iter()
+                .find(|(name, _)| word.eq_ignore_ascii_case(name))
+                .map(|(_, level)| *level)
+        })
+        .unwrap_or(LogLevel::OTHER)
 }
 
 /**************************************************TEST MODULES BELOW********************************************************* */
@@ -161,14 +506,17 @@ mod tests {
 
         match result {
             Ok(list) => {
-                if list.len() > 0 {
+                if !list.is_empty() {
                     let mut dir: Directory = Directory {
                         service_id: None,
                         application_name: list[0].application_name.clone(),
                         files: Vec::new(),
+                        trace_id_pattern: None,
+                        checkpoint_path: PathBuf::new(),
                     };
 
-                    dir.read_dir(&list[0].application_name, &list[0].log_location);
+                    // The sample config points at a developer's machine, so the directory may not exist here
+                    let _ = dir.read_dir(&list[0].log_location);
                 }
             }
             Err(error) => {
@@ -187,12 +535,41 @@ mod tests {
         assert_eq!(result, LogLevel::INFO);
     }
 
+    #[test]
+    fn test_return_fatal_log_level() {
+        let line: String = String::from("2025-10-24 13:15:09 CRITICAL disk /dev/sda1 is full");
+
+        assert_eq!(return_log_level(&line), LogLevel::FATAL);
+    }
+
+    #[test]
+    fn test_return_log_level_matches_whole_words() {
+        assert_eq!(
+            return_log_level("INFO worker panic: index out of bounds"),
+            LogLevel::INFO
+        );
+        assert_eq!(
+            return_log_level("[warning] retrying after an ERROR"),
+            LogLevel::WARN
+        );
+        assert_eq!(
+            return_log_level("2025-10-24 13:15:09 PANIC worker crashed"),
+            LogLevel::FATAL
+        );
+        assert_eq!(
+            return_log_level("reading information about the terror of debugging"),
+            LogLevel::OTHER
+        );
+        assert_eq!(return_log_level("level=warn disk at 91%"), LogLevel::WARN);
+    }
+
     #[test]
     fn test_read_file_logs() {
-        let mut store: ALogFile = ALogFile {
-            application_name: String::from("webclient"),
-            logs_in_file: Vec::new(),
-        };
+        let mut store: ALogFile = ALogFile::new(
+            String::from("webclient"),
+            TraceExtractor::default(),
+            Arc::default(),
+        );
 
         let path: PathBuf = PathBuf::from("log.txt");
         store.read_file_logs("453452345235", &path);
@@ -202,6 +579,133 @@ mod tests {
         assert_eq!(size, 3);
     }
 
+    #[test]
+    fn test_read_file_logs_fills_source_metadata() {
+        let mut store: ALogFile = ALogFile::new(
+            String::from("webclient"),
+            TraceExtractor::default(),
+            Arc::default(),
+        );
+
+        let path: PathBuf = PathBuf::from("log.txt");
+        store.read_file_logs("453452345235", &path);
+
+        let second = &store.logs_in_file[1];
+        let first_line_len = store.logs_in_file[0].line_content.len() as i64;
+
+        assert_eq!(second.source_file.as_deref(), Some("log.txt"));
+        assert_eq!(second.line_offset, Some(first_line_len + 1));
+        assert_eq!(second.host, local_hostname());
+    }
+
+    #[test]
+    fn test_read_file_logs_extracts_trace_context() {
+        let mut store: ALogFile = ALogFile::new(
+            String::from("webclient"),
+            TraceExtractor::default(),
+            Arc::default(),
+        );
+
+        let path: PathBuf = PathBuf::from("log.txt");
+        store.read_file_logs("453452345235", &path);
+
+        assert_eq!(
+            store.logs_in_file[2].trace_id.as_deref(),
+            Some("4bf92f3577b34da6a3ce929d0e0e4736")
+        );
+        assert_eq!(store.logs_in_file[0].trace_id, None);
+    }
+
+    #[test]
+    fn test_read_file_joins_stack_traces() {
+        let path = std::env::temp_dir().join(format!("stack-trace-{}.log", std::process::id()));
+        fs::write(
+            &path,
+            concat!(
+                "2025-10-24 13:15:09.340 ERROR Lookup of order 42 failed\n",
+                "java.lang.IllegalStateException: missing\n",
+                "\tat com.acme.Repo.find(Repo.java:42)\n",
+                "Caused by: java.io.IOException: reset\n",
+                "\t... 12 more\n",
+                "2025-10-24 13:15:10.001 INFO retried\n",
+            ),
+        )
+        .unwrap();
+        let mut store = ALogFile::new(
+            String::from("webclient"),
+            TraceExtractor::default(),
+            Arc::default(),
+        );
+
+        store.read_file("453452345235", &path).unwrap();
+
+        let lines: Vec<&str> = store
+            .logs_in_file
+            .iter()
+            .map(|request| request.line_content.as_str())
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "2025-10-24 13:15:09.340 ERROR Lookup of order 42 failed\njava.lang.IllegalStateException: missing\n\tat com.acme.Repo.find(Repo.java:42)\nCaused by: java.io.IOException: reset\n\t... 12 more",
+                "2025-10-24 13:15:10.001 INFO retried",
+            ]
+        );
+        assert_eq!(store.logs_in_file[0].level, LogLevel::ERROR);
+        assert!(!is_continuation("   "));
+        assert!(!is_continuation("Error: connection refused"));
+        assert!(is_continuation("ValueError: bad id"));
+        assert!(is_continuation("at Object.<anonymous> (app.js:3:9)"));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_parse_json_line() {
+        let line = r#"{"level":"error","msg":"payment declined","ts":"2025-10-24T13:15:06.698Z","trace_id":"4bf92f3577b34da6a3ce929d0e0e4736","span_id":"00f067aa0ba902b7","order_id":981,"region":"eu-west-1"}"#;
+
+        let request = parse_line("453452345235", line);
+
+        assert_eq!(request.level, LogLevel::ERROR);
+        assert_eq!(request.line_content, "payment declined");
+        assert_eq!(request.created_at, "2025-10-24T13:15:06.698Z");
+        assert_eq!(
+            request.trace_id.as_deref(),
+            Some("4bf92f3577b34da6a3ce929d0e0e4736")
+        );
+        assert_eq!(request.span_id.as_deref(), Some("00f067aa0ba902b7"));
+
+        let attributes = request.attributes.unwrap();
+        assert_eq!(attributes.len(), 2);
+        assert_eq!(attributes["order_id"], Value::from(981));
+        assert_eq!(attributes["region"], Value::from("eu-west-1"));
+    }
+
+    #[test]
+    fn test_parse_plain_line() {
+        let line = "2025-10-24 13:15:06.698 [main] WARN  c.p.Gateway - upstream slow";
+
+        let request = parse_line("453452345235", line);
+
+        assert_eq!(request.level, LogLevel::WARN);
+        assert_eq!(request.line_content, line);
+        assert_eq!(request.created_at, "2025-10-24T13:15:06.698Z");
+        assert!(request.attributes.is_none());
+
+        for (line, created_at) in [
+            (
+                "[2025-10-24T15:15:06,5+02:00] ERROR timeout",
+                "2025-10-24T13:15:06.500Z",
+            ),
+            ("2025-10-24T13:15:06Z INFO up", "2025-10-24T13:15:06.000Z"),
+        ] {
+            assert_eq!(parse_line("453452345235", line).created_at, created_at);
+        }
+        // a line without a time of its own keeps the time it was read at
+        let untimed = parse_line_at("453452345235", "INFO up", String::from("then"));
+        assert_eq!(untimed.created_at, "then");
+    }
+
     #[test]
     fn test_addition() {
         let a = 10;
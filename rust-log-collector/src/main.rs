@@ -1,39 +1,337 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use clap::Parser;
+use dal_layer::models::collector_model::FileOffset;
 use dal_layer::models::my_service_model::MyService;
 use dal_layer::models::my_service_model::MyServiceView;
+use dal_layer::pipeline::Pipeline;
+use dal_layer::pipeline::redaction::RedactionError;
 use dal_layer::repository::db::Database;
-use rust_log_collector::{ALogFile, Config, Directory};
-use std::sync::mpsc;
+use dal_layer::utils::trace_helper::TraceExtractor;
+use rust_log_collector::backfill::{self, Window};
+use rust_log_collector::checkpoint::Checkpoint;
+use rust_log_collector::cli::{Cli, Command};
+use rust_log_collector::compression::Compression;
+use rust_log_collector::file_matcher::FileMatcher;
+use rust_log_collector::fleet::{self, FleetConfig};
+use rust_log_collector::service_resolver::ServiceResolver;
+use rust_log_collector::status_server::{self, Identity};
+use rust_log_collector::{ALogFile, Config, Directory, container, dry_run, syslog};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, mpsc};
 use std::thread;
-use tokio;
 
+use log::{debug, error, info, warn};
 use tokio::signal;
 use tokio::time::{Duration, MissedTickBehavior, interval, timeout};
 
 #[tokio::main]
 async fn main() {
-    ///Load the services stored in the database services from db
-    let services: Vec<MyServiceView> = getservices().await.unwrap();
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
-    /// Load config from json
-    let filename: String = String::from("config.json");
+    let cli = Cli::parse();
 
-    let mut configs: Vec<Config> = rust_log_collector::read_config(filename).unwrap();
+    // Load config from json
+    let filename: String = cli.config.to_string_lossy().to_string();
+    let mut configs: Vec<Config> = match rust_log_collector::read_config(filename.clone()) {
+        Ok(configs) => configs,
+        Err(err) => {
+            error!("Could not read the configuration {filename}: {err}");
+            std::process::exit(2);
+        }
+    };
+
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => run(&mut configs).await,
+        Command::Once => once(&mut configs).await,
+        Command::Validate => validate(&configs),
+        Command::DryRun { source } => dry_run(&configs, source.as_deref()),
+        Command::Backfill {
+            from,
+            to,
+            source,
+            paths,
+        } => backfill(&mut configs, from, to, source.as_deref(), &paths).await,
+    }
+}
 
-    ///Pass the service id to each service in config and add the service that dont exits in db
-    let list: &Vec<Config> = tied_service_id_2_configs(&services, &mut configs).await;
+///Give every source its service id, registering the services missing from the database, and
+///build their pipelines. Fails when the services cannot be loaded, e.g. while MongoDB is
+///unreachable.
+async fn prepare(configs: &mut Vec<Config>, build: BuildPipeline) -> Result<Pipelines, String> {
+    //Load the services stored in the database services from db
+    let services: Vec<MyServiceView> = getservices()
+        .await
+        .map_err(|err| format!("Could not load the services from the database: {err}"))?;
+
+    //Pass the service id to each service in config and add the service that dont exits in db
+    let list: &Vec<Config> = tied_service_id_2_configs(&services, configs).await;
+
+    //pipelines are built once so limits and redaction hit counters cover the whole run
+    let pipelines: Pipelines = build_pipelines(list, build);
+    seed_templates(&pipelines).await;
+    Ok(pipelines)
+}
+
+///Ship every source until stopped
+async fn run(configs: &mut Vec<Config>) {
+    let pipelines = match prepare(configs, Config::pipeline).await {
+        Ok(pipelines) => pipelines,
+        Err(err) => {
+            error!("{err}");
+            std::process::exit(1);
+        }
+    };
+    let list: &[Config] = configs;
+
+    //register with the API and report the state of every source in the heartbeats
+    for config in list {
+        fleet::register_source(&config.application_name, config.kind());
+    }
+    let fleet_config = FleetConfig::from_env();
+    if let Some(addr) = status_server::addr_from_env() {
+        status_server::start(
+            addr,
+            Identity {
+                collector_id: fleet::collector_id(),
+                heartbeat_interval_secs: fleet_config
+                    .as_ref()
+                    .map_or(0, |config| config.heartbeat_interval_secs),
+            },
+        );
+    }
+    match fleet_config {
+        Some(fleet_config) => fleet::start(fleet_config),
+        None => info!("COLLECTOR_API_URL is not set, not reporting to the collector fleet"),
+    }
+
+    //syslog and container sources run in the background, the rest are log directories
+    start_background_sources(list, &pipelines).await;
+
+    let directories: Vec<Config> = list.iter().filter(|c| c.is_directory()).cloned().collect();
+
+    //read tru config (each represent a micro service and the log file directory)
+    //
+    // read thru the files
+    let dir_count: usize = directories.len();
+
+    debug!("Directory sources: {dir_count}");
+
+    start_log_worker(dir_count, &directories, &pipelines).await;
+}
+
+///Read and ship the directory sources once, then exit. Syslog and container sources only
+///exist as long running listeners and tails, so they are skipped.
+async fn once(configs: &mut Vec<Config>) {
+    let pipelines = match prepare(configs, Config::pipeline).await {
+        Ok(pipelines) => pipelines,
+        Err(err) => {
+            error!("{err}");
+            std::process::exit(1);
+        }
+    };
 
-    ///read tru config (each represent a micro service and the log file directory)
-    ///
-    /// read thru the files
-    let dir_count: usize = list.len();
+    for config in configs.iter().filter(|c| !c.is_directory()) {
+        warn!(
+            "Skipping {}, a {} source only runs with the run command",
+            config.application_name,
+            config.kind()
+        );
+    }
+    let directories: Vec<Config> = configs
+        .iter()
+        .filter(|c| c.is_directory())
+        .cloned()
+        .collect();
+
+    multiple_transmitter_receiver(directories.len(), &directories, &pipelines).await;
+    info!(
+        "Single pass over {} directory sources done",
+        directories.len()
+    );
+}
 
-    println!("got here dir count {dir_count}");
+fn validate(configs: &[Config]) {
+    let problems = rust_log_collector::validate::validate(configs);
+    if !problems.is_empty() {
+        eprintln!("Invalid configuration:\n  - {}", problems.join("\n  - "));
+        std::process::exit(1);
+    }
+    println!("Configuration is valid: {} sources", configs.len());
+}
 
-    start_log_worker(dir_count, list).await;
+fn dry_run(configs: &[Config], source: Option<&str>) {
+    let mut out = std::io::stdout().lock();
+    match dry_run::print_records(configs, source, &mut out) {
+        Ok(printed) => info!("{printed} records would be shipped"),
+        Err(err) => {
+            error!("{err}");
+            std::process::exit(1);
+        }
+    }
 }
 
-async fn start_log_worker(dir_count: usize, list: &Vec<Config>) {
+///Store the lines of `paths` timed from `from` up to `to`
+async fn backfill(
+    configs: &mut Vec<Config>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    source: Option<&str>,
+    paths: &[PathBuf],
+) {
+    let window = match Window::new(from, to) {
+        Ok(window) => window,
+        Err(err) => {
+            error!("{err}");
+            std::process::exit(2);
+        }
+    };
+
+    // every file is matched to its source before anything is stored
+    let mut by_source: BTreeMap<String, Vec<&PathBuf>> = BTreeMap::new();
+    for path in paths {
+        match backfill::source_for(configs, source, path) {
+            Ok(config) => by_source
+                .entry(config.application_name.clone())
+                .or_default()
+                .push(path),
+            Err(err) => {
+                error!("{err}");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let pipelines = match prepare(configs, Config::backfill_pipeline).await {
+        Ok(pipelines) => pipelines,
+        Err(err) => {
+            error!("{err}");
+            std::process::exit(1);
+        }
+    };
+    let db = Database::init().await;
+    let mut failed = false;
+
+    for (name, paths) in by_source {
+        let config = configs
+            .iter()
+            .find(|config| config.application_name == name)
+            .expect("sources are matched from the configuration");
+        let Some(service_id) = config.service_id.clone() else {
+            error!("{name} has no service id, its files are not backfilled");
+            failed = true;
+            continue;
+        };
+
+        let mut store = ALogFile::new(
+            name.clone(),
+            TraceExtractor::from_pattern(config.trace_id_pattern.as_deref()).unwrap_or_default(),
+            pipelines[&name].clone(),
+        );
+        for path in paths {
+            match backfill::read_file(&mut store, &service_id, path, &window) {
+                Ok((kept, skipped)) => info!(
+                    "{}: {kept} lines to backfill, {skipped} outside the window",
+                    path.display()
+                ),
+                Err(err) => {
+                    error!("Error reading file {}: {err}", path.display());
+                    failed = true;
+                }
+            }
+        }
+
+        if !store.store(&db).await {
+            failed = true;
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+type Pipelines = HashMap<String, Arc<Pipeline>>;
+
+///`Config::pipeline`, or `Config::backfill_pipeline` for a backfill
+type BuildPipeline = fn(&Config) -> Result<Pipeline, RedactionError>;
+
+fn build_pipelines(list: &[Config], build: BuildPipeline) -> Pipelines {
+    list.iter()
+        .map(|config| match build(config) {
+            Ok(pipeline) => (config.application_name.clone(), Arc::new(pipeline)),
+            Err(err) => {
+                error!(
+                    "Invalid redaction config for {}: {err}",
+                    config.application_name
+                );
+                std::process::exit(1);
+            }
+        })
+        .collect()
+}
+
+///Start template mining from the templates already saved, so lines keep their template ids
+async fn seed_templates(pipelines: &Pipelines) {
+    let db = Database::init().await;
+    match db.get_templates().await {
+        Ok(templates) => {
+            for pipeline in pipelines.values() {
+                pipeline.templates.seed(templates.clone());
+            }
+        }
+        Err(err) => error!("Could not load the log templates: {err}"),
+    }
+}
+
+async fn start_background_sources(list: &[Config], pipelines: &Pipelines) {
+    let sources: Vec<&Config> = list.iter().filter(|c| !c.is_directory()).collect();
+    if sources.is_empty() {
+        return;
+    }
+
+    let db = Arc::new(Database::init().await);
+    let resolver = Arc::new(ServiceResolver::new(db.clone()));
+
+    for config in sources {
+        let trace_extractor = TraceExtractor::from_pattern(config.trace_id_pattern.as_deref())
+            .unwrap_or_else(|err| {
+                warn!(
+                    "Invalid trace_id_pattern for {}, using the W3C defaults: {err}",
+                    config.application_name
+                );
+                TraceExtractor::default()
+            });
+
+        if let Some(syslog_config) = config.syslog.clone() {
+            if let Err(err) = syslog::start(
+                &config.application_name,
+                syslog_config,
+                trace_extractor,
+                db.clone(),
+                resolver.clone(),
+                pipelines[&config.application_name].clone(),
+            ) {
+                error!(
+                    "Could not start syslog source {}: {err}",
+                    config.application_name
+                );
+            }
+        } else if let Some(container_config) = config.container.clone() {
+            container::start(
+                &config.application_name,
+                container_config,
+                config.checkpoint_path(),
+                trace_extractor,
+                db.clone(),
+                resolver.clone(),
+                pipelines[&config.application_name].clone(),
+            );
+        }
+    }
+}
+
+async fn start_log_worker(dir_count: usize, list: &[Config], pipelines: &Pipelines) {
     let mut ticker = interval(Duration::from_secs(60));
     ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
@@ -41,46 +339,55 @@ async fn start_log_worker(dir_count: usize, list: &Vec<Config>) {
         tokio::select! {
             _ = ticker.tick() => {
                 if dir_count > 0 {
-                    let job = multiple_transmitter_receiver(dir_count, &list);
+                    let job = multiple_transmitter_receiver(dir_count, list, pipelines);
 
                     match timeout(Duration::from_secs(50), job).await {
-                        Ok(_) => println!("Logs uploaded successfully"),
-                        Ok(_) => eprintln!("Upload error:"),
-                        Err(_) => eprintln!("Upload timed out"),
+                        Ok(_) => info!("Logs uploaded successfully"),
+                        Err(_) => error!("Upload timed out"),
                     }
                 }
             }
             _ = signal::ctrl_c() => {
-                println!("Gracefully shutting down log worker...");
+                info!("Gracefully shutting down log worker...");
                 break;
             }
         }
     }
 }
 
-pub async fn multiple_transmitter_receiver(count: usize, list: &Vec<Config>) {
+pub async fn multiple_transmitter_receiver(count: usize, list: &[Config], pipelines: &Pipelines) {
     let (tx, rx) = mpsc::channel();
 
-    for i in 0..count {
+    for item in list.iter().take(count) {
         // Clone the specific Config so the thread owns it
-        let config = list[i].clone();
+        let config = item.clone();
         let producer = tx.clone();
 
-        thread::spawn(move || match config.service_id {
-            Some(id) => {
+        thread::spawn(move || {
+            if let Some(id) = config.service_id.clone() {
                 let mut dir = Directory {
                     service_id: Some(id),
                     application_name: config.application_name.clone(),
                     files: Vec::new(),
+                    trace_id_pattern: config.trace_id_pattern.clone(),
+                    checkpoint_path: config.checkpoint_path(),
                 };
 
-                dir.read_dir(&config.application_name, &config.log_location);
+                let found = FileMatcher::new(&config)
+                    .map_err(|error| error.into())
+                    .and_then(|matcher| dir.find_files(&matcher));
+                if let Err(error) = found {
+                    error!("Error reading directory {}: {error}", config.log_location);
+                    fleet::record_error(&config.application_name, error);
+                }
 
-                println!("printing the files in the dir {:?}", dir.files);
+                debug!(
+                    "Files found for {}: {:?}",
+                    config.application_name, dir.files
+                );
 
                 producer.send(dir).unwrap(); //This is going to send a directory with list of files in it.
             }
-            _ => {}
         });
     }
 
@@ -88,28 +395,102 @@ pub async fn multiple_transmitter_receiver(count: usize, list: &Vec<Config>) {
 
     //reciever thread
     for dir in rx {
-        read_files_store_in_db(&dir).await;
-        dir.delete_files_in_dir().await;
+        // files whose lines could not be stored are kept and read again
+        if read_files_store_in_db(&dir, pipelines[&dir.application_name].clone()).await {
+            dir.delete_files_in_dir().await;
+        }
     }
 }
 
-async fn read_files_store_in_db(dir: &Directory) {
-    let mut store: ALogFile = ALogFile {
-        application_name: dir.application_name.to_string(),
-        logs_in_file: Vec::new(),
-    };
+///Store the lines of every file of `dir`. Returns false when they could not be stored.
+async fn read_files_store_in_db(dir: &Directory, pipeline: Arc<Pipeline>) -> bool {
+    let trace_extractor = TraceExtractor::from_pattern(dir.trace_id_pattern.as_deref())
+        .unwrap_or_else(|err| {
+            warn!(
+                "Invalid trace_id_pattern for {}, using the W3C defaults: {err}",
+                dir.application_name
+            );
+            TraceExtractor::default()
+        });
 
-    println!("Directory content {}", dir);
-    println!("number of files in dir {}", &dir.files.len());
+    let mut store: ALogFile =
+        ALogFile::new(dir.application_name.to_string(), trace_extractor, pipeline);
+
+    debug!("Directory content {}", dir);
+    debug!("number of files in dir {}", &dir.files.len());
+
+    //compressed files are rotated archives: read once, then remembered in the checkpoint
+    let mut checkpoint = Checkpoint::load(&dir.checkpoint_path);
+    let mut archives: Vec<&PathBuf> = Vec::new();
 
     for path in &dir.files {
         let id: &str = dir.service_id.as_ref().unwrap();
 
-        store.read_file_logs(&id, &path);
+        if Compression::from_path(path).is_some() {
+            if checkpoint.is_completed(path) {
+                continue;
+            }
+            match store.read_file(id, path) {
+                Ok(()) => archives.push(path),
+                Err(error) => error!("Error reading archive {}: {error}", path.display()),
+            }
+        } else {
+            store.read_file_logs(id, path);
+        }
     }
 
     //store the values in the database
-    store.store_in_db().await;
+    let stored = store.store_in_db().await;
+    if stored {
+        //every file is read to its end before it is deleted
+        let offsets = dir
+            .files
+            .iter()
+            .filter_map(|path| {
+                let size = std::fs::metadata(path).ok()?.len();
+                Some(FileOffset {
+                    path: path.to_string_lossy().to_string(),
+                    offset: size,
+                    size,
+                })
+            })
+            .collect();
+        fleet::set_offsets(&dir.application_name, offsets);
+    }
+
+    if !store.pipeline.redactor.is_empty() {
+        info!(
+            "Redaction hits for {}: {:?}",
+            dir.application_name,
+            store.pipeline.redactor.hits()
+        );
+    }
+    if !store.pipeline.limiter.is_empty() {
+        info!(
+            "Ingestion limits for {}: {:?}",
+            dir.application_name,
+            store.pipeline.limiter.stats()
+        );
+    }
+
+    let marked = stored && !archives.is_empty();
+    if marked {
+        for path in archives {
+            checkpoint.mark_completed(path);
+        }
+    }
+    // archives that were deleted since are forgotten
+    let pruned = checkpoint.retain(&dir.files);
+    if (marked || pruned)
+        && let Err(error) = checkpoint.save()
+    {
+        error!(
+            "Could not save checkpoint for {}: {error}",
+            dir.application_name
+        );
+    }
+
+    stored
 }
 
 ///This get the list of services
@@ -120,12 +501,12 @@ async fn getservices() -> Result<Vec<MyServiceView>, Box<dyn std::error::Error>>
     let list = db.get_services().await?;
     // .map_err(|e| Box::new(Error::Database(e.to_string())) as Box<dyn Error>)?;
 
-    Ok(MyServiceView::from_bulk(list)?)
+    MyServiceView::from_bulk(list)
 }
 
 ///This is going to connect the service id to each service in the config
 async fn tied_service_id_2_configs<'a>(
-    services: &'a Vec<MyServiceView>,
+    services: &'a [MyServiceView],
     configs: &'a mut Vec<Config>,
 ) -> &'a Vec<Config> {
     //Result<Vec<MyServiceView>, Box<String>> {
@@ -152,16 +533,13 @@ async fn tied_service_id_2_configs<'a>(
                 .await
             {
                 Ok(service) => {
-                    println!("verify the content {:?}", service);
+                    debug!("Registered service {:?}", service);
 
-                    match service.inserted_id.as_str() {
-                        Some(id) => {
-                            c.service_id = Some(id.to_string());
-                        }
-                        None => {}
+                    if let Some(id) = service.inserted_id.as_object_id() {
+                        c.service_id = Some(id.to_hex());
                     }
                 }
-                Err(err) => println!("{}", err.to_string()),
+                Err(err) => error!("{}", err),
             };
         }
     }
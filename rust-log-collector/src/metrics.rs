@@ -0,0 +1,78 @@
+//! Prometheus metrics of the collector's sources, kept in the registry of
+//! `dal_layer::utils::metrics` next to the MongoDB and ingestion metrics of the data layer.
+
+use std::sync::LazyLock;
+
+use dal_layer::utils::metrics::{NAMESPACE, register};
+use prometheus::{IntCounterVec, IntGaugeVec, Opts};
+
+fn counter(name: &str, help: &str) -> IntCounterVec {
+    register(
+        IntCounterVec::new(
+            Opts::new(name, help)
+                .namespace(NAMESPACE)
+                .subsystem("collector"),
+            &["source"],
+        )
+        .unwrap(),
+    )
+}
+
+fn gauge(name: &str, help: &str) -> IntGaugeVec {
+    register(
+        IntGaugeVec::new(
+            Opts::new(name, help)
+                .namespace(NAMESPACE)
+                .subsystem("collector"),
+            &["source"],
+        )
+        .unwrap(),
+    )
+}
+
+pub static LINES_READ: LazyLock<IntCounterVec> =
+    LazyLock::new(|| counter("lines_read_total", "Lines read from a source"));
+
+pub static BYTES_READ: LazyLock<IntCounterVec> =
+    LazyLock::new(|| counter("bytes_read_total", "Bytes read from a source"));
+
+pub static BATCHES_SENT: LazyLock<IntCounterVec> =
+    LazyLock::new(|| counter("batches_sent_total", "Batches of lines stored"));
+
+pub static BATCH_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    counter(
+        "batch_failures_total",
+        "Batches whose lines could not be stored",
+    )
+});
+
+pub static RETRIES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    counter(
+        "retries_total",
+        "Failed attempts at storing a batch that were retried",
+    )
+});
+
+pub static SPOOL_LINES: LazyLock<IntGaugeVec> =
+    LazyLock::new(|| gauge("spool_lines", "Lines read and waiting to be stored"));
+
+pub static FILE_LAG_BYTES: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    gauge(
+        "file_lag_bytes",
+        "Bytes written to the source's files that were not read yet",
+    )
+});
+
+///Every registered metric in the Prometheus text format, the collector's listed even before
+///anything was recorded
+pub fn encode() -> String {
+    LazyLock::force(&LINES_READ);
+    LazyLock::force(&BYTES_READ);
+    LazyLock::force(&BATCHES_SENT);
+    LazyLock::force(&BATCH_FAILURES);
+    LazyLock::force(&RETRIES);
+    LazyLock::force(&SPOOL_LINES);
+    LazyLock::force(&FILE_LAG_BYTES);
+
+    dal_layer::utils::metrics::encode()
+}
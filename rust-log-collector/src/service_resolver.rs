@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::Utc;
+use dal_layer::models::my_service_model::{MyService, MyServiceView};
+use dal_layer::repository::db::Database;
+use tokio::sync::Mutex;
+
+///Looks up the id of a service by name, registering services the database has not seen yet.
+///Inputs such as syslog and container logs only learn the service name per line, so the
+///answers are cached for the life of the collector.
+pub struct ServiceResolver {
+    db: Arc<Database>,
+    cache: Mutex<HashMap<String, String>>,
+}
+
+impl ServiceResolver {
+    pub fn new(db: Arc<Database>) -> Self {
+        ServiceResolver {
+            db,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    ///Id (hex) of the service called `name`. Database errors are flattened to their message
+    ///because actix errors are not `Send` and the inputs run on spawned tasks.
+    pub async fn resolve(&self, name: &str) -> Result<String, String> {
+        let mut cache = self.cache.lock().await;
+        if let Some(id) = cache.get(name) {
+            return Ok(id.clone());
+        }
+
+        let id = match self
+            .db
+            .find_service_by_name(name)
+            .await
+            .map_err(|err| err.to_string())?
+        {
+            Some(service) => service._id.to_hex(),
+            None => {
+                let model: MyServiceView = MyServiceView {
+                    service_id: None,
+                    name: name.to_string(),
+                    description: Some(String::from("Micro service applciation")),
+                    onboarded_datetime: Some(Utc::now().to_rfc3339()),
+                };
+                let service = MyService::try_from(model).map_err(|err| err.to_string())?;
+                let id = service._id.to_hex();
+                self.db
+                    .create_service(service)
+                    .await
+                    .map_err(|err| err.to_string())?;
+                id
+            }
+        };
+
+        cache.insert(name.to_string(), id.clone());
+        Ok(id)
+    }
+}
@@ -0,0 +1,187 @@
+//! Small local HTTP server answering `GET /metrics` with the collector's Prometheus metrics and
+//! `GET /status` with every source and file it is tracking as JSON.
+
+use std::io;
+
+use log::{debug, info, warn};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::{fleet, metrics};
+
+///Address served when COLLECTOR_STATUS_ADDR is not set
+pub const DEFAULT_ADDR: &str = "127.0.0.1:9102";
+
+///Longest request head read before the connection is dropped
+const MAX_HEAD_BYTES: usize = 8 * 1024;
+
+///Address from COLLECTOR_STATUS_ADDR; `None` when it is set to `off`
+pub fn addr_from_env() -> Option<String> {
+    match std::env::var("COLLECTOR_STATUS_ADDR") {
+        Ok(addr) if addr.eq_ignore_ascii_case("off") => None,
+        Ok(addr) => Some(addr),
+        Err(_) => Some(DEFAULT_ADDR.to_string()),
+    }
+}
+
+///Who the /status page describes; heartbeat_interval_secs is 0 when the collector does not
+///report to the API
+#[derive(Debug, Clone)]
+pub struct Identity {
+    pub collector_id: String,
+    pub heartbeat_interval_secs: u64,
+}
+
+///Status code, content type and body of the answer to `method` `target`
+pub fn respond(method: &str, target: &str, identity: &Identity) -> (u16, &'static str, String) {
+    let path = target.split('?').next().unwrap_or_default();
+
+    match (method, path) {
+        ("GET", "/metrics") => (
+            200,
+            "text/plain; version=0.0.4; charset=utf-8",
+            metrics::encode(),
+        ),
+        ("GET", "/status") => {
+            let status = fleet::snapshot(&identity.collector_id, identity.heartbeat_interval_secs);
+            (
+                200,
+                "application/json",
+                serde_json::to_string_pretty(&status).unwrap_or_default(),
+            )
+        }
+        (_, "/metrics" | "/status") => (405, "text/plain", String::from("Method Not Allowed\n")),
+        _ => (404, "text/plain", String::from("Not Found\n")),
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        405 => "Method Not Allowed",
+        _ => "Not Found",
+    }
+}
+
+async fn handle(stream: TcpStream, identity: &Identity) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (
+        parts.next().unwrap_or_default().to_string(),
+        parts.next().unwrap_or_default().to_string(),
+    );
+
+    // skip the headers; nothing in them changes the answer
+    let mut head_bytes = request_line.len();
+    loop {
+        let mut header = String::new();
+        let read = reader.read_line(&mut header).await?;
+        head_bytes += read;
+        if read == 0 || header == "\r\n" || header == "\n" || head_bytes > MAX_HEAD_BYTES {
+            break;
+        }
+    }
+
+    let (status, content_type, body) = respond(&method, &target, identity);
+    let response = format!(
+        "HTTP/1.1 {status} {}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        reason(status),
+        body.len()
+    );
+
+    let mut stream = reader.into_inner();
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+///Serve /metrics and /status on `addr` until the process ends
+pub async fn run(addr: String, identity: Identity) -> io::Result<()> {
+    let listener = TcpListener::bind(&addr).await?;
+    info!("Status server listening on http://{addr}");
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let identity = identity.clone();
+        tokio::spawn(async move {
+            if let Err(err) = handle(stream, &identity).await {
+                debug!("Status request from {peer} failed: {err}");
+            }
+        });
+    }
+}
+
+///Start the status server in the background
+pub fn start(addr: String, identity: Identity) {
+    tokio::spawn(async move {
+        if let Err(err) = run(addr.clone(), identity).await {
+            warn!("Status server on {addr} stopped: {err}");
+        }
+    });
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    fn identity() -> Identity {
+        Identity {
+            collector_id: String::from("web-01"),
+            heartbeat_interval_secs: 30,
+        }
+    }
+
+    #[test]
+    fn test_routes() {
+        fleet::register_source("status-test", "directory");
+        fleet::record_read("status-test", 3, 120);
+
+        let (status, content_type, body) = respond("GET", "/metrics", &identity());
+        assert_eq!(
+            (status, content_type.starts_with("text/plain")),
+            (200, true)
+        );
+        assert!(body.contains(r#"log_monitor_collector_lines_read_total{source="status-test"}"#));
+
+        let (status, _, body) = respond("GET", "/status?pretty", &identity());
+        assert_eq!(status, 200);
+        let status: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(status["collector_id"], "web-01");
+        assert!(
+            status["sources"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|source| source["name"] == "status-test" && source["bytes_read"] == 120)
+        );
+
+        assert_eq!(respond("POST", "/status", &identity()).0, 405);
+        assert_eq!(respond("GET", "/", &identity()).0, 404);
+    }
+
+    #[tokio::test]
+    async fn test_serves_http() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            handle(stream, &identity()).await.unwrap();
+        });
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client
+            .write_all(b"GET /status HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("\"collector_id\": \"web-01\""));
+    }
+}
@@ -0,0 +1,565 @@
+//! Syslog input: UDP, TCP and TLS listeners for RFC 5424 and RFC 3164 messages. Received
+//! lines are mapped to a service, converted to `LogRequest`s and stored in batches through
+//! the same `ALogFile` path as lines read from files.
+
+pub mod parser;
+
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use dal_layer::models::log_model::{LogLevel, LogRequest};
+use dal_layer::pipeline::Pipeline;
+use dal_layer::repository::db::Database;
+use log::{debug, error, info, warn};
+use regex::Regex;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
+use tokio::net::{TcpListener, UdpSocket};
+use tokio::sync::mpsc;
+use tokio::time::{MissedTickBehavior, interval};
+use tokio_rustls::TlsAcceptor;
+
+use crate::service_resolver::ServiceResolver;
+use crate::{ALogFile, append_continuation, fleet, is_continuation};
+use dal_layer::utils::trace_helper::TraceExtractor;
+use parser::SyslogMessage;
+
+///Largest message accepted on any transport; longer newline-framed lines are cut here
+const MAX_MESSAGE_BYTES: usize = 64 * 1024;
+
+///First wait after a failed receive or accept; it doubles while the failures go on
+const RETRY_DELAY: Duration = Duration::from_millis(50);
+
+///Longest wait between two attempts to receive or accept
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SyslogConfig {
+    ///UDP bind address, e.g. `0.0.0.0:514`
+    pub udp: Option<String>,
+    ///Plain TCP bind address, octet-counting and newline framing are both accepted
+    pub tcp: Option<String>,
+    pub tls: Option<SyslogTlsConfig>,
+    ///Evaluated in order; the first match names the service, otherwise the source's
+    ///application_name is used
+    #[serde(default)]
+    pub rules: Vec<ServiceRule>,
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    #[serde(default = "default_flush_interval_secs")]
+    pub flush_interval_secs: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SyslogTlsConfig {
+    pub bind: String,
+    pub cert_path: String,
+    pub key_path: String,
+}
+
+///Route messages whose hostname and/or app-name match the given regexes to `service`.
+///`service` may use `{hostname}` and `{app_name}` placeholders.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ServiceRule {
+    pub hostname: Option<String>,
+    pub app_name: Option<String>,
+    pub service: String,
+}
+
+fn default_batch_size() -> usize {
+    500
+}
+
+fn default_flush_interval_secs() -> u64 {
+    5
+}
+
+struct CompiledRule {
+    hostname: Option<Regex>,
+    app_name: Option<Regex>,
+    service: String,
+}
+
+///Picks the service a syslog message belongs to
+pub struct ServiceRouter {
+    rules: Vec<CompiledRule>,
+    default_service: String,
+}
+
+impl ServiceRouter {
+    pub fn new(rules: &[ServiceRule], default_service: &str) -> Result<Self, regex::Error> {
+        let compile = |pattern: &Option<String>| pattern.as_deref().map(Regex::new).transpose();
+
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                Ok(CompiledRule {
+                    hostname: compile(&rule.hostname)?,
+                    app_name: compile(&rule.app_name)?,
+                    service: rule.service.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, regex::Error>>()?;
+
+        Ok(ServiceRouter {
+            rules,
+            default_service: default_service.to_string(),
+        })
+    }
+
+    pub fn service_for(&self, message: &SyslogMessage) -> String {
+        let hostname = message.hostname.as_deref().unwrap_or("");
+        let app_name = message.app_name.as_deref().unwrap_or("");
+
+        let matches = |pattern: &Option<Regex>, value: &str| {
+            pattern.as_ref().is_none_or(|regex| regex.is_match(value))
+        };
+
+        let service = self
+            .rules
+            .iter()
+            .find(|rule| matches(&rule.hostname, hostname) && matches(&rule.app_name, app_name))
+            .map(|rule| {
+                rule.service
+                    .replace("{hostname}", hostname)
+                    .replace("{app_name}", app_name)
+            })
+            .filter(|service| !service.trim().is_empty());
+
+        service.unwrap_or_else(|| self.default_service.clone())
+    }
+}
+
+///Convert a parsed message into a request for `service_id`
+pub fn to_log_request(message: SyslogMessage, service_id: &str) -> LogRequest {
+    let mut attributes = Map::new();
+    attributes.insert(
+        String::from("syslog_facility"),
+        Value::from(message.facility),
+    );
+    attributes.insert(
+        String::from("syslog_severity"),
+        Value::from(message.severity),
+    );
+    if let Some(app_name) = &message.app_name {
+        attributes.insert(String::from("app_name"), Value::from(app_name.clone()));
+    }
+    if let Some(proc_id) = &message.proc_id {
+        attributes.insert(String::from("proc_id"), Value::from(proc_id.clone()));
+    }
+    if let Some(msg_id) = &message.msg_id {
+        attributes.insert(String::from("msg_id"), Value::from(msg_id.clone()));
+    }
+    if !message.structured_data.is_empty() {
+        let structured_data = serde_json::to_value(&message.structured_data).unwrap_or_default();
+        attributes.insert(String::from("structured_data"), structured_data);
+    }
+
+    let created_at = message
+        .timestamp
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .unwrap_or_else(Utc::now)
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string();
+
+    LogRequest {
+        level: LogLevel::from_syslog_severity(message.severity),
+        my_service_id: service_id.to_string(),
+        line_content: message.message,
+        created_at,
+        attributes: Some(attributes.into_iter().collect()),
+        host: message.hostname,
+        source_file: None,
+        line_offset: None,
+        trace_id: None,
+        span_id: None,
+    }
+}
+
+///Read one message from a stream. Octet-counted frames (`LEN SP MSG`) are recognised by a
+///leading digit; anything else is read up to the next newline.
+pub async fn read_frame<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    // skip blank lines between frames
+    loop {
+        let buffer = reader.fill_buf().await?;
+        match buffer.first() {
+            None => return Ok(None),
+            Some(b'\n' | b'\r' | b' ' | b'\0') => reader.consume(1),
+            Some(_) => break,
+        }
+    }
+
+    let first = reader.fill_buf().await?[0];
+    if first.is_ascii_digit() {
+        let mut length = Vec::new();
+        (&mut *reader).take(8).read_until(b' ', &mut length).await?;
+
+        let length: usize = std::str::from_utf8(&length)
+            .ok()
+            .and_then(|text| text.trim_end().parse().ok())
+            .filter(|length| *length <= MAX_MESSAGE_BYTES)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "bad octet count"))?;
+
+        let mut frame = vec![0; length];
+        reader.read_exact(&mut frame).await?;
+        return Ok(Some(frame));
+    }
+
+    let mut frame = Vec::new();
+    (&mut *reader)
+        .take(MAX_MESSAGE_BYTES as u64)
+        .read_until(b'\n', &mut frame)
+        .await?;
+    while matches!(frame.last(), Some(b'\n' | b'\r')) {
+        frame.pop();
+    }
+
+    Ok(Some(frame))
+}
+
+///Everything a listener needs to turn raw messages into queued requests
+#[derive(Clone)]
+pub struct SyslogSink {
+    router: Arc<ServiceRouter>,
+    resolver: Arc<ServiceResolver>,
+    trace_extractor: Arc<TraceExtractor>,
+    sender: mpsc::Sender<LogRequest>,
+}
+
+impl SyslogSink {
+    async fn accept(&self, raw: &[u8]) {
+        let text = String::from_utf8_lossy(raw);
+        if text.trim().is_empty() {
+            return;
+        }
+
+        // Keep malformed messages rather than losing them; they go to the default service
+        let message = parser::parse(&text).unwrap_or_else(|_| SyslogMessage {
+            facility: 1,
+            severity: 6,
+            timestamp: None,
+            hostname: None,
+            app_name: None,
+            proc_id: None,
+            msg_id: None,
+            structured_data: Default::default(),
+            message: text.to_string(),
+        });
+
+        let service = self.router.service_for(&message);
+        let service_id = match self.resolver.resolve(&service).await {
+            Ok(id) => id,
+            Err(err) => {
+                warn!("Could not resolve syslog service {service}: {err}");
+                return;
+            }
+        };
+
+        let mut request = to_log_request(message, &service_id);
+        self.trace_extractor.apply_request(&mut request);
+
+        if self.sender.send(request).await.is_err() {
+            error!("Syslog batcher stopped, dropping message");
+        }
+    }
+}
+
+///Wait before the next receive or accept once one failed. Errors such as a full file
+///descriptor table or an ICMP error reported on the UDP socket pass, so the listener stays up.
+#[derive(Default)]
+struct Backoff {
+    failures: u32,
+}
+
+impl Backoff {
+    async fn wait(&mut self, what: &str, err: io::Error) {
+        let delay = (RETRY_DELAY * 2u32.pow(self.failures.min(10))).min(MAX_RETRY_DELAY);
+        self.failures += 1;
+        warn!("Syslog {what} failed, retrying in {delay:?}: {err}");
+        tokio::time::sleep(delay).await;
+    }
+
+    fn reset(&mut self) {
+        self.failures = 0;
+    }
+}
+
+async fn run_udp(addr: String, sink: SyslogSink) -> io::Result<()> {
+    let socket = UdpSocket::bind(&addr).await?;
+    info!("Syslog listening on udp://{addr}");
+
+    let mut buffer = vec![0u8; MAX_MESSAGE_BYTES];
+    let mut backoff = Backoff::default();
+    loop {
+        match socket.recv_from(&mut buffer).await {
+            Ok((length, _)) => {
+                backoff.reset();
+                sink.accept(&buffer[..length]).await;
+            }
+            Err(err) => backoff.wait(&format!("receive on udp://{addr}"), err).await,
+        }
+    }
+}
+
+async fn read_stream<S: AsyncRead + Unpin>(stream: S, sink: SyslogSink) {
+    let mut reader = BufReader::new(stream);
+    loop {
+        match read_frame(&mut reader).await {
+            Ok(Some(frame)) => sink.accept(&frame).await,
+            Ok(None) => break,
+            Err(err) => {
+                debug!("Closing syslog connection: {err}");
+                break;
+            }
+        }
+    }
+}
+
+async fn run_tcp(addr: String, sink: SyslogSink) -> io::Result<()> {
+    let listener = TcpListener::bind(&addr).await?;
+    info!("Syslog listening on tcp://{addr}");
+
+    let mut backoff = Backoff::default();
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                backoff.reset();
+                tokio::spawn(read_stream(stream, sink.clone()));
+            }
+            Err(err) => backoff.wait(&format!("accept on tcp://{addr}"), err).await,
+        }
+    }
+}
+
+pub(crate) fn tls_acceptor(config: &SyslogTlsConfig) -> io::Result<TlsAcceptor> {
+    let invalid = |err: String| io::Error::new(io::ErrorKind::InvalidInput, err);
+
+    let certs: Vec<CertificateDer<'static>> = CertificateDer::pem_file_iter(&config.cert_path)
+        .and_then(|certs| certs.collect())
+        .map_err(|err| invalid(format!("reading {}: {err}", config.cert_path)))?;
+    let key = PrivateKeyDer::from_pem_file(&config.key_path)
+        .map_err(|err| invalid(format!("reading {}: {err}", config.key_path)))?;
+
+    let server_config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .map_err(|err| invalid(err.to_string()))?
+    .with_no_client_auth()
+    .with_single_cert(certs, key)
+    .map_err(|err| invalid(err.to_string()))?;
+
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+async fn run_tls(config: SyslogTlsConfig, sink: SyslogSink) -> io::Result<()> {
+    let acceptor = tls_acceptor(&config)?;
+    let listener = TcpListener::bind(&config.bind).await?;
+    info!("Syslog listening on tls://{}", config.bind);
+
+    let mut backoff = Backoff::default();
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => {
+                backoff.reset();
+                accepted
+            }
+            Err(err) => {
+                backoff
+                    .wait(&format!("accept on tls://{}", config.bind), err)
+                    .await;
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let sink = sink.clone();
+
+        tokio::spawn(async move {
+            match acceptor.accept(stream).await {
+                Ok(stream) => read_stream(stream, sink).await,
+                Err(err) => warn!("TLS handshake with {peer} failed: {err}"),
+            }
+        });
+    }
+}
+
+///Store queued requests once `batch_size` are waiting or every flush interval
+async fn run_batcher(
+    mut receiver: mpsc::Receiver<LogRequest>,
+    application_name: String,
+    batch_size: usize,
+    flush_interval: Duration,
+    db: Arc<Database>,
+    pipeline: Arc<Pipeline>,
+) {
+    let mut batch = ALogFile::new(application_name, TraceExtractor::default(), pipeline);
+
+    let mut ticker = interval(flush_interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            request = receiver.recv() => match request {
+                Some(request) => {
+                    fleet::record_read(&batch.application_name, 1, request.line_content.len());
+                    // the frames of a stack trace arrive as messages of their own
+                    match batch.logs_in_file.last_mut() {
+                        Some(previous)
+                            if previous.my_service_id == request.my_service_id
+                                && previous.host == request.host
+                                && is_continuation(&request.line_content) =>
+                        {
+                            append_continuation(previous, &request.line_content);
+                        }
+                        _ => batch.logs_in_file.push(request),
+                    }
+                    fleet::set_pending(&batch.application_name, batch.logs_in_file.len());
+                    if batch.logs_in_file.len() >= batch_size {
+                        batch.store(&db).await;
+                    }
+                }
+                None => {
+                    batch.store(&db).await;
+                    break;
+                }
+            },
+            _ = ticker.tick() => {
+                if !batch.logs_in_file.is_empty() {
+                    batch.store(&db).await;
+                }
+            }
+        }
+    }
+}
+
+///Start the listeners and batcher of one syslog source
+pub fn start(
+    application_name: &str,
+    config: SyslogConfig,
+    trace_extractor: TraceExtractor,
+    db: Arc<Database>,
+    resolver: Arc<ServiceResolver>,
+    pipeline: Arc<Pipeline>,
+) -> io::Result<()> {
+    let router = ServiceRouter::new(&config.rules, application_name)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+    let batch_size = config.batch_size.max(1);
+    let (sender, receiver) = mpsc::channel(batch_size * 4);
+    tokio::spawn(run_batcher(
+        receiver,
+        application_name.to_string(),
+        batch_size,
+        Duration::from_secs(config.flush_interval_secs.max(1)),
+        db,
+        pipeline,
+    ));
+
+    let sink = SyslogSink {
+        router: Arc::new(router),
+        resolver,
+        trace_extractor: Arc::new(trace_extractor),
+        sender,
+    };
+
+    let report = |transport: &'static str| {
+        move |result: io::Result<()>| {
+            if let Err(err) = result {
+                error!("Syslog {transport} listener stopped: {err}");
+            }
+        }
+    };
+
+    if let Some(addr) = config.udp {
+        let sink = sink.clone();
+        tokio::spawn(async move { report("udp")(run_udp(addr, sink).await) });
+    }
+    if let Some(addr) = config.tcp {
+        let sink = sink.clone();
+        tokio::spawn(async move { report("tcp")(run_tcp(addr, sink).await) });
+    }
+    if let Some(tls) = config.tls {
+        // fail start up on a bad certificate instead of inside the task
+        tls_acceptor(&tls)?;
+        tokio::spawn(async move { report("tls")(run_tls(tls, sink).await) });
+    }
+
+    Ok(())
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_frame_octet_counting_and_newlines() {
+        let input: &[u8] =
+            b"28 <13>1 - host app - - - hello<14>Oct 11 22:14:15 host app: second\n\n";
+        let mut reader = BufReader::new(input);
+
+        let first = read_frame(&mut reader).await.unwrap().unwrap();
+        assert_eq!(first, b"<13>1 - host app - - - hello");
+
+        let second = read_frame(&mut reader).await.unwrap().unwrap();
+        assert_eq!(second, b"<14>Oct 11 22:14:15 host app: second");
+
+        assert_eq!(read_frame(&mut reader).await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_service_router() {
+        let rules = vec![
+            ServiceRule {
+                hostname: Some(String::from("^fw-")),
+                app_name: None,
+                service: String::from("firewall"),
+            },
+            ServiceRule {
+                hostname: None,
+                app_name: Some(String::from("^(nginx|haproxy)$")),
+                service: String::from("edge-{app_name}"),
+            },
+        ];
+        let router = ServiceRouter::new(&rules, "network").unwrap();
+
+        let message = |hostname: &str, app_name: &str| {
+            let mut message = parser::parse("<13>1 - - - - - -").unwrap();
+            message.hostname = Some(hostname.to_string());
+            message.app_name = Some(app_name.to_string());
+            message
+        };
+
+        assert_eq!(router.service_for(&message("fw-01", "pf")), "firewall");
+        assert_eq!(router.service_for(&message("lb-01", "nginx")), "edge-nginx");
+        assert_eq!(router.service_for(&message("sw-01", "ntpd")), "network");
+    }
+
+    #[test]
+    fn test_to_log_request() {
+        let message = parser::parse(
+            r#"<11>1 2025-10-24T13:15:06.698Z fw-01 pf 331 BLOCK [meta zone="dmz"] dropped packet"#,
+        )
+        .unwrap();
+
+        let request = to_log_request(message, "697dff2cbfc969fe967002b7");
+
+        assert_eq!(request.level, LogLevel::ERROR);
+        assert_eq!(request.line_content, "dropped packet");
+        assert_eq!(request.created_at, "2025-10-24T13:15:06.698Z");
+        assert_eq!(request.host.as_deref(), Some("fw-01"));
+
+        let attributes = request.attributes.unwrap();
+        assert_eq!(attributes["app_name"], Value::from("pf"));
+        assert_eq!(
+            attributes["structured_data"]["meta"]["zone"],
+            Value::from("dmz")
+        );
+    }
+}
@@ -0,0 +1,348 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDateTime, TimeZone, Utc};
+
+///One syslog message in either RFC 5424 or RFC 3164 (BSD) format
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyslogMessage {
+    pub facility: u8,
+    pub severity: u8,
+    pub timestamp: Option<DateTime<FixedOffset>>,
+    pub hostname: Option<String>,
+    pub app_name: Option<String>,
+    pub proc_id: Option<String>,
+    pub msg_id: Option<String>,
+    ///SD-ID to its parameters, RFC 5424 only
+    pub structured_data: BTreeMap<String, BTreeMap<String, String>>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyslogParseError(pub String);
+
+impl std::fmt::Display for SyslogParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "invalid syslog message: {}", self.0)
+    }
+}
+
+impl std::error::Error for SyslogParseError {}
+
+///Parse a syslog message, picking RFC 5424 when the PRI is followed by a version number
+pub fn parse(input: &str) -> Result<SyslogMessage, SyslogParseError> {
+    let input = input.trim_end_matches(['\r', '\n', '\0']);
+    let (pri, rest) = parse_pri(input)?;
+    let facility = pri / 8;
+    let severity = pri % 8;
+
+    if let Some(rest) = rest.strip_prefix("1 ") {
+        parse_rfc5424(facility, severity, rest)
+    } else {
+        Ok(parse_rfc3164(facility, severity, rest, Utc::now()))
+    }
+}
+
+fn parse_pri(input: &str) -> Result<(u8, &str), SyslogParseError> {
+    let rest = input
+        .strip_prefix('<')
+        .ok_or_else(|| SyslogParseError(String::from("missing <PRI>")))?;
+    let end = rest
+        .find('>')
+        .filter(|end| (1..=3).contains(end))
+        .ok_or_else(|| SyslogParseError(String::from("unterminated <PRI>")))?;
+
+    let pri: u8 = rest[..end]
+        .parse()
+        .ok()
+        .filter(|pri| *pri <= 191)
+        .ok_or_else(|| SyslogParseError(format!("PRI {} out of range", &rest[..end])))?;
+
+    Ok((pri, &rest[end + 1..]))
+}
+
+fn nil(value: &str) -> Option<String> {
+    (value != "-" && !value.is_empty()).then(|| value.to_string())
+}
+
+///`TIMESTAMP HOSTNAME APP-NAME PROCID MSGID STRUCTURED-DATA [MSG]`
+fn parse_rfc5424(
+    facility: u8,
+    severity: u8,
+    rest: &str,
+) -> Result<SyslogMessage, SyslogParseError> {
+    let mut fields = rest.splitn(6, ' ');
+    let mut next = |name: &str| {
+        fields
+            .next()
+            .ok_or_else(|| SyslogParseError(format!("missing {name}")))
+    };
+
+    let timestamp = next("TIMESTAMP")?;
+    let hostname = next("HOSTNAME")?;
+    let app_name = next("APP-NAME")?;
+    let proc_id = next("PROCID")?;
+    let msg_id = next("MSGID")?;
+    let remainder = next("STRUCTURED-DATA")?;
+
+    let timestamp = match timestamp {
+        "-" => None,
+        value => Some(
+            DateTime::parse_from_rfc3339(value)
+                .map_err(|err| SyslogParseError(format!("TIMESTAMP {value}: {err}")))?,
+        ),
+    };
+
+    let (structured_data, message) = parse_structured_data(remainder)?;
+    let message = message.trim_start_matches('\u{feff}').to_string();
+
+    Ok(SyslogMessage {
+        facility,
+        severity,
+        timestamp,
+        hostname: nil(hostname),
+        app_name: nil(app_name),
+        proc_id: nil(proc_id),
+        msg_id: nil(msg_id),
+        structured_data,
+        message,
+    })
+}
+
+type StructuredData = BTreeMap<String, BTreeMap<String, String>>;
+
+///Split `[id k="v"][id2 ...] message` into the structured data and the message
+fn parse_structured_data(input: &str) -> Result<(StructuredData, &str), SyslogParseError> {
+    let mut elements = BTreeMap::new();
+
+    if let Some(message) = input.strip_prefix('-') {
+        return Ok((elements, message.strip_prefix(' ').unwrap_or(message)));
+    }
+
+    let mut rest = input;
+    while let Some(element) = rest.strip_prefix('[') {
+        let id_end = element
+            .find([' ', ']'])
+            .ok_or_else(|| SyslogParseError(String::from("unterminated SD-ELEMENT")))?;
+        let id = element[..id_end].to_string();
+        let mut params = BTreeMap::new();
+        let mut cursor = &element[id_end..];
+
+        loop {
+            cursor = cursor.trim_start_matches(' ');
+            if let Some(after) = cursor.strip_prefix(']') {
+                cursor = after;
+                break;
+            }
+
+            let eq = cursor
+                .find('=')
+                .ok_or_else(|| SyslogParseError(format!("SD-PARAM without value in [{id}")))?;
+            let name = cursor[..eq].to_string();
+            let quoted = cursor[eq + 1..]
+                .strip_prefix('"')
+                .ok_or_else(|| SyslogParseError(format!("unquoted SD-PARAM {name}")))?;
+
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+            let mut consumed = None;
+            while let Some((index, c)) = chars.next() {
+                match c {
+                    '\\' => {
+                        if let Some((_, escaped)) = chars.next() {
+                            if !matches!(escaped, '"' | '\\' | ']') {
+                                value.push('\\');
+                            }
+                            value.push(escaped);
+                        }
+                    }
+                    '"' => {
+                        consumed = Some(index + 1);
+                        break;
+                    }
+                    other => value.push(other),
+                }
+            }
+
+            let consumed = consumed
+                .ok_or_else(|| SyslogParseError(format!("unterminated SD-PARAM {name}")))?;
+            params.insert(name, value);
+            cursor = &quoted[consumed..];
+        }
+
+        elements.insert(id, params);
+        rest = cursor;
+    }
+
+    Ok((elements, rest.strip_prefix(' ').unwrap_or(rest)))
+}
+
+///`Mmm dd hh:mm:ss HOSTNAME TAG[PID]: MSG`. BSD syslog is loosely followed in practice, so
+///anything that does not fit is kept as the message rather than rejected.
+fn parse_rfc3164(facility: u8, severity: u8, rest: &str, now: DateTime<Utc>) -> SyslogMessage {
+    let mut message = SyslogMessage {
+        facility,
+        severity,
+        timestamp: None,
+        hostname: None,
+        app_name: None,
+        proc_id: None,
+        msg_id: None,
+        structured_data: BTreeMap::new(),
+        message: rest.to_string(),
+    };
+
+    let mut rest = rest;
+    if rest.len() >= 15
+        && let Some(timestamp) = rest.get(..15).and_then(|text| bsd_timestamp(text, now))
+    {
+        message.timestamp = Some(timestamp);
+        rest = rest[15..].trim_start();
+    }
+
+    // HOSTNAME is present unless the next token is already the tag (ends with ':' or '[')
+    if let Some((first, after)) = rest.split_once(' ')
+        && !first.ends_with(':')
+        && !first.contains('[')
+    {
+        message.hostname = nil(first);
+        rest = after;
+    }
+
+    if let Some((tag, msg)) = split_tag(rest) {
+        match tag.split_once('[') {
+            Some((app_name, pid)) => {
+                message.app_name = nil(app_name);
+                message.proc_id = nil(pid.trim_end_matches(']'));
+            }
+            None => message.app_name = nil(tag),
+        }
+        rest = msg;
+    }
+
+    message.message = rest.to_string();
+    message
+}
+
+///TAG is at most 32 alphanumeric characters (plus a few separators) followed by ':' or '['
+fn split_tag(input: &str) -> Option<(&str, &str)> {
+    let colon = input
+        .find(": ")
+        .or_else(|| input.strip_suffix(':').map(str::len))?;
+    let tag = &input[..colon];
+
+    let name = tag.split('[').next().unwrap_or(tag);
+    let valid = !name.is_empty()
+        && name.len() <= 48
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'));
+
+    valid.then(|| (tag, input.get(colon + 2..).unwrap_or("")))
+}
+
+///BSD timestamps carry no year or zone: assume UTC and the current year, or last year when
+///that would put the message more than a day in the future (December logs read in January).
+fn bsd_timestamp(text: &str, now: DateTime<Utc>) -> Option<DateTime<FixedOffset>> {
+    let parse = |year: i32| {
+        NaiveDateTime::parse_from_str(&format!("{year} {text}"), "%Y %b %e %H:%M:%S").ok()
+    };
+
+    let mut timestamp = Utc.from_utc_datetime(&parse(now.year())?);
+    if timestamp > now + chrono::Duration::days(1) {
+        timestamp = Utc.from_utc_datetime(&parse(now.year() - 1)?);
+    }
+
+    Some(timestamp.fixed_offset())
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rfc5424() {
+        let line = r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"][examplePriority@32473 class="high"] BOMAn application event log entry"#;
+
+        let message = parse(line).unwrap();
+
+        assert_eq!(message.facility, 20);
+        assert_eq!(message.severity, 5);
+        assert_eq!(message.hostname.as_deref(), Some("mymachine.example.com"));
+        assert_eq!(message.app_name.as_deref(), Some("evntslog"));
+        assert_eq!(message.proc_id, None);
+        assert_eq!(message.msg_id.as_deref(), Some("ID47"));
+        assert_eq!(
+            message.structured_data["exampleSDID@32473"]["eventSource"],
+            "Application"
+        );
+        assert_eq!(
+            message.structured_data["examplePriority@32473"]["class"],
+            "high"
+        );
+        assert_eq!(message.message, "BOMAn application event log entry");
+        assert_eq!(
+            message.timestamp.unwrap().to_rfc3339(),
+            "2003-10-11T22:14:15.003+00:00"
+        );
+    }
+
+    #[test]
+    fn test_parse_rfc5424_nil_values_and_escapes() {
+        let line = r#"<11>1 - - - - - [meta note="a \"quoted\" \] value"]"#;
+
+        let message = parse(line).unwrap();
+
+        assert_eq!(message.severity, 3);
+        assert_eq!(message.timestamp, None);
+        assert_eq!(message.hostname, None);
+        assert_eq!(
+            message.structured_data["meta"]["note"],
+            r#"a "quoted" ] value"#
+        );
+        assert_eq!(message.message, "");
+    }
+
+    #[test]
+    fn test_parse_rfc3164() {
+        let now = Utc.with_ymd_and_hms(2025, 10, 12, 0, 0, 0).unwrap();
+
+        let message = parse_rfc3164(
+            4,
+            2,
+            "Oct 11 22:14:15 mymachine su[231]: 'su root' failed for lonvick on /dev/pts/8",
+            now,
+        );
+
+        assert_eq!(message.hostname.as_deref(), Some("mymachine"));
+        assert_eq!(message.app_name.as_deref(), Some("su"));
+        assert_eq!(message.proc_id.as_deref(), Some("231"));
+        assert_eq!(
+            message.message,
+            "'su root' failed for lonvick on /dev/pts/8"
+        );
+        assert_eq!(
+            message.timestamp.unwrap().to_rfc3339(),
+            "2025-10-11T22:14:15+00:00"
+        );
+    }
+
+    #[test]
+    fn test_parse_rfc3164_without_hostname_and_previous_year() {
+        let now = Utc.with_ymd_and_hms(2026, 1, 2, 0, 0, 0).unwrap();
+
+        let message = parse_rfc3164(0, 6, "Dec 31 23:59:59 kernel: eth0 link up", now);
+
+        assert_eq!(message.hostname, None);
+        assert_eq!(message.app_name.as_deref(), Some("kernel"));
+        assert_eq!(message.message, "eth0 link up");
+        assert_eq!(message.timestamp.unwrap().year(), 2025);
+    }
+
+    #[test]
+    fn test_parse_rejects_missing_pri() {
+        assert!(parse("Oct 11 22:14:15 mymachine su: hello").is_err());
+        assert!(parse("<999>1 - - - - - -").is_err());
+    }
+}
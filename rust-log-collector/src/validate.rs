@@ -0,0 +1,201 @@
+//! Checks of the sources made by `rust-log-collector validate`: everything that would make a
+//! source fail once running, found without connecting to the database.
+
+use std::collections::HashSet;
+use std::fs;
+use std::net::ToSocketAddrs;
+use std::path::Path;
+
+use dal_layer::utils::trace_helper::TraceExtractor;
+
+use crate::Config;
+use crate::file_matcher::FileMatcher;
+use crate::syslog::{self, ServiceRouter, SyslogConfig};
+
+///Every problem of `configs`, each prefixed with the name of its source
+pub fn validate(configs: &[Config]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut names = HashSet::new();
+
+    if configs.is_empty() {
+        problems.push(String::from("the configuration lists no source"));
+    }
+    for config in configs {
+        let name = &config.application_name;
+        if name.trim().is_empty() {
+            problems.push(String::from("a source has an empty application_name"));
+        } else if !names.insert(name.as_str()) {
+            problems.push(format!(
+                "{name}: application_name is used by another source"
+            ));
+        }
+
+        problems.extend(
+            validate_source(config)
+                .into_iter()
+                .map(|problem| format!("{name}: {problem}")),
+        );
+    }
+
+    problems
+}
+
+fn validate_source(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if let Err(err) = config.pipeline() {
+        problems.push(format!("invalid redaction config: {err}"));
+    }
+    if let Err(err) = TraceExtractor::from_pattern(config.trace_id_pattern.as_deref()) {
+        problems.push(format!("invalid trace_id_pattern: {err}"));
+    }
+    let checkpoint = config.checkpoint_path();
+    if let Some(parent) = checkpoint
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        && !parent.is_dir()
+    {
+        problems.push(format!(
+            "the directory of checkpoint_path {} does not exist",
+            checkpoint.display()
+        ));
+    }
+
+    if let Some(syslog_config) = &config.syslog {
+        problems.extend(validate_syslog(syslog_config, &config.application_name));
+    } else if let Some(container) = &config.container {
+        let root = container.root();
+        if let Err(err) = fs::read_dir(&root) {
+            problems.push(format!(
+                "cannot list the container logs in {}: {err}",
+                root.display()
+            ));
+        }
+        if container.poll_interval_secs == 0 {
+            problems.push(String::from(
+                "container.poll_interval_secs must be at least 1",
+            ));
+        }
+    } else {
+        problems.extend(validate_directory(config));
+    }
+
+    problems
+}
+
+fn validate_directory(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let matcher = match FileMatcher::new(config) {
+        Ok(matcher) => matcher,
+        Err(err) => return vec![format!("invalid include or exclude pattern: {err}")],
+    };
+
+    let location = Path::new(&config.log_location);
+    if config.log_location.is_empty() {
+        problems.push(String::from("log_location is empty"));
+    } else if !location.is_dir() {
+        problems.push(format!(
+            "log_location {} is not a directory",
+            location.display()
+        ));
+    } else if let Err(err) = fs::read_dir(location) {
+        problems.push(format!(
+            "cannot list log_location {}: {err}",
+            location.display()
+        ));
+    } else if fs::metadata(location).is_ok_and(|meta| meta.permissions().readonly()) {
+        // the files read are deleted, which a read only directory does not allow
+        problems.push(format!(
+            "log_location {} is read only, the files read could not be deleted",
+            location.display()
+        ));
+    }
+
+    match matcher.find_files() {
+        Ok(files) => {
+            for file in files {
+                if let Err(err) = fs::File::open(&file) {
+                    problems.push(format!("cannot read {}: {err}", file.display()));
+                }
+            }
+        }
+        Err(err) if problems.is_empty() => problems.push(format!("cannot list the files: {err}")),
+        Err(_) => {}
+    }
+
+    problems
+}
+
+fn validate_syslog(config: &SyslogConfig, application_name: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let binds = [
+        ("syslog.udp", config.udp.as_deref()),
+        ("syslog.tcp", config.tcp.as_deref()),
+        (
+            "syslog.tls.bind",
+            config.tls.as_ref().map(|tls| tls.bind.as_str()),
+        ),
+    ];
+    if binds.iter().all(|(_, bind)| bind.is_none()) {
+        problems.push(String::from("syslog needs at least one of udp, tcp or tls"));
+    }
+    for (field, bind) in binds {
+        if let Some(bind) = bind
+            && bind.to_socket_addrs().is_err()
+        {
+            problems.push(format!("{field}: {bind:?} is not a host:port address"));
+        }
+    }
+
+    if let Some(tls) = &config.tls
+        && let Err(err) = syslog::tls_acceptor(tls)
+    {
+        problems.push(format!("syslog.tls: {err}"));
+    }
+    if let Err(err) = ServiceRouter::new(&config.rules, application_name) {
+        problems.push(format!("invalid syslog rule: {err}"));
+    }
+    if config.batch_size == 0 || config.flush_interval_secs == 0 {
+        problems.push(String::from(
+            "syslog.batch_size and syslog.flush_interval_secs must be at least 1",
+        ));
+    }
+
+    problems
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let configs: Vec<Config> = serde_json::from_str(
+            r#"[
+                {"application_name": "webclient", "log_location": ".", "include": ["log.txt"]},
+                {"application_name": "webclient", "log_location": "/nonexistent/logs",
+                 "trace_id_pattern": "(?P<trace_id>[0-9a-f]{32}", "include": ["[a-"]},
+                {"application_name": "edge", "syslog": {"udp": "0.0.0.0"}}
+            ]"#,
+        )
+        .unwrap();
+
+        let problems = validate(&configs);
+
+        assert_eq!(problems.len(), 4, "{problems:?}");
+        assert_eq!(
+            problems[0],
+            "webclient: application_name is used by another source"
+        );
+        assert!(problems[1].starts_with("webclient: invalid trace_id_pattern"));
+        assert!(problems[2].starts_with("webclient: invalid include or exclude pattern"));
+        assert_eq!(
+            problems[3],
+            "edge: syslog.udp: \"0.0.0.0\" is not a host:port address"
+        );
+    }
+}
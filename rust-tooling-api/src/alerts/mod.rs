@@ -0,0 +1,264 @@
+//! Background evaluation of the alert rules stored in the database.
+
+mod notify;
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use actix_web::web::Data;
+use chrono::Utc;
+use dal_layer::models::alert_model::{
+    AlertCondition, AlertNotification, AlertRule, AlertState, AlertStatus,
+};
+use dal_layer::repository::db::Database;
+use mongodb::bson::DateTime;
+
+///Evaluate every enabled rule each `interval`, command notifiers running the programs of
+///`commands`
+pub fn spawn_alert_scheduler(
+    db: Data<Database>,
+    interval: Duration,
+    commands: BTreeMap<String, String>,
+) {
+    crate::jobs::register("alerts", interval);
+    actix_web::rt::spawn(async move {
+        let mut ticker = actix_web::rt::time::interval(interval);
+        loop {
+            ticker.tick().await;
+
+            let started = Instant::now();
+            let result = evaluate_rules(&db, &commands).await;
+            crate::jobs::record_run("alerts", started.elapsed(), &result);
+            if let Err(err) = result {
+                eprintln!("Could not evaluate the alert rules: {err}");
+            }
+        }
+    });
+}
+
+async fn evaluate_rules(
+    db: &Database,
+    commands: &BTreeMap<String, String>,
+) -> Result<(), actix_web::Error> {
+    let rules = db.get_alert_rules().await?;
+    let mut statuses: HashMap<_, _> = db
+        .get_alert_statuses()
+        .await?
+        .into_iter()
+        .map(|status| (status._id, status))
+        .collect();
+    let silences = db.get_silences().await?;
+
+    for rule in rules.iter().filter(|rule| rule.enabled) {
+        let now = DateTime::now();
+        let count = count_for(db, rule, now).await?;
+        let silenced = silences.iter().any(|silence| silence.mutes(rule, now));
+
+        let (status, notification) =
+            evaluate(rule, statuses.remove(&rule._id), count, silenced, now);
+        db.save_alert_status(&status).await?;
+
+        if let Some(state) = notification {
+            let notification = AlertNotification {
+                rule_id: rule._id.to_hex(),
+                rule_name: rule.name.clone(),
+                service_id: rule.service_id.map(|id| id.to_hex()),
+                state,
+                value: status.value,
+                message: status.message.clone(),
+                at: Utc::now().to_rfc3339(),
+            };
+            for notifier in &rule.notifiers {
+                if let Err(err) = notify::send(notifier, &notification, commands).await {
+                    eprintln!("Could not notify for alert {}: {err}", rule.name);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+///Lines the rule's condition looks for within its window
+async fn count_for(
+    db: &Database,
+    rule: &AlertRule,
+    now: DateTime,
+) -> Result<u64, actix_web::Error> {
+    let window_millis = secs_to_millis(rule.condition.window_secs());
+    let since = DateTime::from_millis(now.timestamp_millis().saturating_sub(window_millis));
+
+    match &rule.condition {
+        AlertCondition::Threshold { min_level, .. } => {
+            db.count_logs_since(rule.service_id, *min_level, None, since)
+                .await
+        }
+        AlertCondition::Match {
+            pattern, min_level, ..
+        } => {
+            db.count_logs_since(rule.service_id, *min_level, Some(pattern), since)
+                .await
+        }
+        AlertCondition::Absence { .. } => {
+            db.count_logs_since(rule.service_id, None, None, since)
+                .await
+        }
+    }
+}
+
+///Milliseconds in `secs`, capped at `i64::MAX` for rules stored before their values were bounded
+fn secs_to_millis(secs: u64) -> i64 {
+    secs.checked_mul(1000)
+        .and_then(|millis| i64::try_from(millis).ok())
+        .unwrap_or(i64::MAX)
+}
+
+///New status of a rule after an evaluation that found `count` lines, and the state to notify
+///about, if any. A firing alert is sent when it starts and again every repeat interval; a
+///resolved one only if its firing was sent. Nothing is sent while the rule is silenced.
+fn evaluate(
+    rule: &AlertRule,
+    previous: Option<AlertStatus>,
+    count: u64,
+    silenced: bool,
+    now: DateTime,
+) -> (AlertStatus, Option<AlertState>) {
+    let state = if rule.condition.is_firing(count) {
+        AlertState::Firing
+    } else {
+        AlertState::Ok
+    };
+    let (since, notified_at) = match &previous {
+        Some(previous) if previous.state == state => (previous.since, previous.notified_at),
+        Some(previous) => (now, previous.notified_at),
+        None => (now, None),
+    };
+
+    let repeat_millis = secs_to_millis(rule.repeat_interval_secs);
+    let (notification, notified_at) = match state {
+        AlertState::Firing => {
+            let due = notified_at.is_none_or(|at| {
+                now.timestamp_millis().saturating_sub(at.timestamp_millis()) >= repeat_millis
+            });
+            if due && !silenced {
+                (Some(AlertState::Firing), Some(now))
+            } else {
+                (None, notified_at)
+            }
+        }
+        AlertState::Ok if notified_at.is_some() && !silenced => (Some(AlertState::Ok), None),
+        AlertState::Ok => (None, None),
+    };
+
+    let status = AlertStatus {
+        _id: rule._id,
+        rule_name: rule.name.clone(),
+        state,
+        since,
+        value: count as i64,
+        message: rule.condition.describe(count),
+        evaluated_at: now,
+        notified_at,
+    };
+    (status, notification)
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_layer::models::log_model::LogLevel;
+    use mongodb::bson::oid::ObjectId;
+
+    fn rule() -> AlertRule {
+        AlertRule {
+            _id: ObjectId::new(),
+            name: String::from("payment errors"),
+            service_id: Some(ObjectId::new()),
+            condition: AlertCondition::Threshold {
+                min_level: Some(LogLevel::ERROR),
+                threshold: 50,
+                window_secs: 300,
+            },
+            notifiers: Vec::new(),
+            repeat_interval_secs: 600,
+            enabled: true,
+            created_at: DateTime::now(),
+        }
+    }
+
+    fn at(secs: i64) -> DateTime {
+        DateTime::from_millis(1_760_000_000_000 + secs * 1000)
+    }
+
+    #[test]
+    fn test_fires_repeats_and_resolves() {
+        let rule = rule();
+
+        let (status, sent) = evaluate(&rule, None, 10, false, at(0));
+        assert_eq!((status.state, sent), (AlertState::Ok, None));
+
+        let (status, sent) = evaluate(&rule, Some(status), 62, false, at(30));
+        assert_eq!(
+            (status.state, sent),
+            (AlertState::Firing, Some(AlertState::Firing))
+        );
+        assert_eq!(
+            status.message,
+            "62 ERROR or worse lines in the last 300s (threshold 50)"
+        );
+
+        // still firing: no duplicate until the repeat interval has passed
+        let (status, sent) = evaluate(&rule, Some(status), 70, false, at(60));
+        assert_eq!(sent, None);
+        assert_eq!(status.since, at(30));
+        let (status, sent) = evaluate(&rule, Some(status), 70, false, at(630));
+        assert_eq!(sent, Some(AlertState::Firing));
+
+        let (status, sent) = evaluate(&rule, Some(status), 3, false, at(660));
+        assert_eq!((status.state, sent), (AlertState::Ok, Some(AlertState::Ok)));
+        assert_eq!(status.notified_at, None);
+    }
+
+    #[test]
+    fn test_silence_defers_notification() {
+        let rule = rule();
+
+        let (status, sent) = evaluate(&rule, None, 80, true, at(0));
+        assert_eq!((status.state, sent), (AlertState::Firing, None));
+
+        // the silence ended while the alert is still firing
+        let (status, sent) = evaluate(&rule, Some(status), 80, false, at(30));
+        assert_eq!(sent, Some(AlertState::Firing));
+
+        // resolving during a silence sends nothing, and the next firing starts afresh
+        let (status, sent) = evaluate(&rule, Some(status), 0, true, at(60));
+        assert_eq!((status.state, sent), (AlertState::Ok, None));
+        let (_, sent) = evaluate(&rule, Some(status), 80, false, at(90));
+        assert_eq!(sent, Some(AlertState::Firing));
+    }
+
+    #[test]
+    fn test_absence_rule() {
+        let mut rule = rule();
+        rule.condition = AlertCondition::Absence { window_secs: 600 };
+
+        let (status, sent) = evaluate(&rule, None, 0, false, at(0));
+        assert_eq!(sent, Some(AlertState::Firing));
+        assert_eq!(status.message, "No logs in the last 600s");
+    }
+
+    #[test]
+    fn test_huge_intervals_do_not_overflow() {
+        assert_eq!(secs_to_millis(300), 300_000);
+        assert_eq!(secs_to_millis(u64::MAX), i64::MAX);
+
+        let mut rule = rule();
+        rule.repeat_interval_secs = u64::MAX;
+        let (status, sent) = evaluate(&rule, None, 80, false, at(0));
+        assert_eq!(sent, Some(AlertState::Firing));
+        let (_, sent) = evaluate(&rule, Some(status), 80, false, at(86_400));
+        assert_eq!(sent, None);
+    }
+}
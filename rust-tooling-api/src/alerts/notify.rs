@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+use std::process::Stdio;
+use std::time::Duration;
+
+use dal_layer::models::alert_model::{AlertNotification, AlertState, Notifier};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+///Longest a webhook or command may take before it is abandoned
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(30);
+
+///Deliver `notification`. Command notifiers run the program `commands` maps their name to.
+pub async fn send(
+    notifier: &Notifier,
+    notification: &AlertNotification,
+    commands: &BTreeMap<String, String>,
+) -> Result<(), String> {
+    match notifier {
+        Notifier::Webhook { url, headers } => webhook(url, headers, notification).await,
+        Notifier::Command { name } => match commands.get(name) {
+            Some(program) => command(program, notification).await,
+            None => Err(format!("No alert command is configured as {name:?}")),
+        },
+    }
+}
+
+async fn webhook(
+    url: &str,
+    headers: &std::collections::HashMap<String, String>,
+    notification: &AlertNotification,
+) -> Result<(), String> {
+    let mut request = awc::Client::default().post(url).timeout(NOTIFY_TIMEOUT);
+    for (name, value) in headers {
+        request = request.insert_header((name.as_str(), value.as_str()));
+    }
+
+    let response = request
+        .send_json(notification)
+        .await
+        .map_err(|err| format!("POST {url}: {err}"))?;
+    if !response.status().is_success() {
+        return Err(format!("POST {url}: {}", response.status()));
+    }
+
+    Ok(())
+}
+
+async fn command(program: &str, notification: &AlertNotification) -> Result<(), String> {
+    let state = match notification.state {
+        AlertState::Firing => "firing",
+        AlertState::Ok => "resolved",
+    };
+    let mut child = Command::new(program)
+        .env("ALERT_RULE_ID", &notification.rule_id)
+        .env("ALERT_NAME", &notification.rule_name)
+        .env("ALERT_STATE", state)
+        .env("ALERT_VALUE", notification.value.to_string())
+        .env("ALERT_MESSAGE", &notification.message)
+        .stdin(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|err| format!("{program}: {err}"))?;
+
+    let body = serde_json::to_vec(notification).map_err(|err| err.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // a command that ignores its input closes the pipe early; that is not a failure
+        let _ = stdin.write_all(&body).await;
+    }
+
+    let status = actix_web::rt::time::timeout(NOTIFY_TIMEOUT, child.wait())
+        .await
+        .map_err(|_| format!("{program}: timed out"))?
+        .map_err(|err| format!("{program}: {err}"))?;
+    if !status.success() {
+        return Err(format!("{program}: {status}"));
+    }
+
+    Ok(())
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[actix_web::test]
+    async fn test_command_receives_notification() {
+        let notification = AlertNotification {
+            rule_id: String::from("6650f0f0f0f0f0f0f0f0f0f0"),
+            rule_name: String::from("oom"),
+            service_id: None,
+            state: AlertState::Firing,
+            value: 2,
+            message: String::from("2 lines matched `OutOfMemoryError` in the last 60s"),
+            at: String::from("2025-10-24T13:15:06Z"),
+        };
+        let script = std::env::temp_dir().join(format!("notify-check-{}", std::process::id()));
+        std::fs::write(
+            &script,
+            "#!/bin/sh\ngrep -q '\"rule_name\":\"oom\"' && [ \"$ALERT_STATE\" = firing ]\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let commands = BTreeMap::from([
+            (String::from("check"), script.display().to_string()),
+            (String::from("failing"), String::from("/bin/false")),
+        ]);
+
+        let check = Notifier::Command {
+            name: String::from("check"),
+        };
+        assert_eq!(send(&check, &notification, &commands).await, Ok(()));
+
+        let failing = Notifier::Command {
+            name: String::from("failing"),
+        };
+        assert!(send(&failing, &notification, &commands).await.is_err());
+
+        // a rule cannot run anything the server does not list
+        let unknown = Notifier::Command {
+            name: String::from("/bin/sh"),
+        };
+        assert!(send(&unknown, &notification, &commands).await.is_err());
+
+        std::fs::remove_file(script).unwrap();
+    }
+}
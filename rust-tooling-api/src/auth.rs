@@ -0,0 +1,154 @@
+//! API key authentication, enabled by the `[auth]` table of the settings.
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::{Error, HttpResponse, http::header};
+use dal_layer::models::response_model::GenericResponse;
+
+use crate::settings::{AuthSettings, Settings};
+
+///Paths answered without a key: the probes of the orchestrator, the Prometheus scrape and the
+///docs
+const PUBLIC_PATHS: [&str; 6] = [
+    "/api/healthchecker",
+    "/api/health/live",
+    "/api/health/ready",
+    "/metrics",
+    "/swagger-ui",
+    "/api-doc",
+];
+
+fn is_public(path: &str) -> bool {
+    PUBLIC_PATHS
+        .iter()
+        .any(|public| path == *public || path.starts_with(&format!("{public}/")))
+}
+
+///Key sent as `Authorization: Bearer <key>` or `X-API-Key: <key>`
+fn presented_key(request: &ServiceRequest) -> Option<&str> {
+    let headers = request.headers();
+    if let Some(bearer) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    {
+        return Some(bearer.trim());
+    }
+    headers
+        .get("X-API-Key")
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+}
+
+///Compare without returning at the first difference, so the time taken tells nothing of a key
+fn same_key(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+impl AuthSettings {
+    ///Name of the key `key` is, when it is one of the configured keys
+    pub fn key_name(&self, key: &str) -> Option<&str> {
+        self.api_keys
+            .iter()
+            .find(|api_key| same_key(&api_key.key, key))
+            .map(|api_key| api_key.name.as_str())
+    }
+}
+
+///Middleware answering 401 to a request to a protected path without a valid API key
+pub async fn require_api_key(
+    request: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let allowed = match request.app_data::<Data<Settings>>() {
+        Some(settings) if settings.auth.enabled && !is_public(request.path()) => {
+            presented_key(&request).is_some_and(|key| settings.auth.key_name(key).is_some())
+        }
+        _ => true,
+    };
+
+    if !allowed {
+        let response = HttpResponse::Unauthorized()
+            .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+            .json(GenericResponse {
+                code: String::from("401"),
+                data: String::from("A valid API key is required"),
+            });
+        return Ok(request.into_response(response).map_into_right_body());
+    }
+
+    next.call(request)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::ApiKey;
+    use actix_web::middleware::from_fn;
+    use actix_web::{App, test, web};
+
+    #[actix_web::test]
+    async fn test_requires_api_key() {
+        let settings = Settings {
+            auth: AuthSettings {
+                enabled: true,
+                api_keys: vec![ApiKey {
+                    name: String::from("collectors"),
+                    key: String::from("0123456789abcdef"),
+                }],
+            },
+            ..Default::default()
+        };
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(settings))
+                .wrap(from_fn(require_api_key))
+                .route("/api/services", web::get().to(HttpResponse::Ok))
+                .route("/api/health/live", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let status = |request: test::TestRequest| {
+            let app = &app;
+            async move { test::call_service(app, request.to_request()).await.status() }
+        };
+
+        assert_eq!(
+            status(test::TestRequest::get().uri("/api/services")).await,
+            401
+        );
+        assert_eq!(
+            status(
+                test::TestRequest::get()
+                    .uri("/api/services")
+                    .insert_header(("X-API-Key", "not-the-key"))
+            )
+            .await,
+            401
+        );
+        assert_eq!(
+            status(
+                test::TestRequest::get()
+                    .uri("/api/services")
+                    .insert_header((header::AUTHORIZATION, "Bearer 0123456789abcdef"))
+            )
+            .await,
+            200
+        );
+        assert_eq!(
+            status(test::TestRequest::get().uri("/api/health/live")).await,
+            200
+        );
+    }
+}
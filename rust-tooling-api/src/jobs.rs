@@ -0,0 +1,132 @@
+//! Health of the background tasks, from the outcome of their runs, for the readiness endpoint.
+
+use std::collections::BTreeMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::metrics;
+
+///A task is unhealthy once it went this many of its intervals without a successful run
+const UNHEALTHY_AFTER_MISSED_RUNS: u32 = 3;
+
+#[derive(Debug, Clone)]
+struct JobState {
+    interval: Duration,
+    started_at: DateTime<Utc>,
+    last_run_at: Option<DateTime<Utc>>,
+    last_success_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+}
+
+static JOBS: LazyLock<Mutex<BTreeMap<String, JobState>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct JobHealth {
+    pub name: String,
+    pub healthy: bool,
+    pub interval_secs: u64,
+    ///RFC 3339 times of the last run and the last successful run
+    pub last_run_at: Option<String>,
+    pub last_success_at: Option<String>,
+    ///Error of the last run when it failed
+    pub last_error: Option<String>,
+}
+
+fn jobs() -> std::sync::MutexGuard<'static, BTreeMap<String, JobState>> {
+    JOBS.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+///Start tracking a task that runs every `interval`
+pub fn register(job: &str, interval: Duration) {
+    jobs().insert(
+        job.to_string(),
+        JobState {
+            interval,
+            started_at: Utc::now(),
+            last_run_at: None,
+            last_success_at: None,
+            last_error: None,
+        },
+    );
+}
+
+///Record the outcome of one run of a task, in its health and its metrics
+pub fn record_run<T, E: std::fmt::Display>(job: &str, elapsed: Duration, result: &Result<T, E>) {
+    metrics::record_job(job, elapsed, result);
+
+    let now = Utc::now();
+    if let Some(state) = jobs().get_mut(job) {
+        state.last_run_at = Some(now);
+        match result {
+            Ok(_) => {
+                state.last_success_at = Some(now);
+                state.last_error = None;
+            }
+            Err(err) => state.last_error = Some(err.to_string()),
+        }
+    }
+}
+
+///Health of every registered task at `now`. A task is healthy while its last success, or its
+///start when it never succeeded, is within three of its intervals.
+pub fn health(now: DateTime<Utc>) -> Vec<JobHealth> {
+    jobs()
+        .iter()
+        .map(|(name, state)| {
+            let since = state.last_success_at.unwrap_or(state.started_at);
+            let allowed = state.interval * UNHEALTHY_AFTER_MISSED_RUNS;
+            let healthy = (now - since).to_std().unwrap_or_default() <= allowed;
+
+            JobHealth {
+                name: name.clone(),
+                healthy,
+                interval_secs: state.interval.as_secs(),
+                last_run_at: state.last_run_at.map(|at| at.to_rfc3339()),
+                last_success_at: state.last_success_at.map(|at| at.to_rfc3339()),
+                last_error: state.last_error.clone(),
+            }
+        })
+        .collect()
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(name: &str) -> JobHealth {
+        health(Utc::now())
+            .into_iter()
+            .find(|job| job.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_job_health() {
+        register("health-test", Duration::from_secs(30));
+        assert!(job("health-test").healthy);
+
+        record_run::<(), _>("health-test", Duration::from_millis(5), &Err("mongo down"));
+        let failed = job("health-test");
+        assert_eq!(failed.last_error.as_deref(), Some("mongo down"));
+        assert!(failed.last_success_at.is_none());
+
+        // no successful run for more than three intervals
+        let later = Utc::now() + chrono::Duration::seconds(91);
+        let stale = health(later)
+            .into_iter()
+            .find(|job| job.name == "health-test")
+            .unwrap();
+        assert!(!stale.healthy);
+
+        record_run::<(), String>("health-test", Duration::from_millis(5), &Ok(()));
+        let recovered = job("health-test");
+        assert!(recovered.healthy && recovered.last_error.is_none());
+    }
+}
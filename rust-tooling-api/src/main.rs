@@ -1,56 +1,218 @@
-use actix_web::web::Data;
+use actix_web::web::{Data, PayloadConfig};
 use actix_web::{App, HttpResponse, HttpServer,http::header, Responder, get};
+use actix_web::middleware::from_fn;
 
 //You must register all your modules for it to be visible within your project
+mod alerts;
+mod auth;
+mod jobs;
+mod metrics;
+mod otlp;
+mod retention;
 mod routes;
+mod settings;
+mod tls;
 
 use actix_cors::Cors;
+use clap::Parser;
+use std::time::{Duration, Instant};
 use dal_layer::models::details::Details;
 
-use crate::routes::{health_check::*, log_routes::*, myservice_routes::*};
-use dal_layer::models::{log_model::*, my_service_model::*, response_model::*};
+use crate::routes::{
+    alert_routes::*, collector_routes::*, health_check::*, issue_routes::*, log_routes::*, metrics_routes::*, myservice_routes::*, otlp_routes::*, pattern_routes::*,
+    pipeline_routes::*, stream_routes::*, trace_routes::*,
+};
+use crate::jobs::JobHealth;
+use crate::settings::{Cli, CorsSettings, Settings};
+use dal_layer::models::{alert_model::*, collector_model::*, issue_model::*, log_model::*, my_service_model::*, response_model::*};
+use dal_layer::pipeline::Pipeline;
 use dal_layer::repository::db::Database;
+use dal_layer::utils::trace_helper::TraceExtractor;
 
-use utoipa_rapidoc::RapiDoc;
-use utoipa_redoc::{Redoc, Servable};
 use utoipa_swagger_ui::{SwaggerUi, Config};
 
-use utoipa::{
-    Modify, OpenApi,
-    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
-};
+use utoipa::OpenApi;
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
        health_check,
+        liveness,
+        readiness,
+        get_metrics,
         create_service,
       get_services,
+        get_service_status,
         create_log,
+        create_logs_bulk,
+        search_logs,
+        stream_logs,
+        get_log_stats,
+        get_log_context,
         get_logs_byservices,
    get_logs_services_by_date_range,
+        get_trace,
+        receive_otlp_logs,
+        get_redaction_stats,
+        get_limit_stats,
+        get_patterns,
+        get_issues,
+        get_issue,
+        get_issue_occurrences,
+        update_issue_status,
+        create_alert_rule,
+        get_alert_rules,
+        delete_alert_rule,
+        get_alerts,
+        create_silence,
+        get_silences,
+        delete_silence,
+        register_collector,
+        collector_heartbeat,
+        get_collectors,
      
     ),
     components(
         schemas(
             LogRequest,
+            BulkLogResult,
+            LogLevel,
+            SearchOrder,
             GenericResponse<String>,
             MyServiceView,         
+            MyService,
+            MyServiceStatus,
+            ServiceHealth,
+            HealthStatus,
+            IssueStatus,
+            IssueStatusUpdate,
+            AlertRuleRequest,
+            AlertCondition,
+            Notifier,
+            SilenceRequest,
+            CollectorHeartbeat,
+            SourceStatus,
+            FileOffset,
+            Readiness,
+            ReadinessStatus,
+            DatabaseCheck,
+            IndexCheck,
+            JobHealth,
         )
     ),
     tags(
      (name = "Health", description = "Health endpoints"),
+        (name = "Metrics", description = "Prometheus metrics of the API Endpoint"),
         (name = "Register Service", description = "Register A Micro Service or Monolith Endpoint"),
 		(name = "Get Service", description = "Get MicroServices or Monoliths registered in the Logging system Endpoint"),
+		(name = "Service Health", description = "Whether a registered service is active, quiet, silent or erroring Endpoint"),
 		(name = "Create Logs", description = "Create Logs for a registered Microservice Endpoint"),
+		(name = "Search Logs", description = "Search logs by service, level, host, source file, trace context or attribute Endpoint"),
+		(name = "Stream Logs", description = "Follow the matching logs as they arrive over Server-Sent Events Endpoint"),
+		(name = "Log Statistics", description = "Count logs per service and level Endpoint"),
+		(name = "Log Context", description = "Get the lines around a log from the same service and source file Endpoint"),
 		(name = "Get Logs", description = "Get Logs for a MIcro service Endpoint"),
 		//(name = "Get_logs_Service", description = "Get Logs by service Endpoint"),
-		(name = "Get logs by Service by date", description = "Get logs by service and date Endpoint")
+		(name = "Get logs by Service by date", description = "Get logs by service and date Endpoint"),
+		(name = "Trace Correlation", description = "Follow one request across services by its W3C trace id Endpoint"),
+		(name = "OpenTelemetry", description = "OTLP/HTTP logs receiver Endpoint"),
+		(name = "Ingest Pipeline", description = "Redaction and ingestion limit statistics of the ingest pipeline Endpoint"),
+		(name = "Log Patterns", description = "Message templates learned from the log lines with their counts Endpoint"),
+		(name = "Issues", description = "Errors grouped by fingerprint with triage status and regression detection Endpoint"),
+		(name = "Alerts", description = "Alert rules, their firing state and silences Endpoint"),
+		(name = "Collector Fleet", description = "Registered log collectors with their sources and heartbeats Endpoint")
     )
 )]
 pub struct ApiDoc;
 
+const OTLP_MAX_BODY_BYTES: usize = 8 * 1024 * 1024;
+const PIPELINE_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+const DB_PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+///Store a "N lines dropped" log for every service that lost lines to its ingestion limit, add
+///the repeats absorbed by deduplication to the logs they repeat and save the new templates
+fn spawn_pipeline_flusher(pipeline: Data<Pipeline>, db: Data<Database>) {
+    jobs::register("pipeline_flush", PIPELINE_FLUSH_INTERVAL);
+    actix_web::rt::spawn(async move {
+        let mut ticker = actix_web::rt::time::interval(PIPELINE_FLUSH_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let started = Instant::now();
+            let mut result = Ok(());
+
+            let logs: Vec<Log> = pipeline
+                .limiter
+                .drop_reports()
+                .iter()
+                .filter_map(|report| Log::try_from(report.to_request()).ok())
+                .collect();
+            if let Err(err) = db.insert_logs_bulk(&logs).await {
+                eprintln!("Could not store the dropped line reports: {err}");
+                result = Err(format!("Could not store the dropped line reports: {err}"));
+            }
+            if let Err(err) = db.apply_repeat_updates(pipeline.dedup.take_updates()).await {
+                eprintln!("Could not update the repeat counts: {err}");
+                result = Err(format!("Could not update the repeat counts: {err}"));
+            }
+            if let Err(err) = db.upsert_templates(pipeline.templates.take_changed()).await {
+                eprintln!("Could not save the log templates: {err}");
+                result = Err(format!("Could not save the log templates: {err}"));
+            }
+
+            jobs::record_run("pipeline_flush", started.elapsed(), &result);
+        }
+    });
+}
+
+
+
+///Ping MongoDB until it answers, up to `attempts` times `retry_delay` apart, so the API does
+///not start serving without its database
+async fn wait_for_database(db: &Database, attempts: u32, retry_delay: Duration) -> Result<(), String> {
+    let attempts = attempts.max(1);
+    for attempt in 1..=attempts {
+        let error = match actix_web::rt::time::timeout(DB_PING_TIMEOUT, db.ping()).await {
+            Ok(Ok(())) => return Ok(()),
+            Ok(Err(err)) => err.to_string(),
+            Err(_) => format!("no answer within {}s", DB_PING_TIMEOUT.as_secs()),
+        };
+
+        if attempt == attempts {
+            return Err(format!(
+                "MongoDB is unreachable after {attempts} attempts ({error}); check database.uri (MONGO_URI) and that the server is running"
+            ));
+        }
+        eprintln!(
+            "MongoDB is unreachable ({error}), attempt {attempt} of {attempts}; retrying in {}s",
+            retry_delay.as_secs()
+        );
+        actix_web::rt::time::sleep(retry_delay).await;
+    }
 
+    Ok(())
+}
+
+///CORS policy allowing the configured origins; `*` allows any origin but without credentials,
+///which browsers refuse to send to a wildcard
+fn build_cors(settings: &CorsSettings) -> Cors {
+    let cors = Cors::default()
+            .allowed_methods(vec!["GET", "POST","PUT","PATCH","DELETE"])
+            .allowed_headers(vec![
+                header::CONTENT_TYPE,
+                header::AUTHORIZATION,
+                header::ACCEPT,
+                header::HeaderName::from_static("x-api-key"),
+            ]);
+
+    if settings.allowed_origins.iter().any(|origin| origin == "*") {
+        return cors.allow_any_origin();
+    }
+    settings
+        .allowed_origins
+        .iter()
+        .fold(cors, |cors, origin| cors.allowed_origin(origin))
+        .supports_credentials()
+}
 
 #[utoipa::path(
 	get,
@@ -62,7 +224,7 @@ pub struct ApiDoc;
 )]
 #[get("/")]
 async fn hello() -> impl Responder {
-    let d: Details = Details {
+    let _d: Details = Details {
         name: "jide".to_string(),
         age: 3,
     };
@@ -72,43 +234,161 @@ async fn hello() -> impl Responder {
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let db = Database::init().await;
+    let cli = Cli::parse();
+    let settings = match Settings::load(&cli) {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(2);
+        }
+    };
+    if cli.check {
+        println!("Configuration is valid");
+        return Ok(());
+    }
+    // read the certificate before anything else so a bad one fails the start up right away
+    let tls_config = match settings.tls.enabled {
+        true => Some(tls::server_config(&settings.tls).map_err(std::io::Error::other)?),
+        false => None,
+    };
+    metrics::init();
+
+    let db = Database::connect(&settings.database).await;
+    wait_for_database(
+        &db,
+        settings.database.connect_attempts,
+        Duration::from_secs(settings.database.connect_retry_secs),
+    )
+    .await
+    .map_err(std::io::Error::other)?;
+    if let Err(err) = db.ensure_indexes().await {
+        eprintln!("Could not create the log indexes: {err}");
+    }
     let db_data = Data::new(db);
 
-    HttpServer::new(move || {
-            let cors = Cors::default()
-            .allowed_origin("http://localhost:5000")
-          //  .allowed_origin("https://localhost:5000")
-            .allowed_methods(vec!["GET", "POST","PUT","PATCH","DELETE"])
-            .allowed_headers(vec![
-                header::CONTENT_TYPE,
-                header::AUTHORIZATION,
-                header::ACCEPT,
-            ])
-            .supports_credentials();
+    let trace_extractor = TraceExtractor::from_pattern(settings.ingest.trace_id_regex.as_deref())
+        .expect("ingest.trace_id_regex was validated with the settings");
+    let trace_data = Data::new(trace_extractor);
+    let pipeline = Pipeline::new(
+        Some(&settings.redaction),
+        Some(&settings.limits),
+        Some(&settings.dedup),
+    )
+    .expect("the redaction config was validated with the settings");
+    match db_data.get_templates().await {
+        Ok(templates) => pipeline.templates.seed(templates),
+        Err(err) => eprintln!("Could not load the log templates: {err}"),
+    }
+    let pipeline_data = Data::new(pipeline);
+    spawn_pipeline_flusher(pipeline_data.clone(), db_data.clone());
+    if settings.features.alerts {
+        alerts::spawn_alert_scheduler(
+            db_data.clone(),
+            Duration::from_secs(settings.alerts.eval_interval_secs),
+            settings.alerts.commands.clone(),
+        );
+    }
+    if settings.retention.enabled {
+        retention::spawn_retention_job(db_data.clone(), settings.retention.clone());
+    }
+    if let Some((_, certificate)) = &tls_config {
+        tls::spawn_certificate_reloader(
+            certificate.clone(),
+            Duration::from_secs(settings.tls.reload_interval_secs),
+        );
+    }
+    let bind_addr = (settings.server.host.clone(), settings.server.port);
+    let redirect_port = settings.tls.redirect_http_port;
+    let settings_data = Data::new(settings);
+
+    let server = HttpServer::new(move || {
+        let cors = build_cors(&settings_data.cors);
+        let features = settings_data.features.clone();
 
         //setting up the service and the endpoints
         App::new()
             .app_data(db_data.clone()) //register or inject the database obj
+            .app_data(trace_data.clone())
+            .app_data(settings_data.clone())
+            .app_data(pipeline_data.clone())
+            //OTLP exporters batch records, so allow bodies well above the 256kB default
+            .app_data(PayloadConfig::new(OTLP_MAX_BODY_BYTES))
+            .wrap(from_fn(auth::require_api_key))
+            .wrap(from_fn(tls::require_collector_certificate))
              .wrap(cors)
+            .wrap(from_fn(metrics::track_requests))
             .service(hello)
             .service(health_check)
-    .service(
+            .service(liveness)
+            .service(readiness)
+            .service(get_metrics)
+    .configure(|cfg| {
+        if features.swagger_ui {
+            cfg.service(
     SwaggerUi::new("/swagger-ui/{_:.*}")
         .url("/api-doc/openapi.json", ApiDoc::openapi())
          .config(
             Config::default() 
             .display_operation_id(true)     // 👈 KEEP TAG ORDER AS DEFINED
         )        
-      )
+      );
+        }
+        if features.otlp {
+            cfg.service(receive_otlp_logs);
+        }
+    })
             .service(create_service)
             .service(get_services)
+            .service(get_service_status)
             .service(create_log)
+            .service(create_logs_bulk)
+            .service(search_logs)
+            .service(stream_logs)
+            .service(get_log_stats)
+            .service(get_log_context)
             .service(get_logs_byservices)
             .service(get_logs_services_by_date_range)
+            .service(get_trace)
+            .service(get_redaction_stats)
+            .service(get_limit_stats)
+            .service(get_patterns)
+            .service(get_issues)
+            .service(get_issue)
+            .service(get_issue_occurrences)
+            .service(update_issue_status)
+            .service(create_alert_rule)
+            .service(get_alert_rules)
+            .service(delete_alert_rule)
+            .service(get_alerts)
+            .service(create_silence)
+            .service(get_silences)
+            .service(delete_silence)
+            .service(register_collector)
+            .service(collector_heartbeat)
+            .service(get_collectors)
            
-    })
-    .bind(("localhost", 5000))?
-    .run()
-    .await
+    });
+
+    let Some((tls_config, _)) = tls_config else {
+        return server.bind(bind_addr)?.run().await;
+    };
+    let https = server
+        .on_connect(tls::record_client_certificate)
+        .bind_rustls_0_23(bind_addr.clone(), tls_config)?
+        .run();
+
+    match redirect_port {
+        Some(redirect_port) => {
+            let https_port = Data::new(tls::HttpsPort(bind_addr.1));
+            let redirect = HttpServer::new(move || {
+                App::new()
+                    .app_data(https_port.clone())
+                    .default_service(actix_web::web::to(tls::redirect_to_https))
+            })
+            .bind((bind_addr.0, redirect_port))?
+            .run();
+            futures::future::try_join(https, redirect).await.map(|_| ())
+        }
+        None => https.await,
+    }
 }
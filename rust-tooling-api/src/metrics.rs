@@ -0,0 +1,156 @@
+//! HTTP and background job metrics of the API, kept in the registry of `dal_layer::utils::metrics`.
+
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use actix_web::Error;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use chrono::Utc;
+use dal_layer::utils::metrics::{NAMESPACE, register};
+use prometheus::{GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, Opts};
+
+static HTTP_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests answered").namespace(NAMESPACE),
+            &["method", "route", "status"],
+        )
+        .unwrap(),
+    )
+});
+
+static HTTP_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time taken to answer an HTTP request",
+            )
+            .namespace(NAMESPACE),
+            &["method", "route"],
+        )
+        .unwrap(),
+    )
+});
+
+static JOB_RUNS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "background_job_runs_total",
+                "Runs of a background job by outcome",
+            )
+            .namespace(NAMESPACE),
+            &["job", "outcome"],
+        )
+        .unwrap(),
+    )
+});
+
+static JOB_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "background_job_duration_seconds",
+                "Time taken by a run of a background job",
+            )
+            .namespace(NAMESPACE),
+            &["job"],
+        )
+        .unwrap(),
+    )
+});
+
+static JOB_LAST_SUCCESS: LazyLock<GaugeVec> = LazyLock::new(|| {
+    register(
+        GaugeVec::new(
+            Opts::new(
+                "background_job_last_success_timestamp_seconds",
+                "Unix time of the last successful run of a background job",
+            )
+            .namespace(NAMESPACE),
+            &["job"],
+        )
+        .unwrap(),
+    )
+});
+
+///Middleware counting and timing every request by its route pattern, so `/api/issues/{id}`
+///is one series however many issues there are
+pub async fn track_requests(
+    request: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .match_pattern()
+        .unwrap_or_else(|| String::from("unmatched"));
+
+    let response = next.call(request).await?;
+
+    HTTP_REQUESTS
+        .with_label_values(&[method.as_str(), route.as_str(), response.status().as_str()])
+        .inc();
+    HTTP_DURATION
+        .with_label_values(&[method.as_str(), route.as_str()])
+        .observe(started.elapsed().as_secs_f64());
+
+    Ok(response)
+}
+
+///Record the outcome of one run of a background job
+pub fn record_job<T, E>(job: &str, elapsed: Duration, result: &Result<T, E>) {
+    let outcome = if result.is_ok() { "success" } else { "failure" };
+    JOB_RUNS.with_label_values(&[job, outcome]).inc();
+    JOB_DURATION
+        .with_label_values(&[job])
+        .observe(elapsed.as_secs_f64());
+    if result.is_ok() {
+        JOB_LAST_SUCCESS
+            .with_label_values(&[job])
+            .set(Utc::now().timestamp() as f64);
+    }
+}
+
+///Make sure the API's metrics are listed before anything was recorded
+pub fn init() {
+    LazyLock::force(&HTTP_REQUESTS);
+    LazyLock::force(&HTTP_DURATION);
+    LazyLock::force(&JOB_RUNS);
+    LazyLock::force(&JOB_DURATION);
+    LazyLock::force(&JOB_LAST_SUCCESS);
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::middleware::from_fn;
+    use actix_web::{App, HttpResponse, test, web};
+
+    #[actix_web::test]
+    async fn test_requests_counted_by_route_pattern() {
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(track_requests))
+                .route("/api/metrics-test/{id}", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        for id in ["a", "b"] {
+            let request = test::TestRequest::get()
+                .uri(&format!("/api/metrics-test/{id}"))
+                .to_request();
+            test::call_service(&app, request).await;
+        }
+
+        let text = dal_layer::utils::metrics::encode();
+        assert!(text.contains(
+            r#"log_monitor_http_requests_total{method="GET",route="/api/metrics-test/{id}",status="200"} 2"#
+        ));
+    }
+}
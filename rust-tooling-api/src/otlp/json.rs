@@ -0,0 +1,267 @@
+//! OTLP/HTTP JSON encoding. Field names are lowerCamelCase, 64 bit integers may arrive as
+//! strings, trace and span ids are hex and bytes values are base64. Each message converts
+//! into its protobuf counterpart so both encodings share one mapping to `Log`.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+use super::proto;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportLogsServiceRequest {
+    #[serde(default)]
+    pub resource_logs: Vec<ResourceLogs>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceLogs {
+    pub resource: Option<Resource>,
+    #[serde(default)]
+    pub scope_logs: Vec<ScopeLogs>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resource {
+    #[serde(default)]
+    pub attributes: Vec<KeyValue>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScopeLogs {
+    pub scope: Option<InstrumentationScope>,
+    #[serde(default)]
+    pub log_records: Vec<LogRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstrumentationScope {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogRecord {
+    #[serde(default, deserialize_with = "lenient_u64")]
+    pub time_unix_nano: u64,
+    #[serde(default, deserialize_with = "lenient_u64")]
+    pub observed_time_unix_nano: u64,
+    #[serde(default, deserialize_with = "lenient_severity")]
+    pub severity_number: i32,
+    #[serde(default)]
+    pub severity_text: String,
+    pub body: Option<AnyValue>,
+    #[serde(default)]
+    pub attributes: Vec<KeyValue>,
+    #[serde(default)]
+    pub trace_id: String,
+    #[serde(default)]
+    pub span_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KeyValue {
+    pub key: String,
+    pub value: Option<AnyValue>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnyValue {
+    pub string_value: Option<String>,
+    pub bool_value: Option<bool>,
+    #[serde(default, deserialize_with = "lenient_i64")]
+    pub int_value: Option<i64>,
+    pub double_value: Option<f64>,
+    pub array_value: Option<ArrayValue>,
+    pub kvlist_value: Option<KeyValueList>,
+    pub bytes_value: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArrayValue {
+    #[serde(default)]
+    pub values: Vec<AnyValue>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KeyValueList {
+    #[serde(default)]
+    pub values: Vec<KeyValue>,
+}
+
+impl TryFrom<ExportLogsServiceRequest> for proto::ExportLogsServiceRequest {
+    type Error = String;
+
+    fn try_from(item: ExportLogsServiceRequest) -> Result<Self, Self::Error> {
+        Ok(proto::ExportLogsServiceRequest {
+            resource_logs: item
+                .resource_logs
+                .into_iter()
+                .map(proto::ResourceLogs::try_from)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl TryFrom<ResourceLogs> for proto::ResourceLogs {
+    type Error = String;
+
+    fn try_from(item: ResourceLogs) -> Result<Self, Self::Error> {
+        Ok(proto::ResourceLogs {
+            resource: item.resource.map(|resource| proto::Resource {
+                attributes: resource.attributes.into_iter().map(Into::into).collect(),
+            }),
+            scope_logs: item
+                .scope_logs
+                .into_iter()
+                .map(proto::ScopeLogs::try_from)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl TryFrom<ScopeLogs> for proto::ScopeLogs {
+    type Error = String;
+
+    fn try_from(item: ScopeLogs) -> Result<Self, Self::Error> {
+        Ok(proto::ScopeLogs {
+            scope: item.scope.map(|scope| proto::InstrumentationScope {
+                name: scope.name,
+                version: scope.version,
+            }),
+            log_records: item
+                .log_records
+                .into_iter()
+                .map(proto::LogRecord::try_from)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl TryFrom<LogRecord> for proto::LogRecord {
+    type Error = String;
+
+    fn try_from(item: LogRecord) -> Result<Self, Self::Error> {
+        Ok(proto::LogRecord {
+            time_unix_nano: item.time_unix_nano,
+            observed_time_unix_nano: item.observed_time_unix_nano,
+            severity_number: item.severity_number,
+            severity_text: item.severity_text,
+            body: item.body.map(Into::into),
+            attributes: item.attributes.into_iter().map(Into::into).collect(),
+            trace_id: hex::decode(&item.trace_id)
+                .map_err(|err| format!("traceId {} is not hex: {err}", item.trace_id))?,
+            span_id: hex::decode(&item.span_id)
+                .map_err(|err| format!("spanId {} is not hex: {err}", item.span_id))?,
+        })
+    }
+}
+
+impl From<KeyValue> for proto::KeyValue {
+    fn from(item: KeyValue) -> Self {
+        proto::KeyValue {
+            key: item.key,
+            value: item.value.map(Into::into),
+        }
+    }
+}
+
+impl From<AnyValue> for proto::AnyValue {
+    fn from(item: AnyValue) -> Self {
+        use proto::any_value::Value as V;
+
+        let value = if let Some(text) = item.string_value {
+            Some(V::StringValue(text))
+        } else if let Some(flag) = item.bool_value {
+            Some(V::BoolValue(flag))
+        } else if let Some(number) = item.int_value {
+            Some(V::IntValue(number))
+        } else if let Some(number) = item.double_value {
+            Some(V::DoubleValue(number))
+        } else if let Some(array) = item.array_value {
+            Some(V::ArrayValue(proto::ArrayValue {
+                values: array.values.into_iter().map(Into::into).collect(),
+            }))
+        } else if let Some(list) = item.kvlist_value {
+            Some(V::KvlistValue(proto::KeyValueList {
+                values: list.values.into_iter().map(Into::into).collect(),
+            }))
+        } else {
+            item.bytes_value
+                .map(|encoded| match STANDARD.decode(&encoded) {
+                    Ok(bytes) => V::BytesValue(bytes),
+                    Err(_) => V::StringValue(encoded),
+                })
+        };
+
+        proto::AnyValue { value }
+    }
+}
+
+fn lenient_u64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::Number(number) => number
+            .as_u64()
+            .ok_or_else(|| D::Error::custom(format!("{number} is not an unsigned integer"))),
+        Value::String(text) if text.is_empty() => Ok(0),
+        Value::String(text) => text.parse().map_err(D::Error::custom),
+        Value::Null => Ok(0),
+        other => Err(D::Error::custom(format!(
+            "expected an integer, got {other}"
+        ))),
+    }
+}
+
+fn lenient_i64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<i64>, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::Number(number) => number
+            .as_i64()
+            .map(Some)
+            .ok_or_else(|| D::Error::custom(format!("{number} is not an integer"))),
+        Value::String(text) => text.parse().map(Some).map_err(D::Error::custom),
+        Value::Null => Ok(None),
+        other => Err(D::Error::custom(format!(
+            "expected an integer, got {other}"
+        ))),
+    }
+}
+
+///Severity may be the enum number or its name, e.g. `SEVERITY_NUMBER_WARN`
+fn lenient_severity<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i32, D::Error> {
+    const NAMES: [&str; 6] = ["TRACE", "DEBUG", "INFO", "WARN", "ERROR", "FATAL"];
+
+    match Value::deserialize(deserializer)? {
+        Value::Number(number) => number
+            .as_i64()
+            .and_then(|n| i32::try_from(n).ok())
+            .ok_or_else(|| D::Error::custom(format!("{number} is not a severity number"))),
+        Value::String(text) => {
+            let name = text.trim_start_matches("SEVERITY_NUMBER_");
+            let (base, offset) = match name.char_indices().find(|(_, c)| c.is_ascii_digit()) {
+                Some((index, _)) => (&name[..index], name[index..].parse::<i32>().unwrap_or(1)),
+                None => (name, 1),
+            };
+
+            Ok(NAMES
+                .iter()
+                .position(|candidate| *candidate == base)
+                .map(|position| position as i32 * 4 + offset)
+                .unwrap_or(0))
+        }
+        Value::Null => Ok(0),
+        other => Err(D::Error::custom(format!(
+            "expected a severity number, got {other}"
+        ))),
+    }
+}
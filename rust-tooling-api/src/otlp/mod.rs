@@ -0,0 +1,382 @@
+//! OTLP/HTTP logs receiver: decodes `ExportLogsServiceRequest` bodies and maps each log
+//! record onto a `Log`.
+
+pub mod json;
+pub mod proto;
+
+use std::collections::HashMap;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use dal_layer::models::log_model::{Log, LogLevel};
+use mongodb::bson::{DateTime, oid::ObjectId};
+use prost::Message;
+use serde_json::{Map, Number, Value};
+
+use proto::any_value;
+
+pub const PROTOBUF_CONTENT_TYPE: &str = "application/x-protobuf";
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+
+///Service name the OpenTelemetry SDKs report when none was configured
+pub const UNKNOWN_SERVICE: &str = "unknown_service";
+
+///Wire encoding of a request, echoed back in the response
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Protobuf,
+    Json,
+}
+
+impl Encoding {
+    pub fn from_content_type(content_type: &str) -> Option<Encoding> {
+        let mime = content_type.split(';').next().unwrap_or("").trim();
+        match mime.to_ascii_lowercase().as_str() {
+            PROTOBUF_CONTENT_TYPE => Some(Encoding::Protobuf),
+            JSON_CONTENT_TYPE => Some(Encoding::Json),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Encoding::Protobuf => PROTOBUF_CONTENT_TYPE,
+            Encoding::Json => JSON_CONTENT_TYPE,
+        }
+    }
+}
+
+pub fn decode_request(
+    encoding: Encoding,
+    body: &[u8],
+) -> Result<proto::ExportLogsServiceRequest, String> {
+    match encoding {
+        Encoding::Protobuf => proto::ExportLogsServiceRequest::decode(body)
+            .map_err(|err| format!("invalid OTLP protobuf body: {err}")),
+        Encoding::Json => {
+            let request: json::ExportLogsServiceRequest = serde_json::from_slice(body)
+                .map_err(|err| format!("invalid OTLP JSON body: {err}"))?;
+            request.try_into()
+        }
+    }
+}
+
+pub fn encode_response(encoding: Encoding, rejected: i64, error_message: String) -> Vec<u8> {
+    let partial_success = (rejected > 0).then_some(proto::ExportLogsPartialSuccess {
+        rejected_log_records: rejected,
+        error_message,
+    });
+
+    match encoding {
+        Encoding::Protobuf => proto::ExportLogsServiceResponse { partial_success }.encode_to_vec(),
+        Encoding::Json => {
+            let body = match partial_success {
+                Some(partial) => serde_json::json!({
+                    "partialSuccess": {
+                        "rejectedLogRecords": partial.rejected_log_records.to_string(),
+                        "errorMessage": partial.error_message,
+                    }
+                }),
+                None => serde_json::json!({}),
+            };
+            body.to_string().into_bytes()
+        }
+    }
+}
+
+///`service.name` of a resource, `unknown_service` when it is missing or empty
+pub fn service_name(resource: Option<&proto::Resource>) -> String {
+    resource
+        .and_then(|resource| {
+            resource
+                .attributes
+                .iter()
+                .find(|attribute| attribute.key == "service.name")
+        })
+        .and_then(
+            |attribute| match attribute.value.as_ref()?.value.as_ref()? {
+                any_value::Value::StringValue(name) if !name.trim().is_empty() => {
+                    Some(name.trim().to_string())
+                }
+                _ => None,
+            },
+        )
+        .unwrap_or_else(|| UNKNOWN_SERVICE.to_string())
+}
+
+///Number of log records carried by a resource
+pub fn record_count(resource_logs: &proto::ResourceLogs) -> usize {
+    resource_logs
+        .scope_logs
+        .iter()
+        .map(|scope| scope.log_records.len())
+        .sum()
+}
+
+///Convert every record of a resource into logs for `service_id`
+pub fn to_logs(resource_logs: proto::ResourceLogs, service_id: ObjectId) -> Vec<Log> {
+    let resource_attributes: Vec<proto::KeyValue> = resource_logs
+        .resource
+        .map(|resource| resource.attributes)
+        .unwrap_or_default();
+
+    let host = resource_attributes
+        .iter()
+        .find(|attribute| attribute.key == "host.name")
+        .and_then(|attribute| attribute.value.as_ref())
+        .map(any_value_to_string);
+
+    let mut logs = Vec::new();
+    for scope_logs in resource_logs.scope_logs {
+        let scope_name = scope_logs
+            .scope
+            .map(|scope| scope.name)
+            .filter(|name| !name.is_empty());
+
+        for record in scope_logs.log_records {
+            let mut attributes: HashMap<String, Value> = HashMap::new();
+            for attribute in resource_attributes.iter().chain(record.attributes.iter()) {
+                if let Some(value) = &attribute.value {
+                    attributes.insert(attribute_key(&attribute.key), any_value_to_json(value));
+                }
+            }
+            if let Some(scope_name) = &scope_name {
+                attributes.insert(
+                    String::from("otel_scope_name"),
+                    Value::String(scope_name.clone()),
+                );
+            }
+
+            let source_file = attributes
+                .get("log_file_path")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+
+            logs.push(Log {
+                _id: ObjectId::new(),
+                my_service_id: service_id,
+                level: level_of(&record),
+                line_content: record
+                    .body
+                    .as_ref()
+                    .map(any_value_to_string)
+                    .unwrap_or_default(),
+                created_at: timestamp_of(&record),
+                attributes: (!attributes.is_empty()).then_some(attributes),
+                host: host.clone(),
+                source_file,
+                line_offset: None,
+                ingested_at: None,
+                trace_id: non_zero_hex(&record.trace_id),
+                span_id: non_zero_hex(&record.span_id),
+                repeat_count: None,
+                first_seen: None,
+                last_seen: None,
+                template_id: None,
+                fingerprint: None,
+            });
+        }
+    }
+
+    logs
+}
+
+fn level_of(record: &proto::LogRecord) -> LogLevel {
+    if record.severity_number > 0 {
+        LogLevel::from_severity_number(record.severity_number)
+    } else {
+        record
+            .severity_text
+            .parse::<LogLevel>()
+            .unwrap_or(LogLevel::OTHER)
+    }
+}
+
+///Event time, falling back to the time the SDK observed the record and then to now
+fn timestamp_of(record: &proto::LogRecord) -> DateTime {
+    let nanos = if record.time_unix_nano > 0 {
+        record.time_unix_nano
+    } else {
+        record.observed_time_unix_nano
+    };
+
+    match i64::try_from(nanos / 1_000_000) {
+        Ok(millis) if millis > 0 => DateTime::from_millis(millis),
+        _ => DateTime::now(),
+    }
+}
+
+///Dots and a leading `$` would be read as a path or an operator by Mongo queries
+fn attribute_key(key: &str) -> String {
+    key.trim_start_matches('$').replace('.', "_")
+}
+
+fn non_zero_hex(bytes: &[u8]) -> Option<String> {
+    if bytes.is_empty() || bytes.iter().all(|b| *b == 0) {
+        None
+    } else {
+        Some(hex::encode(bytes))
+    }
+}
+
+fn any_value_to_string(value: &proto::AnyValue) -> String {
+    match &value.value {
+        Some(any_value::Value::StringValue(text)) => text.clone(),
+        _ => any_value_to_json(value).to_string(),
+    }
+}
+
+pub fn any_value_to_json(value: &proto::AnyValue) -> Value {
+    match &value.value {
+        None => Value::Null,
+        Some(any_value::Value::StringValue(text)) => Value::String(text.clone()),
+        Some(any_value::Value::BoolValue(flag)) => Value::Bool(*flag),
+        Some(any_value::Value::IntValue(number)) => Value::from(*number),
+        Some(any_value::Value::DoubleValue(number)) => Number::from_f64(*number)
+            .map(Value::Number)
+            .unwrap_or(Value::Null),
+        Some(any_value::Value::ArrayValue(array)) => {
+            Value::Array(array.values.iter().map(any_value_to_json).collect())
+        }
+        Some(any_value::Value::KvlistValue(list)) => Value::Object(
+            list.values
+                .iter()
+                .map(|entry| {
+                    (
+                        attribute_key(&entry.key),
+                        entry
+                            .value
+                            .as_ref()
+                            .map(any_value_to_json)
+                            .unwrap_or(Value::Null),
+                    )
+                })
+                .collect::<Map<String, Value>>(),
+        ),
+        Some(any_value::Value::BytesValue(bytes)) => Value::String(STANDARD.encode(bytes)),
+    }
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string_value(text: &str) -> Option<proto::AnyValue> {
+        Some(proto::AnyValue {
+            value: Some(any_value::Value::StringValue(text.to_string())),
+        })
+    }
+
+    #[test]
+    fn test_decode_protobuf_request() {
+        let request = proto::ExportLogsServiceRequest {
+            resource_logs: vec![proto::ResourceLogs {
+                resource: Some(proto::Resource {
+                    attributes: vec![
+                        proto::KeyValue {
+                            key: String::from("service.name"),
+                            value: string_value("payment"),
+                        },
+                        proto::KeyValue {
+                            key: String::from("host.name"),
+                            value: string_value("payment-7d9f8"),
+                        },
+                    ],
+                }),
+                scope_logs: vec![proto::ScopeLogs {
+                    scope: None,
+                    log_records: vec![proto::LogRecord {
+                        time_unix_nano: 1_729_775_706_698_000_000,
+                        severity_number: 17,
+                        body: string_value("card declined"),
+                        attributes: vec![proto::KeyValue {
+                            key: String::from("http.status_code"),
+                            value: Some(proto::AnyValue {
+                                value: Some(any_value::Value::IntValue(402)),
+                            }),
+                        }],
+                        trace_id: hex::decode("4bf92f3577b34da6a3ce929d0e0e4736").unwrap(),
+                        span_id: vec![0; 8],
+                        ..Default::default()
+                    }],
+                }],
+            }],
+        };
+
+        let decoded = decode_request(Encoding::Protobuf, &request.encode_to_vec()).unwrap();
+        let resource_logs = decoded.resource_logs.into_iter().next().unwrap();
+
+        assert_eq!(service_name(resource_logs.resource.as_ref()), "payment");
+
+        let logs = to_logs(resource_logs, ObjectId::new());
+        assert_eq!(logs.len(), 1);
+
+        let log = &logs[0];
+        assert_eq!(log.level, LogLevel::ERROR);
+        assert_eq!(log.line_content, "card declined");
+        assert_eq!(log.host.as_deref(), Some("payment-7d9f8"));
+        assert_eq!(log.created_at.timestamp_millis(), 1_729_775_706_698);
+        assert_eq!(
+            log.trace_id.as_deref(),
+            Some("4bf92f3577b34da6a3ce929d0e0e4736")
+        );
+        assert_eq!(log.span_id, None);
+
+        let attributes = log.attributes.as_ref().unwrap();
+        assert_eq!(attributes["http_status_code"], Value::from(402));
+        assert_eq!(attributes["service_name"], Value::from("payment"));
+    }
+
+    #[test]
+    fn test_decode_json_request() {
+        let body = r#"{
+            "resourceLogs": [{
+                "resource": {"attributes": [{"key": "service.name", "value": {"stringValue": "gateway"}}]},
+                "scopeLogs": [{
+                    "scope": {"name": "io.opentelemetry.gateway"},
+                    "logRecords": [{
+                        "timeUnixNano": "1729775706698000000",
+                        "severityText": "Warning",
+                        "body": {"kvlistValue": {"values": [{"key": "route", "value": {"stringValue": "/pay"}}]}},
+                        "attributes": [{"key": "retry", "value": {"intValue": "3"}}],
+                        "traceId": "4bf92f3577b34da6a3ce929d0e0e4736",
+                        "spanId": "00f067aa0ba902b7"
+                    }]
+                }]
+            }]
+        }"#;
+
+        let decoded = decode_request(Encoding::Json, body.as_bytes()).unwrap();
+        let resource_logs = decoded.resource_logs.into_iter().next().unwrap();
+        assert_eq!(service_name(resource_logs.resource.as_ref()), "gateway");
+
+        let log = to_logs(resource_logs, ObjectId::new()).remove(0);
+        assert_eq!(log.level, LogLevel::WARN);
+        assert_eq!(log.line_content, r#"{"route":"/pay"}"#);
+        assert_eq!(log.span_id.as_deref(), Some("00f067aa0ba902b7"));
+
+        let attributes = log.attributes.unwrap();
+        assert_eq!(attributes["retry"], Value::from(3));
+        assert_eq!(
+            attributes["otel_scope_name"],
+            Value::from("io.opentelemetry.gateway")
+        );
+    }
+
+    #[test]
+    fn test_json_severity_names() {
+        let body = r#"{"resourceLogs":[{"scopeLogs":[{"logRecords":[{"severityNumber":"SEVERITY_NUMBER_FATAL2"}]}]}]}"#;
+
+        let decoded = decode_request(Encoding::Json, body.as_bytes()).unwrap();
+        let resource_logs = decoded.resource_logs.into_iter().next().unwrap();
+        assert_eq!(
+            service_name(resource_logs.resource.as_ref()),
+            UNKNOWN_SERVICE
+        );
+
+        let log = to_logs(resource_logs, ObjectId::new()).remove(0);
+        assert_eq!(log.level, LogLevel::FATAL);
+    }
+}
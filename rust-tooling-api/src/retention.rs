@@ -0,0 +1,64 @@
+//! Background deletion of the logs older than the retention period of their service.
+
+use std::time::{Duration, Instant};
+
+use actix_web::web::Data;
+use chrono::Utc;
+use dal_layer::repository::db::Database;
+use mongodb::bson::DateTime;
+
+use crate::settings::RetentionSettings;
+
+///Time `days` before `now`, the oldest a log kept for that many days can be
+pub fn cutoff(now: chrono::DateTime<Utc>, days: u32) -> DateTime {
+    DateTime::from_millis((now - chrono::Duration::days(i64::from(days))).timestamp_millis())
+}
+
+///Delete the expired logs each `retention.interval_secs`
+pub fn spawn_retention_job(db: Data<Database>, retention: RetentionSettings) {
+    let interval = Duration::from_secs(retention.interval_secs);
+    crate::jobs::register("retention", interval);
+    actix_web::rt::spawn(async move {
+        let mut ticker = actix_web::rt::time::interval(interval);
+        loop {
+            ticker.tick().await;
+
+            let started = Instant::now();
+            let result = delete_expired_logs(&db, &retention).await;
+            crate::jobs::record_run("retention", started.elapsed(), &result);
+            match result {
+                Ok(0) => {}
+                Ok(deleted) => println!("Deleted {deleted} logs past their retention period"),
+                Err(err) => eprintln!("Could not delete the expired logs: {err}"),
+            }
+        }
+    });
+}
+
+///Delete the logs of the services listed in `retention.services` after their own number of
+///days and the logs of every other service after `retention.default_days`
+async fn delete_expired_logs(
+    db: &Database,
+    retention: &RetentionSettings,
+) -> Result<u64, actix_web::Error> {
+    let now = Utc::now();
+    let mut deleted = 0;
+    let mut listed = Vec::new();
+
+    for (name, days) in &retention.services {
+        // a service not registered yet has no logs to delete
+        let Some(service) = db.find_service_by_name(name).await? else {
+            continue;
+        };
+        deleted += db
+            .delete_logs_before(cutoff(now, *days), &[service._id], &[])
+            .await?;
+        listed.push(service._id);
+    }
+
+    deleted += db
+        .delete_logs_before(cutoff(now, retention.default_days), &[], &listed)
+        .await?;
+
+    Ok(deleted)
+}
@@ -0,0 +1,214 @@
+use actix_web::{
+    HttpResponse, delete, get, post,
+    web::{Data, Json, Path},
+};
+use dal_layer::{
+    models::{
+        alert_model::{AlertRule, AlertRuleRequest, Notifier, Silence, SilenceRequest},
+        response_model::GenericResponse,
+    },
+    repository::db::Database,
+};
+use mongodb::results::InsertOneResult;
+
+use crate::settings::Settings;
+
+fn error_response(err: actix_web::Error) -> HttpResponse {
+    let status = err.as_response_error().status_code();
+    HttpResponse::build(status).json(GenericResponse {
+        code: status.as_u16().to_string(),
+        data: err.to_string(),
+    })
+}
+
+fn created(result: Result<InsertOneResult, actix_web::Error>) -> HttpResponse {
+    match result {
+        Ok(result) => HttpResponse::Created().json(GenericResponse {
+            code: String::from("201"),
+            data: result
+                .inserted_id
+                .as_object_id()
+                .map(|id| id.to_hex())
+                .unwrap_or_default(),
+        }),
+        Err(err) => error_response(err),
+    }
+}
+
+fn bad_request(err: Box<dyn std::error::Error>) -> HttpResponse {
+    HttpResponse::BadRequest().json(GenericResponse {
+        code: String::from("400"),
+        data: err.to_string(),
+    })
+}
+
+fn deleted(result: Result<bool, actix_web::Error>, what: &str, id: &str) -> HttpResponse {
+    match result {
+        Ok(true) => HttpResponse::Ok().json(GenericResponse {
+            code: String::from("200"),
+            data: format!("Deleted {what} {id}"),
+        }),
+        Ok(false) => HttpResponse::NotFound().json(GenericResponse {
+            code: String::from("404"),
+            data: format!("No {what} found with id {id}"),
+        }),
+        Err(err) => error_response(err),
+    }
+}
+
+#[utoipa::path(
+	post,
+	path = "/api/alerts/rules",
+	tag = "Alerts",
+	request_body(content = AlertRuleRequest, description = "Rule to evaluate in the background", example = json!({
+    "name": "payment error spike",
+    "service_id": "697dff2cbfc969fe967002b7",
+    "condition": {"kind": "threshold", "min_level": "ERROR", "threshold": 50, "window_secs": 300},
+    "notifiers": [
+        {"kind": "webhook", "url": "http://localhost:9000/alerts"},
+        {"kind": "command", "name": "page_oncall"}
+    ],
+    "repeat_interval_secs": 3600
+})),
+	responses(
+		(status=201, description = "The rule was stored; the id of the rule", body = GenericResponse<String>),
+		(status=400, description = "Malformed service id, pattern or window, or a command not configured in `[alerts] commands`", body = GenericResponse<String>),
+		(status=500, description = "Internal Server Error", body = GenericResponse<String>),
+	)
+)]
+#[post("/api/alerts/rules")]
+pub async fn create_alert_rule(
+    db: Data<Database>,
+    settings: Data<Settings>,
+    request: Json<AlertRuleRequest>,
+) -> HttpResponse {
+    let request = request.into_inner();
+    // rules only name the commands the server lists; they never choose what runs
+    for notifier in &request.notifiers {
+        if let Notifier::Command { name } = notifier
+            && !settings.alerts.commands.contains_key(name)
+        {
+            return bad_request(format!("No alert command is configured as {name:?}").into());
+        }
+    }
+
+    match AlertRule::try_from(request) {
+        Ok(rule) => created(db.create_alert_rule(rule).await),
+        Err(err) => bad_request(err),
+    }
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/alerts/rules",
+	tag = "Alerts",
+	responses(
+		(status=200, description = "Every alert rule, oldest first", body = GenericResponse<String>),
+	)
+)]
+#[get("/api/alerts/rules")]
+pub async fn get_alert_rules(db: Data<Database>) -> HttpResponse {
+    match db.get_alert_rules().await {
+        Ok(rules) => HttpResponse::Ok().json(GenericResponse {
+            code: String::from("200"),
+            data: rules,
+        }),
+        Err(err) => error_response(err),
+    }
+}
+
+#[utoipa::path(
+	delete,
+	path = "/api/alerts/rules/{rule_id}",
+	tag = "Alerts",
+	params(
+		("rule_id" = String, Path, description = "Id of the rule"),
+	),
+	responses(
+		(status=200, description = "The rule and its alert state were deleted", body = GenericResponse<String>),
+		(status=404, description = "No rule with that id", body = GenericResponse<String>),
+	)
+)]
+#[delete("/api/alerts/rules/{rule_id}")]
+pub async fn delete_alert_rule(db: Data<Database>, path: Path<(String,)>) -> HttpResponse {
+    let rule_id: String = path.into_inner().0;
+    deleted(db.delete_alert_rule(&rule_id).await, "alert rule", &rule_id)
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/alerts",
+	tag = "Alerts",
+	responses(
+		(status=200, description = "Firing or ok state of every evaluated rule with the value of its last evaluation", body = GenericResponse<String>),
+	)
+)]
+#[get("/api/alerts")]
+pub async fn get_alerts(db: Data<Database>) -> HttpResponse {
+    match db.get_alert_statuses().await {
+        Ok(statuses) => HttpResponse::Ok().json(GenericResponse {
+            code: String::from("200"),
+            data: statuses,
+        }),
+        Err(err) => error_response(err),
+    }
+}
+
+#[utoipa::path(
+	post,
+	path = "/api/alerts/silences",
+	tag = "Alerts",
+	request_body(content = SilenceRequest, description = "Mute the notifications of a rule, of the rules of a service, or of every rule", example = json!({
+    "service_id": "697dff2cbfc969fe967002b7",
+    "ends_at": "2025-10-24T18:00:00Z",
+    "comment": "payment maintenance"
+})),
+	responses(
+		(status=201, description = "The silence was stored; the id of the silence", body = GenericResponse<String>),
+		(status=400, description = "Malformed id or date, or ends_at is not after starts_at", body = GenericResponse<String>),
+	)
+)]
+#[post("/api/alerts/silences")]
+pub async fn create_silence(db: Data<Database>, request: Json<SilenceRequest>) -> HttpResponse {
+    match Silence::try_from(request.into_inner()) {
+        Ok(silence) => created(db.create_silence(silence).await),
+        Err(err) => bad_request(err),
+    }
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/alerts/silences",
+	tag = "Alerts",
+	responses(
+		(status=200, description = "Silences that have not ended yet", body = GenericResponse<String>),
+	)
+)]
+#[get("/api/alerts/silences")]
+pub async fn get_silences(db: Data<Database>) -> HttpResponse {
+    match db.get_silences().await {
+        Ok(silences) => HttpResponse::Ok().json(GenericResponse {
+            code: String::from("200"),
+            data: silences,
+        }),
+        Err(err) => error_response(err),
+    }
+}
+
+#[utoipa::path(
+	delete,
+	path = "/api/alerts/silences/{silence_id}",
+	tag = "Alerts",
+	params(
+		("silence_id" = String, Path, description = "Id of the silence"),
+	),
+	responses(
+		(status=200, description = "The silence was deleted", body = GenericResponse<String>),
+		(status=404, description = "No silence with that id", body = GenericResponse<String>),
+	)
+)]
+#[delete("/api/alerts/silences/{silence_id}")]
+pub async fn delete_silence(db: Data<Database>, path: Path<(String,)>) -> HttpResponse {
+    let silence_id: String = path.into_inner().0;
+    deleted(db.delete_silence(&silence_id).await, "silence", &silence_id)
+}
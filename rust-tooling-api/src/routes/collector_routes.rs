@@ -0,0 +1,125 @@
+use actix_web::{
+    HttpResponse, get, post, put,
+    web::{Data, Json, Path},
+};
+use dal_layer::{
+    models::{
+        collector_model::{Collector, CollectorHeartbeat, CollectorView},
+        response_model::GenericResponse,
+    },
+    repository::db::Database,
+};
+use mongodb::bson::DateTime;
+
+fn error_response(err: actix_web::Error) -> HttpResponse {
+    let status = err.as_response_error().status_code();
+    HttpResponse::build(status).json(GenericResponse {
+        code: status.as_u16().to_string(),
+        data: err.to_string(),
+    })
+}
+
+#[utoipa::path(
+	post,
+	path = "/api/collectors",
+	tag = "Collector Fleet",
+	request_body(content = CollectorHeartbeat, description = "Sent by a collector when it starts", example = json!({
+    "collector_id": "web-01",
+    "hostname": "web-01",
+    "version": "0.1.0",
+    "heartbeat_interval_secs": 30,
+    "sources": [
+        {"name": "apigateway", "kind": "directory", "offsets": [], "lag_bytes": 0, "pending_lines": 0, "lines_shipped": 0}
+    ],
+    "spool_size": 0
+})),
+	responses(
+		(status=200, description = "The collector was registered; its id", body = GenericResponse<String>),
+		(status=500, description = "Internal Server Error", body = GenericResponse<String>),
+	)
+)]
+#[post("/api/collectors")]
+pub async fn register_collector(
+    db: Data<Database>,
+    request: Json<CollectorHeartbeat>,
+) -> HttpResponse {
+    let collector = Collector::from_heartbeat(request.into_inner(), DateTime::now());
+
+    match db.register_collector(&collector).await {
+        Ok(()) => HttpResponse::Ok().json(GenericResponse {
+            code: String::from("200"),
+            data: collector._id,
+        }),
+        Err(err) => error_response(err),
+    }
+}
+
+#[utoipa::path(
+	put,
+	path = "/api/collectors/{collector_id}/heartbeat",
+	tag = "Collector Fleet",
+	params(
+		("collector_id" = String, Path, description = "Id the collector registered with"),
+	),
+	request_body(content = CollectorHeartbeat, description = "Current state of the collector and its sources"),
+	responses(
+		(status=200, description = "The heartbeat was recorded", body = GenericResponse<String>),
+		(status=400, description = "The body names another collector", body = GenericResponse<String>),
+		(status=500, description = "Internal Server Error", body = GenericResponse<String>),
+	)
+)]
+#[put("/api/collectors/{collector_id}/heartbeat")]
+pub async fn collector_heartbeat(
+    db: Data<Database>,
+    path: Path<(String,)>,
+    request: Json<CollectorHeartbeat>,
+) -> HttpResponse {
+    let collector_id: String = path.into_inner().0;
+    let heartbeat = request.into_inner();
+    if heartbeat.collector_id != collector_id {
+        return HttpResponse::BadRequest().json(GenericResponse {
+            code: String::from("400"),
+            data: format!(
+                "The heartbeat of {} was sent to {collector_id}",
+                heartbeat.collector_id
+            ),
+        });
+    }
+
+    let collector = Collector::from_heartbeat(heartbeat, DateTime::now());
+    match db.record_heartbeat(&collector).await {
+        Ok(()) => HttpResponse::Ok().json(GenericResponse {
+            code: String::from("200"),
+            data: format!("Heartbeat of {collector_id} recorded"),
+        }),
+        Err(err) => error_response(err),
+    }
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/collectors",
+	tag = "Collector Fleet",
+	responses(
+		(status=200, description = "Every collector with its sources, offsets, lag and spool size, flagged stale after three missed heartbeats", body = GenericResponse<String>),
+		(status=500, description = "Internal Server Error", body = GenericResponse<String>),
+	)
+)]
+#[get("/api/collectors")]
+pub async fn get_collectors(db: Data<Database>) -> HttpResponse {
+    match db.get_collectors().await {
+        Ok(collectors) => {
+            let now = DateTime::now();
+            let views: Vec<CollectorView> = collectors
+                .into_iter()
+                .map(|collector| CollectorView::new(collector, now))
+                .collect();
+
+            HttpResponse::Ok().json(GenericResponse {
+                code: String::from("200"),
+                data: views,
+            })
+        }
+        Err(err) => error_response(err),
+    }
+}
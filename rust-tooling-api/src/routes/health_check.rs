@@ -1,5 +1,98 @@
-use actix_web::{HttpResponse, Responder, get};
+use std::time::{Duration, Instant};
+
+use actix_web::{HttpResponse, Responder, get, web::Data};
+use chrono::Utc;
 use dal_layer::models::response_model::GenericResponse;
+use dal_layer::repository::db::Database;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::jobs::{self, JobHealth};
+
+///Longest the readiness check waits for MongoDB before it reports the database as unreachable
+const READINESS_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadinessStatus {
+    ///Database reachable, indexes in place and every background task healthy
+    Ready,
+    ///Serving, but a background task has not succeeded for a while
+    Degraded,
+    ///Database unreachable or indexes missing
+    Unavailable,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DatabaseCheck {
+    pub ok: bool,
+    pub latency_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct IndexCheck {
+    pub ok: bool,
+    pub missing: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Readiness {
+    pub status: ReadinessStatus,
+    pub database: DatabaseCheck,
+    pub indexes: IndexCheck,
+    pub background_tasks: Vec<JobHealth>,
+}
+
+fn readiness_status(database_ok: bool, indexes_ok: bool, tasks: &[JobHealth]) -> ReadinessStatus {
+    if !database_ok || !indexes_ok {
+        ReadinessStatus::Unavailable
+    } else if tasks.iter().any(|task| !task.healthy) {
+        ReadinessStatus::Degraded
+    } else {
+        ReadinessStatus::Ready
+    }
+}
+
+async fn check_database(db: &Database) -> DatabaseCheck {
+    let started = Instant::now();
+    match actix_web::rt::time::timeout(READINESS_TIMEOUT, db.ping()).await {
+        Ok(Ok(())) => DatabaseCheck {
+            ok: true,
+            latency_ms: Some(started.elapsed().as_millis() as u64),
+            error: None,
+        },
+        Ok(Err(err)) => DatabaseCheck {
+            ok: false,
+            latency_ms: None,
+            error: Some(err.to_string()),
+        },
+        Err(_) => DatabaseCheck {
+            ok: false,
+            latency_ms: None,
+            error: Some(format!("No answer within {}s", READINESS_TIMEOUT.as_secs())),
+        },
+    }
+}
+
+async fn check_indexes(db: &Database) -> IndexCheck {
+    let failed = |error: String| IndexCheck {
+        ok: false,
+        missing: Vec::new(),
+        error: Some(error),
+    };
+
+    match actix_web::rt::time::timeout(READINESS_TIMEOUT, db.missing_indexes()).await {
+        Ok(Ok(missing)) => IndexCheck {
+            ok: missing.is_empty(),
+            missing,
+            error: None,
+        },
+        Ok(Err(err)) => failed(err.to_string()),
+        Err(_) => failed(format!("No answer within {}s", READINESS_TIMEOUT.as_secs())),
+    }
+}
 
 #[utoipa::path(
 	get,
@@ -18,3 +111,98 @@ pub async fn health_check() -> impl Responder {
         data: MESSAGE.to_string(),
     })
 }
+
+#[utoipa::path(
+	get,
+	path = "/api/health/live",
+	tag = "Health",
+	responses(
+		(status=200, description = "Liveness: the process is up and answering requests; nothing else is checked", body = GenericResponse<String>),
+	)
+)]
+#[get("/api/health/live")]
+pub async fn liveness() -> impl Responder {
+    HttpResponse::Ok().json(GenericResponse {
+        code: String::from("200"),
+        data: String::from("alive"),
+    })
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/health/ready",
+	tag = "Health",
+	responses(
+		(status=200, description = "Readiness: MongoDB answered a ping, the log indexes exist; background task health is reported and a failing task makes the status degraded", body = Readiness),
+		(status=503, description = "MongoDB is unreachable or required indexes are missing", body = Readiness),
+	)
+)]
+#[get("/api/health/ready")]
+pub async fn readiness(db: Data<Database>) -> HttpResponse {
+    let database = check_database(&db).await;
+    let indexes = if database.ok {
+        check_indexes(&db).await
+    } else {
+        IndexCheck {
+            ok: false,
+            missing: Vec::new(),
+            error: Some(String::from("Not checked, the database is unreachable")),
+        }
+    };
+    let background_tasks = jobs::health(Utc::now());
+
+    let status = readiness_status(database.ok, indexes.ok, &background_tasks);
+    let readiness = Readiness {
+        status,
+        database,
+        indexes,
+        background_tasks,
+    };
+
+    match status {
+        ReadinessStatus::Unavailable => HttpResponse::ServiceUnavailable().json(GenericResponse {
+            code: String::from("503"),
+            data: readiness,
+        }),
+        _ => HttpResponse::Ok().json(GenericResponse {
+            code: String::from("200"),
+            data: readiness,
+        }),
+    }
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readiness_status() {
+        let task = |healthy: bool| JobHealth {
+            name: String::from("alerts"),
+            healthy,
+            interval_secs: 30,
+            last_run_at: None,
+            last_success_at: None,
+            last_error: None,
+        };
+
+        assert_eq!(
+            readiness_status(true, true, &[task(true)]),
+            ReadinessStatus::Ready
+        );
+        assert_eq!(
+            readiness_status(true, true, &[task(true), task(false)]),
+            ReadinessStatus::Degraded
+        );
+        assert_eq!(
+            readiness_status(true, false, &[task(true)]),
+            ReadinessStatus::Unavailable
+        );
+        assert_eq!(
+            readiness_status(false, true, &[]),
+            ReadinessStatus::Unavailable
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use actix_web::{
+    HttpResponse, get, patch,
+    web::{Data, Json, Path, Query},
+};
+use dal_layer::{
+    models::{
+        issue_model::{IssueQuery, IssueStatusUpdate, OccurrenceQuery},
+        response_model::GenericResponse,
+    },
+    repository::db::Database,
+};
+
+fn error_response(err: actix_web::Error) -> HttpResponse {
+    let status = err.as_response_error().status_code();
+    HttpResponse::build(status).json(GenericResponse {
+        code: status.as_u16().to_string(),
+        data: err.to_string(),
+    })
+}
+
+fn not_found(issue_id: &str) -> HttpResponse {
+    HttpResponse::NotFound().json(GenericResponse {
+        code: String::from("404"),
+        data: format!("No issue found with id {issue_id}"),
+    })
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/issues",
+	tag = "Issues",
+	params(IssueQuery),
+	responses(
+		(status=200, description = "Issues grouping ERROR and FATAL lines by fingerprint, the most recently seen first", body = GenericResponse<String>),
+		(status=400, description = "The service id is not a valid ObjectId", body = GenericResponse<String>),
+	)
+)]
+#[get("/api/issues")]
+pub async fn get_issues(db: Data<Database>, query: Query<IssueQuery>) -> HttpResponse {
+    match db.get_issues(&query).await {
+        Ok(issues) => HttpResponse::Ok().json(GenericResponse {
+            code: String::from("200"),
+            data: issues,
+        }),
+        Err(err) => error_response(err),
+    }
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/issues/{issue_id}",
+	tag = "Issues",
+	params(
+		("issue_id" = String, Path, description = "Fingerprint of the issue"),
+	),
+	responses(
+		(status=200, description = "The issue with its status, occurrence count, first and last seen and affected services", body = GenericResponse<String>),
+		(status=404, description = "No issue with that id", body = GenericResponse<String>),
+	)
+)]
+#[get("/api/issues/{issue_id}")]
+pub async fn get_issue(db: Data<Database>, path: Path<(String,)>) -> HttpResponse {
+    let issue_id: String = path.into_inner().0;
+
+    match db.get_issue(&issue_id).await {
+        Ok(Some(issue)) => HttpResponse::Ok().json(GenericResponse {
+            code: String::from("200"),
+            data: issue,
+        }),
+        Ok(None) => not_found(&issue_id),
+        Err(err) => error_response(err),
+    }
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/issues/{issue_id}/occurrences",
+	tag = "Issues",
+	params(
+		("issue_id" = String, Path, description = "Fingerprint of the issue"),
+		OccurrenceQuery,
+	),
+	responses(
+		(status=200, description = "Log lines of the issue, newest first", body = GenericResponse<String>),
+	)
+)]
+#[get("/api/issues/{issue_id}/occurrences")]
+pub async fn get_issue_occurrences(
+    db: Data<Database>,
+    path: Path<(String,)>,
+    query: Query<OccurrenceQuery>,
+) -> HttpResponse {
+    let issue_id: String = path.into_inner().0;
+
+    match db.get_issue_occurrences(&issue_id, query.limit).await {
+        Ok(logs) => HttpResponse::Ok().json(GenericResponse {
+            code: String::from("200"),
+            data: logs,
+        }),
+        Err(err) => error_response(err),
+    }
+}
+
+#[utoipa::path(
+	patch,
+	path = "/api/issues/{issue_id}",
+	tag = "Issues",
+	params(
+		("issue_id" = String, Path, description = "Fingerprint of the issue"),
+	),
+	request_body(content = IssueStatusUpdate, description = "Mark the issue resolved, ignored or unresolved", example = json!({"status": "resolved"})),
+	responses(
+		(status=200, description = "The updated issue. A resolved issue that occurs again becomes regressed", body = GenericResponse<String>),
+		(status=404, description = "No issue with that id", body = GenericResponse<String>),
+	)
+)]
+#[patch("/api/issues/{issue_id}")]
+pub async fn update_issue_status(
+    db: Data<Database>,
+    path: Path<(String,)>,
+    update: Json<IssueStatusUpdate>,
+) -> HttpResponse {
+    let issue_id: String = path.into_inner().0;
+
+    match db.set_issue_status(&issue_id, update.status).await {
+        Ok(Some(issue)) => HttpResponse::Ok().json(GenericResponse {
+            code: String::from("200"),
+            data: issue,
+        }),
+        Ok(None) => not_found(&issue_id),
+        Err(err) => error_response(err),
+    }
+}
@@ -1,16 +1,19 @@
 use actix_web::{
-    Error, HttpResponse,
-    web::{Data, Json, Path},
+    HttpResponse,
+    web::{Data, Json, Path, Query},
 };
 use actix_web::{get, post};
 use dal_layer::{
     models::{
-        log_model::{Log, LogRequest},
+        log_model::{
+            BulkLogResult, Log, LogContextQuery, LogRequest, LogSearchQuery, LogStatsQuery,
+        },
         my_service_model::MyServiceView,
         response_model::GenericResponse,
     },
+    pipeline::{Pipeline, Verdict, issues},
     repository::db::Database,
-    utils::date_helper::Converter,
+    utils::{date_helper::Converter, trace_helper::TraceExtractor},
 };
 use mongodb::bson::Bson;
 use mongodb::bson::DateTime;
@@ -23,28 +26,71 @@ use mongodb::bson::DateTime;
     "my_service_id":"697dff2cbfc969fe967002b7",
     "level":"INFO",
     "line_content":"2025-10-24 13:15:06.698 [restartedMain] INFO  o.s.d.r.c.RepositoryConfigurationDelegate - Bootstrapping Spring Data MongoDB repositories in DEFAULT mode.",
-    "created_at":"2024-05-30T10:00:00.000Z"
+    "created_at":"2024-05-30T10:00:00.000Z",
+    "host":"payment-7d9f8",
+    "source_file":"/var/log/payment/app.log",
+    "line_offset":1024,
+    "trace_id":"4bf92f3577b34da6a3ce929d0e0e4736",
+    "span_id":"00f067aa0ba902b7",
+    "attributes":{"user_id":42,"region":"eu-west-1"}
 })),
 	responses(
 		(status=201, description="Log was successfully sent", body=GenericResponse<String>),
+		(status=200, description="The log matched a drop redaction rule, or repeated a recent line and was counted on it, and was not stored", body=GenericResponse<String>),
+		(status=400, description="The log could not be converted, e.g. a malformed date or service id", body=GenericResponse<String>),
+		(status=429, description="The service is over its ingestion limit and the log was dropped", body=GenericResponse<String>),
 		(status=500, description="Internal Server Error while trying to send the logs", body= GenericResponse<String>)
 	)
 	)]
 #[post("/api/log")]
-pub async fn create_log(db: Data<Database>, request: Json<LogRequest>) -> HttpResponse {
-    match db
-        .create_log(
-            Log::try_from(LogRequest {
-                level: request.level.clone(),
-                my_service_id: request.my_service_id.clone(),
-                line_content: request.line_content.clone(),
-                created_at: request.created_at.clone(),
-            })
-            .expect("Error converting DogRequest to Dog."),
-        )
-        .await
-    {
+pub async fn create_log(
+    db: Data<Database>,
+    trace_extractor: Data<TraceExtractor>,
+    pipeline: Data<Pipeline>,
+    request: Json<LogRequest>,
+) -> HttpResponse {
+    let mut log = match Log::try_from(request.into_inner()) {
+        Ok(log) => log,
+        Err(err) => {
+            return HttpResponse::BadRequest().json(GenericResponse {
+                code: String::from("400"),
+                data: err.to_string(),
+            });
+        }
+    };
+    trace_extractor.apply(&mut log);
+
+    match pipeline.process_log(&mut log) {
+        Verdict::Keep => {}
+        Verdict::Limited => {
+            return HttpResponse::TooManyRequests().json(GenericResponse {
+                code: String::from("429"),
+                data: String::from("The service is over its ingestion limit"),
+            });
+        }
+        Verdict::Redacted => {
+            return HttpResponse::Ok().json(GenericResponse {
+                code: String::from("200"),
+                data: String::from("The log was dropped by a redaction rule"),
+            });
+        }
+    }
+
+    let Some(log) = pipeline.finish_logs(vec![log]).pop() else {
+        return HttpResponse::Ok().json(GenericResponse {
+            code: String::from("200"),
+            data: String::from("The log was counted as a repeat of a recent line"),
+        });
+    };
+
+    let occurrences = issues::occurrences(std::slice::from_ref(&log));
+    let id = log._id;
+
+    match db.create_log(log).await {
         Ok(objectid) => {
+            if let Err(err) = db.record_issue_occurrences(occurrences).await {
+                eprintln!("Could not update the issues: {err}");
+            }
             let id_str = match objectid.inserted_id {
                 Bson::ObjectId(oid) => oid.to_hex(),
                 _ => String::new(), // fallback if something weird happens
@@ -55,10 +101,180 @@ pub async fn create_log(db: Data<Database>, request: Json<LogRequest>) -> HttpRe
                 data: id_str,
             })
         }
-        Err(err) => HttpResponse::InternalServerError().json(GenericResponse {
-            code: String::from("500"),
+        Err(err) => {
+            pipeline.forget([id]);
+            HttpResponse::InternalServerError().json(GenericResponse {
+                code: String::from("500"),
+                data: err.to_string(),
+            })
+        }
+    }
+}
+
+#[utoipa::path(
+	post,
+	path = "/api/logs/bulk",
+    tag = "Create Logs",
+	request_body(content = Vec<LogRequest>, description = "Lines shipped in one batch, e.g. by the client library", example = json!([{
+    "my_service_id":"697dff2cbfc969fe967002b7",
+    "level":"ERROR",
+    "line_content":"payment declined",
+    "created_at":"2024-05-30T10:00:00.000Z",
+    "attributes":{"target":"payment::checkout"}
+}])),
+	responses(
+		(status=200, description = "How many lines were stored, dropped by the pipeline or over their ingestion limit, and why lines were invalid", body = GenericResponse<BulkLogResult>),
+		(status=503, description = "The lines could not be stored; the sender should retry", body = GenericResponse<String>),
+	)
+	)]
+#[post("/api/logs/bulk")]
+pub async fn create_logs_bulk(
+    db: Data<Database>,
+    trace_extractor: Data<TraceExtractor>,
+    pipeline: Data<Pipeline>,
+    request: Json<Vec<LogRequest>>,
+) -> HttpResponse {
+    let mut result = BulkLogResult::default();
+    let mut logs = Vec::new();
+    for (position, item) in request.into_inner().into_iter().enumerate() {
+        match Log::try_from(item) {
+            Ok(log) => logs.push(log),
+            Err(err) => result.invalid.push(format!("line {position}: {err}")),
+        }
+    }
+
+    logs.retain_mut(|log| {
+        trace_extractor.apply(log);
+        match pipeline.process_log(log) {
+            Verdict::Keep => true,
+            Verdict::Limited => {
+                result.limited += 1;
+                false
+            }
+            Verdict::Redacted => {
+                result.dropped += 1;
+                false
+            }
+        }
+    });
+    let kept = logs.len();
+    let logs = pipeline.finish_logs(logs);
+    result.dropped += kept - logs.len();
+    let occurrences = issues::occurrences(&logs);
+
+    if let Err(err) = db.insert_logs_bulk(&logs).await {
+        pipeline.forget(logs.iter().map(|log| log._id));
+        return HttpResponse::ServiceUnavailable().json(GenericResponse {
+            code: String::from("503"),
             data: err.to_string(),
+        });
+    }
+    if let Err(err) = db.record_issue_occurrences(occurrences).await {
+        eprintln!("Could not update the issues: {err}");
+    }
+    result.stored = logs.len();
+
+    HttpResponse::Ok().json(GenericResponse {
+        code: String::from("200"),
+        data: result,
+    })
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/logs/search",
+	tag = "Search Logs",
+	params(LogSearchQuery),
+	responses(
+		(status=200, description = "Logs matching every supplied filter, newest first", body = GenericResponse<String>),
+		(status=400, description = "A filter could not be parsed", body = GenericResponse<String>),
+	)
+	)]
+#[get("/api/logs/search")]
+pub async fn search_logs(db: Data<Database>, query: Query<LogSearchQuery>) -> HttpResponse {
+    match db.search_logs(&query).await {
+        Ok(logs) => HttpResponse::Ok().json(GenericResponse {
+            code: String::from("200"),
+            data: logs,
+        }),
+        Err(err) => {
+            let status = err.as_response_error().status_code();
+            HttpResponse::build(status).json(GenericResponse {
+                code: status.as_u16().to_string(),
+                data: err.to_string(),
+            })
+        }
+    }
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/logs/stats",
+	tag = "Log Statistics",
+	params(LogStatsQuery),
+	responses(
+		(status=200, description = "Lines per service and level with the first and last of each, repeats folded by deduplication included", body = GenericResponse<String>),
+		(status=400, description = "The service id or a date could not be parsed", body = GenericResponse<String>),
+		(status=500, description = "Internal Server Error", body = GenericResponse<String>),
+	)
+	)]
+#[get("/api/logs/stats")]
+pub async fn get_log_stats(db: Data<Database>, query: Query<LogStatsQuery>) -> HttpResponse {
+    match db.get_log_stats(&query).await {
+        Ok(stats) => HttpResponse::Ok().json(GenericResponse {
+            code: String::from("200"),
+            data: stats,
         }),
+        Err(err) => {
+            let status = err.as_response_error().status_code();
+            HttpResponse::build(status).json(GenericResponse {
+                code: status.as_u16().to_string(),
+                data: err.to_string(),
+            })
+        }
+    }
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/logs/{log_id}/context",
+	tag = "Log Context",
+	params(
+		("log_id" = String, Path, description = "Id of the log to centre the window on"),
+		LogContextQuery,
+	),
+	responses(
+		(status=200, description = "The log with the lines before and after it from the same service and source file", body = GenericResponse<String>),
+		(status=400, description = "The log id is not a valid ObjectId", body = GenericResponse<String>),
+		(status=404, description = "No log with that id", body = GenericResponse<String>),
+	)
+	)]
+#[get("/api/logs/{log_id}/context")]
+pub async fn get_log_context(
+    db: Data<Database>,
+    path: Path<(String,)>,
+    query: Query<LogContextQuery>,
+) -> HttpResponse {
+    let log_id: String = path.into_inner().0;
+    let before = query.before.unwrap_or(50);
+    let after = query.after.unwrap_or(50);
+
+    match db.get_log_context(&log_id, before, after).await {
+        Ok(Some(context)) => HttpResponse::Ok().json(GenericResponse {
+            code: String::from("200"),
+            data: context,
+        }),
+        Ok(None) => HttpResponse::NotFound().json(GenericResponse {
+            code: String::from("404"),
+            data: format!("No log found with id {log_id}"),
+        }),
+        Err(err) => {
+            let status = err.as_response_error().status_code();
+            HttpResponse::build(status).json(GenericResponse {
+                code: status.as_u16().to_string(),
+                data: err.to_string(),
+            })
+        }
     }
 }
 
@@ -100,8 +316,8 @@ pub async fn get_logs_services_by_date_range(
 ) -> HttpResponse {
     let (service_id, start_date, end_date) = &path.into_inner();
 
-    let start_date: DateTime = Converter::convert_str_datetime(&start_date);
-    let end_date: DateTime = Converter::convert_str_datetime(&end_date);
+    let start_date: DateTime = Converter::convert_str_datetime(start_date);
+    let end_date: DateTime = Converter::convert_str_datetime(end_date);
 
     match db
         .get_logs_service_by_date_range(service_id, start_date, end_date)
@@ -0,0 +1,16 @@
+use actix_web::{HttpResponse, get};
+
+#[utoipa::path(
+	get,
+	path = "/metrics",
+	tag = "Metrics",
+	responses(
+		(status=200, description = "HTTP request, ingestion, MongoDB and background job metrics in the Prometheus text format", body = String, content_type = "text/plain"),
+	)
+)]
+#[get("/metrics")]
+pub async fn get_metrics() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(dal_layer::utils::metrics::encode())
+}
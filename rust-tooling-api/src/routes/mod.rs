@@ -1,3 +1,12 @@
+pub mod alert_routes;
+pub mod collector_routes;
 pub mod health_check;
+pub mod issue_routes;
 pub mod log_routes;
+pub mod metrics_routes;
 pub mod myservice_routes;
+pub mod otlp_routes;
+pub mod pattern_routes;
+pub mod pipeline_routes;
+pub mod stream_routes;
+pub mod trace_routes;
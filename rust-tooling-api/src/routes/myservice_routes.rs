@@ -1,20 +1,49 @@
 use actix_web::{
-    Error, HttpResponse,
-    error::HttpError,
-    web::{Data, Json},
+    HttpResponse,
+    web::{Data, Json, Path},
 };
 use actix_web::{get, post};
 
-use mongodb::bson::Bson;
+use std::collections::HashMap;
+
+use mongodb::bson::{Bson, DateTime, oid::ObjectId};
 
 use dal_layer::{
     models::{
-        my_service_model::{MyService, MyServiceView},
+        my_service_model::{
+            HealthThresholds, MyService, MyServiceStatus, MyServiceView, ServiceHealth,
+        },
         response_model::GenericResponse,
     },
     repository::db::Database,
 };
 
+use crate::settings::Settings;
+
+///Start of the window the recent line counts of `thresholds` cover
+fn window_start(thresholds: &HealthThresholds, now: DateTime) -> DateTime {
+    DateTime::from_millis(now.timestamp_millis() - (thresholds.window_secs * 1000) as i64)
+}
+
+///Attach the health computed from the service's recent lines
+async fn with_health(
+    db: &Database,
+    settings: &Settings,
+    service: MyService,
+) -> Result<MyServiceStatus, actix_web::Error> {
+    let thresholds = settings.health.thresholds_for(&service._id);
+    let now = DateTime::now();
+
+    let activity = db
+        .get_service_activity(service._id, window_start(thresholds, now))
+        .await?;
+
+    Ok(MyServiceStatus {
+        health: ServiceHealth::from_activity(&activity, thresholds, now),
+        service,
+    })
+}
+
 #[utoipa::path(
 	post,
     tag = "Register Service",
@@ -67,15 +96,107 @@ pub async fn create_service(db: Data<Database>, request: Json<MyServiceView>) ->
 	path = "/api/services",
 	tag = "Get Service",//"Display registered Microservices",
 	responses(
-		(status=200, description = "This is going to display the list of registered microservices running", body = GenericResponse<Vec<MyServiceView>>),
+		(status=200, description = "This is going to display the list of registered microservices running, each with its health: active, quiet, silent or erroring", body = GenericResponse<Vec<MyServiceStatus>>),
 	)
 )]
 #[get("/api/services")]
-pub async fn get_services(db: Data<Database>) -> HttpResponse {
-    match db.get_services().await {
-        Ok(services) => HttpResponse::Ok().json(GenericResponse {
+pub async fn get_services(db: Data<Database>, settings: Data<Settings>) -> HttpResponse {
+    let services = match db.get_services().await {
+        Ok(services) => services,
+        Err(err) => {
+            return HttpResponse::InternalServerError().json(GenericResponse {
+                code: String::from("500"),
+                data: err.to_string(),
+            });
+        }
+    };
+
+    // the activity of every service comes from the same aggregations
+    let now = DateTime::now();
+    let since: HashMap<ObjectId, DateTime> = settings
+        .health
+        .services
+        .iter()
+        .filter_map(|(id, thresholds)| {
+            Some((ObjectId::parse_str(id).ok()?, window_start(thresholds, now)))
+        })
+        .collect();
+    let activity = match db
+        .get_services_activity(window_start(&settings.health.default, now), &since)
+        .await
+    {
+        Ok(activity) => activity,
+        Err(err) => {
+            return HttpResponse::InternalServerError().json(GenericResponse {
+                code: String::from("500"),
+                data: err.to_string(),
+            });
+        }
+    };
+
+    let statuses: Vec<MyServiceStatus> = services
+        .into_iter()
+        .map(|service| {
+            let activity = activity.get(&service._id).cloned().unwrap_or_default();
+            MyServiceStatus {
+                health: ServiceHealth::from_activity(
+                    &activity,
+                    settings.health.thresholds_for(&service._id),
+                    now,
+                ),
+                service,
+            }
+        })
+        .collect();
+
+    HttpResponse::Ok().json(GenericResponse {
+        code: String::from("200"),
+        data: statuses,
+    })
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/service/{service_id}/status",
+	tag = "Service Health",
+	params(
+		("service_id" = String, Path, description = "Id of the registered service"),
+	),
+	responses(
+		(status=200, description = "The service with its health, the time of its newest line and its recent line and error counts", body = GenericResponse<MyServiceStatus>),
+		(status=400, description = "The service id is not a valid ObjectId", body = GenericResponse<String>),
+		(status=404, description = "No service with that id", body = GenericResponse<String>),
+	)
+)]
+#[get("/api/service/{service_id}/status")]
+pub async fn get_service_status(
+    db: Data<Database>,
+    settings: Data<Settings>,
+    path: Path<(String,)>,
+) -> HttpResponse {
+    let service_id: String = path.into_inner().0;
+
+    let service = match db.get_service_by_id(&service_id).await {
+        Ok(Some(service)) => service,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(GenericResponse {
+                code: String::from("404"),
+                data: format!("No service found with id {service_id}"),
+            });
+        }
+        Err(err) => {
+            let status = err.as_response_error().status_code();
+            return HttpResponse::build(status).json(GenericResponse {
+                code: status.as_u16().to_string(),
+                data: err.to_string(),
+            });
+        }
+    };
+
+    match with_health(&db, &settings, service).await {
+        Ok(status) => HttpResponse::Ok().json(GenericResponse {
             code: String::from("200"),
-            data: services,
+            data: status,
         }),
         Err(err) => HttpResponse::InternalServerError().json(GenericResponse {
             code: String::from("500"),
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use actix_web::{
+    HttpRequest, HttpResponse,
+    http::header,
+    post,
+    web::{Bytes, Data},
+};
+use dal_layer::{
+    models::{my_service_model::MyService, response_model::GenericResponse},
+    pipeline::{Pipeline, Verdict, issues},
+    repository::db::Database,
+    utils::trace_helper::TraceExtractor,
+};
+use mongodb::bson::{DateTime, oid::ObjectId};
+
+use crate::otlp::{self, Encoding};
+use crate::settings::Settings;
+
+#[utoipa::path(
+	post,
+	path = "/v1/logs",
+	tag = "OpenTelemetry",
+	request_body(description = "OTLP ExportLogsServiceRequest encoded as protobuf or JSON", content(
+		(String = "application/x-protobuf"),
+		(String = "application/json"),
+	)),
+	responses(
+		(status=200, description = "ExportLogsServiceResponse in the request's encoding, with partialSuccess set when records of unknown services or over an ingestion limit were rejected"),
+		(status=400, description = "The body is not a valid OTLP logs request", body = GenericResponse<String>),
+		(status=415, description = "Content type other than application/x-protobuf or application/json", body = GenericResponse<String>),
+		(status=503, description = "The logs could not be stored; the exporter should retry", body = GenericResponse<String>),
+	)
+)]
+#[post("/v1/logs")]
+pub async fn receive_otlp_logs(
+    db: Data<Database>,
+    settings: Data<Settings>,
+    trace_extractor: Data<TraceExtractor>,
+    pipeline: Data<Pipeline>,
+    request: HttpRequest,
+    body: Bytes,
+) -> HttpResponse {
+    let content_type = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+
+    let Some(encoding) = Encoding::from_content_type(content_type) else {
+        return HttpResponse::UnsupportedMediaType().json(GenericResponse {
+            code: String::from("415"),
+            data: format!("Unsupported content type {content_type:?}, expected application/x-protobuf or application/json"),
+        });
+    };
+
+    let export = match otlp::decode_request(encoding, &body) {
+        Ok(export) => export,
+        Err(err) => {
+            return HttpResponse::BadRequest().json(GenericResponse {
+                code: String::from("400"),
+                data: err,
+            });
+        }
+    };
+
+    let mut service_ids: HashMap<String, Option<ObjectId>> = HashMap::new();
+    let mut logs = Vec::new();
+    let mut rejected: i64 = 0;
+    let mut unknown_services: Vec<String> = Vec::new();
+
+    for resource_logs in export.resource_logs {
+        let name = otlp::service_name(resource_logs.resource.as_ref());
+
+        let service_id = match service_ids.get(&name) {
+            Some(id) => *id,
+            None => {
+                let id = match resolve_service(&db, &name, settings.ingest.otlp_auto_register).await
+                {
+                    Ok(id) => id,
+                    Err(err) => return unavailable(err.to_string()),
+                };
+                service_ids.insert(name.clone(), id);
+                id
+            }
+        };
+
+        match service_id {
+            Some(service_id) => logs.extend(otlp::to_logs(resource_logs, service_id)),
+            None => {
+                rejected += otlp::record_count(&resource_logs) as i64;
+                if !unknown_services.contains(&name) {
+                    unknown_services.push(name);
+                }
+            }
+        }
+    }
+
+    for log in logs.iter_mut() {
+        trace_extractor.apply(log);
+    }
+    // records over an ingestion limit are reported as rejected; records dropped by a
+    // redaction rule are accepted so exporters do not retry them
+    let mut limited: i64 = 0;
+    logs.retain_mut(|log| match pipeline.process_log(log) {
+        Verdict::Keep => true,
+        Verdict::Limited => {
+            limited += 1;
+            false
+        }
+        Verdict::Redacted => false,
+    });
+    rejected += limited;
+    let logs = pipeline.finish_logs(logs);
+    let occurrences = issues::occurrences(&logs);
+
+    if let Err(err) = db.insert_logs_bulk(&logs).await {
+        pipeline.forget(logs.iter().map(|log| log._id));
+        return unavailable(err.to_string());
+    }
+    if let Err(err) = db.record_issue_occurrences(occurrences).await {
+        eprintln!("Could not update the issues: {err}");
+    }
+
+    let mut errors: Vec<String> = Vec::new();
+    if !unknown_services.is_empty() {
+        errors.push(format!(
+            "services are not registered and auto registration is disabled: {}",
+            unknown_services.join(", ")
+        ));
+    }
+    if limited > 0 {
+        errors.push(format!(
+            "{limited} records were over their service's ingestion limit"
+        ));
+    }
+    let error_message = errors.join("; ");
+
+    HttpResponse::Ok()
+        .content_type(encoding.content_type())
+        .body(otlp::encode_response(encoding, rejected, error_message))
+}
+
+///Id of the service called `name`, registering it when allowed
+async fn resolve_service(
+    db: &Database,
+    name: &str,
+    auto_register: bool,
+) -> Result<Option<ObjectId>, actix_web::Error> {
+    if let Some(service) = db.find_service_by_name(name).await? {
+        return Ok(Some(service._id));
+    }
+    if !auto_register {
+        return Ok(None);
+    }
+
+    let service = MyService {
+        _id: ObjectId::new(),
+        name: name.to_string(),
+        description: Some(String::from("Registered by the OpenTelemetry receiver")),
+        onboarded_datetime: DateTime::now(),
+    };
+    let id = service._id;
+    db.create_service(service).await?;
+
+    Ok(Some(id))
+}
+
+fn unavailable(message: String) -> HttpResponse {
+    HttpResponse::ServiceUnavailable().json(GenericResponse {
+        code: String::from("503"),
+        data: message,
+    })
+}
@@ -0,0 +1,36 @@
+use actix_web::{
+    HttpResponse, get,
+    web::{Data, Query},
+};
+use dal_layer::{
+    models::{response_model::GenericResponse, template_model::PatternQuery},
+    repository::db::Database,
+};
+
+#[utoipa::path(
+	get,
+	path = "/api/patterns",
+	tag = "Log Patterns",
+	params(PatternQuery),
+	responses(
+		(status=200, description = "Message templates of the matching lines with their counts, sample lines and first and last occurrence, the most frequent first", body = GenericResponse<String>),
+		(status=400, description = "The service id or a date could not be parsed", body = GenericResponse<String>),
+		(status=500, description = "Internal Server Error", body = GenericResponse<String>),
+	)
+)]
+#[get("/api/patterns")]
+pub async fn get_patterns(db: Data<Database>, query: Query<PatternQuery>) -> HttpResponse {
+    match db.get_patterns(&query).await {
+        Ok(patterns) => HttpResponse::Ok().json(GenericResponse {
+            code: String::from("200"),
+            data: patterns,
+        }),
+        Err(err) => {
+            let status = err.as_response_error().status_code();
+            HttpResponse::build(status).json(GenericResponse {
+                code: status.as_u16().to_string(),
+                data: err.to_string(),
+            })
+        }
+    }
+}
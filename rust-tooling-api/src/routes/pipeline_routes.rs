@@ -0,0 +1,34 @@
+use actix_web::{HttpResponse, get, web::Data};
+use dal_layer::{models::response_model::GenericResponse, pipeline::Pipeline};
+
+#[utoipa::path(
+	get,
+	path = "/api/pipeline/redaction",
+	tag = "Ingest Pipeline",
+	responses(
+		(status=200, description = "Matches per redaction rule since the API started, keyed by rule name", body = GenericResponse<String>),
+	)
+)]
+#[get("/api/pipeline/redaction")]
+pub async fn get_redaction_stats(pipeline: Data<Pipeline>) -> HttpResponse {
+    HttpResponse::Ok().json(GenericResponse {
+        code: String::from("200"),
+        data: pipeline.redactor.hits(),
+    })
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/pipeline/limits",
+	tag = "Ingest Pipeline",
+	responses(
+		(status=200, description = "Accepted and dropped lines and today's bytes per limited service since the API started, keyed by service id", body = GenericResponse<String>),
+	)
+)]
+#[get("/api/pipeline/limits")]
+pub async fn get_limit_stats(pipeline: Data<Pipeline>) -> HttpResponse {
+    HttpResponse::Ok().json(GenericResponse {
+        code: String::from("200"),
+        data: pipeline.limiter.stats(),
+    })
+}
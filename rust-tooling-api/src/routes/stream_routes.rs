@@ -0,0 +1,299 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use actix_web::{
+    Error, HttpResponse, get,
+    web::{Bytes, Data, Query},
+};
+use dal_layer::{
+    models::{
+        log_model::{Log, LogSearchQuery, SearchOrder},
+        response_model::GenericResponse,
+    },
+    repository::db::Database,
+};
+use mongodb::bson::{DateTime, oid::ObjectId};
+
+///How often the database is polled for new lines
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+///How far behind the newest line seen each poll looks. Lines are followed by the time the
+///database stored them, but a write stamped a moment before another may become visible after
+///it, so the lines are read again over this window and the ones already sent are skipped.
+const LOOKBACK: Duration = Duration::from_secs(30);
+
+///A comment is sent after this long without a line, so proxies keep the connection open
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+///Lines sent before following when `limit` is omitted, as `tail` does
+const DEFAULT_BACKLOG: i64 = 10;
+
+///Most lines read by one poll; a full batch is followed by another poll right away
+const POLL_BATCH: i64 = 500;
+
+#[utoipa::path(
+	get,
+	path = "/api/logs/stream",
+	tag = "Stream Logs",
+	params(LogSearchQuery),
+	responses(
+		(status=200, description = "Server-Sent Events, one `data:` event per log as JSON: the `limit` (10 by default) most recent matching lines, then every matching line as it arrives", content_type = "text/event-stream", body = String),
+		(status=400, description = "A filter could not be parsed", body = GenericResponse<String>),
+	)
+)]
+#[get("/api/logs/stream")]
+pub async fn stream_logs(db: Data<Database>, query: Query<LogSearchQuery>) -> HttpResponse {
+    let query = query.into_inner();
+    let backlog = query.limit.unwrap_or(DEFAULT_BACKLOG).max(0);
+    let floor = match db.server_time().await {
+        Ok(floor) => floor,
+        Err(err) => {
+            let status = err.as_response_error().status_code();
+            return HttpResponse::build(status).json(GenericResponse {
+                code: status.as_u16().to_string(),
+                data: err.to_string(),
+            });
+        }
+    };
+
+    // Also rejects the filters that cannot be parsed before the stream starts
+    let recent = db
+        .search_logs(&LogSearchQuery {
+            limit: Some(backlog.max(1)),
+            order: Some(SearchOrder::Desc),
+            ..query.clone()
+        })
+        .await;
+    let mut recent = match recent {
+        Ok(recent) => recent,
+        Err(err) => {
+            let status = err.as_response_error().status_code();
+            return HttpResponse::build(status).json(GenericResponse {
+                code: status.as_u16().to_string(),
+                data: err.to_string(),
+            });
+        }
+    };
+    recent.truncate(backlog as usize);
+    recent.reverse();
+
+    let mut position = Position::new(floor);
+    for log in &recent {
+        position.sent.insert(stored_key(log, floor));
+    }
+    let tail = Tail {
+        db,
+        query,
+        position,
+        pending: recent.iter().map(event).collect(),
+        last_event: Instant::now(),
+    };
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(futures::stream::unfold(tail, Tail::next_event))
+}
+
+///State of one stream between two events
+struct Tail {
+    db: Data<Database>,
+    query: LogSearchQuery,
+    position: Position,
+    pending: VecDeque<Bytes>,
+    last_event: Instant,
+}
+
+impl Tail {
+    async fn next_event(mut self) -> Option<(Result<Bytes, Error>, Self)> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                self.last_event = Instant::now();
+                return Some((Ok(event), self));
+            }
+
+            if self.position.resume.is_none() {
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+            self.poll().await;
+            if self.pending.is_empty() && self.last_event.elapsed() >= KEEP_ALIVE {
+                self.pending
+                    .push_back(Bytes::from_static(b": keep-alive\n\n"));
+            }
+        }
+    }
+
+    async fn poll(&mut self) {
+        let after = self.position.next_poll();
+        match self
+            .db
+            .search_logs_ingested_after(&self.query, after, POLL_BATCH)
+            .await
+        {
+            Ok(logs) => {
+                let full = logs.len() as i64 == POLL_BATCH;
+                for log in self.position.accept(logs, full) {
+                    self.pending.push_back(event(&log));
+                }
+            }
+            Err(err) => eprintln!("Could not poll the logs for a stream: {err}"),
+        }
+    }
+}
+
+///Where a stream is in the lines ordered by (`ingested_at`, `_id`), the time the database
+///stored them and their id
+struct Position {
+    ///Time the stream started by the clock of the database; earlier lines were part of the backlog
+    floor: DateTime,
+    ///Latest `ingested_at` seen
+    newest: DateTime,
+    ///Lines sent within the lookback window
+    sent: BTreeSet<(DateTime, ObjectId)>,
+    ///Line the next poll starts after, following a full batch
+    resume: Option<(DateTime, ObjectId)>,
+}
+
+impl Position {
+    fn new(floor: DateTime) -> Self {
+        Position {
+            floor,
+            newest: floor,
+            sent: BTreeSet::new(),
+            resume: None,
+        }
+    }
+
+    ///Line the next poll reads after: the one a full batch stopped at, otherwise the start of the
+    ///lookback window. Forgets the lines sent before that window.
+    fn next_poll(&mut self) -> (DateTime, ObjectId) {
+        let lookback = i64::try_from(LOOKBACK.as_millis()).unwrap_or(i64::MAX);
+        let window_start = DateTime::from_millis(
+            self.newest
+                .timestamp_millis()
+                .saturating_sub(lookback)
+                .max(self.floor.timestamp_millis()),
+        );
+        let start = (window_start, ObjectId::from_bytes([0; 12]));
+        self.sent = self.sent.split_off(&start);
+
+        self.resume.take().map_or(start, |resume| resume.max(start))
+    }
+
+    ///Lines of a poll that were not sent yet, in order. `full` when the poll hit its limit, so
+    ///the next one continues after its last line.
+    fn accept(&mut self, logs: Vec<Log>, full: bool) -> Vec<Log> {
+        let mut fresh = Vec::new();
+        for log in logs {
+            let key = stored_key(&log, self.floor);
+            self.newest = self.newest.max(key.0);
+            if full {
+                self.resume = Some(key);
+            }
+            if self.sent.insert(key) {
+                fresh.push(log);
+            }
+        }
+        fresh
+    }
+}
+
+///Position of `log` in the order lines are followed in; a line not stamped yet counts as stored
+///at `floor`
+fn stored_key(log: &Log, floor: DateTime) -> (DateTime, ObjectId) {
+    (log.ingested_at.unwrap_or(floor), log._id)
+}
+
+///Server-Sent Event carrying `log` as JSON, with its id as the event id
+fn event(log: &Log) -> Bytes {
+    let data = serde_json::to_string(log).unwrap_or_default();
+    Bytes::from(format!("id: {}\ndata: {data}\n\n", log._id.to_hex()))
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_layer::models::log_model::{LogLevel, LogRequest};
+
+    fn request() -> LogRequest {
+        LogRequest::new(
+            "697dff2cbfc969fe967002b7",
+            LogLevel::WARN,
+            "disk almost full",
+            String::from("2025-10-24T13:15:06.698Z"),
+        )
+    }
+
+    ///A line with id `_id` stored `millis` after the epoch
+    fn stored(_id: ObjectId, millis: i64) -> Log {
+        Log {
+            _id,
+            ingested_at: Some(DateTime::from_millis(millis)),
+            ..Log::try_from(request()).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_event() {
+        let log = Log::try_from(request()).unwrap();
+
+        let event = String::from_utf8(event(&log).to_vec()).unwrap();
+        let (id, data) = event
+            .strip_suffix("\n\n")
+            .and_then(|event| event.split_once('\n'))
+            .unwrap();
+        assert_eq!(id, format!("id: {}", log._id.to_hex()));
+        let data: serde_json::Value =
+            serde_json::from_str(data.strip_prefix("data: ").unwrap()).unwrap();
+        assert_eq!(data["line_content"], "disk almost full");
+    }
+
+    #[test]
+    fn test_follow_a_line_stored_long_after_its_id_was_made() {
+        let start = 1_760_000_000_000;
+        let mut position = Position::new(DateTime::from_millis(start));
+
+        let fresh = ObjectId::new();
+        let after = position.next_poll();
+        assert_eq!(after.0.timestamp_millis(), start);
+        let sent = position.accept(vec![stored(fresh, start + 1_000)], false);
+        assert_eq!(sent.len(), 1);
+
+        // Retried by a collector an hour after it was read: its id is older than the one sent
+        let old = ObjectId::from_parts(1_759_996_400, [1; 5], [0; 3]);
+        assert!(old < fresh);
+        let after = position.next_poll();
+        let polled = vec![stored(fresh, start + 1_000), stored(old, start + 2_000)];
+        assert!(after < (polled[0].ingested_at.unwrap(), fresh));
+        let sent = position.accept(polled, false);
+        assert_eq!(
+            sent.iter().map(|log| log._id).collect::<Vec<_>>(),
+            vec![old]
+        );
+    }
+
+    #[test]
+    fn test_position_window_and_resume() {
+        let start = 1_760_000_000_000;
+        let mut position = Position::new(DateTime::from_millis(start));
+        let first = stored(ObjectId::new(), start + 500);
+        let last = stored(ObjectId::new(), start + 500);
+        let resume_at = (last.ingested_at.unwrap(), last._id);
+
+        position.next_poll();
+        assert_eq!(position.accept(vec![first, last], true).len(), 2);
+        assert_eq!(position.next_poll(), resume_at);
+
+        // Lines sent before the lookback window are forgotten once newer ones arrive
+        let much_later = start + 500 + LOOKBACK.as_millis() as i64 + 1_000;
+        position.accept(vec![stored(ObjectId::new(), much_later)], false);
+        let after = position.next_poll();
+        assert_eq!(
+            after.0.timestamp_millis(),
+            much_later - LOOKBACK.as_millis() as i64
+        );
+        assert_eq!(position.sent.len(), 1);
+    }
+}
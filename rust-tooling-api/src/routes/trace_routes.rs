@@ -0,0 +1,45 @@
+use actix_web::get;
+use actix_web::{
+    HttpResponse,
+    web::{Data, Path},
+};
+use dal_layer::{
+    models::response_model::GenericResponse, repository::db::Database,
+    utils::trace_helper::is_valid_trace_id,
+};
+
+#[utoipa::path(
+	get,
+	path = "/api/traces/{trace_id}",
+	tag = "Trace Correlation",
+	params(
+		("trace_id" = String, Path, description = "W3C trace id, 32 hex characters"),
+	),
+	responses(
+		(status=200, description = "Every log line for the trace from every registered service, grouped by service and ordered by time", body = GenericResponse<String>),
+		(status=400, description = "The trace id is not a W3C trace id", body = GenericResponse<String>),
+		(status=500, description = "Internal Server Error", body = GenericResponse<String>),
+	)
+)]
+#[get("/api/traces/{trace_id}")]
+pub async fn get_trace(db: Data<Database>, path: Path<(String,)>) -> HttpResponse {
+    let trace_id: String = path.into_inner().0;
+
+    if !is_valid_trace_id(&trace_id) {
+        return HttpResponse::BadRequest().json(GenericResponse {
+            code: String::from("400"),
+            data: format!("{trace_id} is not a 32 character hex trace id"),
+        });
+    }
+
+    match db.get_trace(&trace_id).await {
+        Ok(trace) => HttpResponse::Ok().json(GenericResponse {
+            code: String::from("200"),
+            data: trace,
+        }),
+        Err(err) => HttpResponse::InternalServerError().json(GenericResponse {
+            code: String::from("500"),
+            data: err.to_string(),
+        }),
+    }
+}
@@ -0,0 +1,707 @@
+//! Configuration of the API, layered from lowest to highest precedence: the built in defaults,
+//! the TOML file named by `--config` or API_CONFIG (`api.toml` when it exists), the environment
+//! and the command line flags. Everything is checked once at start up and every problem found
+//! is reported together.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use dal_layer::models::my_service_model::HealthConfig;
+use dal_layer::pipeline::Pipeline;
+use dal_layer::pipeline::{dedup::DedupConfig, limiter::LimitConfig, redaction::RedactionConfig};
+use dal_layer::repository::config::DatabaseConfig;
+use dal_layer::utils::trace_helper::TraceExtractor;
+use serde::Deserialize;
+
+///File read when neither `--config` nor API_CONFIG name one
+pub const DEFAULT_CONFIG_FILE: &str = "api.toml";
+
+///Shortest API key accepted, so a key cannot be guessed
+const MIN_API_KEY_LENGTH: usize = 16;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "HTTP API of the log monitor")]
+pub struct Cli {
+    ///TOML configuration file [env: API_CONFIG]
+    #[arg(long, short)]
+    pub config: Option<PathBuf>,
+    ///Address to listen on, overriding server.host
+    #[arg(long)]
+    pub host: Option<String>,
+    ///Port to listen on, overriding server.port
+    #[arg(long)]
+    pub port: Option<u16>,
+    ///MongoDB connection string, overriding database.uri
+    #[arg(long)]
+    pub database_uri: Option<String>,
+    ///MongoDB database, overriding database.name
+    #[arg(long)]
+    pub database_name: Option<String>,
+    ///Check the configuration and exit without starting the server
+    #[arg(long)]
+    pub check: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSettings {
+    pub host: String,
+    pub port: u16,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        ServerSettings {
+            host: String::from("localhost"),
+            port: 5000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsSettings {
+    ///Origins allowed to call the API from a browser; `*` allows any origin, without cookies
+    pub allowed_origins: Vec<String>,
+}
+
+impl Default for CorsSettings {
+    fn default() -> Self {
+        CorsSettings {
+            allowed_origins: vec![String::from("http://localhost:5000")],
+        }
+    }
+}
+
+///Which clients must present a certificate signed by `tls.client_ca_path`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientAuth {
+    #[default]
+    Off,
+    ///Only the collectors, on /api/collectors; other clients may still connect without one
+    Collectors,
+    ///Every client, at the handshake
+    Required,
+}
+
+impl std::str::FromStr for ClientAuth {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "off" => Ok(ClientAuth::Off),
+            "collectors" => Ok(ClientAuth::Collectors),
+            "required" => Ok(ClientAuth::Required),
+            other => Err(format!(
+                "{other:?} is not one of off, collectors or required"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsSettings {
+    ///Serve HTTPS instead of HTTP
+    pub enabled: bool,
+    ///PEM certificate chain, the server's certificate first
+    pub cert_path: Option<PathBuf>,
+    ///PEM private key of the certificate
+    pub key_path: Option<PathBuf>,
+    ///Seconds between two checks of the certificate and key files for a renewal
+    pub reload_interval_secs: u64,
+    ///PEM certificates of the authorities that sign the client certificates
+    pub client_ca_path: Option<PathBuf>,
+    pub client_auth: ClientAuth,
+    ///Port of a plain HTTP listener redirecting every request to HTTPS
+    pub redirect_http_port: Option<u16>,
+}
+
+impl Default for TlsSettings {
+    fn default() -> Self {
+        TlsSettings {
+            enabled: false,
+            cert_path: None,
+            key_path: None,
+            reload_interval_secs: 60,
+            client_ca_path: None,
+            client_auth: ClientAuth::Off,
+            redirect_http_port: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKey {
+    ///Who the key was given to, only used in logs
+    pub name: String,
+    pub key: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthSettings {
+    ///Require one of `api_keys` on every endpoint but the health checks, the metrics and the docs
+    pub enabled: bool,
+    pub api_keys: Vec<ApiKey>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionSettings {
+    ///Delete the logs older than their retention period
+    pub enabled: bool,
+    ///Days the logs of a service are kept unless `services` says otherwise
+    pub default_days: u32,
+    ///Seconds between two runs of the clean up
+    pub interval_secs: u64,
+    ///Days kept by service name
+    pub services: BTreeMap<String, u32>,
+}
+
+impl Default for RetentionSettings {
+    fn default() -> Self {
+        RetentionSettings {
+            enabled: false,
+            default_days: 30,
+            interval_secs: 3600,
+            services: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeatureSettings {
+    ///Accept OTLP/HTTP logs on /v1/logs
+    pub otlp: bool,
+    ///Evaluate the alert rules in the background
+    pub alerts: bool,
+    ///Serve the Swagger UI and the OpenAPI document
+    pub swagger_ui: bool,
+}
+
+impl Default for FeatureSettings {
+    fn default() -> Self {
+        FeatureSettings {
+            otlp: true,
+            alerts: true,
+            swagger_ui: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IngestSettings {
+    ///Regex used to pull trace ids out of line_content when a log is sent without one
+    pub trace_id_regex: Option<String>,
+    ///Create a MyService for an unknown OTLP `service.name` instead of rejecting its records
+    pub otlp_auto_register: bool,
+}
+
+impl Default for IngestSettings {
+    fn default() -> Self {
+        IngestSettings {
+            trace_id_regex: None,
+            otlp_auto_register: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertSettings {
+    ///Seconds between two evaluations of the alert rules
+    pub eval_interval_secs: u64,
+    ///Programs alert rules may run, by the name the rules refer to them with
+    pub commands: BTreeMap<String, String>,
+}
+
+impl Default for AlertSettings {
+    fn default() -> Self {
+        AlertSettings {
+            eval_interval_secs: 30,
+            commands: BTreeMap::new(),
+        }
+    }
+}
+
+///Runtime configuration of the API, one table of the TOML file per field
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub server: ServerSettings,
+    pub cors: CorsSettings,
+    pub database: DatabaseConfig,
+    pub tls: TlsSettings,
+    pub auth: AuthSettings,
+    pub retention: RetentionSettings,
+    pub features: FeatureSettings,
+    pub ingest: IngestSettings,
+    pub alerts: AlertSettings,
+    ///Redaction rules applied to every log before it is stored
+    pub redaction: RedactionConfig,
+    ///Per-service ingestion limits
+    pub limits: LimitConfig,
+    ///Per-service repeated-line deduplication
+    pub dedup: DedupConfig,
+    ///Thresholds of the service health statuses
+    pub health: HealthConfig,
+}
+
+impl Settings {
+    ///Settings of this process from its file, environment and `cli`, or every problem found
+    ///in them as one message
+    pub fn load(cli: &Cli) -> Result<Settings, String> {
+        let path = cli
+            .config
+            .clone()
+            .or_else(|| env::var_os("API_CONFIG").map(PathBuf::from))
+            .or_else(|| {
+                let default = PathBuf::from(DEFAULT_CONFIG_FILE);
+                default.exists().then_some(default)
+            });
+
+        let mut settings = match &path {
+            Some(path) => Settings::from_file(path)?,
+            None => Settings::default(),
+        };
+
+        let mut problems = settings.apply_env(|name| env::var(name).ok());
+        settings.apply_cli(cli);
+        problems.extend(settings.validate());
+
+        if problems.is_empty() {
+            return Ok(settings);
+        }
+        let source = path
+            .map(|path| format!(" (file {})", path.display()))
+            .unwrap_or_default();
+        Err(format!(
+            "Invalid configuration{source}:\n  - {}",
+            problems.join("\n  - ")
+        ))
+    }
+
+    pub fn from_file(path: &Path) -> Result<Settings, String> {
+        let data = fs::read_to_string(path)
+            .map_err(|err| format!("Could not read the configuration {}: {err}", path.display()))?;
+        toml::from_str(&data)
+            .map_err(|err| format!("Invalid configuration {}: {err}", path.display()))
+    }
+
+    ///Override the file with the environment variables set according to `var`, returning the
+    ///ones that could not be used
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if let Some(host) = var("API_HOST") {
+            self.server.host = host;
+        }
+        parse_var(&var, "API_PORT", &mut self.server.port, &mut problems);
+        if let Some(origins) = var("CORS_ALLOWED_ORIGINS") {
+            self.cors.allowed_origins = split_list(&origins);
+        }
+
+        if let Some(uri) = var("MONGO_URI") {
+            self.database.uri = uri;
+        }
+        if let Some(name) = var("MONGO_DB_NAME") {
+            self.database.name = name;
+        }
+        let database = &mut self.database;
+        parse_var(
+            &var,
+            "MONGO_MAX_POOL_SIZE",
+            &mut database.max_pool_size,
+            &mut problems,
+        );
+        parse_var(
+            &var,
+            "MONGO_MIN_POOL_SIZE",
+            &mut database.min_pool_size,
+            &mut problems,
+        );
+        parse_var(
+            &var,
+            "DB_CONNECT_ATTEMPTS",
+            &mut database.connect_attempts,
+            &mut problems,
+        );
+        parse_var(
+            &var,
+            "DB_CONNECT_RETRY_SECS",
+            &mut database.connect_retry_secs,
+            &mut problems,
+        );
+
+        if let Some(cert_path) = var("TLS_CERT_PATH") {
+            self.tls.cert_path = Some(PathBuf::from(cert_path));
+            self.tls.enabled = true;
+        }
+        if let Some(key_path) = var("TLS_KEY_PATH") {
+            self.tls.key_path = Some(PathBuf::from(key_path));
+        }
+        if let Some(ca_path) = var("TLS_CLIENT_CA_PATH") {
+            self.tls.client_ca_path = Some(PathBuf::from(ca_path));
+        }
+        if let Some(client_auth) = var("TLS_CLIENT_AUTH") {
+            match client_auth.parse() {
+                Ok(client_auth) => self.tls.client_auth = client_auth,
+                Err(err) => problems.push(format!("TLS_CLIENT_AUTH: {err}")),
+            }
+        }
+        if var("TLS_REDIRECT_HTTP_PORT").is_some() {
+            let mut port = 0;
+            parse_var(&var, "TLS_REDIRECT_HTTP_PORT", &mut port, &mut problems);
+            self.tls.redirect_http_port = Some(port);
+        }
+
+        // API_KEYS=name:key,name:key replaces the keys of the file and turns authentication on
+        if let Some(keys) = var("API_KEYS") {
+            self.auth.api_keys = Vec::new();
+            for entry in split_list(&keys) {
+                match entry.split_once(':') {
+                    Some((name, key)) => self.auth.api_keys.push(ApiKey {
+                        name: name.trim().to_string(),
+                        key: key.trim().to_string(),
+                    }),
+                    None => problems.push(String::from(
+                        "API_KEYS must be a comma separated list of name:key",
+                    )),
+                }
+            }
+            self.auth.enabled = true;
+        }
+
+        parse_var(
+            &var,
+            "RETENTION_DAYS",
+            &mut self.retention.default_days,
+            &mut problems,
+        );
+        if var("RETENTION_DAYS").is_some() {
+            self.retention.enabled = true;
+        }
+
+        parse_flag(&var, "FEATURE_OTLP", &mut self.features.otlp);
+        parse_flag(&var, "FEATURE_ALERTS", &mut self.features.alerts);
+        parse_flag(&var, "FEATURE_SWAGGER_UI", &mut self.features.swagger_ui);
+
+        if let Some(pattern) = var("TRACE_ID_REGEX") {
+            self.ingest.trace_id_regex = Some(pattern);
+        }
+        parse_flag(
+            &var,
+            "OTLP_AUTO_REGISTER",
+            &mut self.ingest.otlp_auto_register,
+        );
+        parse_var(
+            &var,
+            "ALERT_EVAL_INTERVAL_SECS",
+            &mut self.alerts.eval_interval_secs,
+            &mut problems,
+        );
+
+        // ALERT_COMMANDS=name=/path,name=/path replaces the commands of the file
+        if let Some(commands) = var("ALERT_COMMANDS") {
+            self.alerts.commands = BTreeMap::new();
+            for entry in split_list(&commands) {
+                match entry.split_once('=') {
+                    Some((name, program)) => {
+                        self.alerts
+                            .commands
+                            .insert(name.trim().to_string(), program.trim().to_string());
+                    }
+                    None => problems.push(String::from(
+                        "ALERT_COMMANDS must be a comma separated list of name=program",
+                    )),
+                }
+            }
+        }
+
+        json_file_var(&var, "REDACTION_CONFIG", &mut self.redaction, &mut problems);
+        json_file_var(&var, "LIMITS_CONFIG", &mut self.limits, &mut problems);
+        json_file_var(&var, "DEDUP_CONFIG", &mut self.dedup, &mut problems);
+        json_file_var(&var, "HEALTH_CONFIG", &mut self.health, &mut problems);
+
+        problems
+    }
+
+    ///Override the file and the environment with the flags given on the command line
+    pub fn apply_cli(&mut self, cli: &Cli) {
+        if let Some(host) = &cli.host {
+            self.server.host = host.clone();
+        }
+        if let Some(port) = cli.port {
+            self.server.port = port;
+        }
+        if let Some(uri) = &cli.database_uri {
+            self.database.uri = uri.clone();
+        }
+        if let Some(name) = &cli.database_name {
+            self.database.name = name.clone();
+        }
+    }
+
+    ///Every problem that would stop the API from starting or working as configured
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.server.host.trim().is_empty() {
+            problems.push(String::from("server.host must not be empty"));
+        }
+        if self.server.port == 0 {
+            problems.push(String::from("server.port must not be 0"));
+        }
+
+        if self.cors.allowed_origins.is_empty() {
+            problems.push(String::from(
+                "cors.allowed_origins must list at least one origin, or \"*\"",
+            ));
+        }
+        for origin in &self.cors.allowed_origins {
+            let valid = origin == "*"
+                || ((origin.starts_with("http://") || origin.starts_with("https://"))
+                    && !origin.ends_with('/'));
+            if !valid {
+                problems.push(format!(
+                    "cors.allowed_origins: {origin:?} is not an origin like https://example.com"
+                ));
+            }
+        }
+
+        problems.extend(self.database.validate());
+
+        if self.tls.enabled {
+            for (field, path) in [
+                ("tls.cert_path", &self.tls.cert_path),
+                ("tls.key_path", &self.tls.key_path),
+            ] {
+                match path {
+                    None => problems.push(format!("{field} is required when tls is enabled")),
+                    Some(path) if !path.is_file() => {
+                        problems.push(format!("{field}: {} is not a file", path.display()))
+                    }
+                    Some(_) => {}
+                }
+            }
+            if self.tls.reload_interval_secs == 0 {
+                problems.push(String::from("tls.reload_interval_secs must be at least 1"));
+            }
+        }
+        match (&self.tls.client_ca_path, self.tls.client_auth) {
+            (_, ClientAuth::Off) => {}
+            (None, _) => problems.push(String::from(
+                "tls.client_ca_path is required when tls.client_auth is not off",
+            )),
+            (Some(path), _) if !path.is_file() => problems.push(format!(
+                "tls.client_ca_path: {} is not a file",
+                path.display()
+            )),
+            (Some(_), _) => {}
+        }
+        if !self.tls.enabled
+            && (self.tls.client_auth != ClientAuth::Off || self.tls.redirect_http_port.is_some())
+        {
+            problems.push(String::from(
+                "tls.client_auth and tls.redirect_http_port need tls to be enabled",
+            ));
+        }
+        if self.tls.redirect_http_port == Some(self.server.port) {
+            problems.push(format!(
+                "tls.redirect_http_port is server.port ({}); HTTP and HTTPS need their own port",
+                self.server.port
+            ));
+        }
+
+        if self.auth.enabled && self.auth.api_keys.is_empty() {
+            problems.push(String::from(
+                "auth.api_keys must hold at least one key when auth is enabled",
+            ));
+        }
+        for api_key in &self.auth.api_keys {
+            if api_key.key.len() < MIN_API_KEY_LENGTH {
+                problems.push(format!(
+                    "auth.api_keys: the key of {:?} is shorter than {MIN_API_KEY_LENGTH} characters",
+                    api_key.name
+                ));
+            }
+        }
+
+        if self.retention.enabled {
+            if self.retention.default_days == 0 {
+                problems.push(String::from("retention.default_days must be at least 1"));
+            }
+            if self.retention.interval_secs == 0 {
+                problems.push(String::from("retention.interval_secs must be at least 1"));
+            }
+            for (service, days) in &self.retention.services {
+                if *days == 0 {
+                    problems.push(format!("retention.services.{service} must be at least 1"));
+                }
+            }
+        }
+
+        for (name, program) in &self.alerts.commands {
+            if name.is_empty() || !Path::new(program).is_absolute() {
+                problems.push(format!(
+                    "alerts.commands.{name}: {program:?} must be an absolute path"
+                ));
+            }
+        }
+        if self.alerts.eval_interval_secs == 0 {
+            problems.push(String::from("alerts.eval_interval_secs must be at least 1"));
+        }
+        if let Err(err) = TraceExtractor::from_pattern(self.ingest.trace_id_regex.as_deref()) {
+            problems.push(format!("ingest.trace_id_regex is not a valid regex: {err}"));
+        }
+        if let Err(err) = Pipeline::new(Some(&self.redaction), None, None) {
+            problems.push(format!("redaction: {err}"));
+        }
+
+        problems
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn parse_var<T: std::str::FromStr>(
+    var: &impl Fn(&str) -> Option<String>,
+    name: &str,
+    target: &mut T,
+    problems: &mut Vec<String>,
+) {
+    if let Some(value) = var(name) {
+        match value.trim().parse() {
+            Ok(value) => *target = value,
+            Err(_) => problems.push(format!("{name}: {value:?} is not a valid number")),
+        }
+    }
+}
+
+fn parse_flag(var: &impl Fn(&str) -> Option<String>, name: &str, target: &mut bool) {
+    if let Some(value) = var(name) {
+        *target = matches!(
+            value.trim().to_ascii_lowercase().as_str(),
+            "1" | "true" | "yes" | "on"
+        );
+    }
+}
+
+///Replace `target` with the JSON file named by the `name` variable
+fn json_file_var<T: serde::de::DeserializeOwned>(
+    var: &impl Fn(&str) -> Option<String>,
+    name: &str,
+    target: &mut T,
+    problems: &mut Vec<String>,
+) {
+    let Some(path) = var(name) else {
+        return;
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(data) => match serde_json::from_str(&data) {
+            Ok(value) => *target = value,
+            Err(err) => problems.push(format!("{name}: parsing {path}: {err}")),
+        },
+        Err(err) => problems.push(format!("{name}: reading {path}: {err}")),
+    }
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const FILE: &str = r#"
+        [server]
+        host = "0.0.0.0"
+        port = 8080
+
+        [cors]
+        allowed_origins = ["https://logs.example.com"]
+
+        [database]
+        uri = "mongodb://mongo:27017"
+        name = "logs"
+
+        [auth]
+        enabled = true
+        api_keys = [{ name = "collectors", key = "0123456789abcdef" }]
+
+        [retention.services]
+        checkout = 90
+
+        [alerts]
+        commands = { page_oncall = "/usr/local/bin/page-oncall" }
+    "#;
+
+    #[test]
+    fn test_layering() {
+        let mut settings: Settings = toml::from_str(FILE).unwrap();
+        assert_eq!(settings.server.port, 8080);
+        assert_eq!(settings.database.max_pool_size, 20);
+        assert_eq!(settings.retention.services["checkout"], 90);
+        assert_eq!(
+            settings.alerts.commands["page_oncall"],
+            "/usr/local/bin/page-oncall"
+        );
+
+        let env = HashMap::from([("API_PORT", "9090"), ("MONGO_DB_NAME", "logs_eu")]);
+        let problems = settings.apply_env(|name| env.get(name).map(|value| value.to_string()));
+        assert!(problems.is_empty());
+
+        let cli = Cli::parse_from(["rust-tooling-api", "--port", "7070"]);
+        settings.apply_cli(&cli);
+
+        assert_eq!(settings.server.host, "0.0.0.0");
+        assert_eq!(settings.server.port, 7070);
+        assert_eq!(settings.database.name, "logs_eu");
+        assert!(settings.validate().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_settings_are_all_reported() {
+        let unknown = toml::from_str::<Settings>("[server]\nadress = \"0.0.0.0\"\n");
+        assert!(unknown.unwrap_err().to_string().contains("adress"));
+
+        let mut settings: Settings = toml::from_str(FILE).unwrap();
+        let env = HashMap::from([
+            ("API_PORT", "eighty"),
+            ("CORS_ALLOWED_ORIGINS", "https://logs.example.com/"),
+            ("API_KEYS", "collectors:short"),
+            ("TLS_CERT_PATH", "/nonexistent/cert.pem"),
+            ("ALERT_COMMANDS", "page_oncall=page-oncall"),
+        ]);
+        let mut problems = settings.apply_env(|name| env.get(name).map(|value| value.to_string()));
+        problems.extend(settings.validate());
+
+        assert_eq!(
+            problems,
+            vec![
+                "API_PORT: \"eighty\" is not a valid number",
+                "cors.allowed_origins: \"https://logs.example.com/\" is not an origin like https://example.com",
+                "tls.cert_path: /nonexistent/cert.pem is not a file",
+                "tls.key_path is required when tls is enabled",
+                "auth.api_keys: the key of \"collectors\" is shorter than 16 characters",
+                "alerts.commands.page_oncall: \"page-oncall\" must be an absolute path",
+            ]
+        );
+    }
+}
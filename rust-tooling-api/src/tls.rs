@@ -0,0 +1,396 @@
+//! HTTPS termination from the `[tls]` table of the settings: the rustls configuration, the
+//! reload of a renewed certificate without a restart, the client certificates of the
+//! collectors and the plain HTTP listener redirecting to HTTPS.
+
+use std::any::Any;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use actix_tls::accept::rustls_0_23::TlsStream;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{Extensions, ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::rt::net::TcpStream;
+use actix_web::web::Data;
+use actix_web::{Error, HttpRequest, HttpResponse, http::header};
+use dal_layer::models::response_model::GenericResponse;
+use rustls::RootCertStore;
+use rustls::ServerConfig;
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier};
+use rustls::sign::CertifiedKey;
+
+use crate::jobs;
+use crate::settings::{ClientAuth, Settings, TlsSettings};
+
+///Paths a collector reports on, which need its certificate when `client_auth` is `collectors`
+const COLLECTOR_PATHS: &str = "/api/collectors";
+
+fn load_certificates(field: &str, path: &Path) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certificates = CertificateDer::pem_file_iter(path)
+        .and_then(|certificates| certificates.collect::<Result<Vec<_>, _>>())
+        .map_err(|err| format!("{field}: reading {}: {err}", path.display()))?;
+    if certificates.is_empty() {
+        return Err(format!(
+            "{field}: {} holds no PEM certificate",
+            path.display()
+        ));
+    }
+
+    Ok(certificates)
+}
+
+fn load_private_key(path: &Path) -> Result<PrivateKeyDer<'static>, String> {
+    PrivateKeyDer::from_pem_file(path)
+        .map_err(|err| format!("tls.key_path: reading {}: {err}", path.display()))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+#[derive(Debug)]
+struct Loaded {
+    key: Arc<CertifiedKey>,
+    modified: (Option<SystemTime>, Option<SystemTime>),
+}
+
+///Certificate presented to the clients, read again from its files when they change so a
+///renewed certificate is served without a restart
+#[derive(Debug)]
+pub struct ReloadingCertificate {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    provider: Arc<CryptoProvider>,
+    loaded: RwLock<Loaded>,
+}
+
+impl ReloadingCertificate {
+    pub fn load(
+        cert_path: &Path,
+        key_path: &Path,
+        provider: Arc<CryptoProvider>,
+    ) -> Result<Self, String> {
+        let loaded = Self::read(cert_path, key_path, &provider)?;
+        Ok(ReloadingCertificate {
+            cert_path: cert_path.to_path_buf(),
+            key_path: key_path.to_path_buf(),
+            provider,
+            loaded: RwLock::new(loaded),
+        })
+    }
+
+    fn read(
+        cert_path: &Path,
+        key_path: &Path,
+        provider: &CryptoProvider,
+    ) -> Result<Loaded, String> {
+        // the times are taken first, so a file written while it is read is read again next time
+        let modified = (modified(cert_path), modified(key_path));
+        let certificates = load_certificates("tls.cert_path", cert_path)?;
+        let key = load_private_key(key_path)?;
+        let key = CertifiedKey::from_der(certificates, key, provider)
+            .map_err(|err| format!("tls: the certificate does not match the key: {err}"))?;
+
+        Ok(Loaded {
+            key: Arc::new(key),
+            modified,
+        })
+    }
+
+    ///Read the files again when either changed since they were loaded, returning whether the
+    ///certificate was replaced. The certificate in use is kept when the new files are invalid.
+    pub fn reload_if_changed(&self) -> Result<bool, String> {
+        let modified = (modified(&self.cert_path), modified(&self.key_path));
+        if self
+            .loaded
+            .read()
+            .is_ok_and(|loaded| loaded.modified == modified)
+        {
+            return Ok(false);
+        }
+
+        let loaded = Self::read(&self.cert_path, &self.key_path, &self.provider)?;
+        *self
+            .loaded
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = loaded;
+        Ok(true)
+    }
+
+    fn current(&self) -> Arc<CertifiedKey> {
+        self.loaded
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .key
+            .clone()
+    }
+}
+
+impl ResolvesServerCert for ReloadingCertificate {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current())
+    }
+}
+
+///Server configuration presenting the certificate named by `tls` and checking the client
+///certificates it asks for, with the certificate to hand to `spawn_certificate_reloader`
+pub fn server_config(
+    tls: &TlsSettings,
+) -> Result<(ServerConfig, Arc<ReloadingCertificate>), String> {
+    let (Some(cert_path), Some(key_path)) = (&tls.cert_path, &tls.key_path) else {
+        return Err(String::from(
+            "tls.cert_path and tls.key_path are required when tls is enabled",
+        ));
+    };
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let certificate = Arc::new(ReloadingCertificate::load(
+        cert_path,
+        key_path,
+        provider.clone(),
+    )?);
+
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|err| format!("tls: {err}"))?;
+
+    let builder = match (&tls.client_ca_path, tls.client_auth) {
+        (Some(ca_path), ClientAuth::Collectors | ClientAuth::Required) => {
+            let mut roots = RootCertStore::empty();
+            for certificate in load_certificates("tls.client_ca_path", ca_path)? {
+                roots
+                    .add(certificate)
+                    .map_err(|err| format!("tls.client_ca_path: {err}"))?;
+            }
+
+            let verifier = WebPkiClientVerifier::builder_with_provider(roots.into(), provider);
+            // the collectors' paths are checked by `require_collector_certificate`
+            let verifier = match tls.client_auth {
+                ClientAuth::Collectors => verifier.allow_unauthenticated(),
+                _ => verifier,
+            };
+            builder.with_client_cert_verifier(
+                verifier
+                    .build()
+                    .map_err(|err| format!("tls.client_ca_path: {err}"))?,
+            )
+        }
+        _ => builder.with_no_client_auth(),
+    };
+
+    Ok((builder.with_cert_resolver(certificate.clone()), certificate))
+}
+
+///Check the certificate files every `interval` and serve the new certificate once they changed
+pub fn spawn_certificate_reloader(certificate: Arc<ReloadingCertificate>, interval: Duration) {
+    jobs::register("tls_reload", interval);
+    actix_web::rt::spawn(async move {
+        let mut ticker = actix_web::rt::time::interval(interval);
+        loop {
+            ticker.tick().await;
+
+            let started = Instant::now();
+            let result = certificate.reload_if_changed();
+            jobs::record_run("tls_reload", started.elapsed(), &result);
+            match result {
+                Ok(true) => println!(
+                    "Reloaded the TLS certificate from {}",
+                    certificate.cert_path.display()
+                ),
+                Ok(false) => {}
+                Err(err) => eprintln!(
+                    "Could not reload the TLS certificate, keeping the current one: {err}"
+                ),
+            }
+        }
+    });
+}
+
+///Marks a connection whose client presented a certificate the verifier accepted
+#[derive(Debug, Clone, Copy)]
+pub struct ClientCertificate;
+
+///`on_connect` callback of the HTTPS server recording whether the client was authenticated
+pub fn record_client_certificate(connection: &dyn Any, data: &mut Extensions) {
+    let authenticated = connection
+        .downcast_ref::<TlsStream<TcpStream>>()
+        .and_then(|stream| stream.get_ref().1.peer_certificates())
+        .is_some_and(|certificates| !certificates.is_empty());
+    if authenticated {
+        data.insert(ClientCertificate);
+    }
+}
+
+///Middleware answering 403 to a collector calling /api/collectors without a client
+///certificate when `tls.client_auth` is `collectors`
+pub async fn require_collector_certificate(
+    request: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let required = request
+        .app_data::<Data<Settings>>()
+        .is_some_and(|settings| {
+            settings.tls.enabled && settings.tls.client_auth == ClientAuth::Collectors
+        })
+        && request.path().starts_with(COLLECTOR_PATHS);
+
+    if required && request.conn_data::<ClientCertificate>().is_none() {
+        let response = HttpResponse::Forbidden().json(GenericResponse {
+            code: String::from("403"),
+            data: String::from("Collectors must present a client certificate"),
+        });
+        return Ok(request.into_response(response).map_into_right_body());
+    }
+
+    next.call(request)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+///Port of the HTTPS listener, for the redirect listener
+#[derive(Debug, Clone, Copy)]
+pub struct HttpsPort(pub u16);
+
+///Default service of the redirect listener: the same url over HTTPS. The host is the one of
+///the `Host` header only; `Forwarded` and `X-Forwarded-Host` are set by clients as they please
+///and would make the listener redirect anywhere.
+pub async fn redirect_to_https(request: HttpRequest, port: Data<HttpsPort>) -> HttpResponse {
+    let host = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .filter(|host| {
+            !host.is_empty()
+                && host
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | ':' | '[' | ']'))
+        });
+    let Some(host) = host else {
+        return HttpResponse::BadRequest().finish();
+    };
+    // drop the port of the plain listener, keeping a bracketed IPv6 address whole
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if !port.contains(']') => name,
+        _ => host,
+    };
+    let port = match port.0 {
+        443 => String::new(),
+        port => format!(":{port}"),
+    };
+    let path = request
+        .uri()
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/");
+
+    HttpResponse::PermanentRedirect()
+        .insert_header((header::LOCATION, format!("https://{host}{port}{path}")))
+        .finish()
+}
+
+/**************************************************TEST MODULES BELOW********************************************************* */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, test, web};
+
+    #[actix_web::test]
+    async fn test_redirect_to_https() {
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(HttpsPort(8443)))
+                .default_service(web::to(redirect_to_https)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/api/services?page=2")
+            .insert_header((header::HOST, "logs.example.com:8080"))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+
+        assert_eq!(response.status(), 308);
+        assert_eq!(
+            response.headers().get(header::LOCATION).unwrap(),
+            "https://logs.example.com:8443/api/services?page=2"
+        );
+
+        let forwarded = test::TestRequest::get()
+            .uri("/")
+            .insert_header((header::HOST, "logs.example.com"))
+            .insert_header(("X-Forwarded-Host", "evil.example.net"))
+            .insert_header((header::FORWARDED, "host=evil.example.net"))
+            .to_request();
+        let response = test::call_service(&app, forwarded).await;
+        assert_eq!(
+            response.headers().get(header::LOCATION).unwrap(),
+            "https://logs.example.com:8443/"
+        );
+
+        let malformed = test::TestRequest::get()
+            .uri("/")
+            .insert_header((header::HOST, "evil.example.net/x?"))
+            .to_request();
+        assert_eq!(test::call_service(&app, malformed).await.status(), 400);
+    }
+
+    fn write_certificate(cert_path: &Path, key_path: &Path, modified: SystemTime) -> Vec<u8> {
+        let certified =
+            rcgen::generate_simple_self_signed(vec![String::from("localhost")]).unwrap();
+        fs::write(cert_path, certified.cert.pem()).unwrap();
+        fs::write(key_path, certified.signing_key.serialize_pem()).unwrap();
+        for path in [cert_path, key_path] {
+            fs::File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        }
+        certified.cert.der().to_vec()
+    }
+
+    #[actix_web::test]
+    async fn test_reload_on_change() {
+        let dir = std::env::temp_dir().join(format!("tls-reload-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (cert_path, key_path) = (dir.join("cert.pem"), dir.join("key.pem"));
+        let served = |certificate: &ReloadingCertificate| certificate.current().cert[0].to_vec();
+
+        let first = write_certificate(&cert_path, &key_path, SystemTime::UNIX_EPOCH);
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let certificate = ReloadingCertificate::load(&cert_path, &key_path, provider).unwrap();
+        assert_eq!(served(&certificate), first);
+        assert_eq!(certificate.reload_if_changed(), Ok(false));
+
+        let renewed = SystemTime::UNIX_EPOCH + Duration::from_secs(60);
+        let second = write_certificate(&cert_path, &key_path, renewed);
+        assert_eq!(certificate.reload_if_changed(), Ok(true));
+        assert_eq!(served(&certificate), second);
+
+        // a renewal half written is not served
+        fs::write(&cert_path, "-----BEGIN CERTIFICATE-----\n").unwrap();
+        assert!(certificate.reload_if_changed().is_err());
+        assert_eq!(served(&certificate), second);
+
+        // the same self-signed certificate stands in for the authority of the client certificates
+        let tls = TlsSettings {
+            enabled: true,
+            cert_path: Some(cert_path.clone()),
+            key_path: Some(key_path.clone()),
+            client_ca_path: Some(cert_path.clone()),
+            client_auth: ClientAuth::Collectors,
+            ..Default::default()
+        };
+        write_certificate(&cert_path, &key_path, renewed);
+        assert!(server_config(&tls).is_ok());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}